@@ -2,3 +2,37 @@ hello-world = Hallo Welt!
 greeting = Hallochen { $name }!
         .placeholder = Hallo Freund!
 about = Uber
+
+## order emails
+email-greeting = Hallo { $name },
+email-signature = Vielen Dank für Ihren Einkauf.
+order-details = Bestellung #{ $number }
+order-product = Produkt
+order-quantity = Menge
+order-backordered = Im Rückstand: { $quantity }
+order-price = Preis
+order-shipping = Versand
+order-total = Gesamt
+order-payment-method = Zahlungsart
+order-customer-note = Anmerkung
+order-view = Bestellung ansehen
+order-received-subject = Ihre Bestellung #{ $number } ist eingegangen
+order-received-intro = Wir haben Ihre Bestellung #{ $number } erhalten, ihr Status ist { $status }.
+order-processing-subject = Ihre Bestellung #{ $number } wird bearbeitet
+order-processing-intro = Wir haben Ihre Zahlung erhalten, Ihre Bestellung #{ $number } wird vorbereitet.
+order-completed-subject = Ihre Bestellung #{ $number } ist abgeschlossen
+order-completed-intro = Ihre Bestellung #{ $number } wurde versandt.
+order-refunded-subject = Ihre Bestellung #{ $number } wurde erstattet
+order-refunded-intro = Ihre Bestellung #{ $number } wurde erstattet.
+order-customer-note-subject = Neue Anmerkung zu Ihrer Bestellung #{ $number }
+order-customer-note-intro = Zu Ihrer Bestellung #{ $number } wurde folgende Anmerkung hinzugefügt:
+order-new-order-subject = Neue Bestellung #{ $number }
+order-new-order-intro = Sie haben die Bestellung #{ $number } von { $email } erhalten.
+low-stock-subject = Geringer Bestand: { $product }
+low-stock-intro = { $product } hat nur noch { $stock } auf Lager.
+low-stock-edit = Produkt bearbeiten
+no-stock-subject = Ausverkauft: { $product }
+no-stock-intro = { $product } ist ausverkauft.
+back-in-stock-subject = { $product } ist wieder auf Lager
+back-in-stock-intro = Gute Nachrichten, { $product } ist wieder auf Lager.
+back-in-stock-view = Produkt ansehen
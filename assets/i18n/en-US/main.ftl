@@ -8,3 +8,37 @@ parameter = text with a { $param }
 parameter2 = text one { $param } second { $multi-word-param }
 email = text with an EMAIL("example@example.org")
 fallback = this should fall back
+
+## order emails
+email-greeting = Hi { $name },
+email-signature = Thanks for shopping with us.
+order-details = Order #{ $number }
+order-product = Product
+order-quantity = Quantity
+order-backordered = Backordered: { $quantity }
+order-price = Price
+order-shipping = Shipping
+order-total = Total
+order-payment-method = Payment method
+order-customer-note = Note
+order-view = View your order
+order-received-subject = Your order #{ $number } has been received
+order-received-intro = We have received your order #{ $number }, it is currently { $status }.
+order-processing-subject = Your order #{ $number } is being processed
+order-processing-intro = We have received your payment, your order #{ $number } is being prepared.
+order-completed-subject = Your order #{ $number } is complete
+order-completed-intro = Your order #{ $number } has been shipped.
+order-refunded-subject = Your order #{ $number } has been refunded
+order-refunded-intro = Your order #{ $number } has been refunded.
+order-customer-note-subject = A note has been added to your order #{ $number }
+order-customer-note-intro = The following note has been added to your order #{ $number }:
+order-new-order-subject = New order #{ $number }
+order-new-order-intro = You have received order #{ $number } from { $email }.
+low-stock-subject = Low stock: { $product }
+low-stock-intro = { $product } is low in stock, { $stock } left.
+low-stock-edit = Edit the product
+no-stock-subject = Out of stock: { $product }
+no-stock-intro = { $product } is out of stock.
+back-in-stock-subject = { $product } is back in stock
+back-in-stock-intro = Good news, { $product } is back in stock.
+back-in-stock-view = View the product
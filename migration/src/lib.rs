@@ -6,6 +6,41 @@ mod m20220101_000001_users;
 
 mod m20250202_124347_products;
 mod m20250216_051031_postmetas;
+mod m20250302_101512_login_attempts;
+mod m20250302_103044_audit_logs;
+mod m20250302_104510_add_lockout_to_users;
+mod m20250309_091204_orders;
+mod m20250309_091517_order_items;
+mod m20250309_092033_addresses;
+mod m20250309_093340_add_pending_email_to_users;
+mod m20250316_084512_add_guest_fields_to_orders;
+mod m20250323_090512_api_keys;
+mod m20250330_081204_refresh_tokens;
+mod m20250330_082311_add_token_version_to_users;
+mod m20250406_091532_add_two_factor_to_users;
+mod m20250406_092140_recovery_codes;
+mod m20250413_100214_user_identities;
+mod m20250420_090412_shipping_zones;
+mod m20250420_090733_shipping_zone_locations;
+mod m20250420_091105_shipping_methods;
+mod m20250420_091442_add_shipping_to_orders;
+mod m20250427_083012_tax_rates;
+mod m20250427_084230_add_taxes_to_order_items;
+mod m20250427_084517_add_taxes_to_orders;
+mod m20250504_081530_add_checkout_fields_to_orders;
+mod m20250511_083420_failed_emails;
+mod m20250518_082011_product_downloads;
+mod m20250518_082436_download_permissions;
+mod m20250525_083104_media;
+mod m20250601_081422_reviews;
+mod m20250608_090215_add_published_at_to_products;
+mod m20250615_083512_product_revisions;
+mod m20250622_081045_product_slugs;
+mod m20250629_082514_bundle_items;
+mod m20250706_081233_add_backordered_to_order_items;
+mod m20250713_094107_stock_subscriptions;
+mod m20250720_083516_stock_movements;
+mod m20250727_080512_add_cleared_at_to_login_attempts;
 pub struct Migrator;
 
 #[async_trait::async_trait]
@@ -15,6 +50,41 @@ impl MigratorTrait for Migrator {
             Box::new(m20220101_000001_users::Migration),
             Box::new(m20250202_124347_products::Migration),
             Box::new(m20250216_051031_postmetas::Migration),
+            Box::new(m20250302_101512_login_attempts::Migration),
+            Box::new(m20250302_103044_audit_logs::Migration),
+            Box::new(m20250302_104510_add_lockout_to_users::Migration),
+            Box::new(m20250309_091204_orders::Migration),
+            Box::new(m20250309_091517_order_items::Migration),
+            Box::new(m20250309_092033_addresses::Migration),
+            Box::new(m20250309_093340_add_pending_email_to_users::Migration),
+            Box::new(m20250316_084512_add_guest_fields_to_orders::Migration),
+            Box::new(m20250323_090512_api_keys::Migration),
+            Box::new(m20250330_081204_refresh_tokens::Migration),
+            Box::new(m20250330_082311_add_token_version_to_users::Migration),
+            Box::new(m20250406_091532_add_two_factor_to_users::Migration),
+            Box::new(m20250406_092140_recovery_codes::Migration),
+            Box::new(m20250413_100214_user_identities::Migration),
+            Box::new(m20250420_090412_shipping_zones::Migration),
+            Box::new(m20250420_090733_shipping_zone_locations::Migration),
+            Box::new(m20250420_091105_shipping_methods::Migration),
+            Box::new(m20250420_091442_add_shipping_to_orders::Migration),
+            Box::new(m20250427_083012_tax_rates::Migration),
+            Box::new(m20250427_084230_add_taxes_to_order_items::Migration),
+            Box::new(m20250427_084517_add_taxes_to_orders::Migration),
+            Box::new(m20250504_081530_add_checkout_fields_to_orders::Migration),
+            Box::new(m20250511_083420_failed_emails::Migration),
+            Box::new(m20250518_082011_product_downloads::Migration),
+            Box::new(m20250518_082436_download_permissions::Migration),
+            Box::new(m20250525_083104_media::Migration),
+            Box::new(m20250601_081422_reviews::Migration),
+            Box::new(m20250608_090215_add_published_at_to_products::Migration),
+            Box::new(m20250615_083512_product_revisions::Migration),
+            Box::new(m20250622_081045_product_slugs::Migration),
+            Box::new(m20250629_082514_bundle_items::Migration),
+            Box::new(m20250706_081233_add_backordered_to_order_items::Migration),
+            Box::new(m20250713_094107_stock_subscriptions::Migration),
+            Box::new(m20250720_083516_stock_movements::Migration),
+            Box::new(m20250727_080512_add_cleared_at_to_login_attempts::Migration),
             // inject-above (do not remove this comment)
         ]
     }
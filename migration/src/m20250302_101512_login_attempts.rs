@@ -0,0 +1,54 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(LoginAttempts::Table)
+                    .col(pk_auto(LoginAttempts::Id))
+                    .col(string(LoginAttempts::Email))
+                    .col(string_null(LoginAttempts::IpAddress))
+                    .col(boolean(LoginAttempts::Successful))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-login_attempts-email")
+                    .table(LoginAttempts::Table)
+                    .col(LoginAttempts::Email)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-login_attempts-ip_address")
+                    .table(LoginAttempts::Table)
+                    .col(LoginAttempts::IpAddress)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(LoginAttempts::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum LoginAttempts {
+    Table,
+    Id,
+    Email,
+    IpAddress,
+    Successful,
+}
@@ -0,0 +1,52 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(AuditLogs::Table)
+                    .col(pk_auto(AuditLogs::Id))
+                    .col(integer_null(AuditLogs::UserId))
+                    .col(string(AuditLogs::Action))
+                    .col(string_null(AuditLogs::IpAddress))
+                    .col(text_null(AuditLogs::Details))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-audit_logs-user_ids")
+                            .from(AuditLogs::Table, AuditLogs::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuditLogs::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum AuditLogs {
+    Table,
+    Id,
+    UserId,
+    Action,
+    IpAddress,
+    Details,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
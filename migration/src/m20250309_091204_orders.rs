@@ -0,0 +1,56 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(Orders::Table)
+                    .col(pk_auto(Orders::Id))
+                    .col(integer_null(Orders::UserId))
+                    .col(string(Orders::Status))
+                    .col(float(Orders::Total))
+                    .col(text_null(Orders::BillingAddress))
+                    .col(text_null(Orders::ShippingAddress))
+                    .col(text_null(Orders::CustomerNote))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-orders-user_ids")
+                            .from(Orders::Table, Orders::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Orders::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    Id,
+    UserId,
+    Status,
+    Total,
+    BillingAddress,
+    ShippingAddress,
+    CustomerNote,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
@@ -0,0 +1,70 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(OrderItems::Table)
+                    .col(pk_auto(OrderItems::Id))
+                    .col(integer(OrderItems::OrderId))
+                    .col(integer_null(OrderItems::ProductId))
+                    .col(string(OrderItems::Name))
+                    .col(integer(OrderItems::Quantity))
+                    .col(float(OrderItems::Price))
+                    .col(float(OrderItems::Total))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-order_items-order_ids")
+                            .from(OrderItems::Table, OrderItems::OrderId)
+                            .to(Orders::Table, Orders::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-order_items-product_ids")
+                            .from(OrderItems::Table, OrderItems::ProductId)
+                            .to(Products::Table, Products::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(OrderItems::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum OrderItems {
+    Table,
+    Id,
+    OrderId,
+    ProductId,
+    Name,
+    Quantity,
+    Price,
+    Total,
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    Id,
+}
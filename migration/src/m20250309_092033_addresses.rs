@@ -0,0 +1,81 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(Addresses::Table)
+                    .col(pk_auto(Addresses::Id))
+                    .col(integer(Addresses::UserId))
+                    .col(string(Addresses::Kind))
+                    .col(string(Addresses::FirstName))
+                    .col(string(Addresses::LastName))
+                    .col(string_null(Addresses::Company))
+                    .col(string(Addresses::Address1))
+                    .col(string_null(Addresses::Address2))
+                    .col(string(Addresses::City))
+                    .col(string_null(Addresses::State))
+                    .col(string(Addresses::Postcode))
+                    .col(string(Addresses::Country))
+                    .col(string_null(Addresses::Phone))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-addresses-user_ids")
+                            .from(Addresses::Table, Addresses::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-addresses-user_id-kind")
+                    .table(Addresses::Table)
+                    .col(Addresses::UserId)
+                    .col(Addresses::Kind)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Addresses::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Addresses {
+    Table,
+    Id,
+    UserId,
+    Kind,
+    FirstName,
+    LastName,
+    Company,
+    #[sea_orm(iden = "address_1")]
+    Address1,
+    #[sea_orm(iden = "address_2")]
+    Address2,
+    City,
+    State,
+    Postcode,
+    Country,
+    Phone,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
@@ -0,0 +1,121 @@
+use loco_rs::{prelude::Uuid, schema::table_auto_tz};
+use sea_orm_migration::{prelude::*, schema::*};
+use sha2::{Digest, Sha256};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(ApiKeys::Table)
+                    .col(pk_auto(ApiKeys::Id))
+                    .col(integer(ApiKeys::UserId))
+                    .col(string(ApiKeys::Name))
+                    .col(string(ApiKeys::KeyPrefix))
+                    .col(string_uniq(ApiKeys::KeyHash))
+                    .col(string(ApiKeys::Scope))
+                    .col(timestamp_with_time_zone_null(ApiKeys::LastUsedAt))
+                    .col(timestamp_with_time_zone_null(ApiKeys::RevokedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-api_keys-user_ids")
+                            .from(ApiKeys::Table, ApiKeys::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-api_keys-user_id")
+                    .table(ApiKeys::Table)
+                    .col(ApiKeys::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // keys issued so far keep working, but only their hash is kept
+        let db = manager.get_connection();
+        let builder = manager.get_database_backend();
+        let users = db
+            .query_all(
+                builder.build(
+                    Query::select()
+                        .columns([Users::Id, Users::ApiKey])
+                        .from(Users::Table),
+                ),
+            )
+            .await?;
+        for user in users {
+            let id: i32 = user.try_get("", "id")?;
+            let api_key: String = user.try_get("", "api_key")?;
+
+            let mut hasher = Sha256::new();
+            hasher.update(api_key.as_bytes());
+            db.execute(
+                builder.build(
+                    Query::insert()
+                        .into_table(ApiKeys::Table)
+                        .columns([
+                            ApiKeys::UserId,
+                            ApiKeys::Name,
+                            ApiKeys::KeyPrefix,
+                            ApiKeys::KeyHash,
+                            ApiKeys::Scope,
+                        ])
+                        .values_panic([
+                            id.into(),
+                            "Legacy key".into(),
+                            api_key.chars().take(11).collect::<String>().into(),
+                            format!("{:x}", hasher.finalize()).into(),
+                            "read_write".into(),
+                        ]),
+                ),
+            )
+            .await?;
+            db.execute(
+                builder.build(
+                    Query::update()
+                        .table(Users::Table)
+                        .value(Users::ApiKey, format!("lo-{}", Uuid::new_v4()))
+                        .and_where(Expr::col(Users::Id).eq(id)),
+                ),
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ApiKeys::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ApiKeys {
+    Table,
+    Id,
+    UserId,
+    Name,
+    KeyPrefix,
+    KeyHash,
+    Scope,
+    LastUsedAt,
+    RevokedAt,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+    ApiKey,
+}
@@ -0,0 +1,34 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(ShippingZones::Table)
+                    .col(pk_auto(ShippingZones::Id))
+                    .col(string(ShippingZones::Name))
+                    .col(integer(ShippingZones::Position).default(0))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ShippingZones::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ShippingZones {
+    Table,
+    Id,
+    Name,
+    Position,
+}
@@ -0,0 +1,59 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(ShippingZoneLocations::Table)
+                    .col(pk_auto(ShippingZoneLocations::Id))
+                    .col(integer(ShippingZoneLocations::ZoneId))
+                    .col(string(ShippingZoneLocations::Kind))
+                    .col(string(ShippingZoneLocations::Code))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-shipping_zone_locations-zone_ids")
+                            .from(ShippingZoneLocations::Table, ShippingZoneLocations::ZoneId)
+                            .to(ShippingZones::Table, ShippingZones::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-shipping_zone_locations-zone_id")
+                    .table(ShippingZoneLocations::Table)
+                    .col(ShippingZoneLocations::ZoneId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ShippingZoneLocations::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ShippingZoneLocations {
+    Table,
+    Id,
+    ZoneId,
+    Kind,
+    Code,
+}
+
+#[derive(DeriveIden)]
+enum ShippingZones {
+    Table,
+    Id,
+}
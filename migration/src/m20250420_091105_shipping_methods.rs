@@ -0,0 +1,69 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(ShippingMethods::Table)
+                    .col(pk_auto(ShippingMethods::Id))
+                    .col(integer(ShippingMethods::ZoneId))
+                    .col(string(ShippingMethods::Kind))
+                    .col(string(ShippingMethods::Title))
+                    .col(boolean(ShippingMethods::Enabled).default(true))
+                    .col(integer(ShippingMethods::Position).default(0))
+                    .col(float_null(ShippingMethods::Cost))
+                    .col(float_null(ShippingMethods::MinAmount))
+                    .col(text_null(ShippingMethods::Rates))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-shipping_methods-zone_ids")
+                            .from(ShippingMethods::Table, ShippingMethods::ZoneId)
+                            .to(ShippingZones::Table, ShippingZones::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-shipping_methods-zone_id")
+                    .table(ShippingMethods::Table)
+                    .col(ShippingMethods::ZoneId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ShippingMethods::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ShippingMethods {
+    Table,
+    Id,
+    ZoneId,
+    Kind,
+    Title,
+    Enabled,
+    Position,
+    Cost,
+    MinAmount,
+    Rates,
+}
+
+#[derive(DeriveIden)]
+enum ShippingZones {
+    Table,
+    Id,
+}
@@ -0,0 +1,59 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(TaxRates::Table)
+                    .col(pk_auto(TaxRates::Id))
+                    .col(string(TaxRates::TaxClass).default("standard"))
+                    .col(string_null(TaxRates::Country))
+                    .col(string_null(TaxRates::State))
+                    .col(string_null(TaxRates::Postcode))
+                    .col(string(TaxRates::Name))
+                    .col(float(TaxRates::Rate))
+                    .col(integer(TaxRates::Priority).default(1))
+                    .col(boolean(TaxRates::Compound).default(false))
+                    .col(boolean(TaxRates::Shipping).default(true))
+                    .col(integer(TaxRates::Position).default(0))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-tax_rates-tax_class")
+                    .table(TaxRates::Table)
+                    .col(TaxRates::TaxClass)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TaxRates::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TaxRates {
+    Table,
+    Id,
+    TaxClass,
+    Country,
+    State,
+    Postcode,
+    Name,
+    Rate,
+    Priority,
+    Compound,
+    Shipping,
+    Position,
+}
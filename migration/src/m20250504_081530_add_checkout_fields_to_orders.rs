@@ -0,0 +1,69 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            string_null(Orders::PaymentMethod),
+            string_null(Orders::PaymentMethodTitle),
+            string_null(Orders::CheckoutToken),
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Orders::Table)
+                        .add_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        // a checkout submitted twice must not place two orders
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-orders-checkout_token")
+                    .table(Orders::Table)
+                    .col(Orders::CheckoutToken)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-orders-checkout_token")
+                    .table(Orders::Table)
+                    .to_owned(),
+            )
+            .await?;
+        for column in [
+            Orders::CheckoutToken,
+            Orders::PaymentMethodTitle,
+            Orders::PaymentMethod,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Orders::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    PaymentMethod,
+    PaymentMethodTitle,
+    CheckoutToken,
+}
@@ -0,0 +1,40 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(FailedEmails::Table)
+                    .col(pk_auto(FailedEmails::Id))
+                    .col(string(FailedEmails::Recipient))
+                    .col(string(FailedEmails::Subject))
+                    .col(text(FailedEmails::Email))
+                    .col(text(FailedEmails::Error))
+                    .col(integer(FailedEmails::Attempts).default(0))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(FailedEmails::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum FailedEmails {
+    Table,
+    Id,
+    Recipient,
+    Subject,
+    Email,
+    Error,
+    Attempts,
+}
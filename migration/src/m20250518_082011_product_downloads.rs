@@ -0,0 +1,59 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(ProductDownloads::Table)
+                    .col(pk_auto(ProductDownloads::Id))
+                    .col(integer(ProductDownloads::ProductId))
+                    .col(string(ProductDownloads::Name))
+                    .col(string(ProductDownloads::File))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-product_downloads-product_ids")
+                            .from(ProductDownloads::Table, ProductDownloads::ProductId)
+                            .to(Products::Table, Products::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-product_downloads-product_id")
+                    .table(ProductDownloads::Table)
+                    .col(ProductDownloads::ProductId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ProductDownloads::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ProductDownloads {
+    Table,
+    Id,
+    ProductId,
+    Name,
+    File,
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    Id,
+}
@@ -0,0 +1,87 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(DownloadPermissions::Table)
+                    .col(pk_auto(DownloadPermissions::Id))
+                    .col(integer(DownloadPermissions::OrderId))
+                    .col(integer(DownloadPermissions::ProductId))
+                    .col(integer(DownloadPermissions::DownloadId))
+                    .col(integer_null(DownloadPermissions::UserId))
+                    .col(string(DownloadPermissions::Email))
+                    .col(string_uniq(DownloadPermissions::DownloadKey))
+                    .col(integer_null(DownloadPermissions::DownloadsRemaining))
+                    .col(integer(DownloadPermissions::DownloadCount).default(0))
+                    .col(timestamp_with_time_zone_null(
+                        DownloadPermissions::AccessExpires,
+                    ))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-download_permissions-order_ids")
+                            .from(DownloadPermissions::Table, DownloadPermissions::OrderId)
+                            .to(Orders::Table, Orders::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-download_permissions-download_ids")
+                            .from(DownloadPermissions::Table, DownloadPermissions::DownloadId)
+                            .to(ProductDownloads::Table, ProductDownloads::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-download_permissions-order_id")
+                    .table(DownloadPermissions::Table)
+                    .col(DownloadPermissions::OrderId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DownloadPermissions::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum DownloadPermissions {
+    Table,
+    Id,
+    OrderId,
+    ProductId,
+    DownloadId,
+    UserId,
+    Email,
+    DownloadKey,
+    DownloadsRemaining,
+    DownloadCount,
+    AccessExpires,
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum ProductDownloads {
+    Table,
+    Id,
+}
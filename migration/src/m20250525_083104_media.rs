@@ -0,0 +1,62 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(Media::Table)
+                    .col(pk_auto(Media::Id))
+                    .col(string(Media::Filename))
+                    .col(string_uniq(Media::Path))
+                    .col(string(Media::MimeType))
+                    .col(big_integer(Media::Size))
+                    .col(integer_null(Media::Width))
+                    .col(integer_null(Media::Height))
+                    .col(string_null(Media::Alt))
+                    .col(text_null(Media::Thumbnails))
+                    .col(integer_null(Media::UserId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-media-user_ids")
+                            .from(Media::Table, Media::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Media::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Media {
+    Table,
+    Id,
+    Filename,
+    Path,
+    MimeType,
+    Size,
+    Width,
+    Height,
+    Alt,
+    Thumbnails,
+    UserId,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
@@ -0,0 +1,84 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(Reviews::Table)
+                    .col(pk_auto(Reviews::Id))
+                    .col(integer(Reviews::ProductId))
+                    .col(integer_null(Reviews::UserId))
+                    .col(string(Reviews::Author))
+                    .col(string(Reviews::Email))
+                    .col(integer(Reviews::Rating))
+                    .col(text(Reviews::Content))
+                    .col(string(Reviews::Status))
+                    .col(boolean(Reviews::Verified).default(false))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-reviews-product_ids")
+                            .from(Reviews::Table, Reviews::ProductId)
+                            .to(Products::Table, Products::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-reviews-user_ids")
+                            .from(Reviews::Table, Reviews::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-reviews-product_id-status")
+                    .table(Reviews::Table)
+                    .col(Reviews::ProductId)
+                    .col(Reviews::Status)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Reviews::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Reviews {
+    Table,
+    Id,
+    ProductId,
+    UserId,
+    Author,
+    Email,
+    Rating,
+    Content,
+    Status,
+    Verified,
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
@@ -0,0 +1,85 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(ProductRevisions::Table)
+                    .col(pk_auto(ProductRevisions::Id))
+                    .col(integer(ProductRevisions::ProductId))
+                    .col(integer_null(ProductRevisions::UserId))
+                    .col(string(ProductRevisions::Title))
+                    .col(string_null(ProductRevisions::Excerpt))
+                    .col(string_null(ProductRevisions::Status))
+                    .col(string_null(ProductRevisions::ProductType))
+                    .col(string_null(ProductRevisions::Slug))
+                    .col(timestamp_with_time_zone_null(ProductRevisions::PublishedAt))
+                    .col(text(ProductRevisions::Metas))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-product_revisions-product_ids")
+                            .from(ProductRevisions::Table, ProductRevisions::ProductId)
+                            .to(Products::Table, Products::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-product_revisions-user_ids")
+                            .from(ProductRevisions::Table, ProductRevisions::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-product_revisions-product_id")
+                    .table(ProductRevisions::Table)
+                    .col(ProductRevisions::ProductId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ProductRevisions::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ProductRevisions {
+    Table,
+    Id,
+    ProductId,
+    UserId,
+    Title,
+    Excerpt,
+    Status,
+    ProductType,
+    Slug,
+    PublishedAt,
+    Metas,
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
@@ -0,0 +1,48 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(ProductSlugs::Table)
+                    .col(pk_auto(ProductSlugs::Id))
+                    .col(integer(ProductSlugs::ProductId))
+                    .col(string_uniq(ProductSlugs::Slug))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-product_slugs-product_ids")
+                            .from(ProductSlugs::Table, ProductSlugs::ProductId)
+                            .to(Products::Table, Products::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ProductSlugs::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ProductSlugs {
+    Table,
+    Id,
+    ProductId,
+    Slug,
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    Id,
+}
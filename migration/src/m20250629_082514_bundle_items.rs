@@ -0,0 +1,69 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(BundleItems::Table)
+                    .col(pk_auto(BundleItems::Id))
+                    .col(integer(BundleItems::BundleId))
+                    .col(integer(BundleItems::ProductId))
+                    .col(integer(BundleItems::Quantity).default(1))
+                    .col(boolean(BundleItems::Optional).default(false))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-bundle_items-bundle_ids")
+                            .from(BundleItems::Table, BundleItems::BundleId)
+                            .to(Products::Table, Products::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-bundle_items-product_ids")
+                            .from(BundleItems::Table, BundleItems::ProductId)
+                            .to(Products::Table, Products::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-bundle_items-bundle_id")
+                    .table(BundleItems::Table)
+                    .col(BundleItems::BundleId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BundleItems::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum BundleItems {
+    Table,
+    Id,
+    BundleId,
+    ProductId,
+    Quantity,
+    Optional,
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    Id,
+}
@@ -0,0 +1,59 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(StockSubscriptions::Table)
+                    .col(pk_auto(StockSubscriptions::Id))
+                    .col(integer(StockSubscriptions::ProductId))
+                    .col(string(StockSubscriptions::Email))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-stock_subscriptions-product_ids")
+                            .from(StockSubscriptions::Table, StockSubscriptions::ProductId)
+                            .to(Products::Table, Products::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-stock_subscriptions-product_id-email")
+                    .table(StockSubscriptions::Table)
+                    .col(StockSubscriptions::ProductId)
+                    .col(StockSubscriptions::Email)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(StockSubscriptions::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum StockSubscriptions {
+    Table,
+    Id,
+    ProductId,
+    Email,
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    Id,
+}
@@ -0,0 +1,104 @@
+use loco_rs::schema::table_auto_tz;
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                table_auto_tz(StockMovements::Table)
+                    .col(pk_auto(StockMovements::Id))
+                    .col(integer(StockMovements::ProductId))
+                    .col(float(StockMovements::Quantity))
+                    .col(float(StockMovements::Stock))
+                    .col(string(StockMovements::Reason))
+                    .col(integer_null(StockMovements::UserId))
+                    .col(integer_null(StockMovements::OrderId))
+                    .col(string_null(StockMovements::Note))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-stock_movements-product_ids")
+                            .from(StockMovements::Table, StockMovements::ProductId)
+                            .to(Products::Table, Products::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-stock_movements-user_ids")
+                            .from(StockMovements::Table, StockMovements::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-stock_movements-order_ids")
+                            .from(StockMovements::Table, StockMovements::OrderId)
+                            .to(Orders::Table, Orders::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-stock_movements-product_id")
+                    .table(StockMovements::Table)
+                    .col(StockMovements::ProductId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-stock_movements-order_id")
+                    .table(StockMovements::Table)
+                    .col(StockMovements::OrderId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(StockMovements::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum StockMovements {
+    Table,
+    Id,
+    ProductId,
+    Quantity,
+    Stock,
+    Reason,
+    UserId,
+    OrderId,
+    Note,
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Users {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Orders {
+    Table,
+    Id,
+}
@@ -13,10 +13,23 @@ use loco_rs::{
 };
 use migration::Migrator;
 use sea_orm::DatabaseConnection;
-use std::path::Path;
+use std::{path::Path, sync::Arc};
 
 use crate::{
-    controllers, initializers, models::_entities::users, tasks, workers::downloader::DownloadWorker,
+    common::settings::Settings,
+    controllers, initializers, models,
+    models::_entities::{
+        addresses, api_keys, audit_logs, download_permissions, failed_emails, login_attempts,
+        media, order_items, orders, postmetas, product_downloads, products, recovery_codes,
+        refresh_tokens, reviews, shipping_methods, shipping_zone_locations, shipping_zones,
+        tax_rates, user_identities, users,
+    },
+    tasks,
+    workers::{
+        downloader::DownloadWorker, mail_delivery::MailDeliveryWorker,
+        order_emails::OrderEmailWorker, scheduled_products::ScheduledProductsWorker,
+        stock_webhooks::StockWebhookWorker, thumbnails::ThumbnailWorker,
+    },
 };
 
 pub struct App;
@@ -40,17 +53,41 @@ impl Hooks for App {
         create_app::<Self, Migrator>(mode, environment).await
     }
 
+    async fn after_context(ctx: AppContext) -> Result<AppContext> {
+        let settings = Settings::from_context(&ctx)?;
+        Ok(AppContext {
+            storage: Arc::new(models::media::storage(&settings.media)?),
+            ..ctx
+        })
+    }
+
     async fn initializers(_ctx: &AppContext) -> Result<Vec<Box<dyn Initializer>>> {
-        Ok(vec![Box::new(
-            initializers::view_engine::ViewEngineInitializer,
-        )])
+        Ok(vec![
+            Box::new(initializers::view_engine::ViewEngineInitializer),
+            Box::new(initializers::oauth2::OAuth2Initializer),
+        ])
     }
 
     fn routes(_ctx: &AppContext) -> AppRoutes {
         AppRoutes::with_default_routes() // controller routes below
             .add_route(controllers::cart::routes())
+            .add_route(controllers::checkout::routes())
+            .add_route(controllers::downloads::routes())
+            .add_route(controllers::media::routes())
+            .add_route(controllers::media::file_routes())
             .add_route(controllers::products::routes())
+            .add_route(controllers::products::admin_routes())
+            .add_route(controllers::reviews::routes())
+            .add_route(controllers::reviews::admin_routes())
+            .add_route(controllers::reviews::api_routes())
+            .add_route(controllers::shipping::routes())
+            .add_route(controllers::taxes::routes())
+            .add_route(controllers::orders::routes())
+            .add_route(controllers::emails::routes())
             .add_route(controllers::auth::routes())
+            .add_route(controllers::oauth2::routes())
+            .add_route(controllers::account::routes())
+            .add_route(controllers::account::api_routes())
     }
 
     async fn after_routes(router: AxumRouter, _ctx: &AppContext) -> Result<AxumRouter> {
@@ -68,18 +105,57 @@ impl Hooks for App {
 
     async fn connect_workers(ctx: &AppContext, queue: &Queue) -> Result<()> {
         queue.register(DownloadWorker::build(ctx)).await?;
+        queue.register(OrderEmailWorker::build(ctx)).await?;
+        queue.register(MailDeliveryWorker::build(ctx)).await?;
+        queue.register(ThumbnailWorker::build(ctx)).await?;
+        queue.register(ScheduledProductsWorker::build(ctx)).await?;
+        queue.register(StockWebhookWorker::build(ctx)).await?;
         Ok(())
     }
     fn register_tasks(tasks: &mut Tasks) {
         tasks.register(tasks::seed::SeedData);
+        tasks.register(tasks::product_import::ProductImport);
+        tasks.register(tasks::product_export::ProductExport);
+        tasks.register(tasks::publish_scheduled::PublishScheduled);
     }
     async fn truncate(db: &DatabaseConnection) -> Result<()> {
+        truncate_table(db, download_permissions::Entity).await?;
+        truncate_table(db, order_items::Entity).await?;
+        truncate_table(db, orders::Entity).await?;
+        truncate_table(db, postmetas::Entity).await?;
+        truncate_table(db, product_downloads::Entity).await?;
+        truncate_table(db, reviews::Entity).await?;
+        truncate_table(db, products::Entity).await?;
+        truncate_table(db, addresses::Entity).await?;
+        truncate_table(db, api_keys::Entity).await?;
+        truncate_table(db, audit_logs::Entity).await?;
+        truncate_table(db, failed_emails::Entity).await?;
+        truncate_table(db, login_attempts::Entity).await?;
+        truncate_table(db, media::Entity).await?;
+        truncate_table(db, recovery_codes::Entity).await?;
+        truncate_table(db, refresh_tokens::Entity).await?;
+        truncate_table(db, shipping_methods::Entity).await?;
+        truncate_table(db, shipping_zone_locations::Entity).await?;
+        truncate_table(db, shipping_zones::Entity).await?;
+        truncate_table(db, tax_rates::Entity).await?;
+        truncate_table(db, user_identities::Entity).await?;
         truncate_table(db, users::Entity).await?;
         Ok(())
     }
 
     async fn seed(db: &DatabaseConnection, base: &Path) -> Result<()> {
         db::seed::<users::ActiveModel>(db, &base.join("users.yaml").display().to_string()).await?;
+        db::seed::<products::ActiveModel>(db, &base.join("products.yaml").display().to_string())
+            .await?;
+        db::seed::<postmetas::ActiveModel>(db, &base.join("postmetas.yaml").display().to_string())
+            .await?;
+        db::seed::<orders::ActiveModel>(db, &base.join("orders.yaml").display().to_string())
+            .await?;
+        db::seed::<order_items::ActiveModel>(
+            db,
+            &base.join("order_items.yaml").display().to_string(),
+        )
+        .await?;
         Ok(())
     }
 }
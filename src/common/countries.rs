@@ -0,0 +1,149 @@
+use serde::Serialize;
+
+/// Which address fields a country asks for, and how they look
+#[derive(Clone, Debug, Serialize)]
+pub struct CountryRules {
+    /// two letters code
+    pub code: &'static str,
+    pub name: &'static str,
+    /// what the state is called there, e.g. province
+    pub state_label: &'static str,
+    pub state_required: bool,
+    /// accepted state codes, any when empty
+    pub states: &'static [&'static str],
+    pub postcode_required: bool,
+    /// accepted postcodes once spaces and dashes are removed, `9` standing
+    /// for a digit and `A` for a letter. Any postcode when empty.
+    pub postcode_formats: &'static [&'static str],
+}
+
+/// Rules of the countries not listed in [`COUNTRIES`]
+const DEFAULT_RULES: CountryRules = CountryRules {
+    code: "",
+    name: "",
+    state_label: "State / County",
+    state_required: false,
+    states: &[],
+    postcode_required: true,
+    postcode_formats: &[],
+};
+
+pub const COUNTRIES: &[CountryRules] = &[
+    CountryRules {
+        code: "AU",
+        name: "Australia",
+        state_label: "State / Territory",
+        state_required: true,
+        states: &["ACT", "NSW", "NT", "QLD", "SA", "TAS", "VIC", "WA"],
+        postcode_formats: &["9999"],
+        ..DEFAULT_RULES
+    },
+    CountryRules {
+        code: "CA",
+        name: "Canada",
+        state_label: "Province",
+        state_required: true,
+        states: &[
+            "AB", "BC", "MB", "NB", "NL", "NS", "NT", "NU", "ON", "PE", "QC", "SK", "YT",
+        ],
+        postcode_formats: &["A9A9A9"],
+        ..DEFAULT_RULES
+    },
+    CountryRules {
+        code: "DE",
+        name: "Germany",
+        postcode_formats: &["99999"],
+        ..DEFAULT_RULES
+    },
+    CountryRules {
+        code: "FR",
+        name: "France",
+        postcode_formats: &["99999"],
+        ..DEFAULT_RULES
+    },
+    CountryRules {
+        code: "GB",
+        name: "United Kingdom",
+        state_label: "County",
+        postcode_formats: &["A99AA", "A999AA", "A9A9AA", "AA99AA", "AA999AA", "AA9A9AA"],
+        ..DEFAULT_RULES
+    },
+    CountryRules {
+        code: "HK",
+        name: "Hong Kong",
+        state_label: "Region",
+        postcode_required: false,
+        ..DEFAULT_RULES
+    },
+    CountryRules {
+        code: "IE",
+        name: "Ireland",
+        state_label: "County",
+        postcode_required: false,
+        ..DEFAULT_RULES
+    },
+    CountryRules {
+        code: "NL",
+        name: "Netherlands",
+        postcode_formats: &["9999AA"],
+        ..DEFAULT_RULES
+    },
+    CountryRules {
+        code: "US",
+        name: "United States",
+        state_label: "State",
+        state_required: true,
+        states: &[
+            "AL", "AK", "AZ", "AR", "CA", "CO", "CT", "DE", "DC", "FL", "GA", "HI", "ID", "IL",
+            "IN", "IA", "KS", "KY", "LA", "ME", "MD", "MA", "MI", "MN", "MS", "MO", "MT", "NE",
+            "NV", "NH", "NJ", "NM", "NY", "NC", "ND", "OH", "OK", "OR", "PA", "RI", "SC", "SD",
+            "TN", "TX", "UT", "VT", "VA", "WA", "WV", "WI", "WY",
+        ],
+        postcode_formats: &["99999", "999999999"],
+        ..DEFAULT_RULES
+    },
+];
+
+/// Rules of a country, the default ones when it is not listed
+#[must_use]
+pub fn rules(code: &str) -> &'static CountryRules {
+    COUNTRIES
+        .iter()
+        .find(|rules| rules.code.eq_ignore_ascii_case(code.trim()))
+        .unwrap_or(&DEFAULT_RULES)
+}
+
+fn matches_format(postcode: &str, format: &str) -> bool {
+    postcode.len() == format.len()
+        && postcode.chars().zip(format.chars()).all(|(c, f)| match f {
+            '9' => c.is_ascii_digit(),
+            'A' => c.is_ascii_alphabetic(),
+            _ => c == f,
+        })
+}
+
+impl CountryRules {
+    /// Whether the postcode is written the way of the country
+    #[must_use]
+    pub fn is_valid_postcode(&self, postcode: &str) -> bool {
+        let postcode = postcode
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-')
+            .collect::<String>();
+        self.postcode_formats.is_empty()
+            || self
+                .postcode_formats
+                .iter()
+                .any(|format| matches_format(&postcode, format))
+    }
+
+    /// Whether the state is one of the country, when it has a list
+    #[must_use]
+    pub fn is_valid_state(&self, state: &str) -> bool {
+        self.states.is_empty()
+            || self
+                .states
+                .iter()
+                .any(|code| code.eq_ignore_ascii_case(state.trim()))
+    }
+}
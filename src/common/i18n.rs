@@ -0,0 +1,29 @@
+use std::sync::OnceLock;
+
+use fluent_templates::ArcLoader;
+use loco_rs::{Error, Result};
+
+pub const I18N_DIR: &str = "assets/i18n";
+pub const I18N_SHARED: &str = "assets/i18n/shared.ftl";
+/// Language used when none is configured or asked for
+pub const DEFAULT_LOCALE: &str = "en-US";
+
+static LOADER: OnceLock<ArcLoader> = OnceLock::new();
+
+/// The translations of `assets/i18n`, loaded once and shared by the views
+/// and the mailers
+///
+/// # Errors
+///
+/// When the translation files cannot be read or parsed
+pub fn loader() -> Result<&'static ArcLoader> {
+    if let Some(loader) = LOADER.get() {
+        return Ok(loader);
+    }
+    let loader = ArcLoader::builder(&I18N_DIR, unic_langid::langid!("en-US"))
+        .shared_resources(Some(&[I18N_SHARED.into()]))
+        .customize(|bundle| bundle.set_use_isolating(false))
+        .build()
+        .map_err(|e| Error::string(&e.to_string()))?;
+    Ok(LOADER.get_or_init(|| loader))
+}
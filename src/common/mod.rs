@@ -0,0 +1,6 @@
+pub mod countries;
+pub mod i18n;
+pub mod payments;
+pub mod product_csv;
+pub mod settings;
+pub mod stock_alerts;
@@ -0,0 +1,50 @@
+use serde::Serialize;
+
+use super::settings::Settings;
+use crate::models::orders::{STATUS_ON_HOLD, STATUS_PROCESSING};
+
+/// An offline way of paying for an order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct PaymentMethod {
+    pub id: &'static str,
+    pub title: &'static str,
+    /// shown to the customer choosing the method
+    pub description: &'static str,
+    /// status of the order once placed with this method
+    pub order_status: &'static str,
+}
+
+pub const BACS: PaymentMethod = PaymentMethod {
+    id: "bacs",
+    title: "Direct bank transfer",
+    description: "Make your payment directly into our bank account, using your order number \
+                  as the payment reference. Your order ships once the funds have cleared.",
+    order_status: STATUS_ON_HOLD,
+};
+
+pub const CHEQUE: PaymentMethod = PaymentMethod {
+    id: "cheque",
+    title: "Check payments",
+    description: "Please send a check to our store, your order ships once it is received.",
+    order_status: STATUS_ON_HOLD,
+};
+
+pub const COD: PaymentMethod = PaymentMethod {
+    id: "cod",
+    title: "Cash on delivery",
+    description: "Pay with cash upon delivery.",
+    order_status: STATUS_PROCESSING,
+};
+
+pub const METHODS: [PaymentMethod; 3] = [BACS, CHEQUE, COD];
+
+/// The methods enabled in the settings, in the order they are offered
+#[must_use]
+pub fn enabled(settings: &Settings) -> Vec<PaymentMethod> {
+    settings
+        .payments
+        .methods
+        .iter()
+        .filter_map(|id| METHODS.iter().find(|method| method.id == id).copied())
+        .collect()
+}
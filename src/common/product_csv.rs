@@ -0,0 +1,672 @@
+//! Products in the CSV format of the WooCommerce product importer and
+//! exporter, so catalogs can move between both stores.
+//!
+//! Only the columns this store knows are read, the others are ignored. The
+//! type column combines the product type with its `virtual` and
+//! `downloadable` flags, like `simple, virtual`. Variations point to their
+//! variable product with the `Parent` column, either its SKU or `id:<id>`.
+//! The products of a bundle are not part of the file, only how it is priced.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::{Read, Write},
+};
+
+use loco_rs::{model::ModelValidation, prelude::*};
+use sea_orm::{QueryOrder, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use slug::slugify;
+
+use super::stock_alerts;
+use crate::{
+    controllers::products::{load_view, save_optional_meta, save_product_meta, Params},
+    models::{
+        _entities::{postmetas, products, users},
+        bundle_items::{PRICINGS, PRICING_DISCOUNT},
+        product_revisions,
+        products::{
+            unique_slug, ProductStatus, BACKORDERS_NO, BACKORDERS_NOTIFY, BACKORDERS_YES, TYPES,
+            TYPE_BUNDLE, TYPE_EXTERNAL, TYPE_GROUPED, TYPE_SIMPLE, TYPE_VARIABLE, TYPE_VARIATION,
+        },
+        stock_movements, tax_rates,
+    },
+};
+
+/// A line of the CSV file
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ProductRow {
+    #[serde(rename = "Type")]
+    pub kind: Option<String>,
+    #[serde(rename = "SKU")]
+    pub sku: Option<String>,
+    #[serde(rename = "Name")]
+    pub name: Option<String>,
+    #[serde(rename = "Slug")]
+    pub slug: Option<String>,
+    /// 1 when published, 0 for a draft and -1 when private
+    #[serde(rename = "Published")]
+    pub published: Option<i8>,
+    #[serde(rename = "Short description")]
+    pub short_description: Option<String>,
+    #[serde(rename = "Tax class")]
+    pub tax_class: Option<String>,
+    #[serde(rename = "Stock")]
+    pub stock: Option<f32>,
+    /// `1`, `0` or `notify`, as written by WooCommerce
+    #[serde(rename = "Backorders allowed?")]
+    pub backorders: Option<String>,
+    #[serde(rename = "Low stock amount")]
+    pub low_stock_amount: Option<f32>,
+    #[serde(rename = "Weight (kg)")]
+    pub weight: Option<f32>,
+    #[serde(rename = "Length (cm)")]
+    pub length: Option<f32>,
+    #[serde(rename = "Width (cm)")]
+    pub width: Option<f32>,
+    #[serde(rename = "Height (cm)")]
+    pub height: Option<f32>,
+    #[serde(rename = "Sale price")]
+    pub sale_price: Option<f32>,
+    #[serde(rename = "Regular price")]
+    pub regular_price: Option<f32>,
+    /// comma separated, `>` separating a category from its parents
+    #[serde(rename = "Categories")]
+    pub categories: Option<String>,
+    /// comma separated image links, the first one being the main image
+    #[serde(rename = "Images")]
+    pub images: Option<String>,
+    #[serde(rename = "Parent")]
+    pub parent: Option<String>,
+    /// comma separated SKUs, or `id:` followed by an id, of the products of
+    /// a grouped product
+    #[serde(rename = "Grouped products")]
+    pub grouped_products: Option<String>,
+    #[serde(rename = "External URL")]
+    pub external_url: Option<String>,
+    #[serde(rename = "Button text")]
+    pub button_text: Option<String>,
+    /// `fixed` or `discount`, with the percentage of the discount
+    #[serde(rename = "Bundle pricing")]
+    pub bundle_pricing: Option<String>,
+    #[serde(rename = "Bundle discount")]
+    pub bundle_discount: Option<f32>,
+    #[serde(rename = "Download limit")]
+    pub download_limit: Option<i32>,
+    #[serde(rename = "Download expiry days")]
+    pub download_expiry: Option<i32>,
+    #[serde(rename = "Attribute 1 name")]
+    pub attribute_name: Option<String>,
+    #[serde(rename = "Attribute 1 value(s)")]
+    pub attribute_values: Option<String>,
+}
+
+/// A line of the file which could not be imported
+#[derive(Debug)]
+pub struct RowError {
+    pub line: u64,
+    pub message: String,
+}
+
+/// What an import did, or would do on a dry run
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub created: usize,
+    pub updated: usize,
+    pub errors: Vec<RowError>,
+}
+
+/// The products the lines already read create, so the next ones can refer
+/// to them even on a dry run where they are not saved
+#[derive(Default)]
+struct ImportState {
+    /// product of each SKU, without id on a dry run
+    skus: HashMap<String, Option<i32>>,
+    variable_skus: HashSet<String>,
+    slugs: HashSet<String>,
+}
+
+/// A line checked and ready to be saved
+struct ImportRow {
+    /// product of the same SKU, updated instead of created
+    id: Option<i32>,
+    update: bool,
+    params: Params,
+    slug: Option<String>,
+    parent_id: Option<i32>,
+    metas: Vec<(&'static str, Option<String>)>,
+}
+
+fn invalid(message: impl Into<String>) -> ModelError {
+    ModelError::ModelValidation {
+        errors: ModelValidation {
+            code: "row".to_string(),
+            message: Some(message.into()),
+        },
+    }
+}
+
+fn trimmed(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(ToString::to_string)
+}
+
+/// Normalizes a comma separated list, none when it is empty
+fn list(value: Option<&str>) -> Option<String> {
+    let items: Vec<&str> = value
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .collect();
+    (!items.is_empty()).then(|| items.join(", "))
+}
+
+async fn find_by_sku(db: &DatabaseConnection, sku: &str) -> ModelResult<Option<products::Model>> {
+    let Some(meta) = postmetas::Entity::find()
+        .filter(postmetas::Column::MetaKey.eq("_sku"))
+        .filter(postmetas::Column::MetaValue.eq(sku))
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+    Ok(products::Entity::find_by_id(meta.product_id)
+        .one(db)
+        .await?)
+}
+
+/// Finds the variable product of a variation, none on a dry run when it is
+/// created by a previous line
+async fn find_parent(
+    db: &DatabaseConnection,
+    state: &ImportState,
+    parent: &str,
+) -> ModelResult<Option<i32>> {
+    if let Some(id) = parent.strip_prefix("id:") {
+        let product = match id.trim().parse::<i32>() {
+            Ok(id) => products::Entity::find_by_id(id).one(db).await?,
+            Err(_) => None,
+        };
+        return match product {
+            Some(product) if product.product_type.as_deref() == Some(TYPE_VARIABLE) => {
+                Ok(Some(product.id))
+            }
+            _ => Err(invalid(format!(
+                "parent {parent} is not a variable product"
+            ))),
+        };
+    }
+    if let Some(id) = state.skus.get(parent) {
+        return if state.variable_skus.contains(parent) {
+            Ok(*id)
+        } else {
+            Err(invalid(format!(
+                "parent {parent} is not a variable product"
+            )))
+        };
+    }
+    match find_by_sku(db, parent).await? {
+        Some(product) if product.product_type.as_deref() == Some(TYPE_VARIABLE) => {
+            Ok(Some(product.id))
+        }
+        _ => Err(invalid(format!(
+            "parent {parent} is not a variable product"
+        ))),
+    }
+}
+
+/// Finds the simple products of a grouped product, without the ids of the
+/// ones created by a previous line on a dry run
+async fn find_children(
+    db: &DatabaseConnection,
+    state: &ImportState,
+    children: &str,
+) -> ModelResult<Vec<i32>> {
+    let mut ids = vec![];
+    for child in children
+        .split(',')
+        .map(str::trim)
+        .filter(|child| !child.is_empty())
+    {
+        let not_simple = || invalid(format!("grouped product {child} is not a simple product"));
+        if let Some(id) = child.strip_prefix("id:") {
+            let product = match id.trim().parse::<i32>() {
+                Ok(id) => products::Entity::find_by_id(id).one(db).await?,
+                Err(_) => None,
+            };
+            match product {
+                Some(product) if product.product_type.as_deref() == Some(TYPE_SIMPLE) => {
+                    ids.push(product.id);
+                }
+                _ => return Err(not_simple()),
+            }
+        } else if let Some(id) = state.skus.get(child) {
+            if state.variable_skus.contains(child) {
+                return Err(not_simple());
+            }
+            ids.extend(id);
+        } else {
+            match find_by_sku(db, child).await? {
+                Some(product) if product.product_type.as_deref() == Some(TYPE_SIMPLE) => {
+                    ids.push(product.id);
+                }
+                _ => return Err(not_simple()),
+            }
+        }
+    }
+    Ok(ids)
+}
+
+/// Checks a line, and finds the product it updates
+async fn check_row(
+    db: &DatabaseConnection,
+    state: &ImportState,
+    row: ProductRow,
+) -> ModelResult<ImportRow> {
+    let title = trimmed(row.name.as_deref()).ok_or_else(|| invalid("Name is required"))?;
+
+    let mut kind = TYPE_SIMPLE;
+    let mut is_virtual = false;
+    let mut downloadable = false;
+    for part in row.kind.as_deref().unwrap_or_default().split(',') {
+        match part.trim() {
+            "" => {}
+            "virtual" => is_virtual = true,
+            "downloadable" => downloadable = true,
+            part => {
+                kind = TYPES
+                    .into_iter()
+                    .find(|kind| *kind == part)
+                    .ok_or_else(|| invalid(format!("unknown type {part}")))?;
+            }
+        }
+    }
+    let status = match row.published {
+        None | Some(0) => ProductStatus::Draft,
+        Some(1) => ProductStatus::Publish,
+        Some(-1) => ProductStatus::Private,
+        Some(published) => {
+            return Err(invalid(format!(
+                "Published must be 1, 0 or -1, not {published}"
+            )))
+        }
+    };
+    let backorders = match trimmed(row.backorders.as_deref()).as_deref() {
+        None | Some("0") => BACKORDERS_NO,
+        Some("1") => BACKORDERS_YES,
+        Some(BACKORDERS_NOTIFY) => BACKORDERS_NOTIFY,
+        Some(backorders) => {
+            return Err(invalid(format!(
+                "Backorders allowed? must be 1, 0 or notify, not {backorders}"
+            )))
+        }
+    };
+    let tax_class = trimmed(row.tax_class.as_deref());
+    if let Some(tax_class) = &tax_class {
+        if !tax_rates::CLASSES.contains(&tax_class.as_str()) {
+            return Err(invalid(format!("unknown tax class {tax_class}")));
+        }
+    }
+    for (column, value) in [
+        ("Regular price", row.regular_price),
+        ("Sale price", row.sale_price),
+        ("Low stock amount", row.low_stock_amount),
+    ] {
+        if value.is_some_and(|value| value < 0.0) {
+            return Err(invalid(format!("{column} cannot be negative")));
+        }
+    }
+
+    let sku = trimmed(row.sku.as_deref());
+    let (id, update) = match &sku {
+        Some(sku) => match state.skus.get(sku) {
+            Some(id) => (*id, true),
+            None => {
+                let id = find_by_sku(db, sku).await?.map(|product| product.id);
+                (id, id.is_some())
+            }
+        },
+        None => (None, false),
+    };
+
+    let slug = trimmed(row.slug.as_deref()).map(slugify);
+    if let Some(slug) = &slug {
+        // slugs other products have or had are taken
+        let taken = unique_slug(db, slug, id).await? != *slug;
+        if taken || state.slugs.contains(slug) {
+            return Err(invalid(format!("slug {slug} is used by another product")));
+        }
+    }
+
+    let parent = trimmed(row.parent.as_deref());
+    let parent_id = match (kind == TYPE_VARIATION, parent) {
+        (true, Some(parent)) => find_parent(db, state, &parent).await?,
+        (true, None) => return Err(invalid("a variation needs a Parent")),
+        (false, Some(_)) => return Err(invalid("only variations have a Parent")),
+        (false, None) => None,
+    };
+
+    let children = match trimmed(row.grouped_products.as_deref()) {
+        Some(children) if kind == TYPE_GROUPED => find_children(db, state, &children).await?,
+        Some(_) => return Err(invalid("only grouped products have Grouped products")),
+        None => vec![],
+    };
+    let external_url = trimmed(row.external_url.as_deref());
+    if kind == TYPE_EXTERNAL
+        && !external_url
+            .as_deref()
+            .is_some_and(|url| url.starts_with("https://") || url.starts_with("http://"))
+    {
+        return Err(invalid("External URL must be a http or https link"));
+    }
+    let bundle_pricing = trimmed(row.bundle_pricing.as_deref());
+    match &bundle_pricing {
+        Some(_) if kind != TYPE_BUNDLE => {
+            return Err(invalid("only bundles have a Bundle pricing"));
+        }
+        Some(pricing) if !PRICINGS.contains(&pricing.as_str()) => {
+            return Err(invalid(format!("unknown bundle pricing {pricing}")));
+        }
+        _ => {}
+    }
+    if row
+        .bundle_discount
+        .is_some_and(|discount| !(0.0..=100.0).contains(&discount))
+    {
+        return Err(invalid("Bundle discount must be from 0 to 100"));
+    }
+
+    let flag = |value: bool| value.then(|| "yes".to_string());
+    Ok(ImportRow {
+        id,
+        update,
+        params: Params {
+            title,
+            excerpt: trimmed(row.short_description.as_deref()),
+            status: Some(status),
+            published_at: None,
+            product_type: Some(kind.to_string()),
+            slug: slug.clone(),
+            _sku: sku,
+            _regular_price: row.regular_price,
+            _sale_price: row.sale_price.map(|price| price.to_string()),
+            _stock: row.stock,
+            _backorders: Some(backorders.to_string()),
+            _low_stock_amount: row.low_stock_amount,
+            _weight: row.weight,
+            _length: row.length,
+            _width: row.width,
+            _height: row.height,
+            _tax_class: tax_class,
+            _virtual: flag(is_virtual),
+            _downloadable: flag(downloadable),
+            _download_limit: row.download_limit,
+            _download_expiry: row.download_expiry,
+            _children: Some(
+                children
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            _product_url: external_url,
+            _button_text: trimmed(row.button_text.as_deref()),
+            _bundle_pricing: bundle_pricing,
+            _bundle_discount: row.bundle_discount,
+        },
+        slug,
+        parent_id,
+        metas: vec![
+            ("_categories", list(row.categories.as_deref())),
+            ("_images", list(row.images.as_deref())),
+            ("_attribute_name", trimmed(row.attribute_name.as_deref())),
+            ("_attribute_values", list(row.attribute_values.as_deref())),
+        ],
+    })
+}
+
+/// Creates or updates the product of a checked line, the created ones
+/// being credited to `author_id`. Nothing of the line is saved when it fails
+/// halfway.
+async fn save_row(ctx: &AppContext, row: ImportRow, author_id: i32) -> Result<i32> {
+    let txn = ctx.db.begin().await?;
+    let mut item = match row.id {
+        Some(id) => products::Entity::find_by_id(id)
+            .one(&txn)
+            .await?
+            .ok_or_else(|| Error::NotFound)?
+            .into_active_model(),
+        None => products::ActiveModel {
+            author_id: ActiveValue::set(author_id),
+            ..Default::default()
+        },
+    };
+    row.params.update(&mut item);
+    let mut item = match row.id {
+        Some(_) => item.update(&txn).await?,
+        None => item.insert(&txn).await?,
+    };
+    // the slug of the file replaces the current one, which then redirects
+    if row.slug.is_some() || item.slug.is_none() {
+        item = item
+            .set_slug(&txn, row.slug.as_deref().unwrap_or_default())
+            .await?;
+    }
+
+    // the product form keeps a missing sale price, the file removes it
+    let clear_sale_price = row.params._sale_price.is_none();
+    let (stock_before, stock_after) = save_product_meta(
+        &txn,
+        item.id,
+        row.params,
+        stock_movements::REASON_IMPORT,
+        stock_movements::Source::default(),
+    )
+    .await?;
+    if clear_sale_price {
+        save_optional_meta(&txn, item.id, "_sale_price", None).await?;
+    }
+    let parent_id = row.parent_id.map(|id| id.to_string());
+    save_optional_meta(&txn, item.id, "_parent_id", parent_id).await?;
+    for (key, value) in row.metas {
+        save_optional_meta(&txn, item.id, key, value).await?;
+    }
+    product_revisions::Model::record(&txn, item.id, None).await?;
+    txn.commit().await?;
+    stock_alerts::stock_changed(ctx, item.id, &stock_before, &stock_after).await?;
+
+    Ok(item.id)
+}
+
+/// Imports the products of a CSV file, updating the ones of the same SKU
+/// and crediting the new ones to `author`. Lines which cannot be imported
+/// are reported and skipped, the others are saved unless `dry_run` is set.
+///
+/// # Errors
+///
+/// When the file cannot be read or has DB query error
+pub async fn import(
+    ctx: &AppContext,
+    reader: impl Read + Send,
+    dry_run: bool,
+    author: &users::Model,
+) -> Result<ImportReport> {
+    let mut reader = csv::Reader::from_reader(reader);
+    let headers = reader.headers().map_err(Error::wrap)?.clone();
+    let mut state = ImportState::default();
+    let mut report = ImportReport::default();
+
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => {
+                report.errors.push(RowError {
+                    line: err.position().map_or(0, csv::Position::line),
+                    message: err.to_string(),
+                });
+                continue;
+            }
+        };
+        let line = record.position().map_or(0, csv::Position::line);
+        let row = match record.deserialize::<ProductRow>(Some(&headers)) {
+            Ok(row) => row,
+            Err(err) => {
+                report.errors.push(RowError {
+                    line,
+                    message: err.to_string(),
+                });
+                continue;
+            }
+        };
+
+        let row = match check_row(&ctx.db, &state, row).await {
+            Ok(row) => row,
+            Err(ModelError::ModelValidation { errors }) => {
+                report.errors.push(RowError {
+                    line,
+                    message: errors.message.unwrap_or(errors.code),
+                });
+                continue;
+            }
+            Err(err) => return Err(err.into()),
+        };
+        if row.update {
+            report.updated += 1;
+        } else {
+            report.created += 1;
+        }
+
+        let sku = row.params._sku.clone();
+        let variable = row.params.product_type.as_deref() == Some(TYPE_VARIABLE);
+        if let Some(slug) = &row.slug {
+            state.slugs.insert(slug.clone());
+        }
+        let id = if dry_run {
+            row.id
+        } else {
+            Some(save_row(ctx, row, author.id).await?)
+        };
+        if let Some(sku) = sku {
+            if variable {
+                state.variable_skus.insert(sku.clone());
+            }
+            state.skus.insert(sku, id);
+        }
+    }
+
+    Ok(report)
+}
+
+/// Writes every product but the trashed ones to a CSV file the import reads
+/// back, returning how many were written
+///
+/// # Errors
+///
+/// When the file cannot be written or has DB query error
+pub async fn export(ctx: &AppContext, writer: impl Write) -> Result<usize> {
+    let items = products::Entity::find()
+        .filter(products::Column::Status.ne(ProductStatus::Trash.as_str()))
+        .order_by_asc(products::Column::Id)
+        .all(&ctx.db)
+        .await?;
+    let skus: HashMap<i32, String> = postmetas::Entity::find()
+        .filter(postmetas::Column::MetaKey.eq("_sku"))
+        .all(&ctx.db)
+        .await?
+        .into_iter()
+        .filter_map(|meta| meta.meta_value.map(|sku| (meta.product_id, sku)))
+        .collect();
+
+    let mut writer = csv::Writer::from_writer(writer);
+    let count = items.len();
+    for item in items {
+        let metas: HashMap<String, String> = item
+            .find_related(postmetas::Entity)
+            .all(&ctx.db)
+            .await?
+            .into_iter()
+            .filter_map(|meta| meta.meta_key.zip(meta.meta_value))
+            .collect();
+        let meta = |key: &str| metas.get(key).cloned();
+        let product = load_view(ctx, item).await?;
+
+        let mut kind = vec![product.product_type.as_str()];
+        if product.is_virtual {
+            kind.push("virtual");
+        }
+        if product.downloadable {
+            kind.push("downloadable");
+        }
+        let sku_of = |id: i32| skus.get(&id).cloned().unwrap_or_else(|| format!("id:{id}"));
+        let parent = meta("_parent_id")
+            .and_then(|id| id.parse::<i32>().ok())
+            .map(sku_of);
+        let grouped_products = (!product.children.is_empty()).then(|| {
+            product
+                .children
+                .iter()
+                .map(|id| sku_of(*id))
+                .collect::<Vec<_>>()
+                .join(", ")
+        });
+
+        // the prices of such bundles are the ones of their products
+        let priced_by_products =
+            product.product_type == TYPE_BUNDLE && product.bundle_pricing == PRICING_DISCOUNT;
+
+        writer
+            .serialize(ProductRow {
+                kind: Some(kind.join(", ")),
+                sku: product.sku,
+                name: Some(product.name),
+                slug: Some(product.slug),
+                published: Some(match product.status {
+                    ProductStatus::Publish => 1,
+                    ProductStatus::Private => -1,
+                    _ => 0,
+                }),
+                short_description: Some(product.excerpt),
+                // like WooCommerce, the standard class is written as no class
+                tax_class: Some(product.tax_class)
+                    .filter(|class| class != tax_rates::CLASS_STANDARD),
+                stock: product.stock,
+                backorders: Some(
+                    match product.backorders.as_str() {
+                        BACKORDERS_YES => "1",
+                        BACKORDERS_NOTIFY => BACKORDERS_NOTIFY,
+                        _ => "0",
+                    }
+                    .to_string(),
+                ),
+                low_stock_amount: product.low_stock_amount,
+                weight: product.weight,
+                length: product.length,
+                width: product.width,
+                height: product.height,
+                sale_price: product.sale_price.filter(|_| !priced_by_products),
+                regular_price: product.regular_price.filter(|_| !priced_by_products),
+                categories: meta("_categories"),
+                images: meta("_images"),
+                parent,
+                grouped_products,
+                external_url: product.product_url,
+                button_text: product.button_text,
+                bundle_pricing: (product.product_type == TYPE_BUNDLE)
+                    .then_some(product.bundle_pricing),
+                bundle_discount: product
+                    .bundle_discount
+                    .filter(|_| product.product_type == TYPE_BUNDLE),
+                download_limit: product.download_limit,
+                download_expiry: product.download_expiry,
+                attribute_name: meta("_attribute_name"),
+                attribute_values: meta("_attribute_values"),
+            })
+            .map_err(Error::wrap)?;
+    }
+    writer.flush()?;
+
+    Ok(count)
+}
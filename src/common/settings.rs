@@ -0,0 +1,159 @@
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Application settings, read from the `settings` section of the config
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Settings {
+    #[serde(default)]
+    pub two_factor: TwoFactorSettings,
+    #[serde(default)]
+    pub tax: TaxSettings,
+    #[serde(default)]
+    pub payments: PaymentSettings,
+    #[serde(default)]
+    pub checkout: CheckoutSettings,
+    #[serde(default)]
+    pub emails: EmailSettings,
+    #[serde(default)]
+    pub mail_delivery: MailDeliverySettings,
+    #[serde(default)]
+    pub downloads: DownloadSettings,
+    #[serde(default)]
+    pub media: MediaSettings,
+    #[serde(default)]
+    pub inventory: InventorySettings,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct TwoFactorSettings {
+    /// Roles which cannot log in without two-factor authentication, users
+    /// having one of them are asked to enroll at their next login
+    #[serde(default)]
+    pub required_roles: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct TaxSettings {
+    /// Whether product prices are entered with their taxes included, taxes
+    /// are then taken out of them instead of added on top. Shipping costs
+    /// never include taxes.
+    #[serde(default)]
+    pub prices_include_tax: bool,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct PaymentSettings {
+    /// Ids of the payment methods offered at checkout, in this order. Orders
+    /// are placed without payment method when none is enabled.
+    #[serde(default)]
+    pub methods: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct CheckoutSettings {
+    /// Page of the terms and conditions, customers have to accept them to
+    /// place an order when set
+    #[serde(default)]
+    pub terms_url: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct EmailSettings {
+    /// Address of the store staff, receiving the new order and stock
+    /// emails. They are not sent when missing.
+    #[serde(default)]
+    pub admin_email: Option<String>,
+    /// Language of the emails, one of the locales of `assets/i18n`, en-US
+    /// when missing
+    #[serde(default)]
+    pub locale: Option<String>,
+}
+
+/// Where the emails end up
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MailSink {
+    /// the `mailer` section of the config, usually SMTP
+    #[default]
+    Mailer,
+    /// one JSON file per email, in `dir`
+    File,
+    /// kept in memory, for the tests
+    Mailbox,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct MailDeliverySettings {
+    #[serde(default)]
+    pub sink: MailSink,
+    /// Directory of the file sink, `tmp/mails` when missing
+    #[serde(default)]
+    pub dir: Option<String>,
+    /// Attempts at sending an email before keeping it as failed, 5 when
+    /// missing
+    #[serde(default)]
+    pub max_attempts: Option<u32>,
+    /// Wait before the first retry in milliseconds, doubled at every retry,
+    /// 1000 when missing
+    #[serde(default)]
+    pub backoff_ms: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct DownloadSettings {
+    /// Directory of the files of the downloadable products,
+    /// `storage/downloads` when missing
+    #[serde(default)]
+    pub dir: Option<String>,
+    /// How long the download links shown to the customers work, in hours, 24
+    /// when missing. Their access to the files may expire earlier.
+    #[serde(default)]
+    pub link_ttl_hours: Option<i64>,
+}
+
+/// Where the media library keeps the uploaded images and their thumbnails
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaStorage {
+    /// files in `dir` on the local disk
+    #[default]
+    Local,
+    /// kept in memory, for the tests
+    Memory,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct MediaSettings {
+    #[serde(default)]
+    pub storage: MediaStorage,
+    /// Directory of the local storage, `storage/media` when missing
+    #[serde(default)]
+    pub dir: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct InventorySettings {
+    /// Stock at or under which a product is low in stock, for the products
+    /// without a threshold of their own, 2 when missing
+    #[serde(default)]
+    pub low_stock_amount: Option<f32>,
+    /// Address the stock alerts are posted to as JSON, along with the low
+    /// stock emails. Nothing is posted when missing.
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+}
+
+impl Settings {
+    /// Reads the settings of the app config, defaults apply when the section
+    /// is missing
+    ///
+    /// # Errors
+    ///
+    /// When the section does not match the expected settings
+    pub fn from_context(ctx: &AppContext) -> Result<Self> {
+        match &ctx.config.settings {
+            Some(settings) => Ok(serde_json::from_value(settings.clone())?),
+            None => Ok(Self::default()),
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Alerts about the stock of the products. The store staff hear of the
+//! products getting low or out of stock, by email and on the webhook of the
+//! `inventory` settings, and the customers who asked are emailed when a
+//! product is back in stock.
+
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::settings::Settings;
+use crate::{
+    models::{_entities::postmetas, products::LOW_STOCK_AMOUNT_META, stock_movements::StockChange},
+    workers::{
+        order_emails::{OrderEmailWorker, OrderEmailWorkerArgs},
+        stock_webhooks::{StockWebhookWorker, StockWebhookWorkerArgs},
+    },
+};
+
+/// Low stock threshold of the store when the settings have none
+pub const DEFAULT_LOW_STOCK_AMOUNT: f32 = 2.0;
+
+const IN_STOCK: &str = "instock";
+
+/// The stock of a product and its stock status, at some point
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StockLevel {
+    /// none when the stock is not managed
+    pub stock: Option<f32>,
+    pub status: Option<String>,
+}
+
+impl StockLevel {
+    /// Reads the stock metas of a product
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn load<C>(db: &C, product_id: i32) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let metas = postmetas::Entity::find()
+            .filter(postmetas::Column::ProductId.eq(product_id))
+            .filter(postmetas::Column::MetaKey.is_in(["_stock", "_stock_status"]))
+            .all(db)
+            .await?;
+        let meta = |key: &str| {
+            metas
+                .iter()
+                .find(|meta| meta.meta_key.as_deref() == Some(key))
+                .and_then(|meta| meta.meta_value.clone())
+        };
+        Ok(Self {
+            stock: meta("_stock").and_then(|stock| stock.parse().ok()),
+            status: meta("_stock_status"),
+        })
+    }
+}
+
+/// What happened to the stock of a product
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum StockAlert {
+    /// the stock went down to the low stock threshold or under
+    LowStock { stock: f32 },
+    /// the stock went down to zero or under
+    NoStock { stock: f32 },
+    /// the product can be bought again
+    BackInStock,
+}
+
+impl StockAlert {
+    /// The alert a stock change is worth, none unless the stock crosses the
+    /// threshold or zero going down, or the product gets back in stock
+    #[must_use]
+    pub fn for_change(
+        before: &StockLevel,
+        after: &StockLevel,
+        low_stock_amount: f32,
+    ) -> Option<Self> {
+        let in_stock = |level: &StockLevel| level.status.as_deref() == Some(IN_STOCK);
+        if in_stock(after) && before.status.is_some() && !in_stock(before) {
+            return Some(Self::BackInStock);
+        }
+
+        let (Some(before), Some(stock)) = (before.stock, after.stock) else {
+            return None;
+        };
+        if stock <= 0.0 && before > 0.0 {
+            Some(Self::NoStock { stock })
+        } else if stock > 0.0 && stock <= low_stock_amount && before > low_stock_amount {
+            Some(Self::LowStock { stock })
+        } else {
+            None
+        }
+    }
+}
+
+/// The low stock threshold of a product, its own or the store one
+///
+/// # Errors
+///
+/// When the settings are invalid or has DB query error
+pub async fn low_stock_amount(ctx: &AppContext, product_id: i32) -> Result<f32> {
+    let meta = postmetas::Entity::find()
+        .filter(postmetas::Column::ProductId.eq(product_id))
+        .filter(postmetas::Column::MetaKey.eq(LOW_STOCK_AMOUNT_META))
+        .one(&ctx.db)
+        .await?;
+    if let Some(amount) = meta
+        .and_then(|meta| meta.meta_value)
+        .and_then(|amount| amount.parse().ok())
+    {
+        return Ok(amount);
+    }
+    let settings = Settings::from_context(ctx)?;
+    Ok(settings
+        .inventory
+        .low_stock_amount
+        .unwrap_or(DEFAULT_LOW_STOCK_AMOUNT))
+}
+
+/// Queues the emails and the webhook call of a stock change, if it is worth
+/// an alert
+///
+/// # Errors
+///
+/// When the alerts cannot be queued or has DB query error
+pub async fn stock_changed(
+    ctx: &AppContext,
+    product_id: i32,
+    before: &StockLevel,
+    after: &StockLevel,
+) -> Result<()> {
+    let amount = low_stock_amount(ctx, product_id).await?;
+    let Some(alert) = StockAlert::for_change(before, after, amount) else {
+        return Ok(());
+    };
+    tracing::info!(product_id, ?alert, "stock alert");
+
+    let email = match alert {
+        StockAlert::LowStock { stock } => OrderEmailWorkerArgs::LowStock { product_id, stock },
+        StockAlert::NoStock { .. } => OrderEmailWorkerArgs::NoStock { product_id },
+        StockAlert::BackInStock => OrderEmailWorkerArgs::BackInStock { product_id },
+    };
+    OrderEmailWorker::perform_later(ctx, email).await?;
+
+    let settings = Settings::from_context(ctx)?;
+    if settings.inventory.webhook_url.is_some() {
+        StockWebhookWorker::perform_later(ctx, StockWebhookWorkerArgs { product_id, alert })
+            .await?;
+    }
+    Ok(())
+}
+
+/// Queues the alerts of the movements recorded in the stock ledger
+///
+/// # Errors
+///
+/// When the alerts cannot be queued or has DB query error
+pub async fn stock_moved(ctx: &AppContext, changes: &[StockChange]) -> Result<()> {
+    for change in changes {
+        stock_changed(
+            ctx,
+            change.movement.product_id,
+            &change.before,
+            &change.after,
+        )
+        .await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,636 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+use axum::{debug_handler, extract::Form, http::StatusCode, response::Redirect, Extension};
+use axum_extra::extract::CookieJar;
+use axum_session::{Session, SessionNullPool};
+use loco_rs::{
+    controller::ErrorDetail,
+    prelude::{cookie::Cookie, *},
+};
+use sea_orm::PaginatorTrait;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    auth::{add_jwt_cookie, client_ip, current_user, jwt_cookie_name, two_factor_setup},
+    downloads,
+};
+use crate::{
+    common::settings::Settings,
+    initializers::oauth2::OAuth2Providers,
+    mailers::auth::AuthMailer,
+    models::{
+        _entities::products,
+        addresses::{self, AddressParams},
+        api_keys::{self, ApiKeyUser},
+        audit_logs, orders, recovery_codes, user_identities,
+        users::SecondFactor,
+    },
+    views::{self, auth::CurrentResponse},
+};
+
+/// How many orders are listed on the account overview
+const RECENT_ORDERS: usize = 5;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProfileParams {
+    pub name: String,
+    pub email: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PasswordParams {
+    pub current_password: String,
+    pub password: String,
+    pub password_confirmation: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeleteAccountParams {
+    pub password: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ApiKeyParams {
+    pub name: String,
+    pub scope: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EnableTwoFactorParams {
+    pub code: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ConfirmPasswordParams {
+    pub password: String,
+}
+
+fn take_errors(session: &Session<SessionNullPool>) -> serde_json::Value {
+    let errors = session
+        .get::<serde_json::Value>("errors")
+        .unwrap_or(data!({}));
+    session.set("errors", data!({}));
+    errors
+}
+
+#[debug_handler]
+pub async fn show(
+    auth: auth::JWT,
+    session: Session<SessionNullPool>,
+    ViewEngine(v): ViewEngine<TeraView>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = current_user(&ctx, &auth).await?;
+    let mut orders = orders::Model::find_by_user(&ctx.db, user.id).await?;
+    orders.truncate(RECENT_ORDERS);
+    let errors = take_errors(&session);
+
+    views::account::show(
+        &v,
+        &CurrentResponse::new(&user),
+        user.pending_email.as_deref(),
+        &orders,
+        &errors,
+    )
+}
+
+/// Updates the user name. A new email is only used once confirmed from the
+/// link sent to it.
+#[debug_handler]
+pub async fn update_profile(
+    auth: auth::JWT,
+    session: Session<SessionNullPool>,
+    remote_ip: RemoteIP,
+    State(ctx): State<AppContext>,
+    Form(params): Form<ProfileParams>,
+) -> Result<Redirect> {
+    let user = current_user(&ctx, &auth).await?;
+    let ip_address = client_ip(remote_ip);
+
+    let name = params.name.trim();
+    let user = if user.name == name {
+        user
+    } else {
+        let mut item = user.into_active_model();
+        item.name = Set(name.to_string());
+        let user = match item.update(&ctx.db).await {
+            Ok(user) => user,
+            Err(err) => {
+                session.set("errors", data!({ "name": err.to_string() }));
+                return Ok(Redirect::to("/account"));
+            }
+        };
+        audit_logs::Model::record(
+            &ctx.db,
+            audit_logs::ACCOUNT_UPDATED,
+            Some(user.id),
+            ip_address.as_deref(),
+            &data!({ "name": user.name }),
+        )
+        .await?;
+        user
+    };
+
+    if !params.email.trim().eq_ignore_ascii_case(&user.email) {
+        let user_id = user.id;
+        match user
+            .into_active_model()
+            .request_email_change(&ctx.db, &params.email)
+            .await
+        {
+            Ok(user) => {
+                audit_logs::Model::record(
+                    &ctx.db,
+                    audit_logs::EMAIL_CHANGE_REQUESTED,
+                    Some(user_id),
+                    ip_address.as_deref(),
+                    &data!({ "email": user.pending_email }),
+                )
+                .await?;
+                AuthMailer::confirm_email_change(&ctx, &user).await?;
+            }
+            Err(err) => {
+                let message = match err {
+                    ModelError::EntityAlreadyExists => "this email is already used".to_string(),
+                    err => err.to_string(),
+                };
+                session.set("errors", data!({ "email": message }));
+            }
+        }
+    }
+
+    Ok(Redirect::to("/account"))
+}
+
+/// Changes the password, which signs out every other session. This one gets a
+/// new access token.
+#[debug_handler]
+pub async fn update_password(
+    auth: auth::JWT,
+    session: Session<SessionNullPool>,
+    jar: CookieJar,
+    remote_ip: RemoteIP,
+    State(ctx): State<AppContext>,
+    Form(params): Form<PasswordParams>,
+) -> Result<(CookieJar, Redirect)> {
+    let user = current_user(&ctx, &auth).await?;
+
+    if !user.verify_password(&params.current_password) {
+        session.set("errors", data!({ "current_password": "invalid password" }));
+        return Ok((jar, Redirect::to("/account")));
+    }
+    if params.password.is_empty() || params.password != params.password_confirmation {
+        session.set("errors", data!({ "password": "passwords do not match" }));
+        return Ok((jar, Redirect::to("/account")));
+    }
+
+    let user = user
+        .into_active_model()
+        .reset_password(&ctx.db, &params.password)
+        .await?;
+    audit_logs::Model::record(
+        &ctx.db,
+        audit_logs::PASSWORD_CHANGED,
+        Some(user.id),
+        client_ip(remote_ip).as_deref(),
+        &data!({ "email": user.email }),
+    )
+    .await?;
+
+    Ok((add_jwt_cookie(&ctx, jar, &user)?, Redirect::to("/account")))
+}
+
+#[debug_handler]
+pub async fn addresses(
+    auth: auth::JWT,
+    session: Session<SessionNullPool>,
+    ViewEngine(v): ViewEngine<TeraView>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = current_user(&ctx, &auth).await?;
+    let billing =
+        addresses::Model::find_by_user_and_kind(&ctx.db, user.id, addresses::BILLING).await?;
+    let shipping =
+        addresses::Model::find_by_user_and_kind(&ctx.db, user.id, addresses::SHIPPING).await?;
+    let errors = take_errors(&session);
+
+    views::account::addresses(&v, billing.as_ref(), shipping.as_ref(), &errors)
+}
+
+#[debug_handler]
+pub async fn save_address(
+    auth: auth::JWT,
+    Path(kind): Path<String>,
+    session: Session<SessionNullPool>,
+    State(ctx): State<AppContext>,
+    Form(params): Form<AddressParams>,
+) -> Result<Redirect> {
+    let user = current_user(&ctx, &auth).await?;
+
+    if let Err(err) = addresses::Model::save_for_user(&ctx.db, user.id, &kind, &params).await {
+        session.set("errors", data!({ kind: err.to_string() }));
+    }
+
+    Ok(Redirect::to("/account/addresses"))
+}
+
+#[debug_handler]
+pub async fn orders(
+    auth: auth::JWT,
+    ViewEngine(v): ViewEngine<TeraView>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = current_user(&ctx, &auth).await?;
+    let orders = orders::Model::find_by_user(&ctx.db, user.id).await?;
+
+    views::account::orders(&v, &orders)
+}
+
+#[debug_handler]
+pub async fn order(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    ViewEngine(v): ViewEngine<TeraView>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = current_user(&ctx, &auth).await?;
+    let order = match orders::Model::find_for_user(&ctx.db, user.id, id).await {
+        Ok(order) => order,
+        Err(ModelError::EntityNotFound) => return not_found(),
+        Err(err) => return Err(err.into()),
+    };
+    let items = order.items(&ctx.db).await?;
+    let downloads = downloads::links(&ctx, &order).await?;
+
+    views::account::order(&v, &order, &items, &downloads)
+}
+
+/// Deletes the account once the password is confirmed. Orders are kept for
+/// the shop records but no longer linked to the user.
+#[debug_handler]
+pub async fn remove(
+    auth: auth::JWT,
+    session: Session<SessionNullPool>,
+    jar: CookieJar,
+    remote_ip: RemoteIP,
+    State(ctx): State<AppContext>,
+    Form(params): Form<DeleteAccountParams>,
+) -> Result<(CookieJar, Redirect)> {
+    let user = current_user(&ctx, &auth).await?;
+
+    if !user.verify_password(&params.password) {
+        session.set("errors", data!({ "delete": "invalid password" }));
+        return Ok((jar, Redirect::to("/account")));
+    }
+    // the products of the store would go along with their author
+    let authored = products::Entity::find()
+        .filter(products::Column::AuthorId.eq(user.id))
+        .count(&ctx.db)
+        .await?;
+    if user.can_manage_shop() || authored > 0 {
+        session.set(
+            "errors",
+            data!({ "delete": "the accounts of the store staff and of product authors cannot be deleted" }),
+        );
+        return Ok((jar, Redirect::to("/account")));
+    }
+
+    audit_logs::Model::record(
+        &ctx.db,
+        audit_logs::ACCOUNT_DELETED,
+        Some(user.id),
+        client_ip(remote_ip).as_deref(),
+        &data!({ "email": user.email }),
+    )
+    .await?;
+    let pid = user.pid.to_string();
+    user.delete(&ctx.db).await?;
+    tracing::info!(pid, "user deleted");
+
+    let jar = match jwt_cookie_name(&ctx)? {
+        Some(name) => jar.remove(Cookie::build(name).path("/")),
+        None => jar,
+    };
+
+    Ok((jar, Redirect::to("/auth/login")))
+}
+
+#[debug_handler]
+pub async fn list_api_keys(
+    auth: auth::JWT,
+    session: Session<SessionNullPool>,
+    ViewEngine(v): ViewEngine<TeraView>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = current_user(&ctx, &auth).await?;
+    let api_keys = api_keys::Model::find_by_user(&ctx.db, user.id).await?;
+    // a new key is only ever displayed right after its creation
+    let new_key = session.get::<String>("new_api_key");
+    session.remove("new_api_key");
+    let errors = take_errors(&session);
+
+    views::account::api_keys(&v, &api_keys, new_key.as_deref(), &errors)
+}
+
+#[debug_handler]
+pub async fn create_api_key(
+    auth: auth::JWT,
+    session: Session<SessionNullPool>,
+    remote_ip: RemoteIP,
+    State(ctx): State<AppContext>,
+    Form(params): Form<ApiKeyParams>,
+) -> Result<Redirect> {
+    let user = current_user(&ctx, &auth).await?;
+
+    match api_keys::Model::generate(&ctx.db, user.id, &params.name, &params.scope).await {
+        Ok((api_key, key)) => {
+            audit_logs::Model::record(
+                &ctx.db,
+                audit_logs::API_KEY_CREATED,
+                Some(user.id),
+                client_ip(remote_ip).as_deref(),
+                &data!({ "id": api_key.id, "name": api_key.name, "scope": api_key.scope }),
+            )
+            .await?;
+            session.set("new_api_key", key);
+        }
+        Err(err) => session.set("errors", data!({ "api_key": err.to_string() })),
+    }
+
+    Ok(Redirect::to("/account/api-keys"))
+}
+
+#[debug_handler]
+pub async fn revoke_api_key(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    remote_ip: RemoteIP,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = current_user(&ctx, &auth).await?;
+    let api_key = match api_keys::Model::revoke(&ctx.db, user.id, id).await {
+        Ok(api_key) => api_key,
+        Err(ModelError::EntityNotFound) => return not_found(),
+        Err(err) => return Err(err.into()),
+    };
+    audit_logs::Model::record(
+        &ctx.db,
+        audit_logs::API_KEY_REVOKED,
+        Some(user.id),
+        client_ip(remote_ip).as_deref(),
+        &data!({ "id": api_key.id, "name": api_key.name }),
+    )
+    .await?;
+
+    Ok(Redirect::to("/account/api-keys").into_response())
+}
+
+/// Two-factor authentication of the user: the pending secret to scan until
+/// enrolled, then the recovery codes left
+#[debug_handler]
+pub async fn two_factor(
+    auth: auth::JWT,
+    session: Session<SessionNullPool>,
+    ViewEngine(v): ViewEngine<TeraView>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = current_user(&ctx, &auth).await?;
+    let user = if user.has_two_factor() || user.totp_secret.is_some() {
+        user
+    } else {
+        user.into_active_model().start_two_factor(&ctx.db).await?
+    };
+    let setup = if user.has_two_factor() {
+        None
+    } else {
+        two_factor_setup(&user)?
+    };
+    let remaining_codes = recovery_codes::Model::remaining(&ctx.db, user.id).await?;
+    // new recovery codes are only ever displayed right after their creation
+    let new_codes = session.get::<Vec<String>>("recovery_codes");
+    session.remove("recovery_codes");
+    let settings = Settings::from_context(&ctx)?;
+    let required = user.must_use_two_factor(&settings.two_factor.required_roles);
+    let errors = take_errors(&session);
+
+    views::account::two_factor(
+        &v,
+        user.has_two_factor(),
+        required,
+        setup.as_ref(),
+        remaining_codes,
+        new_codes.as_ref(),
+        &errors,
+    )
+}
+
+/// Enables two-factor authentication once a first code of the authenticator
+/// app confirms the pending secret
+#[debug_handler]
+pub async fn enable_two_factor(
+    auth: auth::JWT,
+    session: Session<SessionNullPool>,
+    remote_ip: RemoteIP,
+    State(ctx): State<AppContext>,
+    Form(params): Form<EnableTwoFactorParams>,
+) -> Result<Redirect> {
+    let user = current_user(&ctx, &auth).await?;
+    if user.has_two_factor() {
+        return Ok(Redirect::to("/account/two-factor"));
+    }
+
+    match user.verify_second_factor(&ctx.db, &params.code).await? {
+        SecondFactor::Enrolled(user, codes) => {
+            audit_logs::Model::record(
+                &ctx.db,
+                audit_logs::TWO_FACTOR_ENABLED,
+                Some(user.id),
+                client_ip(remote_ip).as_deref(),
+                &data!({ "email": user.email }),
+            )
+            .await?;
+            session.set("recovery_codes", codes);
+        }
+        _ => session.set("errors", data!({ "code": "invalid code" })),
+    }
+
+    Ok(Redirect::to("/account/two-factor"))
+}
+
+/// Replaces the recovery codes, the previous ones can no longer be used
+#[debug_handler]
+pub async fn regenerate_recovery_codes(
+    auth: auth::JWT,
+    session: Session<SessionNullPool>,
+    remote_ip: RemoteIP,
+    State(ctx): State<AppContext>,
+    Form(params): Form<ConfirmPasswordParams>,
+) -> Result<Redirect> {
+    let user = current_user(&ctx, &auth).await?;
+    if !user.has_two_factor() {
+        return Ok(Redirect::to("/account/two-factor"));
+    }
+    if !user.verify_password(&params.password) {
+        session.set("errors", data!({ "recovery_codes": "invalid password" }));
+        return Ok(Redirect::to("/account/two-factor"));
+    }
+
+    let codes = recovery_codes::Model::generate(&ctx.db, user.id).await?;
+    audit_logs::Model::record(
+        &ctx.db,
+        audit_logs::RECOVERY_CODES_GENERATED,
+        Some(user.id),
+        client_ip(remote_ip).as_deref(),
+        &data!({ "email": user.email }),
+    )
+    .await?;
+    session.set("recovery_codes", codes);
+
+    Ok(Redirect::to("/account/two-factor"))
+}
+
+/// Turns two-factor authentication off, unless the role of the user requires
+/// it
+#[debug_handler]
+pub async fn disable_two_factor(
+    auth: auth::JWT,
+    session: Session<SessionNullPool>,
+    remote_ip: RemoteIP,
+    State(ctx): State<AppContext>,
+    Form(params): Form<ConfirmPasswordParams>,
+) -> Result<Redirect> {
+    let user = current_user(&ctx, &auth).await?;
+    if !user.verify_password(&params.password) {
+        session.set("errors", data!({ "disable": "invalid password" }));
+        return Ok(Redirect::to("/account/two-factor"));
+    }
+    let settings = Settings::from_context(&ctx)?;
+    if user.must_use_two_factor(&settings.two_factor.required_roles) {
+        session.set(
+            "errors",
+            data!({ "disable": "your role requires two-factor authentication" }),
+        );
+        return Ok(Redirect::to("/account/two-factor"));
+    }
+
+    let user = user.into_active_model().disable_two_factor(&ctx.db).await?;
+    audit_logs::Model::record(
+        &ctx.db,
+        audit_logs::TWO_FACTOR_DISABLED,
+        Some(user.id),
+        client_ip(remote_ip).as_deref(),
+        &data!({ "email": user.email }),
+    )
+    .await?;
+
+    Ok(Redirect::to("/account/two-factor"))
+}
+
+/// External identities linked to the account, along with the providers it
+/// can be linked to
+#[debug_handler]
+pub async fn identities(
+    auth: auth::JWT,
+    Extension(providers): Extension<OAuth2Providers>,
+    session: Session<SessionNullPool>,
+    ViewEngine(v): ViewEngine<TeraView>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = current_user(&ctx, &auth).await?;
+    let identities = user_identities::Model::find_by_user(&ctx.db, user.id).await?;
+    let errors = take_errors(&session);
+
+    views::account::identities(&v, &identities, &providers.names(), &errors)
+}
+
+#[debug_handler]
+pub async fn unlink_identity(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    remote_ip: RemoteIP,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = current_user(&ctx, &auth).await?;
+    let identity = match user_identities::Model::unlink(&ctx.db, user.id, id).await {
+        Ok(identity) => identity,
+        Err(ModelError::EntityNotFound) => return not_found(),
+        Err(err) => return Err(err.into()),
+    };
+    audit_logs::Model::record(
+        &ctx.db,
+        audit_logs::IDENTITY_UNLINKED,
+        Some(user.id),
+        client_ip(remote_ip).as_deref(),
+        &data!({ "email": user.email, "provider": identity.provider }),
+    )
+    .await?;
+
+    Ok(Redirect::to("/account/identities").into_response())
+}
+
+pub(crate) fn forbidden_scope() -> Error {
+    Error::CustomError(
+        StatusCode::FORBIDDEN,
+        ErrorDetail::new("forbidden", "The API key scope does not allow this request"),
+    )
+}
+
+/// Orders of the key owner, for keys with the read scope
+#[debug_handler]
+pub async fn api_orders(
+    auth: auth::ApiToken<ApiKeyUser>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    if !auth.user.api_key.can_read() {
+        return Err(forbidden_scope());
+    }
+    let orders = orders::Model::find_by_user(&ctx.db, auth.user.user.id).await?;
+
+    format::json(orders)
+}
+
+/// Saves an address of the key owner, for keys with the write scope
+#[debug_handler]
+pub async fn api_save_address(
+    auth: auth::ApiToken<ApiKeyUser>,
+    Path(kind): Path<String>,
+    State(ctx): State<AppContext>,
+    Json(params): Json<AddressParams>,
+) -> Result<Response> {
+    if !auth.user.api_key.can_write() {
+        return Err(forbidden_scope());
+    }
+    let address =
+        addresses::Model::save_for_user(&ctx.db, auth.user.user.id, &kind, &params).await?;
+
+    format::json(address)
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("account/")
+        .add("/", get(show))
+        .add("profile", post(update_profile))
+        .add("password", post(update_password))
+        .add("addresses", get(addresses))
+        .add("addresses/:kind", post(save_address))
+        .add("orders", get(orders))
+        .add("orders/:id", get(order))
+        .add("api-keys", get(list_api_keys))
+        .add("api-keys", post(create_api_key))
+        .add("api-keys/:id/revoke", post(revoke_api_key))
+        .add("two-factor", get(two_factor))
+        .add("two-factor/enable", post(enable_two_factor))
+        .add("two-factor/recovery-codes", post(regenerate_recovery_codes))
+        .add("two-factor/disable", post(disable_two_factor))
+        .add("identities", get(identities))
+        .add("identities/:id/unlink", post(unlink_identity))
+        .add("delete", post(remove))
+}
+
+pub fn api_routes() -> Routes {
+    Routes::new()
+        .prefix("api/account/")
+        .add("orders", get(api_orders))
+        .add("addresses/:kind", post(api_save_address))
+}
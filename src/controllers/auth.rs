@@ -1,16 +1,28 @@
 use crate::{
+    common::settings::Settings,
+    initializers::oauth2::OAuth2Providers,
     mailers::auth::AuthMailer,
     models::{
         _entities::users,
-        users::{LoginParams, RegisterParams},
+        audit_logs,
+        login_attempts::{self, AttemptCheck, LOCK_AFTER_FAILURES},
+        refresh_tokens,
+        users::{LoginParams, RegisterParams, SecondFactor},
     },
     views,
-    views::auth::{CurrentResponse, LoginResponse},
+    views::auth::{
+        CurrentResponse, LoginResponse, TwoFactorChallengeResponse, TwoFactorSetupResponse,
+    },
 };
 use axum::debug_handler;
-use axum::{extract::Form, response::Redirect};
+use axum::{extract::Form, http::StatusCode, response::Redirect, Extension};
+use axum_extra::extract::CookieJar;
 use axum_session::{Session, SessionNullPool};
-use loco_rs::prelude::*;
+use loco_rs::{
+    config::JWTLocation,
+    controller::ErrorDetail,
+    prelude::{cookie::Cookie, *},
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -18,6 +30,11 @@ pub struct VerifyParams {
     pub token: String,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UnlockParams {
+    pub token: String,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ForgotParams {
     pub email: String,
@@ -29,6 +46,40 @@ pub struct ResetParams {
     pub password: String,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RefreshParams {
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LogoutParams {
+    pub refresh_token: Option<String>,
+    /// also revoke the sessions opened on other devices
+    #[serde(default)]
+    pub all: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TwoFactorParams {
+    pub challenge_token: String,
+    /// code of the authenticator app or one of the recovery codes
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TwoFactorSetupParams {
+    pub challenge_token: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TwoFactorFormParams {
+    pub code: String,
+}
+
+/// Session key holding the challenge token between the two login steps of
+/// the form
+const TWO_FACTOR_CHALLENGE: &str = "two_factor_challenge";
+
 #[debug_handler]
 pub async fn register_view(ViewEngine(v): ViewEngine<TeraView>) -> Result<Response> {
     views::auth::register_view(&v)
@@ -37,6 +88,7 @@ pub async fn register_view(ViewEngine(v): ViewEngine<TeraView>) -> Result<Respon
 #[debug_handler]
 pub async fn login_view(
     ViewEngine(v): ViewEngine<TeraView>,
+    Extension(providers): Extension<OAuth2Providers>,
     session: Session<SessionNullPool>,
 ) -> Result<Response> {
     // todo: check if user is already logged in
@@ -47,7 +99,7 @@ pub async fn login_view(
         .unwrap_or(data!({}));
     session.set("errors", data!({}));
 
-    views::auth::login_view(&v, &errors)
+    views::auth::login_view(&v, &errors, &providers.names())
 }
 
 /// Register function creates a new user with the given parameters and sends a
@@ -76,9 +128,28 @@ async fn verify(
     State(ctx): State<AppContext>,
     Json(params): Json<VerifyParams>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_verification_token(&ctx.db, &params.token).await?;
+    verify_user(&ctx, &params.token).await?;
+
+    format::json(())
+}
+
+/// Target of the link sent by email to confirm a new email address
+#[debug_handler]
+async fn verify_via_link(
+    Path(token): Path<String>,
+    State(ctx): State<AppContext>,
+) -> Result<Redirect> {
+    if verify_user(&ctx, &token).await.is_err() {
+        tracing::info!("verification token not found");
+    }
 
-    if user.email_verified_at.is_some() {
+    Ok(Redirect::to("/account"))
+}
+
+async fn verify_user(ctx: &AppContext, token: &str) -> Result<()> {
+    let user = users::Model::find_by_verification_token(&ctx.db, token).await?;
+
+    if user.email_verified_at.is_some() && user.pending_email.is_none() {
         tracing::info!(pid = user.pid.to_string(), "user already verified");
     } else {
         let active_model = user.into_active_model();
@@ -86,7 +157,7 @@ async fn verify(
         tracing::info!(pid = user.pid.to_string(), "user verified");
     }
 
-    format::json(())
+    Ok(())
 }
 
 /// In case the user forgot his password  this endpoints generate a forgot token
@@ -131,34 +202,499 @@ async fn reset(State(ctx): State<AppContext>, Json(params): Json<ResetParams>) -
     format::json(())
 }
 
+/// Outcome of a login attempt once throttling and lockout rules are applied
+enum LoginOutcome {
+    Authenticated(Box<users::Model>),
+    /// the password is right but a code is needed too, because the user
+    /// enrolled or their role requires two-factor authentication
+    SecondFactorRequired(Box<users::Model>),
+    /// unknown email, wrong password or locked account. callers must answer
+    /// the same way for all of them to avoid exposing our users email
+    Rejected,
+    /// too many failed attempts for this email or from this IP address
+    Throttled,
+}
+
+pub(crate) fn client_ip(remote_ip: RemoteIP) -> Option<String> {
+    match remote_ip {
+        RemoteIP::Forwarded(ip) | RemoteIP::Socket(ip) => Some(ip.to_string()),
+        RemoteIP::None => None,
+    }
+}
+
+/// Name of the cookie holding the JWT, when the auth config reads it from a
+/// cookie
+pub(crate) fn jwt_cookie_name(ctx: &AppContext) -> Result<Option<String>> {
+    match &ctx.config.get_jwt_config()?.location {
+        Some(JWTLocation::Cookie { name }) => Ok(Some(name.clone())),
+        _ => Ok(None),
+    }
+}
+
+/// Stores a new access token of the user in the cookie pages behind
+/// `auth::JWT` read it from, when configured so
+pub(crate) fn add_jwt_cookie(
+    ctx: &AppContext,
+    jar: CookieJar,
+    user: &users::Model,
+) -> Result<CookieJar> {
+    let Some(name) = jwt_cookie_name(ctx)? else {
+        return Ok(jar);
+    };
+    let jwt_config = ctx.config.get_jwt_config()?;
+    let token = user
+        .generate_jwt(&jwt_config.secret, &jwt_config.expiration)
+        .or_else(|_| unauthorized("unauthorized!"))?;
+
+    Ok(jar.add(
+        Cookie::build((name, token))
+            .path("/")
+            .http_only(true)
+            .secure(false),
+    ))
+}
+
+/// Loads the user of the access token, rejecting tokens revoked since they
+/// were issued
+pub(crate) async fn current_user(ctx: &AppContext, auth: &auth::JWT) -> Result<users::Model> {
+    match users::Model::find_by_claims(&ctx.db, &auth.claims).await {
+        Ok(user) => Ok(user),
+        Err(ModelError::EntityNotFound) => unauthorized("unauthorized!"),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Loads the user of the access token, only if they may manage the shop
+pub(crate) async fn current_manager(ctx: &AppContext, auth: &auth::JWT) -> Result<users::Model> {
+    let user = current_user(ctx, auth).await?;
+    if user.can_manage_shop() {
+        Ok(user)
+    } else {
+        Err(Error::CustomError(
+            StatusCode::FORBIDDEN,
+            ErrorDetail::new("forbidden", "Only shop managers may do this"),
+        ))
+    }
+}
+
+/// Answers with a new access token, along with the refresh token to renew it
+/// once expired
+fn login_response(
+    ctx: &AppContext,
+    user: &users::Model,
+    refresh_token: &str,
+    recovery_codes: Option<Vec<String>>,
+) -> Result<Response> {
+    let jwt_config = ctx.config.get_jwt_config()?;
+    let token = user
+        .generate_jwt(&jwt_config.secret, &jwt_config.expiration)
+        .or_else(|_| unauthorized("unauthorized!"))?;
+
+    format::json(
+        LoginResponse::new(user, &token, refresh_token).with_recovery_codes(recovery_codes),
+    )
+}
+
+fn too_many_requests() -> Error {
+    Error::CustomError(
+        StatusCode::TOO_MANY_REQUESTS,
+        ErrorDetail::new(
+            "too_many_requests",
+            "Too many login attempts, please try again later",
+        ),
+    )
+}
+
+/// The pending secret of a user who has to enroll, ready to be added to an
+/// authenticator app
+pub(crate) fn two_factor_setup(user: &users::Model) -> Result<Option<TwoFactorSetupResponse>> {
+    let (Some(secret), Some((provisioning_uri, qr_code))) =
+        (&user.totp_secret, user.totp_provisioning()?)
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(TwoFactorSetupResponse {
+        secret: secret.clone(),
+        provisioning_uri,
+        qr_code,
+    }))
+}
+
+/// Whether the login of the user needs a code, because they enrolled or
+/// their role requires two-factor authentication
+pub(crate) fn requires_second_factor(ctx: &AppContext, user: &users::Model) -> Result<bool> {
+    let settings = Settings::from_context(ctx)?;
+    Ok(user.has_two_factor() || user.must_use_two_factor(&settings.two_factor.required_roles))
+}
+
+/// Sends a user who passed the first login step to the form asking for their
+/// code, the challenge token being kept in the session meanwhile
+pub(crate) fn redirect_to_second_factor(
+    ctx: &AppContext,
+    session: &Session<SessionNullPool>,
+    user: &users::Model,
+) -> Result<Redirect> {
+    let jwt_config = ctx.config.get_jwt_config()?;
+    let challenge_token = user
+        .generate_challenge_jwt(&jwt_config.secret)
+        .or_else(|_| unauthorized("unauthorized!"))?;
+    session.set(TWO_FACTOR_CHALLENGE, challenge_token);
+
+    Ok(Redirect::to("/auth/two-factor"))
+}
+
+/// Records a failed attempt and locks the account once it reaches too many
+/// of them
+async fn record_failure(
+    ctx: &AppContext,
+    user: users::Model,
+    action: &str,
+    ip_address: Option<&str>,
+) -> Result<()> {
+    let details = serde_json::json!({ "email": user.email });
+    login_attempts::Model::record(&ctx.db, &user.email, ip_address, false).await?;
+    audit_logs::Model::record(&ctx.db, action, Some(user.id), ip_address, &details).await?;
+
+    if !user.is_locked()
+        && login_attempts::Model::recent_failures_for_email(&ctx.db, &user.email).await?
+            >= LOCK_AFTER_FAILURES
+    {
+        let user = user.into_active_model().lock(&ctx.db).await?;
+        audit_logs::Model::record(
+            &ctx.db,
+            audit_logs::ACCOUNT_LOCKED,
+            Some(user.id),
+            ip_address,
+            &details,
+        )
+        .await?;
+        AuthMailer::unlock_account(ctx, &user).await?;
+        tracing::info!(pid = user.pid.to_string(), "user locked");
+    }
+
+    Ok(())
+}
+
+/// Checks the given credentials, records the attempt and locks the account
+/// once it reaches too many failed attempts
+async fn authenticate(
+    ctx: &AppContext,
+    params: &LoginParams,
+    ip_address: Option<&str>,
+) -> Result<LoginOutcome> {
+    let details = serde_json::json!({ "email": params.email });
+
+    let check = login_attempts::Model::check(&ctx.db, &params.email, ip_address).await?;
+    if check != AttemptCheck::Allowed {
+        audit_logs::Model::record(
+            &ctx.db,
+            audit_logs::LOGIN_THROTTLED,
+            None,
+            ip_address,
+            &details,
+        )
+        .await?;
+        return Ok(LoginOutcome::Throttled);
+    }
+
+    let Ok(user) = users::Model::find_by_email(&ctx.db, &params.email).await else {
+        users::Model::verify_dummy_password(&params.password);
+        login_attempts::Model::record(&ctx.db, &params.email, ip_address, false).await?;
+        audit_logs::Model::record(
+            &ctx.db,
+            audit_logs::LOGIN_FAILED,
+            None,
+            ip_address,
+            &details,
+        )
+        .await?;
+        return Ok(LoginOutcome::Rejected);
+    };
+
+    if user.verify_password(&params.password) && !user.is_locked() {
+        login_attempts::Model::record(&ctx.db, &params.email, ip_address, true).await?;
+        if requires_second_factor(ctx, &user)? {
+            // failures are only cleared once the code is checked too, or a
+            // known password would reset the throttling of wrong codes
+            return Ok(LoginOutcome::SecondFactorRequired(Box::new(user)));
+        }
+        login_attempts::Model::clear_failures(&ctx.db, &params.email).await?;
+        audit_logs::Model::record(
+            &ctx.db,
+            audit_logs::LOGIN_SUCCEEDED,
+            Some(user.id),
+            ip_address,
+            &details,
+        )
+        .await?;
+        return Ok(LoginOutcome::Authenticated(Box::new(user)));
+    }
+
+    record_failure(ctx, user, audit_logs::LOGIN_FAILED, ip_address).await?;
+
+    Ok(LoginOutcome::Rejected)
+}
+
+/// Outcome of the second login step
+enum SecondStepOutcome {
+    /// the code is valid. Recovery codes are given when the code completed
+    /// the enrollment.
+    Verified(Box<users::Model>, Option<Vec<String>>),
+    /// invalid or expired challenge, wrong code or locked account
+    Rejected,
+    Throttled,
+}
+
+/// Checks the code given with the challenge token of the first login step.
+/// Wrong codes count as failed logins, throttling and locking the account
+/// the same way.
+async fn authenticate_second_factor(
+    ctx: &AppContext,
+    challenge_token: &str,
+    code: &str,
+    ip_address: Option<&str>,
+) -> Result<SecondStepOutcome> {
+    let jwt_config = ctx.config.get_jwt_config()?;
+    let Ok(user) =
+        users::Model::find_by_challenge_jwt(&ctx.db, &jwt_config.secret, challenge_token).await
+    else {
+        return Ok(SecondStepOutcome::Rejected);
+    };
+    let details = serde_json::json!({ "email": user.email });
+
+    let check = login_attempts::Model::check(&ctx.db, &user.email, ip_address).await?;
+    if check != AttemptCheck::Allowed {
+        audit_logs::Model::record(
+            &ctx.db,
+            audit_logs::LOGIN_THROTTLED,
+            Some(user.id),
+            ip_address,
+            &details,
+        )
+        .await?;
+        return Ok(SecondStepOutcome::Throttled);
+    }
+    if user.is_locked() {
+        return Ok(SecondStepOutcome::Rejected);
+    }
+
+    let (user, recovery_codes) = match user.clone().verify_second_factor(&ctx.db, code).await? {
+        SecondFactor::Totp(user) => (user, None),
+        SecondFactor::Enrolled(user, recovery_codes) => {
+            audit_logs::Model::record(
+                &ctx.db,
+                audit_logs::TWO_FACTOR_ENABLED,
+                Some(user.id),
+                ip_address,
+                &details,
+            )
+            .await?;
+            (user, Some(recovery_codes))
+        }
+        SecondFactor::RecoveryCode(user) => {
+            audit_logs::Model::record(
+                &ctx.db,
+                audit_logs::RECOVERY_CODE_USED,
+                Some(user.id),
+                ip_address,
+                &details,
+            )
+            .await?;
+            (user, None)
+        }
+        SecondFactor::Invalid => {
+            record_failure(ctx, user, audit_logs::TWO_FACTOR_FAILED, ip_address).await?;
+            return Ok(SecondStepOutcome::Rejected);
+        }
+    };
+    login_attempts::Model::clear_failures(&ctx.db, &user.email).await?;
+    audit_logs::Model::record(
+        &ctx.db,
+        audit_logs::LOGIN_SUCCEEDED,
+        Some(user.id),
+        ip_address,
+        &details,
+    )
+    .await?;
+
+    Ok(SecondStepOutcome::Verified(user, recovery_codes))
+}
+
 /// Creates a user login and returns a token
 #[debug_handler]
 async fn login(
     // auth: auth::JWT,
+    remote_ip: RemoteIP,
     State(ctx): State<AppContext>,
     Json(params): Json<LoginParams>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_email(&ctx.db, &params.email).await?;
+    let ip_address = client_ip(remote_ip);
+    let user = match authenticate(&ctx, &params, ip_address.as_deref()).await? {
+        LoginOutcome::Authenticated(user) => user,
+        LoginOutcome::SecondFactorRequired(user) => {
+            let jwt_config = ctx.config.get_jwt_config()?;
+            let challenge_token = user
+                .generate_challenge_jwt(&jwt_config.secret)
+                .or_else(|_| unauthorized("unauthorized!"))?;
+            return format::json(TwoFactorChallengeResponse::new(&user, &challenge_token));
+        }
+        LoginOutcome::Rejected => return unauthorized("unauthorized!"),
+        LoginOutcome::Throttled => return Err(too_many_requests()),
+    };
+
+    let (_, refresh_token) = refresh_tokens::Model::issue(&ctx.db, user.id, None).await?;
+
+    login_response(&ctx, &user, &refresh_token, None)
+}
+
+/// Second login step, exchanging the challenge token given by `login` and a
+/// code for an access token. The first code of a user who had to enroll
+/// enables two-factor authentication, the answer then holds the recovery
+/// codes.
+#[debug_handler]
+async fn login_second_factor(
+    remote_ip: RemoteIP,
+    State(ctx): State<AppContext>,
+    Json(params): Json<TwoFactorParams>,
+) -> Result<Response> {
+    let ip_address = client_ip(remote_ip);
+    let (user, recovery_codes) = match authenticate_second_factor(
+        &ctx,
+        &params.challenge_token,
+        &params.code,
+        ip_address.as_deref(),
+    )
+    .await?
+    {
+        SecondStepOutcome::Verified(user, recovery_codes) => (user, recovery_codes),
+        SecondStepOutcome::Rejected => return unauthorized("unauthorized!"),
+        SecondStepOutcome::Throttled => return Err(too_many_requests()),
+    };
+
+    let (_, refresh_token) = refresh_tokens::Model::issue(&ctx.db, user.id, None).await?;
+
+    login_response(&ctx, &user, &refresh_token, recovery_codes)
+}
+
+/// Gives the secret to add to an authenticator app to a user who has to
+/// enroll before logging in. Users who already enrolled are rejected, their
+/// secret can only be changed once logged in.
+#[debug_handler]
+async fn setup_second_factor(
+    State(ctx): State<AppContext>,
+    Json(params): Json<TwoFactorSetupParams>,
+) -> Result<Response> {
+    let jwt_config = ctx.config.get_jwt_config()?;
+    let Ok(user) =
+        users::Model::find_by_challenge_jwt(&ctx.db, &jwt_config.secret, &params.challenge_token)
+            .await
+    else {
+        return unauthorized("unauthorized!");
+    };
+    if user.has_two_factor() || user.is_locked() {
+        return unauthorized("unauthorized!");
+    }
 
-    let valid = user.verify_password(&params.password);
+    let user = user.into_active_model().start_two_factor(&ctx.db).await?;
+    match two_factor_setup(&user)? {
+        Some(setup) => format::json(setup),
+        None => unauthorized("unauthorized!"),
+    }
+}
 
-    if !valid {
+/// Exchanges a refresh token for a new access token. The refresh token is
+/// rotated, the one given can not be used again.
+#[debug_handler]
+async fn refresh(
+    State(ctx): State<AppContext>,
+    Json(params): Json<RefreshParams>,
+) -> Result<Response> {
+    let Ok((refresh_token, new_refresh_token)) =
+        refresh_tokens::Model::rotate(&ctx.db, &params.refresh_token).await
+    else {
+        return unauthorized("unauthorized!");
+    };
+    let user = users::Entity::find_by_id(refresh_token.user_id)
+        .one(&ctx.db)
+        .await?
+        .ok_or_else(|| Error::Unauthorized("unauthorized!".to_string()))?;
+    if user.is_locked() {
+        refresh_tokens::Model::revoke_all(&ctx.db, user.id).await?;
         return unauthorized("unauthorized!");
     }
 
-    let jwt_secret = ctx.config.get_jwt_config()?;
+    login_response(&ctx, &user, &new_refresh_token, None)
+}
 
-    let token = user
-        .generate_jwt(&jwt_secret.secret, &jwt_secret.expiration)
-        .or_else(|_| unauthorized("unauthorized!"))?;
-    // save token in a cookie header of the response
+/// Revokes the given refresh token, or every session of the user with `all`.
+/// The access token stays valid until it expires unless `all` is set.
+#[debug_handler]
+async fn logout(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<LogoutParams>,
+) -> Result<Response> {
+    let user = current_user(&ctx, &auth).await?;
+
+    if params.all {
+        user.into_active_model().revoke_sessions(&ctx.db).await?;
+    } else if let Some(refresh_token) = params.refresh_token {
+        refresh_tokens::Model::revoke(&ctx.db, user.id, &refresh_token).await?;
+    }
 
-    format::json(LoginResponse::new(&user, &token))
+    format::json(())
+}
+
+/// Unlocks an account locked after too many failed logins. Like `reset`, an
+/// unknown token is still answered with a success.
+#[debug_handler]
+async fn unlock(
+    remote_ip: RemoteIP,
+    State(ctx): State<AppContext>,
+    Json(params): Json<UnlockParams>,
+) -> Result<Response> {
+    unlock_account(&ctx, &params.token, client_ip(remote_ip).as_deref()).await?;
+
+    format::json(())
+}
+
+/// Target of the link sent by email when an account gets locked
+#[debug_handler]
+async fn unlock_via_link(
+    Path(token): Path<String>,
+    remote_ip: RemoteIP,
+    State(ctx): State<AppContext>,
+) -> Result<Redirect> {
+    unlock_account(&ctx, &token, client_ip(remote_ip).as_deref()).await?;
+
+    Ok(Redirect::to("/auth/login"))
+}
+
+async fn unlock_account(ctx: &AppContext, token: &str, ip_address: Option<&str>) -> Result<()> {
+    let Ok(user) = users::Model::find_by_unlock_token(&ctx.db, token).await else {
+        tracing::info!("unlock token not found");
+        return Ok(());
+    };
+
+    let user = user.into_active_model().unlock(&ctx.db).await?;
+    login_attempts::Model::clear_failures(&ctx.db, &user.email).await?;
+    audit_logs::Model::record(
+        &ctx.db,
+        audit_logs::ACCOUNT_UNLOCKED,
+        Some(user.id),
+        ip_address,
+        &serde_json::json!({ "email": user.email }),
+    )
+    .await?;
+    tracing::info!(pid = user.pid.to_string(), "user unlocked");
+
+    Ok(())
 }
 
 #[debug_handler]
 async fn current(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = current_user(&ctx, &auth).await?;
     format::json(CurrentResponse::new(&user))
 }
 
@@ -270,30 +806,111 @@ async fn register_via_form(
 
 async fn login_via_form(
     session: Session<SessionNullPool>,
-    ctx: State<AppContext>,
-    params: Form<LoginParams>,
-) -> Result<Redirect> {
-    let user = users::Model::find_by_email(&ctx.db, &params.email).await?;
+    jar: CookieJar,
+    remote_ip: RemoteIP,
+    State(ctx): State<AppContext>,
+    Form(params): Form<LoginParams>,
+) -> Result<(CookieJar, Redirect)> {
+    let ip_address = client_ip(remote_ip);
+    let message = match authenticate(&ctx, &params, ip_address.as_deref()).await? {
+        LoginOutcome::Authenticated(user) => {
+            let jar = add_jwt_cookie(&ctx, jar, &user)?;
+
+            return Ok((jar, Redirect::to("/account")));
+        }
+        LoginOutcome::SecondFactorRequired(user) => {
+            return Ok((jar, redirect_to_second_factor(&ctx, &session, &user)?));
+        }
+        LoginOutcome::Rejected => "invalid email or password",
+        LoginOutcome::Throttled => "too many login attempts, please try again later",
+    };
 
-    let valid = user.verify_password(&params.password);
+    let errors = serde_json::json!({
+        "email": message,
+        "password": message,
+    });
+    session.set("errors", errors);
 
-    if !valid {
-        let errors = serde_json::json!({
-            "email": "invalid email or password",
-            "password": "invalid email or password",
-        });
-        session.set("errors", errors);
+    Ok((jar, Redirect::to("/auth/login")))
+}
 
-        return Ok(Redirect::to("/auth/login"));
-    }
+/// Second step of the login form, asking for a code. Users who have to
+/// enroll get their pending secret along with it.
+#[debug_handler]
+async fn two_factor_view(
+    session: Session<SessionNullPool>,
+    ViewEngine(v): ViewEngine<TeraView>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let jwt_config = ctx.config.get_jwt_config()?;
+    let user = match session.get::<String>(TWO_FACTOR_CHALLENGE) {
+        Some(challenge_token) => {
+            users::Model::find_by_challenge_jwt(&ctx.db, &jwt_config.secret, &challenge_token)
+                .await
+                .ok()
+        }
+        None => None,
+    };
+    let Some(user) = user else {
+        session.remove(TWO_FACTOR_CHALLENGE);
+        return Ok(Redirect::to("/auth/login").into_response());
+    };
 
-    let _jwt_secret = ctx.config.get_jwt_config()?;
+    let setup = if user.has_two_factor() {
+        None
+    } else {
+        let user = if user.totp_secret.is_none() {
+            user.into_active_model().start_two_factor(&ctx.db).await?
+        } else {
+            user
+        };
+        two_factor_setup(&user)?
+    };
+    let errors = session
+        .get::<serde_json::Value>("errors")
+        .unwrap_or(data!({}));
+    session.set("errors", data!({}));
+
+    views::auth::two_factor_view(&v, setup.as_ref(), &errors)
+}
+
+async fn two_factor_via_form(
+    session: Session<SessionNullPool>,
+    jar: CookieJar,
+    remote_ip: RemoteIP,
+    State(ctx): State<AppContext>,
+    Form(params): Form<TwoFactorFormParams>,
+) -> Result<(CookieJar, Redirect)> {
+    let Some(challenge_token) = session.get::<String>(TWO_FACTOR_CHALLENGE) else {
+        return Ok((jar, Redirect::to("/auth/login")));
+    };
+    let ip_address = client_ip(remote_ip);
+    let message = match authenticate_second_factor(
+        &ctx,
+        &challenge_token,
+        &params.code,
+        ip_address.as_deref(),
+    )
+    .await?
+    {
+        SecondStepOutcome::Verified(user, recovery_codes) => {
+            session.remove(TWO_FACTOR_CHALLENGE);
+            let jar = add_jwt_cookie(&ctx, jar, &user)?;
+            if let Some(recovery_codes) = recovery_codes {
+                // shown once on the two-factor page of the account
+                session.set("recovery_codes", recovery_codes);
+                return Ok((jar, Redirect::to("/account/two-factor")));
+            }
+
+            return Ok((jar, Redirect::to("/account")));
+        }
+        SecondStepOutcome::Rejected => "invalid code",
+        SecondStepOutcome::Throttled => "too many login attempts, please try again later",
+    };
 
-    // let token = user
-    //     .generate_jwt(&jwt_secret.secret, &jwt_secret.expiration)
-    //     .or_else(|_| unauthorized("unauthorized!"))?;
+    session.set("errors", serde_json::json!({ "code": message }));
 
-    Ok(Redirect::to("/dashboard"))
+    Ok((jar, Redirect::to("/auth/two-factor")))
 }
 
 pub fn routes() -> Routes {
@@ -301,6 +918,14 @@ pub fn routes() -> Routes {
         .add("/api/auth/register", post(register))
         .add("/api/auth/verify", post(verify))
         .add("/api/auth/login", post(login))
+        .add("/api/auth/login/two-factor", post(login_second_factor))
+        .add(
+            "/api/auth/login/two-factor/setup",
+            post(setup_second_factor),
+        )
+        .add("/api/auth/refresh", post(refresh))
+        .add("/api/auth/logout", post(logout))
+        .add("/api/auth/unlock", post(unlock))
         .add("/api/auth/forgot", post(forgot))
         .add("/api/auth/reset", post(reset))
         .add("/api/auth/current", get(current))
@@ -308,4 +933,8 @@ pub fn routes() -> Routes {
         .add("/auth/register", post(register_via_form))
         .add("/auth/login", get(login_view))
         .add("/auth/login", post(login_via_form))
+        .add("/auth/two-factor", get(two_factor_view))
+        .add("/auth/two-factor", post(two_factor_via_form))
+        .add("/auth/unlock/:token", get(unlock_via_link))
+        .add("/auth/verify/:token", get(verify_via_link))
 }
@@ -1,6 +1,8 @@
 #![allow(clippy::missing_errors_doc)]
 #![allow(clippy::unnecessary_struct_initialization)]
 #![allow(clippy::unused_async)]
+use std::collections::HashMap;
+
 use axum::{debug_handler, extract::Form, response::Redirect};
 use axum_extra::extract::CookieJar;
 use axum_session::{Session, SessionNullPool};
@@ -10,11 +12,25 @@ use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use tracing::info;
 
+use super::products;
 use crate::{
-    models::{_entities::{postmetas, products::{Column, Entity}}},
+    common::settings::Settings,
+    models::{
+        _entities::{
+            postmetas,
+            products::{Column, Entity},
+        },
+        bundle_items,
+        products::{BACKORDERS_META, BACKORDERS_NO, BACKORDERS_NOTIFY, TYPE_BUNDLE},
+        shipping_zones::{self, ShippingPackage, ShippingRate},
+        tax_rates::{self, TaxLine, TaxLocation},
+    },
     views,
 };
 
+/// Session key of the destination shipping is calculated for
+const SHIPPING_CHOICE: &str = "commust_shipping";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CartParams {
     pub id: i32,
@@ -44,6 +60,9 @@ pub struct CartSession {
     id: i32,
     qty: i32,
     // variations: Option<Vec>,
+    /// ids of the bundle items chosen among the optional ones of a bundle
+    #[serde(default)]
+    options: Vec<i32>,
 }
 
 #[derive(FromQueryResult)]
@@ -53,100 +72,150 @@ struct PartialMetaModel {
     pub meta_value: Option<String>,
 }
 
-#[debug_handler]
-pub async fn add(
-    session: Session<SessionNullPool>,
-    jar: CookieJar,
-    State(ctx): State<AppContext>,
-    Form(params): Form<CartParams>,
-) -> Result<(CookieJar, Redirect)> {
-    let redirect_to = format!("/products/p/{}", &params.slug);
-    let item = postmetas::Entity::find()
+/// Whether a product can be added to the cart that many times
+enum Availability {
+    /// no such product
+    Missing,
+    /// the error to show on the product page
+    Refused(&'static str),
+    Available,
+}
+
+/// Quantity of each product the cart holds, the products of its bundles
+/// included
+async fn reserved(ctx: &AppContext, cart_session: &[CartSession]) -> Result<HashMap<i32, i32>> {
+    let mut reserved = HashMap::new();
+    for item in cart_session {
+        *reserved.entry(item.id).or_insert(0) += item.qty;
+        for bundled in bundle_items::Model::included(&ctx.db, item.id, Some(&item.options)).await? {
+            *reserved.entry(bundled.product_id).or_insert(0) += bundled.quantity * item.qty;
+        }
+    }
+    Ok(reserved)
+}
+
+/// Whether the stock of the product covers that many items, `None` when the
+/// product has no stock status. Products allowing backorders can be bought
+/// beyond their stock, and those whose stock is not managed without limit.
+async fn in_stock(ctx: &AppContext, id: i32, wanted: i32) -> Result<Option<bool>> {
+    let metas = postmetas::Entity::find()
         .select_only()
         .column(postmetas::Column::MetaKey)
         .column(postmetas::Column::MetaValue)
-        .filter(postmetas::Column::MetaKey.eq("_stock_status"))
-        .filter(postmetas::Column::ProductId.eq(params.id))
+        .filter(postmetas::Column::MetaKey.is_in(["_stock_status", "_stock", BACKORDERS_META]))
+        .filter(postmetas::Column::ProductId.eq(id))
         .into_model::<PartialMetaModel>()
-        .one(&ctx.db)
+        .all(&ctx.db)
         .await?;
-    
-    // this check if the product exists 
-    if item.is_none() {
-        return Ok((jar, Redirect::to("/products")));
-    } 
-    
-    let stock_status = item.unwrap().meta_value.unwrap();
-    
-    if stock_status == "outofstock" {
-        // todo: add a flash message to the session
-        let errors = serde_json::json!({
-            "global": "The requested quantity is not available",
-        });
-        session.set("errors", errors);
+    let meta = |key: &str| {
+        metas
+            .iter()
+            .find(|meta| meta.meta_key.as_deref() == Some(key))
+            .and_then(|meta| meta.meta_value.clone())
+    };
 
-        return Ok((jar, Redirect::to(redirect_to.as_str())));
+    // this check if the product exists
+    let Some(stock_status) = meta("_stock_status") else {
+        return Ok(None);
+    };
+    if stock_status == "outofstock" {
+        return Ok(Some(false));
     }
 
-    let mut cart_session: Vec<CartSession> = session.get("commust_cart_items").unwrap_or(vec![]);
-    let item_position = cart_session.iter().position(|x| x.id == params.id);
-
-    if stock_status == "instock" {
-        let stock_qty = postmetas::Entity::find()
-            .select_only()
-            .column(postmetas::Column::MetaValue)
-            .filter(postmetas::Column::MetaKey.eq("_stock"))
-            .filter(postmetas::Column::ProductId.eq(params.id))
-            .into_model::<PartialMetaModel>()
-            .one(&ctx.db)
-            .await?;
-        let stock_qty = stock_qty.unwrap().meta_value.unwrap().parse::<i32>().unwrap();
-        
-        // note: add current qty(already in cart) to the requested qty as well
-        
-        let current_qty = if item_position.is_some() {
-            let index = item_position.unwrap();
-            cart_session[index].qty
-        } else {
-            0
-        };
+    let stock_qty = meta("_stock").and_then(|stock_qty| stock_qty.parse::<f32>().ok());
+    let backorders = meta(BACKORDERS_META).is_some_and(|backorders| backorders != BACKORDERS_NO);
+    Ok(Some(
+        backorders || stock_qty.is_none_or(|stock_qty| stock_qty >= wanted as f32),
+    ))
+}
 
-        if stock_qty < params.qty + current_qty {
-            // todo: set error in session
-            let errors = serde_json::json!({
-                "global": "The requested quantity is not available",
-            });
-            session.set("errors", errors);
+/// Checks the stock of the product, counting what the cart already has. The
+/// products a bundle is sold with, `options` being the optional ones chosen,
+/// must all be in stock too.
+async fn availability(
+    ctx: &AppContext,
+    cart_session: &[CartSession],
+    id: i32,
+    qty: i32,
+    options: &[i32],
+) -> Result<Availability> {
+    let product = Entity::find_by_id(id).one(&ctx.db).await?;
+    if product
+        .as_ref()
+        .is_some_and(|product| !product.purchasable())
+    {
+        return Ok(Availability::Refused(
+            "This product cannot be added to the cart",
+        ));
+    }
 
-            return Ok((jar, Redirect::to(redirect_to.as_str())));
+    // note: add current qty(already in cart) to the requested qty as well
+    let reserved = reserved(ctx, cart_session).await?;
+    let in_cart = |id: i32| reserved.get(&id).copied().unwrap_or(0);
+    match in_stock(ctx, id, qty + in_cart(id)).await? {
+        None => return Ok(Availability::Missing),
+        Some(false) => {
+            return Ok(Availability::Refused(
+                "The requested quantity is not available",
+            ))
+        }
+        Some(true) => {}
+    }
+
+    if product.is_some_and(|product| product.product_type.as_deref() == Some(TYPE_BUNDLE)) {
+        let bundled = bundle_items::Model::included(&ctx.db, id, Some(options)).await?;
+        if bundled.is_empty() {
+            return Ok(Availability::Refused("This bundle has no products yet"));
+        }
+        for item in bundled {
+            let wanted = item.quantity * qty + in_cart(item.product_id);
+            if in_stock(ctx, item.product_id, wanted).await? != Some(true) {
+                return Ok(Availability::Refused(
+                    "A product of this bundle is not available in the requested quantity",
+                ));
+            }
         }
     }
 
     // todo: check if variations are valid
 
-    let session_id = match jar.get("commust_session_id") {
-        Some(cookie) => cookie.value().to_string(),
-        None => Uuid::new_v4().to_string(),
-    };
-    
-    if item_position.is_some() {
-        let index = item_position.unwrap();
-        cart_session[index].qty += params.qty;
+    Ok(Availability::Available)
+}
+
+/// Adds the product to the cart, or more of it when it is already there
+/// with the same options
+fn push_item(cart_session: &mut Vec<CartSession>, id: i32, qty: i32, options: Vec<i32>) {
+    let item_position = cart_session
+        .iter()
+        .position(|x| x.id == id && x.options == options);
+    if let Some(index) = item_position {
+        cart_session[index].qty += qty;
     } else {
         let new_cart_item = CartSession {
             key: Uuid::new_v4().to_string(),
-            id: params.id,
-            qty: params.qty,
+            id,
+            qty,
+            options,
         };
         cart_session.push(new_cart_item);
     }
+}
+
+/// Saves the cart in the session, along with the cookies telling it changed
+fn save_cart(
+    session: &Session<SessionNullPool>,
+    jar: CookieJar,
+    cart_session: Vec<CartSession>,
+) -> CookieJar {
+    let session_id = match jar.get("commust_session_id") {
+        Some(cookie) => cookie.value().to_string(),
+        None => Uuid::new_v4().to_string(),
+    };
 
     let items = cart_session.len();
     let cart_hash = generate_hash(&cart_session, &session_id);
     session.set("commust_cart_items", cart_session);
 
-    info!("Product {} added {} times to cart", params.id, params.qty);
-
     let hash_cookie = Cookie::build(("commust_cart_hash", cart_hash))
         .path("/")
         .http_only(true)
@@ -160,19 +229,145 @@ pub async fn add(
         .http_only(true)
         .secure(false);
 
-    Ok((
-        // the updated jar must be returned for the changes
-        // to be included in the response
-        jar.add(hash_cookie).add(items_cookie).add(session_cookie),
-        Redirect::to(redirect_to.as_str()),
-    ))
+    // the updated jar must be returned for the changes
+    // to be included in the response
+    jar.add(hash_cookie).add(items_cookie).add(session_cookie)
 }
 
-#[derive(FromQueryResult)]
-struct PartialProductModel {
-    pub id: i32,
-    pub name: String,
-    pub slug: Option<String>,
+#[debug_handler]
+pub async fn add(
+    session: Session<SessionNullPool>,
+    jar: CookieJar,
+    State(ctx): State<AppContext>,
+    Form(params): Form<CartParams>,
+) -> Result<(CookieJar, Redirect)> {
+    let redirect_to = format!("/products/p/{}", &params.slug);
+    let mut cart_session: Vec<CartSession> = session.get("commust_cart_items").unwrap_or(vec![]);
+
+    match availability(&ctx, &cart_session, params.id, params.qty, &[]).await? {
+        Availability::Missing => return Ok((jar, Redirect::to("/products"))),
+        Availability::Refused(error) => {
+            session.set("errors", serde_json::json!({ "global": error }));
+            return Ok((jar, Redirect::to(redirect_to.as_str())));
+        }
+        Availability::Available => {}
+    }
+
+    push_item(&mut cart_session, params.id, params.qty, vec![]);
+    let jar = save_cart(&session, jar, cart_session);
+
+    info!("Product {} added {} times to cart", params.id, params.qty);
+
+    Ok((jar, Redirect::to(redirect_to.as_str())))
+}
+
+/// Adds the products of a grouped product to the cart, each in the
+/// quantity given by its `quantity[<id>]` field. Nothing is added when one
+/// of them is not available.
+#[debug_handler]
+pub async fn add_items(
+    session: Session<SessionNullPool>,
+    jar: CookieJar,
+    State(ctx): State<AppContext>,
+    Form(fields): Form<Vec<(String, String)>>,
+) -> Result<(CookieJar, Redirect)> {
+    let slug = fields
+        .iter()
+        .find(|(name, _)| name == "slug")
+        .map(|(_, slug)| slug.clone())
+        .unwrap_or_default();
+    let redirect_to = format!("/products/p/{slug}");
+    let quantities: Vec<(i32, i32)> = fields
+        .iter()
+        .filter_map(|(name, qty)| {
+            let id = name.strip_prefix("quantity[")?.strip_suffix(']')?;
+            Some((id.parse().ok()?, qty.trim().parse().unwrap_or(0)))
+        })
+        .filter(|(_, qty)| *qty > 0)
+        .collect();
+    if quantities.is_empty() {
+        let errors = serde_json::json!({
+            "global": "Please choose the quantity of the products to add to the cart",
+        });
+        session.set("errors", errors);
+        return Ok((jar, Redirect::to(redirect_to.as_str())));
+    }
+
+    let mut cart_session: Vec<CartSession> = session.get("commust_cart_items").unwrap_or(vec![]);
+    for (id, qty) in &quantities {
+        match availability(&ctx, &cart_session, *id, *qty, &[]).await? {
+            Availability::Available => {}
+            Availability::Missing => {
+                let errors = serde_json::json!({ "global": "This product does not exist" });
+                session.set("errors", errors);
+                return Ok((jar, Redirect::to(redirect_to.as_str())));
+            }
+            Availability::Refused(error) => {
+                session.set("errors", serde_json::json!({ "global": error }));
+                return Ok((jar, Redirect::to(redirect_to.as_str())));
+            }
+        }
+    }
+
+    for (id, qty) in quantities {
+        push_item(&mut cart_session, id, qty, vec![]);
+        info!("Product {} added {} times to cart", id, qty);
+    }
+    let jar = save_cart(&session, jar, cart_session);
+
+    Ok((jar, Redirect::to(redirect_to.as_str())))
+}
+
+/// Adds a bundle to the cart, with the optional products chosen by their
+/// `option[<bundle item id>]` field
+#[debug_handler]
+pub async fn add_bundle(
+    session: Session<SessionNullPool>,
+    jar: CookieJar,
+    State(ctx): State<AppContext>,
+    Form(fields): Form<Vec<(String, String)>>,
+) -> Result<(CookieJar, Redirect)> {
+    let field = |name: &str| {
+        fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.trim().to_string())
+    };
+    let redirect_to = format!("/products/p/{}", field("slug").unwrap_or_default());
+    let (Some(id), Some(qty)) = (
+        field("id").and_then(|id| id.parse::<i32>().ok()),
+        field("qty").and_then(|qty| qty.parse::<i32>().ok()),
+    ) else {
+        return Ok((jar, Redirect::to("/products")));
+    };
+    let mut options: Vec<i32> = fields
+        .iter()
+        .filter_map(|(name, _)| {
+            name.strip_prefix("option[")?
+                .strip_suffix(']')?
+                .parse()
+                .ok()
+        })
+        .collect();
+    options.sort_unstable();
+    options.dedup();
+
+    let mut cart_session: Vec<CartSession> = session.get("commust_cart_items").unwrap_or(vec![]);
+    match availability(&ctx, &cart_session, id, qty, &options).await? {
+        Availability::Missing => return Ok((jar, Redirect::to("/products"))),
+        Availability::Refused(error) => {
+            session.set("errors", serde_json::json!({ "global": error }));
+            return Ok((jar, Redirect::to(redirect_to.as_str())));
+        }
+        Availability::Available => {}
+    }
+
+    push_item(&mut cart_session, id, qty, options);
+    let jar = save_cart(&session, jar, cart_session);
+
+    info!("Bundle {} added {} times to cart", id, qty);
+
+    Ok((jar, Redirect::to(redirect_to.as_str())))
 }
 
 #[derive(Debug, Serialize)]
@@ -182,41 +377,253 @@ pub struct PartialCartProduct {
     pub slug: Option<String>,
     pub name: String,
     pub quantity: i32,
+    pub price: f32,
+    pub total: f32,
+    /// weight of one item, in kg
+    pub weight: f32,
+    /// false for virtual products
+    pub needs_shipping: bool,
+    pub tax_class: String,
+    /// taxes of the line, filled in by `totals`
+    pub taxes: Vec<TaxLine>,
+    pub tax_total: f32,
+    /// items beyond the stock of the product, and whether the customer is
+    /// told about them
+    pub backordered: i32,
+    pub backorder_notice: bool,
+    /// optional products chosen, for a bundle
+    pub options: Vec<i32>,
 }
 
-#[debug_handler]
-pub async fn show(
-    session: Session<SessionNullPool>,
-    ViewEngine(v): ViewEngine<TeraView>,
-    State(ctx): State<AppContext>,
-) -> Result<Response> {
+/// Loads the products of the cart in the session along with their current
+/// price
+pub(crate) async fn load_items(
+    ctx: &AppContext,
+    session: &Session<SessionNullPool>,
+) -> Result<Vec<PartialCartProduct>> {
     let cart_session: Vec<CartSession> = session.get("commust_cart_items").unwrap_or(vec![]);
     let ids = cart_session.iter().map(|x| x.id).collect::<Vec<i32>>();
     let products_list = Entity::find()
-        .select_only()
-        .column(Column::Id)
-        .column(Column::Slug)
-        .column_as(Column::Title, "name")
         .order_by(Column::Id, Order::Asc)
         .filter(Column::Id.is_in(ids))
-        .into_model::<PartialProductModel>()
         .all(&ctx.db)
         .await?;
-    let products = products_list
+
+    let mut products = vec![];
+    // products unpublished since they got in the cart cannot be ordered
+    for product in products_list
         .into_iter()
-        .map(|product| {
-            let current_cart_item = cart_session.iter().find(|x| x.id == product.id).unwrap();
-            PartialCartProduct {
+        .filter(|product| product.purchasable())
+    {
+        let slug = product.slug.clone();
+        let product = products::load_view(ctx, product).await?;
+        let weight = product.weight.unwrap_or(0.0);
+        let mut in_stock = match product.stock {
+            Some(stock) if product.backorders != BACKORDERS_NO => stock.max(0.0) as i32,
+            _ => i32::MAX,
+        };
+        // a bundle is in the cart once for each choice of its optional products
+        for current_cart_item in cart_session.iter().filter(|x| x.id == product.id) {
+            let backordered = (current_cart_item.qty - in_stock).max(0);
+            in_stock = (in_stock - current_cart_item.qty).max(0);
+            let mut name = product.name.clone();
+            let mut price = product.price.unwrap_or(0.0);
+            if product.product_type == TYPE_BUNDLE {
+                let contents =
+                    products::bundle_contents(ctx, product.id, Some(&current_cart_item.options))
+                        .await?;
+                price = products::bundle_price(&product, &contents);
+                let options: Vec<String> = contents
+                    .iter()
+                    .filter(|(item, _)| item.optional)
+                    .map(|(item, component)| format!("{} × {}", component.name, item.quantity))
+                    .collect();
+                if !options.is_empty() {
+                    name = format!("{name} (with {})", options.join(", "));
+                }
+            }
+            products.push(PartialCartProduct {
                 key: current_cart_item.key.clone(),
                 id: product.id,
-                slug: product.slug,
-                name: product.name,
+                slug: slug.clone(),
+                name,
                 quantity: current_cart_item.qty,
-            }
+                price,
+                total: price * current_cart_item.qty as f32,
+                weight,
+                needs_shipping: !product.is_virtual,
+                tax_class: product.tax_class.clone(),
+                taxes: vec![],
+                tax_total: 0.0,
+                backordered,
+                backorder_notice: backordered > 0 && product.backorders == BACKORDERS_NOTIFY,
+                options: current_cart_item.options.clone(),
+            });
+        }
+    }
+
+    Ok(products)
+}
+
+/// Where the cart is shipped to and with which method, as chosen in the
+/// shipping calculator of the cart or at checkout
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShippingChoice {
+    pub country: String,
+    pub state: Option<String>,
+    pub postcode: String,
+    pub shipping_method: Option<i32>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CartTotals {
+    pub subtotal: f32,
+    /// whether shipping zones are set up and some items are not virtual, a
+    /// rate has to be chosen then
+    pub needs_shipping: bool,
+    /// rates available for the destination, if known
+    pub shipping_rates: Vec<ShippingRate>,
+    /// the chosen rate, or the first one available
+    pub shipping: Option<ShippingRate>,
+    /// whether the subtotal already includes the taxes of the items
+    pub prices_include_tax: bool,
+    pub shipping_taxes: Vec<TaxLine>,
+    /// taxes of the items and shipping, by rate
+    pub taxes: Vec<TaxLine>,
+    pub tax_total: f32,
+    pub total: f32,
+}
+
+/// The shipping calculator choice saved in the session
+pub(crate) fn shipping_choice(session: &Session<SessionNullPool>) -> Option<ShippingChoice> {
+    session.get(SHIPPING_CHOICE)
+}
+
+/// Remembers the destination shipping is calculated for
+pub(crate) fn save_shipping_choice(session: &Session<SessionNullPool>, choice: &ShippingChoice) {
+    session.set(SHIPPING_CHOICE, choice);
+}
+
+/// Calculates the totals of the items, shipping to the chosen destination.
+/// The taxes of each item are filled in on the way.
+pub(crate) async fn totals(
+    ctx: &AppContext,
+    items: &mut [PartialCartProduct],
+    choice: Option<&ShippingChoice>,
+) -> Result<CartTotals> {
+    let prices_include_tax = Settings::from_context(ctx)?.tax.prices_include_tax;
+    let subtotal = items.iter().map(|item| item.total).sum::<f32>();
+    let needs_shipping = items.iter().any(|item| item.needs_shipping)
+        && shipping_zones::Model::any(&ctx.db).await?;
+
+    // without destination, only the rates applying anywhere are charged
+    let location = choice
+        .map(|choice| TaxLocation {
+            country: choice.country.trim().to_uppercase(),
+            state: choice
+                .state
+                .as_deref()
+                .map(str::trim)
+                .filter(|state| !state.is_empty())
+                .map(str::to_uppercase),
+            postcode: choice.postcode.clone(),
         })
-        .collect::<Vec<PartialCartProduct>>();
+        .unwrap_or_default();
+    for item in items.iter_mut() {
+        let rates = tax_rates::Model::find_for(&ctx.db, &item.tax_class, &location).await?;
+        item.taxes = tax_rates::calculate(item.total, &rates, prices_include_tax);
+        item.tax_total = tax_rates::total(&item.taxes);
+    }
+
+    let shipping_rates = match choice {
+        Some(_) if needs_shipping && !location.country.is_empty() => {
+            let package = ShippingPackage {
+                country: location.country.clone(),
+                state: location.state.clone(),
+                postcode: location.postcode.clone(),
+                contents_cost: subtotal,
+                weight: items
+                    .iter()
+                    .filter(|item| item.needs_shipping)
+                    .map(|item| item.weight * item.quantity as f32)
+                    .sum(),
+            };
+            shipping_zones::Model::rates(&ctx.db, &package).await?
+        }
+        _ => vec![],
+    };
+    let shipping = choice
+        .and_then(|choice| choice.shipping_method)
+        .and_then(|id| shipping_rates.iter().find(|rate| rate.method_id == id))
+        .or_else(|| shipping_rates.first())
+        .cloned();
+    // shipping costs are entered without taxes
+    let shipping_taxes = match &shipping {
+        Some(rate) => {
+            let rates = tax_rates::Model::find_for_shipping(&ctx.db, &location).await?;
+            tax_rates::calculate(rate.cost, &rates, false)
+        }
+        None => vec![],
+    };
+    let taxes = tax_rates::merge(
+        items
+            .iter()
+            .flat_map(|item| &item.taxes)
+            .chain(&shipping_taxes),
+    );
+    let tax_total = tax_rates::total(&taxes);
+    let total = subtotal
+        + shipping.as_ref().map_or(0.0, |rate| rate.cost)
+        + if prices_include_tax {
+            tax_rates::total(&shipping_taxes)
+        } else {
+            tax_total
+        };
+
+    Ok(CartTotals {
+        subtotal,
+        needs_shipping,
+        shipping_rates,
+        shipping,
+        prices_include_tax,
+        shipping_taxes,
+        taxes,
+        tax_total,
+        total,
+    })
+}
+
+/// Empties the cart, once its items got ordered
+pub(crate) fn clear(session: &Session<SessionNullPool>, jar: CookieJar) -> CookieJar {
+    session.set("commust_cart_items", Vec::<CartSession>::new());
+
+    jar.remove(Cookie::build("commust_cart_hash").path("/"))
+        .remove(Cookie::build("commust_cart_items").path("/"))
+}
+
+#[debug_handler]
+pub async fn show(
+    session: Session<SessionNullPool>,
+    ViewEngine(v): ViewEngine<TeraView>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let mut products = load_items(&ctx, &session).await?;
+    let choice = shipping_choice(&session);
+    let totals = totals(&ctx, &mut products, choice.as_ref()).await?;
+
+    views::cart::show(&v, &products, &totals, choice.as_ref())
+}
+
+/// Saves the destination of the shipping calculator, and the method picked
+/// among its rates
+#[debug_handler]
+pub async fn shipping(
+    session: Session<SessionNullPool>,
+    Form(params): Form<ShippingChoice>,
+) -> Result<Redirect> {
+    save_shipping_choice(&session, &params);
 
-    views::cart::show(&v, &products)
+    Ok(Redirect::to("/cart"))
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -329,6 +736,9 @@ pub fn routes() -> Routes {
         .prefix("cart/")
         .add("/", get(show))
         .add("add-item", post(add))
+        .add("add-items", post(add_items))
+        .add("add-bundle", post(add_bundle))
         .add("remove-item", post(remove))
         .add("update-item", post(update))
+        .add("shipping", post(shipping))
 }
@@ -0,0 +1,491 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+use axum::{
+    debug_handler,
+    extract::{Form, Query},
+    response::Redirect,
+};
+use axum_extra::extract::CookieJar;
+use axum_session::{Session, SessionNullPool};
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    auth::current_user,
+    cart::{self, ShippingChoice},
+    downloads,
+};
+use crate::{
+    common::{
+        payments::{self, PaymentMethod},
+        settings::Settings,
+        stock_alerts,
+    },
+    mailers::auth::AuthMailer,
+    models::{
+        addresses::{self, AddressParams},
+        orders::{self, OrderLine, PlaceOrderParams},
+        stock_movements,
+        users::{self, RegisterParams},
+    },
+    views,
+    workers::{
+        downloader::{DownloadWorker, DownloadWorkerArgs},
+        order_emails::OrderEmailWorker,
+    },
+};
+
+/// Session key of the last checkout form which could not be placed, shown
+/// again to fix it
+const CHECKOUT_FORM: &str = "commust_checkout";
+
+/// Shipping address fields of the checkout form, prefixed as they share the
+/// form with the billing ones
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ShippingAddressFields {
+    #[serde(default)]
+    pub shipping_first_name: String,
+    #[serde(default)]
+    pub shipping_last_name: String,
+    pub shipping_company: Option<String>,
+    #[serde(default)]
+    pub shipping_address_1: String,
+    pub shipping_address_2: Option<String>,
+    #[serde(default)]
+    pub shipping_city: String,
+    pub shipping_state: Option<String>,
+    #[serde(default)]
+    pub shipping_postcode: String,
+    #[serde(default)]
+    pub shipping_country: String,
+}
+
+impl ShippingAddressFields {
+    fn address(&self) -> AddressParams {
+        AddressParams {
+            first_name: self.shipping_first_name.clone(),
+            last_name: self.shipping_last_name.clone(),
+            company: self.shipping_company.clone(),
+            address_1: self.shipping_address_1.clone(),
+            address_2: self.shipping_address_2.clone(),
+            city: self.shipping_city.clone(),
+            state: self.shipping_state.clone(),
+            postcode: self.shipping_postcode.clone(),
+            country: self.shipping_country.clone(),
+            phone: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CheckoutParams {
+    pub email: String,
+    #[serde(flatten)]
+    pub billing: AddressParams,
+    /// Checkbox asking to ship somewhere else than the billing address
+    pub ship_to_different_address: Option<String>,
+    #[serde(flatten)]
+    pub shipping: ShippingAddressFields,
+    pub customer_note: Option<String>,
+    /// Checkbox asking to create an account with the billing email
+    pub create_account: Option<String>,
+    #[serde(skip_serializing)]
+    pub password: Option<String>,
+    /// id of the chosen shipping method, the first available one otherwise
+    pub shipping_method: Option<String>,
+    pub payment_method: Option<String>,
+    /// Checkbox accepting the terms and conditions
+    pub terms: Option<String>,
+    /// Unique to each display of the form, so submitting it twice places a
+    /// single order
+    pub checkout_token: Option<String>,
+}
+
+impl CheckoutParams {
+    /// Where the order ships to
+    fn destination(&self) -> AddressParams {
+        if self.ship_to_different_address.is_some() {
+            self.shipping.address()
+        } else {
+            self.billing.clone()
+        }
+    }
+}
+
+/// Values the checkout form is filled with
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct CheckoutForm {
+    pub email: Option<String>,
+    pub billing: Option<AddressParams>,
+    pub shipping: Option<AddressParams>,
+    pub ship_to_different_address: bool,
+    pub customer_note: Option<String>,
+    pub payment_method: Option<String>,
+    pub checkout_token: String,
+    /// accounts are only offered to guests
+    pub logged_in: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct OrderReceivedParams {
+    pub key: String,
+}
+
+fn take_errors(session: &Session<SessionNullPool>) -> serde_json::Value {
+    let errors = session
+        .get::<serde_json::Value>("errors")
+        .unwrap_or(data!({}));
+    session.set("errors", data!({}));
+    errors
+}
+
+async fn load_user(ctx: &AppContext, auth: Option<&auth::JWT>) -> Result<Option<users::Model>> {
+    match auth {
+        Some(auth) => Ok(Some(current_user(ctx, auth).await?)),
+        None => Ok(None),
+    }
+}
+
+/// Creates the account asked for at checkout. The welcome email carries the
+/// verification link, verifying links the previous guest orders too.
+async fn create_account(
+    ctx: &AppContext,
+    params: &CheckoutParams,
+) -> Result<std::result::Result<users::Model, serde_json::Value>> {
+    let password = params.password.as_deref().unwrap_or_default();
+    if password.is_empty() {
+        return Ok(Err(
+            data!({ "password": "a password is required to create an account" }),
+        ));
+    }
+
+    let register = RegisterParams {
+        email: params.email.trim().to_string(),
+        password: password.to_string(),
+        name: format!(
+            "{} {}",
+            params.billing.first_name.trim(),
+            params.billing.last_name.trim()
+        ),
+    };
+    let user = match users::Model::create_with_password(&ctx.db, &register).await {
+        Ok(user) => user,
+        Err(ModelError::EntityAlreadyExists) => {
+            return Ok(Err(data!({
+                "email": "an account already exists for this email, please log in"
+            })));
+        }
+        Err(err) => return Ok(Err(data!({ "email": err.to_string() }))),
+    };
+    let user = user
+        .into_active_model()
+        .set_email_verification_sent(&ctx.db)
+        .await?;
+    AuthMailer::send_welcome(ctx, &user).await?;
+
+    Ok(Ok(user))
+}
+
+fn choice_for(address: &AddressParams, shipping_method: Option<i32>) -> ShippingChoice {
+    ShippingChoice {
+        country: address.country.clone(),
+        state: address.state.clone(),
+        postcode: address.postcode.clone(),
+        shipping_method,
+    }
+}
+
+fn received_url(order: &orders::Model) -> String {
+    format!(
+        "/checkout/order-received/{}?key={}",
+        order.id,
+        order.order_key.as_deref().unwrap_or_default()
+    )
+}
+
+/// Sends the customer back to the form, filled with what was submitted
+fn retry(
+    session: &Session<SessionNullPool>,
+    params: &CheckoutParams,
+    errors: serde_json::Value,
+) -> Redirect {
+    session.set(CHECKOUT_FORM, params);
+    session.set("errors", errors);
+    Redirect::to("/checkout")
+}
+
+/// Checks the addresses, payment method and terms of the submitted form,
+/// returning the errors by field name
+fn check_form(
+    settings: &Settings,
+    params: &CheckoutParams,
+) -> (
+    serde_json::Map<String, serde_json::Value>,
+    Option<PaymentMethod>,
+) {
+    let mut errors = serde_json::Map::new();
+    for (field, message) in params.billing.check() {
+        errors.insert(field.to_string(), message.into());
+    }
+    if params.ship_to_different_address.is_some() {
+        for (field, message) in params.shipping.address().check() {
+            errors.insert(format!("shipping_{field}"), message.into());
+        }
+    }
+
+    let methods = payments::enabled(settings);
+    let payment_method = params
+        .payment_method
+        .as_deref()
+        .and_then(|id| methods.iter().find(|method| method.id == id))
+        .copied();
+    if !methods.is_empty() && payment_method.is_none() {
+        errors.insert(
+            "payment_method".to_string(),
+            "please choose a payment method".into(),
+        );
+    }
+
+    if settings.checkout.terms_url.is_some() && params.terms.is_none() {
+        errors.insert(
+            "terms".to_string(),
+            "please accept the terms and conditions to place your order".into(),
+        );
+    }
+    (errors, payment_method)
+}
+
+#[debug_handler]
+pub async fn show(
+    auth: Option<auth::JWT>,
+    session: Session<SessionNullPool>,
+    ViewEngine(v): ViewEngine<TeraView>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let mut items = cart::load_items(&ctx, &session).await?;
+    if items.is_empty() {
+        return Ok(Redirect::to("/cart").into_response());
+    }
+    let settings = Settings::from_context(&ctx)?;
+
+    let user = load_user(&ctx, auth.as_ref()).await?;
+    let mut form = CheckoutForm {
+        email: user.as_ref().map(|user| user.email.clone()),
+        logged_in: user.is_some(),
+        checkout_token: Uuid::new_v4().to_string(),
+        ..Default::default()
+    };
+    if let Some(user) = &user {
+        for address in addresses::Model::find_by_user(&ctx.db, user.id).await? {
+            if address.kind == addresses::BILLING {
+                form.billing = Some(address.into());
+            } else {
+                form.shipping = Some(address.into());
+            }
+        }
+    }
+    if let Some(params) = session.get::<CheckoutParams>(CHECKOUT_FORM) {
+        form.email = Some(params.email.clone());
+        form.billing = Some(params.billing.clone());
+        form.ship_to_different_address = params.ship_to_different_address.is_some();
+        if form.ship_to_different_address {
+            form.shipping = Some(params.shipping.address());
+        }
+        form.customer_note = params.customer_note;
+        form.payment_method = params.payment_method;
+    }
+
+    let choice = cart::shipping_choice(&session).or_else(|| {
+        let destination = if form.ship_to_different_address {
+            form.shipping.as_ref()
+        } else {
+            form.billing.as_ref()
+        };
+        destination.map(|address| choice_for(address, None))
+    });
+    let totals = cart::totals(&ctx, &mut items, choice.as_ref()).await?;
+    let errors = take_errors(&session);
+
+    views::checkout::show(
+        &v,
+        &items,
+        &totals,
+        &form,
+        &payments::enabled(&settings),
+        settings.checkout.terms_url.as_deref(),
+        &errors,
+    )
+}
+
+/// Places the order of the cart. Guests may create their account on the way,
+/// otherwise the order is only known by its email until claimed.
+///
+/// Each display of the form carries its own token, stored on the order: a
+/// form submitted again, e.g. by a double-click, leads to the order it
+/// placed the first time.
+#[debug_handler]
+pub async fn place(
+    auth: Option<auth::JWT>,
+    session: Session<SessionNullPool>,
+    jar: CookieJar,
+    State(ctx): State<AppContext>,
+    Form(params): Form<CheckoutParams>,
+) -> Result<(CookieJar, Redirect)> {
+    let Some(token) = params
+        .checkout_token
+        .as_deref()
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+    else {
+        let errors = data!({ "global": "please review your order and place it again" });
+        return Ok((jar, retry(&session, &params, errors)));
+    };
+    if let Some(order) = orders::Model::find_by_checkout_token(&ctx.db, &token).await? {
+        return Ok((jar, Redirect::to(&received_url(&order))));
+    }
+
+    let mut items = cart::load_items(&ctx, &session).await?;
+    if items.is_empty() {
+        return Ok((jar, Redirect::to("/cart")));
+    }
+    let settings = Settings::from_context(&ctx)?;
+
+    let (errors, payment_method) = check_form(&settings, &params);
+    if !errors.is_empty() {
+        return Ok((jar, retry(&session, &params, errors.into())));
+    }
+
+    let choice = choice_for(
+        &params.destination(),
+        params
+            .shipping_method
+            .as_deref()
+            .and_then(|id| id.trim().parse().ok()),
+    );
+    cart::save_shipping_choice(&session, &choice);
+    let totals = cart::totals(&ctx, &mut items, Some(&choice)).await?;
+    if totals.needs_shipping {
+        let shipping_error = match (&totals.shipping, choice.shipping_method) {
+            (None, _) => Some("no shipping method is available for this address"),
+            (Some(rate), Some(id)) if rate.method_id != id => {
+                Some("the chosen shipping method is not available for this address")
+            }
+            _ => None,
+        };
+        if let Some(message) = shipping_error {
+            let errors = data!({ "shipping": message });
+            return Ok((jar, retry(&session, &params, errors)));
+        }
+    }
+
+    let user = match load_user(&ctx, auth.as_ref()).await? {
+        Some(user) => Some(user),
+        None if params.create_account.is_some() => match create_account(&ctx, &params).await? {
+            Ok(user) => Some(user),
+            Err(errors) => return Ok((jar, retry(&session, &params, errors))),
+        },
+        None => None,
+    };
+
+    let order_params = PlaceOrderParams {
+        user_id: user.as_ref().map(|user| user.id),
+        email: params.email.clone(),
+        billing: params.billing.clone(),
+        shipping: params
+            .ship_to_different_address
+            .is_some()
+            .then(|| params.shipping.address()),
+        customer_note: params
+            .customer_note
+            .as_deref()
+            .map(str::trim)
+            .filter(|note| !note.is_empty())
+            .map(str::to_string),
+        lines: items
+            .iter()
+            .map(|item| {
+                // orders keep prices without taxes
+                let total = if totals.prices_include_tax {
+                    item.total - item.tax_total
+                } else {
+                    item.total
+                };
+                OrderLine {
+                    product_id: item.id,
+                    name: item.name.clone(),
+                    quantity: item.quantity,
+                    price: total / item.quantity as f32,
+                    taxes: item.taxes.clone(),
+                    backordered: item.backordered,
+                    options: item.options.clone(),
+                }
+            })
+            .collect(),
+        shipping_rate: totals.shipping,
+        shipping_taxes: totals.shipping_taxes,
+        prices_include_tax: totals.prices_include_tax,
+        payment_method,
+        checkout_token: Some(token.clone()),
+    };
+    let order = match orders::Model::place(&ctx.db, &order_params).await {
+        Ok(order) => order,
+        Err(err) => {
+            // the same form got placed meanwhile
+            if let Some(order) = orders::Model::find_by_checkout_token(&ctx.db, &token).await? {
+                return Ok((jar, Redirect::to(&received_url(&order))));
+            }
+            tracing::info!(message = err.to_string(), "could not place order");
+            let message = match err {
+                ModelError::ModelValidation { errors } => errors.message.unwrap_or(errors.code),
+                err => err.to_string(),
+            };
+            let errors = data!({ "global": message });
+            return Ok((jar, retry(&session, &params, errors)));
+        }
+    };
+    tracing::info!(order_id = order.id, "order placed");
+    session.remove(CHECKOUT_FORM);
+    let changes: Vec<_> = stock_movements::Model::find_by_order(&ctx.db, order.id)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    stock_alerts::stock_moved(&ctx, &changes).await?;
+    OrderEmailWorker::order_placed(&ctx, &order).await?;
+    if order.is_paid() {
+        DownloadWorker::perform_later(&ctx, DownloadWorkerArgs { order_id: order.id }).await?;
+    }
+
+    Ok((
+        cart::clear(&session, jar),
+        Redirect::to(&received_url(&order)),
+    ))
+}
+
+/// Thank you page, reachable by guests thanks to the order key
+#[debug_handler]
+pub async fn received(
+    Path(id): Path<i32>,
+    Query(params): Query<OrderReceivedParams>,
+    ViewEngine(v): ViewEngine<TeraView>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let order = match orders::Model::find_by_key(&ctx.db, id, &params.key).await {
+        Ok(order) => order,
+        Err(ModelError::EntityNotFound) => return not_found(),
+        Err(err) => return Err(err.into()),
+    };
+    let items = order.items(&ctx.db).await?;
+    let downloads = downloads::links(&ctx, &order).await?;
+
+    views::checkout::received(&v, &order, &items, &downloads)
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("checkout/")
+        .add("/", get(show))
+        .add("/", post(place))
+        .add("order-received/:id", get(received))
+}
@@ -0,0 +1,113 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+use axum::{
+    debug_handler,
+    extract::Query,
+    http::{header, StatusCode},
+    response::{IntoResponse, Redirect},
+};
+use chrono::Duration;
+use loco_rs::{controller::ErrorDetail, prelude::*};
+use serde::Deserialize;
+
+use crate::{
+    common::settings::Settings,
+    models::{
+        download_permissions::{self, DownloadLink},
+        orders,
+    },
+};
+
+pub const DEFAULT_DIR: &str = "storage/downloads";
+pub const DEFAULT_LINK_TTL_HOURS: i64 = 24;
+
+#[derive(Debug, Deserialize)]
+pub struct DownloadQuery {
+    pub key: String,
+    pub expires: i64,
+    pub signature: String,
+}
+
+/// The files the customer of an order may download, with fresh links
+pub(crate) async fn links(ctx: &AppContext, order: &orders::Model) -> Result<Vec<DownloadLink>> {
+    let settings = Settings::from_context(ctx)?.downloads;
+    let ttl = Duration::hours(settings.link_ttl_hours.unwrap_or(DEFAULT_LINK_TTL_HOURS));
+    let secret = ctx.config.get_jwt_config()?.secret.clone();
+
+    Ok(download_permissions::Model::links_for_order(&ctx.db, &secret, order.id, ttl).await?)
+}
+
+/// Sends a file through a signed link, counting the download
+#[debug_handler]
+pub async fn download(
+    Path(id): Path<i32>,
+    Query(query): Query<DownloadQuery>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let secret = ctx.config.get_jwt_config()?.secret.clone();
+    let (permission, download) = match download_permissions::Model::download(
+        &ctx.db,
+        &secret,
+        id,
+        &query.key,
+        query.expires,
+        &query.signature,
+    )
+    .await
+    {
+        Ok(granted) => granted,
+        Err(ModelError::EntityNotFound) => return not_found(),
+        Err(ModelError::ModelValidation { errors }) => {
+            return Err(Error::CustomError(
+                StatusCode::FORBIDDEN,
+                ErrorDetail::new(
+                    errors.code.as_str(),
+                    errors.message.as_deref().unwrap_or("forbidden"),
+                ),
+            ));
+        }
+        Err(err) => return Err(err.into()),
+    };
+    tracing::info!(
+        permission_id = permission.id,
+        download_count = permission.download_count,
+        "file downloaded"
+    );
+
+    if download.is_url() {
+        return Ok(Redirect::to(&download.file).into_response());
+    }
+    let dir = Settings::from_context(&ctx)?
+        .downloads
+        .dir
+        .unwrap_or_else(|| DEFAULT_DIR.to_string());
+    let content = tokio::fs::read(download.path(&dir)).await.map_err(|err| {
+        tracing::error!(
+            err = err.to_string(),
+            file = download.file,
+            "cannot read download"
+        );
+        Error::NotFound
+    })?;
+    let filename = download
+        .path(&dir)
+        .file_name()
+        .map(|name| name.to_string_lossy().replace('"', ""))
+        .unwrap_or_default();
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        content,
+    )
+        .into_response())
+}
+
+pub fn routes() -> Routes {
+    Routes::new().prefix("downloads/").add(":id", get(download))
+}
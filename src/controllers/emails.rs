@@ -0,0 +1,61 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+use axum::{debug_handler, response::Redirect};
+
+use loco_rs::prelude::*;
+
+use super::auth::current_manager;
+use crate::{models::failed_emails, views, workers::mail_delivery::MailDeliveryWorker};
+
+/// Emails which could not be delivered, after every retry
+#[debug_handler]
+pub async fn failed(
+    auth: auth::JWT,
+    ViewEngine(v): ViewEngine<TeraView>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    current_manager(&ctx, &auth).await?;
+    let emails = failed_emails::Model::list(&ctx.db).await?;
+
+    views::emails::failed(&v, &emails)
+}
+
+/// Queues a failed email again, it comes back to the list if it fails again
+#[debug_handler]
+pub async fn retry(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    current_manager(&ctx, &auth).await?;
+    let email = match failed_emails::Model::take(&ctx.db, id).await {
+        Ok(email) => email,
+        Err(ModelError::EntityNotFound) => return not_found(),
+        Err(err) => return Err(err.into()),
+    };
+    MailDeliveryWorker::perform_later(&ctx, email).await?;
+
+    Ok(Redirect::to("/admin/emails/failed").into_response())
+}
+
+#[debug_handler]
+pub async fn remove(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    current_manager(&ctx, &auth).await?;
+    match failed_emails::Model::remove(&ctx.db, id).await {
+        Ok(()) => Ok(Redirect::to("/admin/emails/failed").into_response()),
+        Err(ModelError::EntityNotFound) => not_found(),
+        Err(err) => Err(err.into()),
+    }
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("admin/emails/")
+        .add("failed", get(failed))
+        .add("failed/:id/retry", post(retry))
+        .add("failed/:id/delete", post(remove))
+}
@@ -0,0 +1,177 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+use std::path::PathBuf;
+
+use axum::{
+    debug_handler,
+    extract::Multipart,
+    http::header,
+    response::{IntoResponse, Redirect},
+};
+use axum_session::{Session, SessionNullPool};
+use loco_rs::prelude::*;
+
+use super::auth::current_manager;
+use crate::{
+    models::media,
+    views,
+    workers::thumbnails::{ThumbnailWorker, ThumbnailWorkerArgs},
+};
+
+#[debug_handler]
+pub async fn library(
+    auth: auth::JWT,
+    session: Session<SessionNullPool>,
+    ViewEngine(v): ViewEngine<TeraView>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    current_manager(&ctx, &auth).await?;
+    let media = media::Model::list(&ctx.db).await?;
+    let errors = session
+        .get::<serde_json::Value>("errors")
+        .unwrap_or(data!({}));
+    session.set("errors", data!({}));
+
+    views::media::library(&v, &media, &errors)
+}
+
+/// Adds the image of the `file` field to the library, with the text of the
+/// `alt` field. Its thumbnails are made in the background.
+#[debug_handler]
+pub async fn upload(
+    auth: auth::JWT,
+    session: Session<SessionNullPool>,
+    State(ctx): State<AppContext>,
+    mut multipart: Multipart,
+) -> Result<Redirect> {
+    let user = current_manager(&ctx, &auth).await?;
+
+    let mut file = None;
+    let mut alt = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|err| Error::BadRequest(err.body_text()))?
+    {
+        match field.name() {
+            Some("file") => {
+                let filename = field.file_name().unwrap_or_default().to_string();
+                let content = field
+                    .bytes()
+                    .await
+                    .map_err(|err| Error::BadRequest(err.body_text()))?;
+                file = Some((filename, content));
+            }
+            Some("alt") => {
+                alt = Some(
+                    field
+                        .text()
+                        .await
+                        .map_err(|err| Error::BadRequest(err.body_text()))?,
+                );
+            }
+            _ => {}
+        }
+    }
+    let Some((filename, content)) = file.filter(|(_, content)| !content.is_empty()) else {
+        session.set("errors", data!({ "file": "choose an image to upload" }));
+        return Ok(Redirect::to("/admin/media"));
+    };
+
+    match media::Model::upload(
+        &ctx.db,
+        &ctx.storage,
+        &filename,
+        content,
+        alt,
+        Some(user.id),
+    )
+    .await
+    {
+        Ok(media) => {
+            ThumbnailWorker::perform_later(&ctx, ThumbnailWorkerArgs { media_id: media.id })
+                .await?;
+        }
+        Err(ModelError::ModelValidation { errors }) => {
+            session.set(
+                "errors",
+                data!({ "file": errors.message.unwrap_or(errors.code) }),
+            );
+        }
+        Err(err) => return Err(err.into()),
+    }
+
+    Ok(Redirect::to("/admin/media"))
+}
+
+#[debug_handler]
+pub async fn remove(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    current_manager(&ctx, &auth).await?;
+    match media::Model::remove(&ctx.db, &ctx.storage, id).await {
+        Ok(()) => Ok(Redirect::to("/admin/media").into_response()),
+        Err(ModelError::EntityNotFound) => not_found(),
+        Err(err) => Err(err.into()),
+    }
+}
+
+async fn serve(ctx: &AppContext, id: i32, size: Option<&str>) -> Result<Response> {
+    let Some(media) = media::Entity::find_by_id(id).one(&ctx.db).await? else {
+        return not_found();
+    };
+    let path = PathBuf::from(media.path_of(size));
+    let content: Vec<u8> = ctx.storage.download(&path).await.map_err(|err| {
+        tracing::error!(
+            err = err.to_string(),
+            path = media.path,
+            "cannot read media"
+        );
+        Error::NotFound
+    })?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, media.mime_type),
+            (
+                header::CACHE_CONTROL,
+                "public, max-age=31536000".to_string(),
+            ),
+        ],
+        content,
+    )
+        .into_response())
+}
+
+/// Serves an image of the library in its original size
+#[debug_handler]
+pub async fn file(Path(id): Path<i32>, State(ctx): State<AppContext>) -> Result<Response> {
+    serve(&ctx, id, None).await
+}
+
+/// Serves a thumbnail of an image, or the original one while the thumbnail
+/// is not made or when the image is smaller than the size
+#[debug_handler]
+pub async fn thumbnail(
+    Path((id, size)): Path<(i32, String)>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    serve(&ctx, id, Some(&size)).await
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("admin/media/")
+        .add("/", get(library))
+        .add("/", post(upload))
+        .add(":id/delete", post(remove))
+}
+
+pub fn file_routes() -> Routes {
+    Routes::new()
+        .prefix("media/")
+        .add(":id", get(file))
+        .add(":id/:size", get(thumbnail))
+}
@@ -1,4 +1,14 @@
+pub mod account;
 pub mod auth;
+pub mod checkout;
+pub mod downloads;
+pub mod emails;
+pub mod media;
+pub mod oauth2;
+pub mod orders;
 
 pub mod products;
+pub mod reviews;
+pub mod shipping;
+pub mod taxes;
 pub mod cart;
\ No newline at end of file
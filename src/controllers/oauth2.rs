@@ -0,0 +1,179 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+use axum::{
+    debug_handler,
+    extract::Query,
+    response::{IntoResponse, Redirect},
+    Extension,
+};
+use axum_extra::extract::CookieJar;
+use axum_session::{Session, SessionNullPool};
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::auth::{
+    add_jwt_cookie, client_ip, current_user, redirect_to_second_factor, requires_second_factor,
+};
+use crate::{
+    initializers::oauth2::OAuth2Providers,
+    models::{audit_logs, user_identities},
+};
+
+/// Session key of the authorization in progress
+const PENDING_AUTHORIZATION: &str = "oauth2_pending";
+
+#[derive(Debug, Deserialize)]
+pub struct CallbackParams {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    /// set by the provider when the user denied the access
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct PendingAuthorization {
+    provider: String,
+    state: String,
+    pkce_verifier: String,
+}
+
+/// Flashes the error on the page the user came from
+fn fail(session: &Session<SessionNullPool>, logged_in: bool, message: &str) -> Redirect {
+    session.set("errors", data!({ "oauth2": message }));
+    if logged_in {
+        Redirect::to("/account/identities")
+    } else {
+        Redirect::to("/auth/login")
+    }
+}
+
+/// Sends the user to the provider, which redirects them to `callback`
+#[debug_handler]
+pub async fn authorize(
+    Path(provider): Path<String>,
+    Extension(providers): Extension<OAuth2Providers>,
+    session: Session<SessionNullPool>,
+) -> Result<Response> {
+    let Some(oauth2_provider) = providers.get(&provider) else {
+        return not_found();
+    };
+    let authorization = oauth2_provider.authorize_url();
+    session.set(
+        PENDING_AUTHORIZATION,
+        PendingAuthorization {
+            provider,
+            state: authorization.state,
+            pkce_verifier: authorization.pkce_verifier,
+        },
+    );
+
+    Ok(Redirect::to(&authorization.url).into_response())
+}
+
+/// Logs the user in with the identity given by the provider, or links it to
+/// the logged in user
+#[debug_handler]
+#[allow(clippy::too_many_arguments)]
+pub async fn callback(
+    auth: Option<auth::JWT>,
+    Path(provider): Path<String>,
+    Query(params): Query<CallbackParams>,
+    Extension(providers): Extension<OAuth2Providers>,
+    session: Session<SessionNullPool>,
+    jar: CookieJar,
+    remote_ip: RemoteIP,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let Some(oauth2_provider) = providers.get(&provider) else {
+        return not_found();
+    };
+    let current = match auth {
+        Some(auth) => current_user(&ctx, &auth).await.ok(),
+        None => None,
+    };
+    let logged_in = current.is_some();
+
+    let pending = session.get::<PendingAuthorization>(PENDING_AUTHORIZATION);
+    session.remove(PENDING_AUTHORIZATION);
+    if params.error.is_some() {
+        return Ok(fail(&session, logged_in, "the login was cancelled").into_response());
+    }
+    let (Some(pending), Some(code), Some(state)) = (pending, params.code, params.state) else {
+        return Ok(
+            fail(&session, logged_in, "the login expired, please try again").into_response(),
+        );
+    };
+    if pending.provider != provider || pending.state != state {
+        return Ok(
+            fail(&session, logged_in, "the login expired, please try again").into_response(),
+        );
+    }
+
+    let profile = match oauth2_provider
+        .fetch_profile(&code, &pending.pkce_verifier)
+        .await
+    {
+        Ok(profile) => profile,
+        Err(err) => {
+            tracing::warn!(provider, error = err.to_string(), "oauth2 login failed");
+            let message = format!("could not log in with {provider}");
+            return Ok(fail(&session, logged_in, &message).into_response());
+        }
+    };
+
+    let (user, linked) =
+        match user_identities::Model::login(&ctx.db, &provider, &profile, current.as_ref()).await {
+            Ok(login) => login,
+            Err(ModelError::EntityAlreadyExists) => {
+                let message = format!("this {provider} account is linked to another user");
+                return Ok(fail(&session, logged_in, &message).into_response());
+            }
+            Err(ModelError::ModelValidation { errors }) => {
+                let message = errors
+                    .message
+                    .unwrap_or_else(|| "could not log in".to_string());
+                return Ok(fail(&session, logged_in, &message).into_response());
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+    let ip_address = client_ip(remote_ip);
+    let details = data!({ "email": user.email, "provider": provider });
+    if linked {
+        audit_logs::Model::record(
+            &ctx.db,
+            audit_logs::IDENTITY_LINKED,
+            Some(user.id),
+            ip_address.as_deref(),
+            &details,
+        )
+        .await?;
+    }
+    if logged_in {
+        return Ok(Redirect::to("/account/identities").into_response());
+    }
+
+    if user.is_locked() {
+        return Ok(fail(&session, false, "this account is locked").into_response());
+    }
+    if requires_second_factor(&ctx, &user)? {
+        return Ok(redirect_to_second_factor(&ctx, &session, &user)?.into_response());
+    }
+    audit_logs::Model::record(
+        &ctx.db,
+        audit_logs::LOGIN_SUCCEEDED,
+        Some(user.id),
+        ip_address.as_deref(),
+        &details,
+    )
+    .await?;
+
+    Ok((add_jwt_cookie(&ctx, jar, &user)?, Redirect::to("/account")).into_response())
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("auth/oauth2/")
+        .add(":provider", get(authorize))
+        .add(":provider/callback", get(callback))
+}
@@ -0,0 +1,86 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+use axum::{debug_handler, extract::Form, response::Redirect};
+use loco_rs::{controller::bad_request, prelude::*};
+use serde::Deserialize;
+
+use super::auth::current_manager;
+use crate::{
+    common::stock_alerts,
+    mailers::order::OrderEmail,
+    models::{orders, stock_movements},
+    views,
+    workers::{
+        downloader::{DownloadWorker, DownloadWorkerArgs},
+        order_emails::{OrderEmailWorker, OrderEmailWorkerArgs},
+    },
+};
+
+#[derive(Debug, Deserialize)]
+pub struct StatusParams {
+    pub status: String,
+}
+
+#[debug_handler]
+pub async fn list(
+    auth: auth::JWT,
+    ViewEngine(v): ViewEngine<TeraView>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    current_manager(&ctx, &auth).await?;
+    let orders = orders::Model::list(&ctx.db).await?;
+
+    views::orders::list(&v, &orders, &orders::STATUSES)
+}
+
+/// Changes the status of an order, giving its stock back when it gets
+/// cancelled or refunded, telling the customer and granting the
+/// downloads once it is paid
+#[debug_handler]
+pub async fn update_status(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    Form(params): Form<StatusParams>,
+) -> Result<Response> {
+    let manager = current_manager(&ctx, &auth).await?;
+    let Some(order) = orders::Entity::find_by_id(id).one(&ctx.db).await? else {
+        return not_found();
+    };
+    if order.status == params.status {
+        return Ok(Redirect::to("/admin/orders").into_response());
+    }
+    let order = match order.update_status(&ctx.db, &params.status).await {
+        Ok(order) => order,
+        Err(ModelError::ModelValidation { errors }) => {
+            return bad_request(errors.message.unwrap_or(errors.code));
+        }
+        Err(err) => return Err(err.into()),
+    };
+    // a cancelled or refunded order gives its stock back
+    let changes = stock_movements::Model::sync_order(&ctx.db, &order, Some(manager.id)).await?;
+    stock_alerts::stock_moved(&ctx, &changes).await?;
+
+    if let Some(email) = OrderEmail::for_status(&order.status) {
+        OrderEmailWorker::perform_later(
+            &ctx,
+            OrderEmailWorkerArgs::Order {
+                order_id: order.id,
+                email,
+            },
+        )
+        .await?;
+    }
+    if order.is_paid() {
+        DownloadWorker::perform_later(&ctx, DownloadWorkerArgs { order_id: order.id }).await?;
+    }
+
+    Ok(Redirect::to("/admin/orders").into_response())
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("admin/orders/")
+        .add("/", get(list))
+        .add(":id/status", post(update_status))
+}
@@ -2,24 +2,55 @@
 #![allow(clippy::unnecessary_struct_initialization)]
 #![allow(clippy::unused_async)]
 use axum::debug_handler;
-use axum::{extract::Form, response::Redirect};
+use axum::{extract::{Form, Query}, http::{header, StatusCode}, response::Redirect};
 use axum_session::{Session, SessionNullPool};
+use chrono::{DateTime, NaiveDateTime, Utc};
 use loco_rs::prelude::*;
-use migration::{Expr};
-use sea_orm::{FromQueryResult, QuerySelect, UpdateResult};
+use sea_orm::{FromQueryResult, QuerySelect};
 use sea_orm::{sea_query::Order, QueryOrder};
 use serde::{Deserialize, Deserializer, Serialize};
-extern crate slug;
-use slug::slugify;
 use tracing::info;
 
+use super::auth::{current_manager, current_user};
 use crate::{
-    models::_entities::products::{ActiveModel, Column, Entity, Model},
+    common::{
+        settings::Settings,
+        stock_alerts::{self, StockLevel, DEFAULT_LOW_STOCK_AMOUNT},
+    },
+    models::{
+        _entities::products::{ActiveModel, Column, Entity, Model},
+        bundle_items::{
+            self, DISCOUNT_META, PRICINGS, PRICING_DISCOUNT, PRICING_FIXED, PRICING_META,
+        },
+        media, product_downloads, product_revisions, product_slugs,
+        products::{
+            ProductStatus, BACKORDERS, BACKORDERS_META, BACKORDERS_NO, BUTTON_TEXT_META,
+            CHILDREN_META, LOW_STOCK_AMOUNT_META, PRODUCT_URL_META, TYPES, TYPE_BUNDLE,
+            TYPE_EXTERNAL, TYPE_GROUPED, TYPE_SIMPLE,
+        },
+        reviews, stock_movements, stock_subscriptions, tax_rates,
+    },
     views,
 };
 use crate::models::_entities::postmetas::{self, ActiveModel as PmActiveModel, Entity as PmEntity};
 
-fn empty_string_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+/// Dates of `datetime-local` inputs, in UTC, with or without seconds
+fn datetime_local<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: String = String::deserialize(deserializer)?;
+    if s.is_empty() {
+        return Ok(None);
+    }
+    ["%Y-%m-%dT%H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(&s, format).ok())
+        .map(|date| Some(date.and_utc()))
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid date {s}")))
+}
+
+pub(crate) fn empty_string_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
 where
     D: Deserializer<'de>,
     T: std::str::FromStr,
@@ -39,7 +70,11 @@ where
 pub struct Params {
     pub title: String,
     pub excerpt: Option<String>,
-    pub status: Option<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub status: Option<ProductStatus>,
+    /// when a scheduled product gets published
+    #[serde(default, deserialize_with = "datetime_local")]
+    pub published_at: Option<DateTime<Utc>>,
     pub product_type: Option<String>,
     pub slug: Option<String>,
 
@@ -51,16 +86,114 @@ pub struct Params {
     pub _sale_price: Option<String>,
     #[serde(deserialize_with = "empty_string_as_none")]
     pub _stock: Option<f32>,
+    /// one of `BACKORDERS`, whether the product can be bought beyond its
+    /// stock, `no` when missing
+    #[serde(default)]
+    pub _backorders: Option<String>,
+    /// low stock threshold of the product, the store one when missing
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub _low_stock_amount: Option<f32>,
+    // shipping, in kg and cm
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub _weight: Option<f32>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub _length: Option<f32>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub _width: Option<f32>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub _height: Option<f32>,
+    /// one of `tax_rates::CLASSES`, standard when missing
+    #[serde(default)]
+    pub _tax_class: Option<String>,
+    /// checkboxes, present when checked. Virtual products are not shipped,
+    /// downloadable ones give access to their files once paid.
+    #[serde(default)]
+    pub _virtual: Option<String>,
+    #[serde(default)]
+    pub _downloadable: Option<String>,
+    /// times each file can be downloaded, unlimited when missing
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub _download_limit: Option<i32>,
+    /// days the files can be downloaded after the purchase, forever when
+    /// missing
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub _download_expiry: Option<i32>,
+    /// comma separated ids of the simple products of a grouped product
+    #[serde(default)]
+    pub _children: Option<String>,
+    /// where the buy button of an external product leads, and its label
+    #[serde(default)]
+    pub _product_url: Option<String>,
+    #[serde(default)]
+    pub _button_text: Option<String>,
+    /// `fixed` for a bundle sold at its own price, `discount` for one sold
+    /// at the price of its products less `_bundle_discount` percent
+    #[serde(default)]
+    pub _bundle_pricing: Option<String>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub _bundle_discount: Option<f32>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct DownloadParams {
+    pub name: String,
+    pub file: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct BundleItemParams {
+    pub product_id: i32,
+    pub quantity: i32,
+    /// checked when the customer chooses whether to buy the product
+    #[serde(default)]
+    pub optional: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ListParams {
+    /// `rating` lists the best rated products first, the latest ones come
+    /// first otherwise
+    pub orderby: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AdminListParams {
+    /// every status but the trash when missing
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub status: Option<ProductStatus>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RevisionParams {
+    /// revision the changes are shown against, the one before when missing
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub compare: Option<i32>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct StockSubscriptionParams {
+    pub email: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ImagesParams {
+    /// media id of the main image
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub featured: Option<i32>,
+    /// comma separated media ids of the gallery, in their order
+    #[serde(default)]
+    pub gallery: String,
 }
 
 impl Params {
-    fn update(&self, item: &mut ActiveModel) {
+    pub(crate) fn update(&self, item: &mut ActiveModel) {
         item.title = Set(self.title.clone());
         item.excerpt = Set(self.excerpt.clone());
-        item.status = Set(self.status.clone());
+        item.status = Set(Some(self.status.unwrap_or_default().to_string()));
+        if let Some(published_at) = self.published_at {
+            item.published_at = Set(Some(published_at.into()));
+        }
         item.product_type = Set(self.product_type.clone());
-        // todo: this is just a placeholder so needed to implement
-        item.author_id = Set(1);
     }
 }
 
@@ -69,46 +202,145 @@ async fn load_item(ctx: &AppContext, id: i32) -> Result<Model> {
     item.ok_or_else(|| Error::NotFound)
 }
 
+/// Records a revision of the product as just saved, by the logged in user
+async fn record_revision(ctx: &AppContext, id: i32, user_id: Option<i32>) -> Result<()> {
+    product_revisions::Model::record(&ctx.db, id, user_id).await?;
+    Ok(())
+}
+
+async fn load_revision(
+    ctx: &AppContext,
+    id: i32,
+    revision_id: i32,
+) -> Result<product_revisions::Model> {
+    match product_revisions::Model::find_for_product(&ctx.db, id, revision_id).await {
+        Ok(revision) => Ok(revision),
+        Err(ModelError::EntityNotFound) => Err(Error::NotFound),
+        Err(err) => Err(err.into()),
+    }
+}
+
 #[debug_handler]
 pub async fn list(
+    Query(params): Query<ListParams>,
     ViewEngine(v): ViewEngine<TeraView>,
     State(ctx): State<AppContext>,
 ) -> Result<Response> {
-    let item = Entity::find()
-        .order_by(Column::Id, Order::Desc)
-        .all(&ctx.db)
-        .await?;
+    let mut item = Model::list_published(&ctx.db).await?;
+    if params.orderby.as_deref() == Some("rating") {
+        let ratings: std::collections::HashMap<i32, f32> = PmEntity::find()
+            .filter(postmetas::Column::MetaKey.eq(reviews::AVERAGE_RATING_META))
+            .all(&ctx.db)
+            .await?
+            .into_iter()
+            .filter_map(|meta| Some((meta.product_id, meta.meta_value?.parse().ok()?)))
+            .collect();
+        let rating = |item: &Model| ratings.get(&item.id).copied().unwrap_or_default();
+        item.sort_by(|a, b| rating(b).total_cmp(&rating(a)));
+    }
     views::products::list(&v, &item)
 }
 
+/// Products of every status for the store staff, to restore the trashed ones
+#[debug_handler]
+pub async fn admin_list(
+    auth: auth::JWT,
+    Query(params): Query<AdminListParams>,
+    ViewEngine(v): ViewEngine<TeraView>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    current_manager(&ctx, &auth).await?;
+    let items = match params.status {
+        Some(status) => Model::list_by_status(&ctx.db, status).await?,
+        None => {
+            Entity::find()
+                .filter(Column::Status.ne(ProductStatus::Trash.as_str()))
+                .order_by(Column::Id, Order::Desc)
+                .all(&ctx.db)
+                .await?
+        }
+    };
+
+    views::products::admin_list(&v, &items, params.status, &ProductStatus::ALL)
+}
+
 #[debug_handler]
 pub async fn new(
+    auth: auth::JWT,
+    session: Session<SessionNullPool>,
     ViewEngine(v): ViewEngine<TeraView>,
-    State(_ctx): State<AppContext>,
+    State(ctx): State<AppContext>,
 ) -> Result<Response> {
-    views::products::create(&v)
+    current_manager(&ctx, &auth).await?;
+    let errors = session.get::<serde_json::Value>("errors").unwrap_or(data!({}));
+    session.set("errors", data!({}));
+
+    views::products::create(&v, &ProductStatus::ALL, &TYPES, &errors)
 }
 
-async fn generate_product_slug(ctx: &AppContext, id: i32, title: &String) -> Result<UpdateResult> {
-    let slug = slugify(format!("{} {}", title, id));
-    let res = Entity::update_many()
-        .col_expr(Column::Slug, Expr::value(slug))
-        .filter(Column::Id.eq(id))
-        .exec(&ctx.db)
+/// Sets a meta of the product, or deletes it when there is no value
+pub(crate) async fn save_optional_meta<C>(
+    db: &C,
+    id: i32,
+    key: &str,
+    value: Option<String>,
+) -> Result<()>
+where
+    C: ConnectionTrait,
+{
+    let old_meta = PmEntity::find()
+        .filter(postmetas::Column::ProductId.eq(id))
+        .filter(postmetas::Column::MetaKey.eq(key))
+        .one(db)
         .await?;
 
-    Ok(res)
+    match (old_meta, value) {
+        (Some(old_meta), Some(value)) => {
+            let mut old_meta: PmActiveModel = old_meta.into();
+            old_meta.meta_value = Set(Some(value));
+            old_meta.update(db).await?;
+        }
+        (Some(old_meta), None) => {
+            old_meta.delete(db).await?;
+        }
+        (None, Some(value)) => {
+            PmActiveModel {
+                product_id: Set(id),
+                meta_key: Set(Some(key.to_string())),
+                meta_value: Set(Some(value)),
+                ..Default::default()
+            }
+            .insert(db)
+            .await?;
+        }
+        (None, None) => {}
+    }
+
+    Ok(())
 }
 
-async fn save_product_meta(ctx: &AppContext, id: i32, params: Params) -> Result<()> {
+/// Saves the metas of the product form, a stock change being recorded in the
+/// stock ledger with the given reason and source. Answers the stock levels
+/// before and after, for the alerts to go once the save is done.
+pub(crate) async fn save_product_meta<C>(
+    db: &C,
+    id: i32,
+    params: Params,
+    reason: &str,
+    source: stock_movements::Source,
+) -> Result<(StockLevel, StockLevel)>
+where
+    C: ConnectionTrait,
+{
     let mut meta_data:Vec<PmActiveModel> = vec![];
+    let stock_before = StockLevel::load(db, id).await?;
 
     // todo: update product metadata such as price, stock, etc
     if params._regular_price.is_some() {
         let old_price = PmEntity::find()
             .filter(postmetas::Column::ProductId.eq(id))
             .filter(postmetas::Column::MetaKey.eq("_regular_price"))
-            .one(&ctx.db)
+            .one(db)
             .await?;
 
         if old_price.is_none() {
@@ -122,7 +354,7 @@ async fn save_product_meta(ctx: &AppContext, id: i32, params: Params) -> Result<
         } else {
             let mut old_price: PmActiveModel = old_price.unwrap().into();
             old_price.meta_value = Set(Some(params._regular_price.unwrap().to_string()));
-            old_price.update(&ctx.db).await?;
+            old_price.update(db).await?;
         }
     }
 
@@ -130,7 +362,7 @@ async fn save_product_meta(ctx: &AppContext, id: i32, params: Params) -> Result<
         let old_sale_price = PmEntity::find()
             .filter(postmetas::Column::ProductId.eq(id))
             .filter(postmetas::Column::MetaKey.eq("_sale_price"))
-            .one(&ctx.db)
+            .one(db)
             .await?;
 
         if old_sale_price.is_none() {
@@ -144,7 +376,7 @@ async fn save_product_meta(ctx: &AppContext, id: i32, params: Params) -> Result<
         } else {
             let mut old_sale_price: PmActiveModel = old_sale_price.unwrap().into();
             old_sale_price.meta_value = Set(Some(params._sale_price.unwrap().to_string()));
-            old_sale_price.update(&ctx.db).await?;
+            old_sale_price.update(db).await?;
         }
     }
 
@@ -152,7 +384,7 @@ async fn save_product_meta(ctx: &AppContext, id: i32, params: Params) -> Result<
         let old_sku = PmEntity::find()
             .filter(postmetas::Column::ProductId.eq(id))
             .filter(postmetas::Column::MetaKey.eq("_sku"))
-            .one(&ctx.db)
+            .one(db)
             .await?;
 
         if old_sku.is_none() {
@@ -166,52 +398,54 @@ async fn save_product_meta(ctx: &AppContext, id: i32, params: Params) -> Result<
         } else {
             let mut old_sku: PmActiveModel = old_sku.unwrap().into();
             old_sku.meta_value = Set(params._sku);
-            old_sku.update(&ctx.db).await?;
+            old_sku.update(db).await?;
         }
     }
     
     let old_stock_status = PmEntity::find()
         .filter(postmetas::Column::ProductId.eq(id))
         .filter(postmetas::Column::MetaKey.eq("_stock_status"))
-        .one(&ctx.db)
+        .one(db)
         .await?;
     let old_manage_stock = PmEntity::find()
         .filter(postmetas::Column::ProductId.eq(id))
         .filter(postmetas::Column::MetaKey.eq("_manage_stock"))
-        .one(&ctx.db)
-        .await?;
-    let old_stock = PmEntity::find()
-        .filter(postmetas::Column::ProductId.eq(id))
-        .filter(postmetas::Column::MetaKey.eq("_stock"))
-        .one(&ctx.db)
+        .one(db)
         .await?;
+    let backorders = params
+        ._backorders
+        .clone()
+        .filter(|backorders| BACKORDERS.contains(&backorders.as_str()))
+        .unwrap_or_else(|| BACKORDERS_NO.to_string());
+    let allows_backorders = backorders != BACKORDERS_NO;
+    save_optional_meta(db, id, BACKORDERS_META, Some(backorders)).await?;
+    let low_stock_amount = params
+        ._low_stock_amount
+        .filter(|amount| params._stock.is_some() && *amount >= 0.0);
+    save_optional_meta(
+        db,
+        id,
+        LOW_STOCK_AMOUNT_META,
+        low_stock_amount.map(|amount| amount.to_string()),
+    )
+    .await?;
 
     if params._stock.is_some() {
         let stock = params._stock.unwrap();
         let stock_status = if stock > 0.0 {
             "instock".to_string()
+        } else if allows_backorders {
+            "onbackorder".to_string()
         } else {
             "outofstock".to_string()
         };
-        
-        if old_stock.is_none() {
-            let meta_stock = PmActiveModel {
-                product_id: Set(id),
-                meta_key: Set(Some("_stock".to_string())),
-                meta_value: Set(Some(stock.to_string())),
-                ..Default::default()
-            };
-            meta_data.push(meta_stock);
-        } else {
-            let mut old_stock: PmActiveModel = old_stock.unwrap().into();
-            old_stock.meta_value = Set(Some(stock.to_string()));
-            old_stock.update(&ctx.db).await?;
-        }
-        
+        // the stock only moves through the ledger, which keeps `_stock`
+        stock_movements::Model::set_stock(db, id, stock, reason, source).await?;
+
         let old_stock_status = PmEntity::find()
             .filter(postmetas::Column::ProductId.eq(id))
             .filter(postmetas::Column::MetaKey.eq("_stock_status"))
-            .one(&ctx.db)
+            .one(db)
             .await?;
 
         if old_stock_status.is_none() {
@@ -225,7 +459,7 @@ async fn save_product_meta(ctx: &AppContext, id: i32, params: Params) -> Result<
         } else {
             let mut old_stock_status: PmActiveModel = old_stock_status.unwrap().into();
             old_stock_status.meta_value = Set(Some(stock_status));
-            old_stock_status.update(&ctx.db).await?;
+            old_stock_status.update(db).await?;
         }
 
         if old_manage_stock.is_none() {
@@ -239,14 +473,16 @@ async fn save_product_meta(ctx: &AppContext, id: i32, params: Params) -> Result<
         } else {
             let mut old_manage_stock: PmActiveModel = old_manage_stock.unwrap().into();
             old_manage_stock.meta_value = Set(Some(true.to_string()));
-            old_manage_stock.update(&ctx.db).await?;
+            old_manage_stock.update(db).await?;
         }
     } else {
-        // delete _stock to avoid parsing error of an empty string
-        if old_stock.is_some() {
-            let old_stock: PmActiveModel = old_stock.unwrap().into();
-            old_stock.delete(&ctx.db).await?;
-        }
+        // delete _stock to avoid parsing error of an empty string, the
+        // ledger keeps the stock for when it gets managed again
+        PmEntity::delete_many()
+            .filter(postmetas::Column::ProductId.eq(id))
+            .filter(postmetas::Column::MetaKey.eq("_stock"))
+            .exec(db)
+            .await?;
 
         if old_manage_stock.is_none() {
             let meta_manage_stock = PmActiveModel {
@@ -259,42 +495,167 @@ async fn save_product_meta(ctx: &AppContext, id: i32, params: Params) -> Result<
         } else {
             let mut old_manage_stock: PmActiveModel = old_manage_stock.unwrap().into();
             old_manage_stock.meta_value = Set(Some(false.to_string()));
-            old_manage_stock.update(&ctx.db).await?;
+            old_manage_stock.update(db).await?;
         }
         
+        // without a stock to count down, the product can always be bought
+        let stock_status = if allows_backorders {
+            "onbackorder".to_string()
+        } else {
+            "instock".to_string()
+        };
         if old_stock_status.is_none() {
             let meta_status_stock = PmActiveModel {
                 product_id: Set(id),
                 meta_key: Set(Some("_stock_status".to_string())),
-                meta_value: Set(Some("onbackorder".to_string())),
+                meta_value: Set(Some(stock_status)),
                 ..Default::default()
             };
             meta_data.push(meta_status_stock);
         } else {
             let mut old_stock_status: PmActiveModel = old_stock_status.unwrap().into();
-            old_stock_status.meta_value = Set(Some("onbackorder".to_string()));
-            old_stock_status.update(&ctx.db).await?;
+            old_stock_status.meta_value = Set(Some(stock_status));
+            old_stock_status.update(db).await?;
         }
     }
 
     if meta_data.len() > 0 {
-        PmEntity::insert_many(meta_data).exec(&ctx.db).await?;
+        PmEntity::insert_many(meta_data).exec(db).await?;
     }
+    let stock_after = StockLevel::load(db, id).await?;
 
-    Ok(())
+    for (key, value) in [
+        ("_weight", params._weight),
+        ("_length", params._length),
+        ("_width", params._width),
+        ("_height", params._height),
+    ] {
+        save_optional_meta(db, id, key, value.map(|value| value.to_string())).await?;
+    }
+
+    // like WooCommerce, the standard class is stored as no class at all
+    let tax_class = params._tax_class.filter(|class| {
+        class != tax_rates::CLASS_STANDARD && tax_rates::CLASSES.contains(&class.as_str())
+    });
+    save_optional_meta(db, id, "_tax_class", tax_class).await?;
+
+    // like WooCommerce, flags are stored as "yes" and unset as no meta
+    let flag = |value: Option<String>| value.map(|_| "yes".to_string());
+    save_optional_meta(db, id, "_virtual", flag(params._virtual)).await?;
+    let downloadable = params._downloadable.is_some();
+    save_optional_meta(db, id, "_downloadable", flag(params._downloadable)).await?;
+    for (key, value) in [
+        ("_download_limit", params._download_limit),
+        ("_download_expiry", params._download_expiry),
+    ] {
+        let value = value.filter(|value| downloadable && *value >= 0);
+        save_optional_meta(db, id, key, value.map(|value| value.to_string())).await?;
+    }
+
+    // the metas of the other types are dropped when the type changes
+    let kind = params.product_type.as_deref();
+    let children = params
+        ._children
+        .map(|ids| media::parse_ids(&ids))
+        .filter(|ids| kind == Some(TYPE_GROUPED) && !ids.is_empty())
+        .map(|ids| {
+            ids.iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
+        });
+    save_optional_meta(db, id, CHILDREN_META, children).await?;
+    for (key, value) in [
+        (PRODUCT_URL_META, params._product_url),
+        (BUTTON_TEXT_META, params._button_text),
+    ] {
+        let value = value
+            .map(|value| value.trim().to_string())
+            .filter(|value| kind == Some(TYPE_EXTERNAL) && !value.is_empty());
+        save_optional_meta(db, id, key, value).await?;
+    }
+    let bundle = kind == Some(TYPE_BUNDLE);
+    let pricing = params
+        ._bundle_pricing
+        .filter(|pricing| bundle && PRICINGS.contains(&pricing.as_str()));
+    let discount = params
+        ._bundle_discount
+        .filter(|_| pricing.as_deref() == Some(PRICING_DISCOUNT))
+        .map(|discount| discount.clamp(0.0, 100.0).to_string());
+    save_optional_meta(db, id, PRICING_META, pricing).await?;
+    save_optional_meta(db, id, DISCOUNT_META, discount).await?;
+
+    Ok((stock_before, stock_after))
+}
+
+/// Checks the products of a grouped product, which must be simple ones,
+/// and the link of an external product. Answers the errors to show on the
+/// form, if any.
+pub(crate) async fn check_linked(
+    ctx: &AppContext,
+    id: Option<i32>,
+    params: &Params,
+) -> Result<Option<serde_json::Value>> {
+    match params.product_type.as_deref() {
+        Some(TYPE_GROUPED) => {
+            let ids = media::parse_ids(params._children.as_deref().unwrap_or_default());
+            let simple = Entity::find()
+                .filter(Column::Id.is_in(ids.clone()))
+                .filter(Column::ProductType.eq(TYPE_SIMPLE))
+                .all(&ctx.db)
+                .await?;
+            let valid = ids.iter().all(|child| {
+                Some(*child) != id && simple.iter().any(|product| product.id == *child)
+            });
+            if !valid {
+                return Ok(Some(
+                    data!({ "children": "Grouped products must be ids of simple products." }),
+                ));
+            }
+        }
+        Some(TYPE_EXTERNAL) => {
+            let url = params._product_url.as_deref().unwrap_or_default().trim();
+            if !url.starts_with("https://") && !url.starts_with("http://") {
+                return Ok(Some(
+                    data!({ "product_url": "Product URL must be a http or https link." }),
+                ));
+            }
+        }
+        _ => {}
+    }
+    Ok(None)
 }
 
 #[debug_handler]
 pub async fn update(
+    auth: auth::JWT,
     Path(id): Path<i32>,
+    session: Session<SessionNullPool>,
     State(ctx): State<AppContext>,
     Form(params): Form<Params>,
 ) -> Result<Redirect> {
+    let user = current_manager(&ctx, &auth).await?;
+    if let Some(errors) = check_linked(&ctx, Some(id), &params).await? {
+        session.set("errors", errors);
+        return Ok(Redirect::to(&format!("/products/{id}/edit")));
+    }
     let mut item = load_item(&ctx, id).await?.into_active_model();
     params.update(&mut item);
-    item.update(&ctx.db).await?;
+    let item = item.update(&ctx.db).await?;
+    // an empty slug keeps the current one, so renaming breaks no link
+    let slug = params.slug.clone().unwrap_or_default();
+    if !slug.trim().is_empty() || item.slug.is_none() {
+        item.set_slug(&ctx.db, &slug).await?;
+    }
 
-    save_product_meta(&ctx, id, params).await?;
+    let source = stock_movements::Source {
+        user_id: Some(user.id),
+        ..Default::default()
+    };
+    let (stock_before, stock_after) =
+        save_product_meta(&ctx.db, id, params, stock_movements::REASON_MANUAL, source).await?;
+    stock_alerts::stock_changed(&ctx, id, &stock_before, &stock_after).await?;
+    record_revision(&ctx, id, Some(user.id)).await?;
     info!("Product updated {:?}", id);
 
     let redirect_url = format!("/products/{}/edit", id); 
@@ -304,11 +665,222 @@ pub async fn update(
 
 #[debug_handler]
 pub async fn edit(
+    auth: auth::JWT,
     Path(id): Path<i32>,
+    session: Session<SessionNullPool>,
     ViewEngine(v): ViewEngine<TeraView>,
     State(ctx): State<AppContext>,
 ) -> Result<Response> {
+    current_manager(&ctx, &auth).await?;
     let item = load_item(&ctx, id).await?;
+    let product = load_view(&ctx, item).await?;
+    let downloads = product_downloads::Model::find_by_product(&ctx.db, id).await?;
+    let library = media::Model::list(&ctx.db).await?;
+    let children = Entity::find()
+        .filter(Column::Id.is_in(product.children.clone()))
+        .all(&ctx.db)
+        .await?;
+    let bundle = bundle_items::Model::find_by_bundle(&ctx.db, id).await?;
+    let errors = session.get::<serde_json::Value>("errors").unwrap_or(data!({}));
+    session.set("errors", data!({}));
+
+    views::products::edit(
+        &v,
+        &product,
+        &downloads,
+        &library,
+        &children,
+        &bundle,
+        &ProductStatus::ALL,
+        &TYPES,
+        &errors,
+    )
+}
+
+/// Sets the featured image and the gallery of the product, from the media
+/// library
+#[debug_handler]
+pub async fn update_images(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    session: Session<SessionNullPool>,
+    State(ctx): State<AppContext>,
+    Form(params): Form<ImagesParams>,
+) -> Result<Redirect> {
+    let user = current_manager(&ctx, &auth).await?;
+    load_item(&ctx, id).await?;
+    let gallery = media::parse_ids(&params.gallery);
+    let ids: Vec<i32> = params.featured.iter().chain(&gallery).copied().collect();
+    let found = media::Entity::find()
+        .filter(media::Column::Id.is_in(ids.clone()))
+        .all(&ctx.db)
+        .await?;
+    if !ids.iter().all(|id| found.iter().any(|media| media.id == *id)) {
+        session.set("errors", data!({ "images": "Images must be ids of the media library." }));
+        return Ok(Redirect::to(&format!("/products/{id}/edit")));
+    }
+
+    let featured = params.featured.map(|featured| featured.to_string());
+    save_optional_meta(&ctx.db, id, media::FEATURED_IMAGE_META, featured).await?;
+    let gallery = (!gallery.is_empty()).then(|| {
+        gallery.iter().map(ToString::to_string).collect::<Vec<_>>().join(",")
+    });
+    save_optional_meta(&ctx.db, id, media::GALLERY_META, gallery).await?;
+    record_revision(&ctx, id, Some(user.id)).await?;
+    info!("Product images updated {:?}", id);
+
+    Ok(Redirect::to(&format!("/products/{id}/edit")))
+}
+
+/// Attaches a file to a downloadable product
+#[debug_handler]
+pub async fn add_download(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    session: Session<SessionNullPool>,
+    State(ctx): State<AppContext>,
+    Form(params): Form<DownloadParams>,
+) -> Result<Redirect> {
+    current_manager(&ctx, &auth).await?;
+    load_item(&ctx, id).await?;
+    if let Err(err) =
+        product_downloads::Model::create(&ctx.db, id, &params.name, &params.file).await
+    {
+        info!("could not add download: {}", err);
+        session.set(
+            "errors",
+            data!({ "download": "Name is required and the file must be a link or a path inside the downloads directory." }),
+        );
+    }
+
+    Ok(Redirect::to(&format!("/products/{id}/edit")))
+}
+
+/// Adds a product to a bundle
+#[debug_handler]
+pub async fn add_bundle_item(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    session: Session<SessionNullPool>,
+    State(ctx): State<AppContext>,
+    Form(params): Form<BundleItemParams>,
+) -> Result<Redirect> {
+    current_manager(&ctx, &auth).await?;
+    load_item(&ctx, id).await?;
+    let optional = params.optional.is_some();
+    if let Err(err) =
+        bundle_items::Model::create(&ctx.db, id, params.product_id, params.quantity, optional).await
+    {
+        info!("could not add bundle item: {}", err);
+        session.set(
+            "errors",
+            data!({ "bundle": "Bundles are made of simple products and variations, at least once each." }),
+        );
+    }
+
+    Ok(Redirect::to(&format!("/products/{id}/edit")))
+}
+
+#[debug_handler]
+pub async fn remove_bundle_item(
+    auth: auth::JWT,
+    Path((id, item_id)): Path<(i32, i32)>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    current_manager(&ctx, &auth).await?;
+    match bundle_items::Model::remove(&ctx.db, id, item_id).await {
+        Ok(()) => Ok(Redirect::to(&format!("/products/{id}/edit")).into_response()),
+        Err(ModelError::EntityNotFound) => not_found(),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[debug_handler]
+pub async fn remove_download(
+    auth: auth::JWT,
+    Path((id, download_id)): Path<(i32, i32)>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    current_manager(&ctx, &auth).await?;
+    match product_downloads::Model::remove(&ctx.db, id, download_id).await {
+        Ok(()) => Ok(Redirect::to(&format!("/products/{id}/edit")).into_response()),
+        Err(ModelError::EntityNotFound) => not_found(),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Loads the meta data of the product, prices and stock included. A bundle
+/// sold at a discount costs what the products always part of it cost, less
+/// the discount.
+pub(crate) async fn load_view(ctx: &AppContext, item: Model) -> Result<ProductView> {
+    let mut product = load_metas(ctx, item).await?;
+    if product.product_type == TYPE_BUNDLE && product.bundle_pricing == PRICING_DISCOUNT {
+        let regular_price = bundle_contents(ctx, product.id, Some(&[]))
+            .await?
+            .iter()
+            .map(|(item, component)| component.price.unwrap_or(0.0) * item.quantity as f32)
+            .sum::<f32>();
+        product.regular_price = Some(regular_price);
+        product.sale_price = product
+            .bundle_discount
+            .filter(|discount| *discount > 0.0)
+            .map(|discount| discounted(regular_price, discount));
+        product.price = product.sale_price.or(product.regular_price);
+    }
+
+    Ok(product)
+}
+
+/// A price less a discount in percent, to the cent
+fn discounted(price: f32, discount: f32) -> f32 {
+    (price * (100.0 - discount)).round() / 100.0
+}
+
+/// Products a bundle is sold with, along with their prices: those always
+/// part of it, and the optional ones among `options`, every one of them when
+/// `None`
+pub(crate) async fn bundle_contents(
+    ctx: &AppContext,
+    id: i32,
+    options: Option<&[i32]>,
+) -> Result<Vec<(bundle_items::Model, ProductView)>> {
+    let items = bundle_items::Model::included(&ctx.db, id, options).await?;
+    let products = Entity::find()
+        .filter(Column::Id.is_in(items.iter().map(|item| item.product_id)))
+        .all(&ctx.db)
+        .await?;
+    let mut contents = vec![];
+    for item in items {
+        if let Some(product) = products
+            .iter()
+            .find(|product| product.id == item.product_id)
+        {
+            let component = load_metas(ctx, product.clone()).await?;
+            contents.push((item, component));
+        }
+    }
+    Ok(contents)
+}
+
+/// Price of a bundle sold with `contents`, the optional products chosen
+/// coming on top of the price of the bundle, discounted alike
+pub(crate) fn bundle_price(
+    bundle: &ProductView,
+    contents: &[(bundle_items::Model, ProductView)],
+) -> f32 {
+    let extras = contents
+        .iter()
+        .filter(|(item, _)| item.optional)
+        .map(|(item, component)| component.price.unwrap_or(0.0) * item.quantity as f32)
+        .sum::<f32>();
+    let extras = match bundle.bundle_discount {
+        Some(discount) if bundle.bundle_pricing == PRICING_DISCOUNT => discounted(extras, discount),
+        _ => extras,
+    };
+    bundle.price.unwrap_or(0.0) + extras
+}
+
+async fn load_metas(ctx: &AppContext, item: Model) -> Result<ProductView> {
     let meta_data = item.find_related(PmEntity)
         .select_only()
         .column(postmetas::Column::Id)
@@ -318,9 +890,7 @@ pub async fn edit(
         .all(&ctx.db) 
         .await?;
 
-    let product = ProductView::build(item, meta_data);
-
-    views::products::edit(&v, &product)
+    Ok(ProductView::build(item, meta_data))
 }
 
 #[derive(FromQueryResult)]
@@ -337,7 +907,8 @@ pub struct ProductView{
     pub slug: String,
     pub product_type: String,
     pub excerpt: String,
-    pub status: String,
+    pub status: ProductStatus,
+    pub published_at: Option<DateTimeWithTimeZone>,
     
     // meta data
     pub sku: Option<String>,
@@ -346,6 +917,28 @@ pub struct ProductView{
     pub price: Option<f32>,
     pub stock: Option<f32>,
     pub stock_status: String,
+    /// one of `BACKORDERS`
+    pub backorders: String,
+    pub low_stock_amount: Option<f32>,
+    pub weight: Option<f32>,
+    pub length: Option<f32>,
+    pub width: Option<f32>,
+    pub height: Option<f32>,
+    pub tax_class: String,
+    pub is_virtual: bool,
+    pub downloadable: bool,
+    pub download_limit: Option<i32>,
+    pub download_expiry: Option<i32>,
+    pub featured_image: Option<i32>,
+    pub gallery: Vec<i32>,
+    /// products of a grouped product
+    pub children: Vec<i32>,
+    pub product_url: Option<String>,
+    pub button_text: Option<String>,
+    /// how a bundle is priced, and the percentage taken off the price of
+    /// its products when sold at a discount
+    pub bundle_pricing: String,
+    pub bundle_discount: Option<f32>,
 }
 
 impl Default for ProductView {
@@ -356,13 +949,32 @@ impl Default for ProductView {
             slug: "".to_string(),
             product_type: "simple".to_string(),
             excerpt: "".to_string(),
-            status: "draft".to_string(),
+            status: ProductStatus::Draft,
+            published_at: None,
             sku: None,
             regular_price: None,
             sale_price: None,
             price: None,
             stock: None,
             stock_status: "".to_string(),
+            backorders: BACKORDERS_NO.to_string(),
+            low_stock_amount: None,
+            weight: None,
+            length: None,
+            width: None,
+            height: None,
+            tax_class: tax_rates::CLASS_STANDARD.to_string(),
+            is_virtual: false,
+            downloadable: false,
+            download_limit: None,
+            download_expiry: None,
+            featured_image: None,
+            gallery: vec![],
+            children: vec![],
+            product_url: None,
+            button_text: None,
+            bundle_pricing: PRICING_FIXED.to_string(),
+            bundle_discount: None,
         }
     } 
 }
@@ -370,11 +982,12 @@ impl Default for ProductView {
 impl ProductView {
     fn build(model: Model, meta_data: Vec<PartialMetaModel>) -> Self {
         let mut product = ProductView::default();
+        product.status = model.status();
         product.id = model.id;
         product.name = model.title;
         product.slug = model.slug.unwrap_or("".to_string());
         product.excerpt = model.excerpt.unwrap_or("".to_string());
-        product.status = model.status.unwrap_or("".to_string());
+        product.published_at = model.published_at;
         product.product_type = model.product_type.unwrap_or("".to_string());
 
         for meta in meta_data {
@@ -394,6 +1007,66 @@ impl ProductView {
                 Some("_stock_status") => {
                     product.stock_status = meta.meta_value.unwrap();
                 }
+                Some(BACKORDERS_META) => {
+                    if let Some(backorders) = meta.meta_value {
+                        product.backorders = backorders;
+                    }
+                }
+                Some(LOW_STOCK_AMOUNT_META) => {
+                    product.low_stock_amount = meta.meta_value.and_then(|value| value.parse().ok());
+                }
+                Some("_weight") => {
+                    product.weight = meta.meta_value.and_then(|value| value.parse().ok());
+                }
+                Some("_length") => {
+                    product.length = meta.meta_value.and_then(|value| value.parse().ok());
+                }
+                Some("_width") => {
+                    product.width = meta.meta_value.and_then(|value| value.parse().ok());
+                }
+                Some("_height") => {
+                    product.height = meta.meta_value.and_then(|value| value.parse().ok());
+                }
+                Some("_tax_class") => {
+                    if let Some(tax_class) = meta.meta_value {
+                        product.tax_class = tax_class;
+                    }
+                }
+                Some("_virtual") => {
+                    product.is_virtual = meta.meta_value.as_deref() == Some("yes");
+                }
+                Some("_downloadable") => {
+                    product.downloadable = meta.meta_value.as_deref() == Some("yes");
+                }
+                Some("_download_limit") => {
+                    product.download_limit = meta.meta_value.and_then(|value| value.parse().ok());
+                }
+                Some("_download_expiry") => {
+                    product.download_expiry = meta.meta_value.and_then(|value| value.parse().ok());
+                }
+                Some(media::FEATURED_IMAGE_META) => {
+                    product.featured_image = meta.meta_value.and_then(|value| value.parse().ok());
+                }
+                Some(media::GALLERY_META) => {
+                    product.gallery = media::parse_ids(&meta.meta_value.unwrap_or_default());
+                }
+                Some(CHILDREN_META) => {
+                    product.children = media::parse_ids(&meta.meta_value.unwrap_or_default());
+                }
+                Some(PRODUCT_URL_META) => {
+                    product.product_url = meta.meta_value;
+                }
+                Some(BUTTON_TEXT_META) => {
+                    product.button_text = meta.meta_value;
+                }
+                Some(PRICING_META) => {
+                    if let Some(pricing) = meta.meta_value {
+                        product.bundle_pricing = pricing;
+                    }
+                }
+                Some(DISCOUNT_META) => {
+                    product.bundle_discount = meta.meta_value.and_then(|value| value.parse().ok());
+                }
                 _ => {}
             }
         }
@@ -410,22 +1083,64 @@ impl ProductView {
     }
 }
 
+/// Renders the page of a product, with the products of a grouped one or of
+/// a bundle
+async fn render_show(
+    v: &TeraView,
+    ctx: &AppContext,
+    session: &Session<SessionNullPool>,
+    item: Model,
+) -> Result<Response> {
+    let errors = session.get::<serde_json::Value>("errors").unwrap_or(data!({}));
+    session.set("errors", data!({}));
+    let subscribed = session.get::<bool>("stock_subscribed").unwrap_or(false);
+    session.remove("stock_subscribed");
+    let (featured_image, gallery) = media::Model::for_product(&ctx.db, item.id).await?;
+    let reviews = reviews::Model::approved_for_product(&ctx.db, item.id).await?;
+    let mut children = vec![];
+    for child in item.children(&ctx.db).await? {
+        children.push(load_view(ctx, child).await?);
+    }
+    let bundle = bundle_contents(ctx, item.id, None).await?;
+    let details = load_view(ctx, item.clone()).await?;
+
+    views::products::show(
+        v,
+        &item,
+        &details,
+        &children,
+        &bundle,
+        featured_image.as_ref(),
+        &gallery,
+        &reviews,
+        subscribed,
+        &errors,
+    )
+}
+
 #[debug_handler]
 pub async fn show(
+    auth: Option<auth::JWT>,
     Path(id): Path<i32>,
     session: Session<SessionNullPool>,
     ViewEngine(v): ViewEngine<TeraView>,
     State(ctx): State<AppContext>,
 ) -> Result<Response> {
     let item = load_item(&ctx, id).await?;
+    // only the staff see the products which are not published
+    if item.status() != ProductStatus::Publish {
+        let Some(auth) = auth else {
+            return Err(Error::NotFound);
+        };
+        if !current_user(&ctx, &auth).await?.can_manage_shop() {
+            return Err(Error::NotFound);
+        }
+    }
     // todo: map meta_data to a object like when mapping remove _ from meta_key converting from
     // vectore/array to object using data! or json! macro from serde
      
     // todo: merge item and meta_data object into one object
-    let errors = session.get::<serde_json::Value>("errors").unwrap_or(data!({}));
-    session.set("errors", data!({}));
-    
-    views::products::show(&v, &item, &errors)
+    render_show(&v, &ctx, &session, item).await
 }
 
 #[debug_handler]
@@ -435,23 +1150,40 @@ pub async fn show_by_slug(
     ViewEngine(v): ViewEngine<TeraView>,
     State(ctx): State<AppContext>,
 ) -> Result<Response> {
-    let item = Entity::find()
-        .filter(Column::Slug.eq(slug))
-        .one(&ctx.db)
-        .await?;
-    let product = item.ok_or_else(|| Error::NotFound)?;
-    let errors = session.get::<serde_json::Value>("errors").unwrap_or(data!({}));
-    session.set("errors", data!({}));
-
-    views::products::show(&v, &product, &errors)
+    let product = match Model::find_published_by_slug(&ctx.db, &slug).await {
+        Ok(product) => product,
+        Err(ModelError::EntityNotFound) => {
+            // links to a slug the product had before lead to its current one
+            let product = product_slugs::Model::find_product(&ctx.db, &slug).await?;
+            return match product {
+                Some(product) if product.status() == ProductStatus::Publish => {
+                    let location = format!("/products/p/{}", product.slug.unwrap_or_default());
+                    let headers = [(header::LOCATION, location)];
+                    Ok((StatusCode::MOVED_PERMANENTLY, headers).into_response())
+                }
+                _ => Err(Error::NotFound),
+            };
+        }
+        Err(err) => return Err(err.into()),
+    };
+    render_show(&v, &ctx, &session, product).await
 }
 
 #[debug_handler]
 pub async fn add(
+    auth: auth::JWT,
+    session: Session<SessionNullPool>,
     State(ctx): State<AppContext>,
     Form(params): Form<Params>,
 ) -> Result<Redirect> {
+    // the product is authored by whoever adds it
+    let user = current_manager(&ctx, &auth).await?;
+    if let Some(errors) = check_linked(&ctx, None, &params).await? {
+        session.set("errors", errors);
+        return Ok(Redirect::to("/products/new"));
+    }
     let mut item = ActiveModel {
+        author_id: Set(user.id),
         ..Default::default()
     };
 
@@ -459,22 +1191,186 @@ pub async fn add(
 
     let res = item.insert(&ctx.db).await?;
 
-    generate_product_slug(&ctx, res.id, &params.title).await?;
-    save_product_meta(&ctx, res.id, params).await?;
+    let slug = params.slug.clone().unwrap_or_default();
+    let res = res.set_slug(&ctx.db, &slug).await?;
+    let source = stock_movements::Source {
+        user_id: Some(user.id),
+        ..Default::default()
+    };
+    let (stock_before, stock_after) = save_product_meta(
+        &ctx.db,
+        res.id,
+        params,
+        stock_movements::REASON_MANUAL,
+        source,
+    )
+    .await?;
+    stock_alerts::stock_changed(&ctx, res.id, &stock_before, &stock_after).await?;
+    record_revision(&ctx, res.id, Some(user.id)).await?;
     
     info!("Product added: {:#?}", res);
     
     Ok(Redirect::to("products"))
 }
 
+/// Moves the product to the trash, or deletes it for good when it already is
 #[debug_handler]
-pub async fn remove(Path(id): Path<i32>, State(ctx): State<AppContext>) -> Result<Response> {
-    load_item(&ctx, id).await?.delete(&ctx.db).await?;
-    info!("Product removed: {}", id);
+pub async fn remove(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    current_manager(&ctx, &auth).await?;
+    let item = load_item(&ctx, id).await?;
+    if item.status() == ProductStatus::Trash {
+        item.delete(&ctx.db).await?;
+        info!("Product removed: {}", id);
+    } else {
+        item.trash(&ctx.db).await?;
+        info!("Product trashed: {}", id);
+    }
     
     format::empty()
 }
 
+/// Takes the product out of the trash
+#[debug_handler]
+pub async fn restore(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Redirect> {
+    current_manager(&ctx, &auth).await?;
+    load_item(&ctx, id).await?.restore(&ctx.db).await?;
+    info!("Product restored: {}", id);
+
+    Ok(Redirect::to("/admin/products?status=trash"))
+}
+
+/// Asks for an email once the product, out of stock for now, is back
+#[debug_handler]
+pub async fn subscribe_stock(
+    Path(id): Path<i32>,
+    session: Session<SessionNullPool>,
+    State(ctx): State<AppContext>,
+    Form(params): Form<StockSubscriptionParams>,
+) -> Result<Redirect> {
+    let item = load_view(&ctx, load_item(&ctx, id).await?).await?;
+    let redirect_to = format!("/products/p/{}", item.slug);
+    if item.stock_status != "outofstock" {
+        session.set(
+            "errors",
+            data!({ "subscription": "This product is in stock." }),
+        );
+        return Ok(Redirect::to(&redirect_to));
+    }
+
+    match stock_subscriptions::Model::subscribe(&ctx.db, id, &params.email).await {
+        Ok(_) => session.set("stock_subscribed", true),
+        Err(err) => {
+            info!(err = err.to_string(), "could not subscribe to the stock");
+            session.set(
+                "errors",
+                data!({ "subscription": "Enter a valid email address." }),
+            );
+        }
+    }
+
+    Ok(Redirect::to(&redirect_to))
+}
+
+/// Products whose managed stock got to their low stock threshold, for the
+/// store staff
+#[debug_handler]
+pub async fn low_stock(
+    auth: auth::JWT,
+    ViewEngine(v): ViewEngine<TeraView>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    current_manager(&ctx, &auth).await?;
+    let settings = Settings::from_context(&ctx)?;
+    let default_amount = settings
+        .inventory
+        .low_stock_amount
+        .unwrap_or(DEFAULT_LOW_STOCK_AMOUNT);
+    let items = Model::list_low_stock(&ctx.db, default_amount).await?;
+
+    views::products::low_stock(&v, &items, default_amount)
+}
+
+/// Revisions of the product, latest first
+#[debug_handler]
+pub async fn revisions(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    ViewEngine(v): ViewEngine<TeraView>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    current_manager(&ctx, &auth).await?;
+    let item = load_item(&ctx, id).await?;
+    let revisions = product_revisions::Model::list_for_product(&ctx.db, id).await?;
+
+    views::products::revisions(&v, &item, &revisions)
+}
+
+/// Movements of the stock of the product, latest first, checking the stock
+/// of the product against them
+#[debug_handler]
+pub async fn stock_history(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    ViewEngine(v): ViewEngine<TeraView>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    current_manager(&ctx, &auth).await?;
+    let item = load_item(&ctx, id).await?;
+    let movements = stock_movements::Model::list_for_product(&ctx.db, id).await?;
+    let stock = StockLevel::load(&ctx.db, id).await?.stock;
+    let balance = stock_movements::Model::balance(&ctx.db, id).await?;
+
+    views::products::stock_history(&v, &item, &movements, stock, balance)
+}
+
+/// What changed in a revision, from the one before or from the revision to
+/// compare with
+#[debug_handler]
+pub async fn revision(
+    auth: auth::JWT,
+    Path((id, revision_id)): Path<(i32, i32)>,
+    Query(params): Query<RevisionParams>,
+    ViewEngine(v): ViewEngine<TeraView>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    current_manager(&ctx, &auth).await?;
+    let item = load_item(&ctx, id).await?;
+    let revision = load_revision(&ctx, id, revision_id).await?;
+    let compared = match params.compare {
+        Some(compare) => Some(load_revision(&ctx, id, compare).await?),
+        None => revision.previous(&ctx.db).await?,
+    };
+    let changes = compared.as_ref().map(|compared| compared.diff(&revision));
+
+    views::products::revision(&v, &item, &revision, compared.as_ref(), changes.as_deref())
+}
+
+/// Puts the product back as it was in a revision
+#[debug_handler]
+pub async fn restore_revision(
+    auth: auth::JWT,
+    Path((id, revision_id)): Path<(i32, i32)>,
+    State(ctx): State<AppContext>,
+) -> Result<Redirect> {
+    let user = current_manager(&ctx, &auth).await?;
+    let revision = load_revision(&ctx, id, revision_id).await?;
+    let stock_before = StockLevel::load(&ctx.db, id).await?;
+    revision.restore(&ctx.db, Some(user.id)).await?;
+    let stock_after = StockLevel::load(&ctx.db, id).await?;
+    stock_alerts::stock_changed(&ctx, id, &stock_before, &stock_after).await?;
+    info!("Product {} restored to revision {}", id, revision_id);
+
+    Ok(Redirect::to(&format!("/admin/products/{id}/revisions")))
+}
+
 pub fn routes() -> Routes {
     Routes::new()
         .prefix("products/")
@@ -486,4 +1382,22 @@ pub fn routes() -> Routes {
         .add(":id/edit", get(edit))
         .add(":id", delete(remove))
         .add(":id", post(update))
+        .add(":id/downloads", post(add_download))
+        .add(":id/downloads/:download_id/delete", post(remove_download))
+        .add(":id/bundle-items", post(add_bundle_item))
+        .add(":id/bundle-items/:item_id/delete", post(remove_bundle_item))
+        .add(":id/images", post(update_images))
+        .add(":id/restore", post(restore))
+        .add(":id/stock-subscriptions", post(subscribe_stock))
+}
+
+pub fn admin_routes() -> Routes {
+    Routes::new()
+        .prefix("admin/products/")
+        .add("/", get(admin_list))
+        .add("low-stock", get(low_stock))
+        .add(":id/stock", get(stock_history))
+        .add(":id/revisions", get(revisions))
+        .add(":id/revisions/:revision_id", get(revision))
+        .add(":id/revisions/:revision_id/restore", post(restore_revision))
 }
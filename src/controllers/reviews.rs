@@ -0,0 +1,143 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+use axum::{debug_handler, extract::Form, extract::Query, response::Redirect};
+use axum_session::{Session, SessionNullPool};
+use loco_rs::{controller::bad_request, prelude::*};
+use serde::Deserialize;
+
+use super::{
+    account::forbidden_scope,
+    auth::{current_manager, current_user},
+};
+use crate::{
+    models::{
+        _entities::products,
+        api_keys::ApiKeyUser,
+        reviews::{self, Rating, ReviewParams},
+    },
+    views,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct StatusParams {
+    pub status: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QueueParams {
+    pub status: Option<String>,
+}
+
+async fn load_product(ctx: &AppContext, id: i32) -> Result<products::Model> {
+    let product = products::Entity::find_by_id(id).one(&ctx.db).await?;
+    product.ok_or_else(|| Error::NotFound)
+}
+
+/// Adds a review of the signed in customer, shown once approved
+#[debug_handler]
+pub async fn add(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    session: Session<SessionNullPool>,
+    State(ctx): State<AppContext>,
+    Form(params): Form<ReviewParams>,
+) -> Result<Redirect> {
+    let user = current_user(&ctx, &auth).await?;
+    let product = load_product(&ctx, id).await?;
+    if let Err(err) = reviews::Model::create(&ctx.db, product.id, &user, &params).await {
+        tracing::info!(err = err.to_string(), "could not add review");
+        session.set(
+            "errors",
+            data!({ "review": "Choose from 1 to 5 stars and write a review." }),
+        );
+    }
+
+    Ok(Redirect::to(&format!("/products/{id}")))
+}
+
+/// Reviews waiting for moderation, or the ones in another status
+#[debug_handler]
+pub async fn queue(
+    auth: auth::JWT,
+    Query(params): Query<QueueParams>,
+    ViewEngine(v): ViewEngine<TeraView>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    current_manager(&ctx, &auth).await?;
+    let status = params
+        .status
+        .unwrap_or_else(|| reviews::STATUS_PENDING.to_string());
+    let reviews = reviews::Model::list_by_status(&ctx.db, &status).await?;
+
+    views::reviews::queue(&v, &reviews, &status, &reviews::STATUSES)
+}
+
+/// Approves a review or marks it as spam
+#[debug_handler]
+pub async fn moderate(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    Form(params): Form<StatusParams>,
+) -> Result<Response> {
+    current_manager(&ctx, &auth).await?;
+    let Some(review) = reviews::Entity::find_by_id(id).one(&ctx.db).await? else {
+        return not_found();
+    };
+    match review.moderate(&ctx.db, &params.status).await {
+        Ok(_) => Ok(Redirect::to("/admin/reviews").into_response()),
+        Err(ModelError::ModelValidation { errors }) => {
+            bad_request(errors.message.unwrap_or(errors.code))
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Approved reviews of a product with its rating
+#[debug_handler]
+pub async fn api_list(Path(id): Path<i32>, State(ctx): State<AppContext>) -> Result<Response> {
+    let product = load_product(&ctx, id).await?;
+    let reviews = reviews::Model::approved_for_product(&ctx.db, product.id).await?;
+
+    format::json(views::reviews::ReviewsResponse::new(
+        &Rating::of(&reviews),
+        &reviews,
+    ))
+}
+
+/// Adds a review of the key owner, for keys with the write scope
+#[debug_handler]
+pub async fn api_add(
+    auth: auth::ApiToken<ApiKeyUser>,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    Json(params): Json<ReviewParams>,
+) -> Result<Response> {
+    if !auth.user.api_key.can_write() {
+        return Err(forbidden_scope());
+    }
+    let product = load_product(&ctx, id).await?;
+    let review = reviews::Model::create(&ctx.db, product.id, &auth.user.user, &params).await?;
+
+    format::json(views::reviews::ReviewResponse::new(&review))
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("products/")
+        .add(":id/reviews", post(add))
+}
+
+pub fn admin_routes() -> Routes {
+    Routes::new()
+        .prefix("admin/reviews/")
+        .add("/", get(queue))
+        .add(":id/status", post(moderate))
+}
+
+pub fn api_routes() -> Routes {
+    Routes::new()
+        .prefix("api/products/")
+        .add(":id/reviews", get(api_list))
+        .add(":id/reviews", post(api_add))
+}
@@ -0,0 +1,187 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+use axum::{debug_handler, extract::Form, response::Redirect};
+use axum_session::{Session, SessionNullPool};
+use loco_rs::prelude::*;
+use serde::Deserialize;
+
+use super::{auth::current_manager, products::empty_string_as_none};
+use crate::{
+    models::{
+        shipping_methods::{self, MethodParams},
+        shipping_zones::{self, ZoneParams},
+    },
+    views,
+};
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ZoneForm {
+    pub name: String,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub position: Option<i32>,
+    /// comma or line separated codes
+    #[serde(default)]
+    pub countries: String,
+    #[serde(default)]
+    pub states: String,
+    #[serde(default)]
+    pub postcodes: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct MethodForm {
+    pub kind: String,
+    pub title: String,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub cost: Option<f32>,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub min_amount: Option<f32>,
+    /// table rate, one `max_weight:cost` row per line
+    #[serde(default)]
+    pub rates: String,
+}
+
+fn split_codes(input: &str) -> Vec<String> {
+    input
+        .split([',', '\n'])
+        .map(str::trim)
+        .filter(|code| !code.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn take_errors(session: &Session<SessionNullPool>) -> serde_json::Value {
+    let errors = session
+        .get::<serde_json::Value>("errors")
+        .unwrap_or(data!({}));
+    session.set("errors", data!({}));
+    errors
+}
+
+/// Shows why the zone or method could not be saved
+fn flash_error(session: &Session<SessionNullPool>, err: &ModelError) {
+    let message = match err {
+        ModelError::ModelValidation { errors } => errors
+            .message
+            .clone()
+            .unwrap_or_else(|| errors.code.clone()),
+        err => err.to_string(),
+    };
+    session.set("errors", data!({ "global": message }));
+}
+
+/// Shipping zones in the order they are matched, with their methods
+#[debug_handler]
+pub async fn index(
+    auth: auth::JWT,
+    session: Session<SessionNullPool>,
+    ViewEngine(v): ViewEngine<TeraView>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    current_manager(&ctx, &auth).await?;
+    let mut zones = vec![];
+    for (zone, locations) in shipping_zones::Model::list(&ctx.db).await? {
+        let methods = zone.methods(&ctx.db).await?;
+        zones.push((zone, locations, methods));
+    }
+    let errors = take_errors(&session);
+
+    views::shipping::index(&v, &zones, &errors)
+}
+
+#[debug_handler]
+pub async fn add_zone(
+    auth: auth::JWT,
+    session: Session<SessionNullPool>,
+    State(ctx): State<AppContext>,
+    Form(params): Form<ZoneForm>,
+) -> Result<Redirect> {
+    current_manager(&ctx, &auth).await?;
+    let params = ZoneParams {
+        name: params.name,
+        position: params.position.unwrap_or_default(),
+        countries: split_codes(&params.countries),
+        states: split_codes(&params.states),
+        postcodes: split_codes(&params.postcodes),
+    };
+    if let Err(err) = shipping_zones::Model::create(&ctx.db, &params).await {
+        flash_error(&session, &err);
+    }
+
+    Ok(Redirect::to("/admin/shipping"))
+}
+
+#[debug_handler]
+pub async fn remove_zone(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    current_manager(&ctx, &auth).await?;
+    match shipping_zones::Model::remove(&ctx.db, id).await {
+        Ok(()) => Ok(Redirect::to("/admin/shipping").into_response()),
+        Err(ModelError::EntityNotFound) => not_found(),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[debug_handler]
+pub async fn add_method(
+    auth: auth::JWT,
+    Path(zone_id): Path<i32>,
+    session: Session<SessionNullPool>,
+    State(ctx): State<AppContext>,
+    Form(params): Form<MethodForm>,
+) -> Result<Redirect> {
+    current_manager(&ctx, &auth).await?;
+    if shipping_zones::Entity::find_by_id(zone_id)
+        .one(&ctx.db)
+        .await?
+        .is_none()
+    {
+        return Err(Error::NotFound);
+    }
+    let rates = match shipping_methods::parse_weight_rates(&params.rates) {
+        Ok(rates) => rates,
+        Err(err) => {
+            flash_error(&session, &err);
+            return Ok(Redirect::to("/admin/shipping"));
+        }
+    };
+    let params = MethodParams {
+        kind: params.kind,
+        title: params.title,
+        cost: params.cost,
+        min_amount: params.min_amount,
+        rates,
+    };
+    if let Err(err) = shipping_methods::Model::create(&ctx.db, zone_id, &params).await {
+        flash_error(&session, &err);
+    }
+
+    Ok(Redirect::to("/admin/shipping"))
+}
+
+#[debug_handler]
+pub async fn remove_method(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    current_manager(&ctx, &auth).await?;
+    match shipping_methods::Model::remove(&ctx.db, id).await {
+        Ok(()) => Ok(Redirect::to("/admin/shipping").into_response()),
+        Err(ModelError::EntityNotFound) => not_found(),
+        Err(err) => Err(err.into()),
+    }
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("admin/shipping/")
+        .add("/", get(index))
+        .add("zones", post(add_zone))
+        .add("zones/:id/delete", post(remove_zone))
+        .add("zones/:id/methods", post(add_method))
+        .add("methods/:id/delete", post(remove_method))
+}
@@ -0,0 +1,105 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+use axum::{debug_handler, extract::Form, response::Redirect};
+use axum_session::{Session, SessionNullPool};
+use loco_rs::prelude::*;
+use serde::Deserialize;
+
+use super::{auth::current_manager, products::empty_string_as_none};
+use crate::{
+    models::tax_rates::{self, RateParams},
+    views,
+};
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RateForm {
+    pub tax_class: String,
+    #[serde(default)]
+    pub country: Option<String>,
+    #[serde(default)]
+    pub state: Option<String>,
+    #[serde(default)]
+    pub postcode: Option<String>,
+    pub name: String,
+    pub rate: f32,
+    #[serde(default, deserialize_with = "empty_string_as_none")]
+    pub priority: Option<i32>,
+    /// checkboxes, present when checked
+    pub compound: Option<String>,
+    pub shipping: Option<String>,
+}
+
+fn take_errors(session: &Session<SessionNullPool>) -> serde_json::Value {
+    let errors = session
+        .get::<serde_json::Value>("errors")
+        .unwrap_or(data!({}));
+    session.set("errors", data!({}));
+    errors
+}
+
+/// Tax rates by class, in the order they are matched
+#[debug_handler]
+pub async fn index(
+    auth: auth::JWT,
+    session: Session<SessionNullPool>,
+    ViewEngine(v): ViewEngine<TeraView>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    current_manager(&ctx, &auth).await?;
+    let rates = tax_rates::Model::list(&ctx.db).await?;
+    let errors = take_errors(&session);
+
+    views::taxes::index(&v, &rates, &errors)
+}
+
+#[debug_handler]
+pub async fn add_rate(
+    auth: auth::JWT,
+    session: Session<SessionNullPool>,
+    State(ctx): State<AppContext>,
+    Form(params): Form<RateForm>,
+) -> Result<Redirect> {
+    current_manager(&ctx, &auth).await?;
+    let params = RateParams {
+        tax_class: params.tax_class,
+        country: params.country,
+        state: params.state,
+        postcode: params.postcode,
+        name: params.name,
+        rate: params.rate,
+        priority: params.priority.unwrap_or(1),
+        compound: params.compound.is_some(),
+        shipping: params.shipping.is_some(),
+    };
+    if let Err(err) = tax_rates::Model::create(&ctx.db, &params).await {
+        let message = match err {
+            ModelError::ModelValidation { errors } => errors.message.unwrap_or(errors.code),
+            err => err.to_string(),
+        };
+        session.set("errors", data!({ "global": message }));
+    }
+
+    Ok(Redirect::to("/admin/taxes"))
+}
+
+#[debug_handler]
+pub async fn remove_rate(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    current_manager(&ctx, &auth).await?;
+    match tax_rates::Model::remove(&ctx.db, id).await {
+        Ok(()) => Ok(Redirect::to("/admin/taxes").into_response()),
+        Err(ModelError::EntityNotFound) => not_found(),
+        Err(err) => Err(err.into()),
+    }
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("admin/taxes/")
+        .add("/", get(index))
+        .add("rates", post(add_rate))
+        .add("rates/:id/delete", post(remove_rate))
+}
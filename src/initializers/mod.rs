@@ -1 +1,2 @@
+pub mod oauth2;
 pub mod view_engine;
@@ -0,0 +1,283 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{async_trait, Extension, Router as AxumRouter};
+use loco_rs::{
+    app::{AppContext, Initializer},
+    Error, Result,
+};
+use oauth2::{
+    basic::BasicClient, AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken,
+    EndpointNotSet, EndpointSet, PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, Scope,
+    TokenResponse, TokenUrl,
+};
+use serde::Deserialize;
+use tracing::info;
+
+use crate::models::user_identities::ExternalProfile;
+
+/// `initializers.oauth2` section of the config
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct OAuth2Config {
+    #[serde(default)]
+    pub authorization_code: Vec<AuthorizationCodeConfig>,
+}
+
+/// An identity provider using the authorization code grant
+#[derive(Clone, Debug, Deserialize)]
+pub struct AuthorizationCodeConfig {
+    /// name of the provider in the login URLs, e.g. `google`
+    pub client_identifier: String,
+    pub client_credentials: ClientCredentials,
+    pub url_config: UrlConfig,
+    #[serde(default)]
+    pub profile_fields: ProfileFields,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ClientCredentials {
+    pub client_id: String,
+    pub client_secret: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct UrlConfig {
+    pub auth_url: String,
+    pub token_url: String,
+    /// must point to `/auth/oauth2/<client_identifier>/callback`
+    pub redirect_url: String,
+    /// endpoint answering the profile of the access token owner as JSON
+    pub profile_url: String,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+/// Names of the profile fields, which differ between providers
+#[derive(Clone, Debug, Deserialize)]
+pub struct ProfileFields {
+    #[serde(default = "default_subject_field")]
+    pub subject: String,
+    #[serde(default = "default_email_field")]
+    pub email: String,
+    #[serde(default = "default_email_verified_field")]
+    pub email_verified: String,
+    #[serde(default = "default_name_field")]
+    pub name: String,
+}
+
+fn default_subject_field() -> String {
+    "sub".to_string()
+}
+
+fn default_email_field() -> String {
+    "email".to_string()
+}
+
+fn default_email_verified_field() -> String {
+    "email_verified".to_string()
+}
+
+fn default_name_field() -> String {
+    "name".to_string()
+}
+
+impl Default for ProfileFields {
+    fn default() -> Self {
+        Self {
+            subject: default_subject_field(),
+            email: default_email_field(),
+            email_verified: default_email_verified_field(),
+            name: default_name_field(),
+        }
+    }
+}
+
+type ConfiguredClient =
+    BasicClient<EndpointSet, EndpointNotSet, EndpointNotSet, EndpointNotSet, EndpointSet>;
+
+/// A configured identity provider
+pub struct OAuth2Provider {
+    config: AuthorizationCodeConfig,
+    client: ConfiguredClient,
+    http_client: reqwest::Client,
+}
+
+/// What the callback needs to check and complete an authorization started by
+/// [`OAuth2Provider::authorize_url`]
+pub struct PendingAuthorization {
+    pub url: String,
+    pub state: String,
+    pub pkce_verifier: String,
+}
+
+impl OAuth2Provider {
+    /// # Errors
+    ///
+    /// When one of the configured URLs is invalid
+    pub fn new(config: AuthorizationCodeConfig) -> Result<Self> {
+        let invalid_url = |e: oauth2::url::ParseError| Error::string(&e.to_string());
+        let mut client =
+            BasicClient::new(ClientId::new(config.client_credentials.client_id.clone()))
+                .set_auth_uri(
+                    AuthUrl::new(config.url_config.auth_url.clone()).map_err(invalid_url)?,
+                )
+                .set_token_uri(
+                    TokenUrl::new(config.url_config.token_url.clone()).map_err(invalid_url)?,
+                )
+                .set_redirect_uri(
+                    RedirectUrl::new(config.url_config.redirect_url.clone())
+                        .map_err(invalid_url)?,
+                );
+        if let Some(client_secret) = &config.client_credentials.client_secret {
+            client = client.set_client_secret(ClientSecret::new(client_secret.clone()));
+        }
+        // following redirects on the token endpoint would expose the code
+        let http_client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(|e| Error::string(&e.to_string()))?;
+
+        Ok(Self {
+            config,
+            client,
+            http_client,
+        })
+    }
+
+    /// URL of the provider to send the user to, along with the state and PKCE
+    /// verifier to keep until the callback
+    #[must_use]
+    pub fn authorize_url(&self) -> PendingAuthorization {
+        let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+        let (url, state) = self
+            .client
+            .authorize_url(CsrfToken::new_random)
+            .add_scopes(
+                self.config
+                    .url_config
+                    .scopes
+                    .iter()
+                    .cloned()
+                    .map(Scope::new),
+            )
+            .set_pkce_challenge(pkce_challenge)
+            .url();
+
+        PendingAuthorization {
+            url: url.to_string(),
+            state: state.secret().clone(),
+            pkce_verifier: pkce_verifier.secret().clone(),
+        }
+    }
+
+    /// Exchanges the code given to the callback for an access token, then
+    /// reads the profile of the user with it
+    ///
+    /// # Errors
+    ///
+    /// When the provider rejects the code or answers an unexpected profile
+    pub async fn fetch_profile(&self, code: &str, pkce_verifier: &str) -> Result<ExternalProfile> {
+        let token = self
+            .client
+            .exchange_code(AuthorizationCode::new(code.to_string()))
+            .set_pkce_verifier(PkceCodeVerifier::new(pkce_verifier.to_string()))
+            .request_async(&self.http_client)
+            .await
+            .map_err(|e| Error::string(&format!("could not exchange the code: {e}")))?;
+
+        let profile = self
+            .http_client
+            .get(&self.config.url_config.profile_url)
+            .bearer_auth(token.access_token().secret())
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| Error::string(&format!("could not fetch the profile: {e}")))?
+            .json::<serde_json::Value>()
+            .await
+            .map_err(|e| Error::string(&format!("invalid profile: {e}")))?;
+
+        self.read_profile(&profile)
+    }
+
+    fn read_profile(&self, profile: &serde_json::Value) -> Result<ExternalProfile> {
+        let fields = &self.config.profile_fields;
+        // some providers use numeric ids
+        let subject = match profile.get(&fields.subject) {
+            Some(serde_json::Value::String(subject)) => subject.clone(),
+            Some(serde_json::Value::Number(subject)) => subject.to_string(),
+            _ => return Err(Error::string("the profile has no subject")),
+        };
+        let text = |field: &str| {
+            profile
+                .get(field)
+                .and_then(serde_json::Value::as_str)
+                .map(str::to_string)
+        };
+
+        Ok(ExternalProfile {
+            subject,
+            email: text(&fields.email),
+            email_verified: profile
+                .get(&fields.email_verified)
+                .and_then(serde_json::Value::as_bool)
+                .unwrap_or(false),
+            name: text(&fields.name),
+        })
+    }
+}
+
+/// The configured identity providers by name, available to handlers as an
+/// `Extension`
+#[derive(Clone, Default)]
+pub struct OAuth2Providers(Arc<HashMap<String, OAuth2Provider>>);
+
+impl OAuth2Providers {
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&OAuth2Provider> {
+        self.0.get(name)
+    }
+
+    /// Names of the providers, sorted
+    #[must_use]
+    pub fn names(&self) -> Vec<String> {
+        let mut names = self.0.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+}
+
+#[allow(clippy::module_name_repetitions)]
+pub struct OAuth2Initializer;
+
+#[async_trait]
+impl Initializer for OAuth2Initializer {
+    fn name(&self) -> String {
+        "oauth2".to_string()
+    }
+
+    async fn after_routes(&self, router: AxumRouter, ctx: &AppContext) -> Result<AxumRouter> {
+        let config = match ctx
+            .config
+            .initializers
+            .as_ref()
+            .and_then(|initializers| initializers.get("oauth2"))
+        {
+            Some(config) => serde_json::from_value::<OAuth2Config>(config.clone())?,
+            None => OAuth2Config::default(),
+        };
+
+        let mut providers = HashMap::new();
+        for provider in config.authorization_code {
+            info!(
+                provider = provider.client_identifier,
+                "oauth2 provider loaded"
+            );
+            providers.insert(
+                provider.client_identifier.clone(),
+                OAuth2Provider::new(provider)?,
+            );
+        }
+
+        Ok(router.layer(Extension(OAuth2Providers(Arc::new(providers)))))
+    }
+}
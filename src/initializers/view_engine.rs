@@ -1,14 +1,14 @@
 use axum::{async_trait, Extension, Router as AxumRouter};
-use fluent_templates::{ArcLoader, FluentLoader};
+use fluent_templates::FluentLoader;
 use loco_rs::{
     app::{AppContext, Initializer},
     controller::views::{engines, ViewEngine},
-    Error, Result,
+    Result,
 };
 use tracing::info;
 
-const I18N_DIR: &str = "assets/i18n";
-const I18N_SHARED: &str = "assets/i18n/shared.ftl";
+use crate::common::i18n::{self, I18N_DIR};
+
 #[allow(clippy::module_name_repetitions)]
 pub struct ViewEngineInitializer;
 
@@ -22,11 +22,7 @@ impl Initializer for ViewEngineInitializer {
         #[allow(unused_mut)]
         let mut tera_engine = engines::TeraView::build()?;
         if std::path::Path::new(I18N_DIR).exists() {
-            let arc = ArcLoader::builder(&I18N_DIR, unic_langid::langid!("en-US"))
-                .shared_resources(Some(&[I18N_SHARED.into()]))
-                .customize(|bundle| bundle.set_use_isolating(false))
-                .build()
-                .map_err(|e| Error::string(&e.to_string()))?;
+            let arc = i18n::loader()?;
             #[cfg(debug_assertions)]
             tera_engine
                 .tera
@@ -4,16 +4,24 @@
 use loco_rs::prelude::*;
 use serde_json::json;
 
+use super::delivery;
 use crate::models::users;
 
 static welcome: Dir<'_> = include_dir!("src/mailers/auth/welcome");
 static forgot: Dir<'_> = include_dir!("src/mailers/auth/forgot");
+static unlock: Dir<'_> = include_dir!("src/mailers/auth/unlock");
+static email_change: Dir<'_> = include_dir!("src/mailers/auth/email_change");
 // #[derive(Mailer)] // -- disabled for faster build speed. it works. but lets
 // move on for now.
 
 #[allow(clippy::module_name_repetitions)]
 pub struct AuthMailer {}
-impl Mailer for AuthMailer {}
+#[async_trait]
+impl Mailer for AuthMailer {
+    async fn mail(ctx: &AppContext, email: &mailer::Email) -> Result<()> {
+        delivery::deliver(ctx, &Self::opts(), email).await
+    }
+}
 impl AuthMailer {
     /// Sending welcome email the the given user
     ///
@@ -62,4 +70,57 @@ impl AuthMailer {
 
         Ok(())
     }
+
+    /// Sending the unlock link of an account locked after too many failed
+    /// logins
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn unlock_account(ctx: &AppContext, user: &users::Model) -> Result<()> {
+        Self::mail_template(
+            ctx,
+            &unlock,
+            mailer::Args {
+                to: user.email.to_string(),
+                locals: json!({
+                  "name": user.name,
+                  "unlockToken": user.unlock_token,
+                  "domain": ctx.config.server.full_url()
+                }),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Sending the verification link of a new email to the pending address
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn confirm_email_change(ctx: &AppContext, user: &users::Model) -> Result<()> {
+        let Some(pending_email) = &user.pending_email else {
+            return Ok(());
+        };
+
+        Self::mail_template(
+            ctx,
+            &email_change,
+            mailer::Args {
+                to: pending_email.to_string(),
+                locals: json!({
+                  "name": user.name,
+                  "verifyToken": user.email_verification_token,
+                  "domain": ctx.config.server.full_url()
+                }),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
 }
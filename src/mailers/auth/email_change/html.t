@@ -0,0 +1,12 @@
+;<html>
+
+<body>
+  Hey {{name}},
+  You asked to use this address as your new email.
+  Confirm the change by clicking the link below:
+  <a href="{{domain}}/auth/verify/{{verifyToken}}">Confirm Your New Email</a>
+  If you didn't ask for it, please ignore this email.
+  Best regards,<br>The Loco Team</br>
+</body>
+
+</html>
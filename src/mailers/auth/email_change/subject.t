@@ -0,0 +1 @@
+Confirm your new email
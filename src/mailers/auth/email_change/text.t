@@ -0,0 +1,7 @@
+Hey {{name}},
+You asked to use this address as your new email.
+Confirm the change with the link below:
+
+{{domain}}/auth/verify/{{verifyToken}}
+
+If you didn't ask for it, please ignore this email.
@@ -0,0 +1,12 @@
+;<html>
+
+<body>
+  Hey {{name}},
+  We locked your account after several failed login attempts.
+  It will unlock by itself in a while, or right away by clicking the link below:
+  <a href="{{domain}}/auth/unlock/{{unlockToken}}">Unlock Your Account</a>
+  If these attempts were not yours, consider changing your password.
+  Best regards,<br>The Loco Team</br>
+</body>
+
+</html>
@@ -0,0 +1 @@
+Unlock your account
@@ -0,0 +1,7 @@
+Hey {{name}},
+We locked your account after several failed login attempts.
+It will unlock by itself in a while, or right away with this link:
+
+{{domain}}/auth/unlock/{{unlockToken}}
+
+If these attempts were not yours, consider changing your password.
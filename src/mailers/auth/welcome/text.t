@@ -1,4 +1,4 @@
 Welcome {{name}}, you can now log in.
   Verify your account with the link below:
 
-  http://localhost/verify#{{verifyToken}}
+  {{domain}}/auth/verify/{{verifyToken}}
@@ -0,0 +1,92 @@
+//! Delivery of the emails of every mailer: queued to the
+//! [`MailDeliveryWorker`], retried, and kept as failed when they cannot be
+//! sent, so a mail outage never fails the request sending the email.
+
+use std::{path::PathBuf, sync::Mutex};
+
+use loco_rs::{
+    mailer::{Email, MailerOpts},
+    prelude::*,
+};
+
+use crate::{
+    common::settings::{MailDeliverySettings, MailSink},
+    models::failed_emails,
+    workers::mail_delivery::MailDeliveryWorker,
+};
+
+pub const DEFAULT_DIR: &str = "tmp/mails";
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+pub const DEFAULT_BACKOFF_MS: u64 = 1000;
+
+static MAILBOX: Mutex<Vec<Email>> = Mutex::new(vec![]);
+
+/// Emails delivered to the mailbox sink, oldest first
+///
+/// # Panics
+///
+/// When the mailbox lock is poisoned
+#[must_use]
+pub fn mailbox() -> Vec<Email> {
+    MAILBOX.lock().expect("mailbox lock").clone()
+}
+
+/// Empties the mailbox sink
+///
+/// # Panics
+///
+/// When the mailbox lock is poisoned
+pub fn clear_mailbox() {
+    MAILBOX.lock().expect("mailbox lock").clear();
+}
+
+/// Queues an email for delivery, with the defaults of the mailer. When it
+/// cannot even be queued, it is kept as failed instead.
+///
+/// # Errors
+///
+/// When the email cannot be kept as failed either
+pub async fn deliver(ctx: &AppContext, opts: &MailerOpts, email: &Email) -> Result<()> {
+    let mut email = email.clone();
+    email.from = Some(email.from.unwrap_or_else(|| opts.from.clone()));
+    email.reply_to = email.reply_to.or_else(|| opts.reply_to.clone());
+
+    if let Err(err) = MailDeliveryWorker::perform_later(ctx, email.clone()).await {
+        tracing::error!(err = err.to_string(), "could not queue email");
+        failed_emails::Model::record(&ctx.db, &email, &err.to_string(), 0).await?;
+    }
+    Ok(())
+}
+
+/// Sends an email once to the configured sink
+///
+/// # Errors
+///
+/// When the sink fails to take the email
+pub async fn send(ctx: &AppContext, settings: &MailDeliverySettings, email: &Email) -> Result<()> {
+    match settings.sink {
+        MailSink::Mailer => {
+            let Some(mailer) = &ctx.mailer else {
+                return Err(Error::Message(
+                    "attempting to send email but no email sender configured".to_string(),
+                ));
+            };
+            mailer.mail(email).await
+        }
+        MailSink::File => {
+            let dir = PathBuf::from(settings.dir.as_deref().unwrap_or(DEFAULT_DIR));
+            tokio::fs::create_dir_all(&dir).await?;
+            let name = format!(
+                "{}-{}.json",
+                chrono::Utc::now().format("%Y%m%d%H%M%S"),
+                Uuid::new_v4().simple()
+            );
+            tokio::fs::write(dir.join(name), serde_json::to_vec_pretty(email)?).await?;
+            Ok(())
+        }
+        MailSink::Mailbox => {
+            MAILBOX.lock().expect("mailbox lock").push(email.clone());
+            Ok(())
+        }
+    }
+}
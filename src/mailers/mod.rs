@@ -1 +1,3 @@
 pub mod auth;
+pub mod delivery;
+pub mod order;
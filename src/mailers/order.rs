@@ -0,0 +1,255 @@
+// order mailer
+#![allow(non_upper_case_globals)]
+
+use fluent_templates::FluentLoader;
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::delivery;
+use crate::{
+    common::{
+        i18n::{self, DEFAULT_LOCALE},
+        settings::Settings,
+    },
+    models::{_entities::products, orders},
+};
+
+static received: Dir<'_> = include_dir!("src/mailers/order/received");
+static processing: Dir<'_> = include_dir!("src/mailers/order/processing");
+static completed: Dir<'_> = include_dir!("src/mailers/order/completed");
+static refunded: Dir<'_> = include_dir!("src/mailers/order/refunded");
+static customer_note: Dir<'_> = include_dir!("src/mailers/order/customer_note");
+static new_order: Dir<'_> = include_dir!("src/mailers/order/new_order");
+static low_stock: Dir<'_> = include_dir!("src/mailers/order/low_stock");
+static no_stock: Dir<'_> = include_dir!("src/mailers/order/no_stock");
+static back_in_stock: Dir<'_> = include_dir!("src/mailers/order/back_in_stock");
+
+/// The emails sent about an order, to the customer unless told otherwise
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum OrderEmail {
+    /// order placed, waiting for its payment
+    Received,
+    /// order paid, being prepared
+    Processing,
+    Completed,
+    Refunded,
+    /// note written by the staff for the customer
+    CustomerNote {
+        note: String,
+    },
+    /// order placed, to the store staff
+    NewOrder,
+}
+
+impl OrderEmail {
+    /// The customer email telling an order got this status, none when the
+    /// status is not worth an email
+    #[must_use]
+    pub fn for_status(status: &str) -> Option<Self> {
+        match status {
+            orders::STATUS_ON_HOLD | orders::STATUS_PENDING => Some(Self::Received),
+            orders::STATUS_PROCESSING => Some(Self::Processing),
+            orders::STATUS_COMPLETED => Some(Self::Completed),
+            orders::STATUS_REFUNDED => Some(Self::Refunded),
+            _ => None,
+        }
+    }
+
+    const fn template(&self) -> &'static Dir<'static> {
+        match self {
+            Self::Received => &received,
+            Self::Processing => &processing,
+            Self::Completed => &completed,
+            Self::Refunded => &refunded,
+            Self::CustomerNote { .. } => &customer_note,
+            Self::NewOrder => &new_order,
+        }
+    }
+}
+
+fn embedded_file(dir: &Dir<'_>, name: &str) -> Result<String> {
+    let file = dir
+        .get_file(name)
+        .ok_or_else(|| Error::Message(format!("no mailer template file found {name}")))?;
+    Ok(String::from_utf8_lossy(file.contents()).to_string())
+}
+
+/// Renders the subject, text and html of a template, like
+/// `Mailer::mail_template` does but with the `t` function of the views
+fn render(
+    dir: &Dir<'_>,
+    locale: &str,
+    to: &str,
+    locals: &serde_json::Value,
+) -> Result<mailer::Email> {
+    let lang = locale
+        .parse()
+        .map_err(|_| Error::Message(format!("invalid email locale {locale}")))?;
+    let mut tera = tera::Tera::default();
+    tera.register_function(
+        "t",
+        FluentLoader::new(i18n::loader()?).with_default_lang(lang),
+    );
+    let context = tera::Context::from_serialize(locals)?;
+    let mut render_file = |name: &str| -> Result<String> {
+        Ok(tera.render_str(&embedded_file(dir, name)?, &context)?)
+    };
+
+    Ok(mailer::Email {
+        to: to.to_string(),
+        subject: render_file("subject.t")?.trim().to_string(),
+        text: render_file("text.t")?,
+        html: render_file("html.t")?,
+        ..Default::default()
+    })
+}
+
+#[allow(clippy::module_name_repetitions)]
+pub struct OrderMailer {}
+#[async_trait]
+impl Mailer for OrderMailer {
+    async fn mail(ctx: &AppContext, email: &mailer::Email) -> Result<()> {
+        delivery::deliver(ctx, &Self::opts(), email).await
+    }
+}
+impl OrderMailer {
+    /// Builds an email about an order, none when it has no recipient: a
+    /// customer email of an order without email address, or a staff email
+    /// without `admin_email` setting.
+    ///
+    /// # Errors
+    ///
+    /// When the templates cannot be rendered or has DB query error
+    pub async fn order_email(
+        ctx: &AppContext,
+        order: &orders::Model,
+        email: &OrderEmail,
+    ) -> Result<Option<mailer::Email>> {
+        let settings = Settings::from_context(ctx)?;
+        let to = match email {
+            OrderEmail::NewOrder => settings.emails.admin_email.clone(),
+            _ => order.email.clone(),
+        };
+        let Some(to) = to else {
+            return Ok(None);
+        };
+        let url = match order.user_id {
+            Some(_) => format!("/account/orders/{}", order.id),
+            None => format!(
+                "/checkout/order-received/{}?key={}",
+                order.id,
+                order.order_key.as_deref().unwrap_or_default()
+            ),
+        };
+        let note = match email {
+            OrderEmail::CustomerNote { note } => Some(note),
+            _ => None,
+        };
+
+        let email = render(
+            email.template(),
+            settings.emails.locale.as_deref().unwrap_or(DEFAULT_LOCALE),
+            &to,
+            &json!({
+              "name": order.billing().map(|billing| billing.first_name).unwrap_or_default(),
+              "order": order,
+              "items": order.items(&ctx.db).await?,
+              "taxes": order.tax_lines(),
+              "note": note,
+              "domain": ctx.config.server.full_url(),
+              "url": url,
+            }),
+        )?;
+        Ok(Some(email))
+    }
+
+    /// Sending an email about an order
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn send(ctx: &AppContext, order: &orders::Model, email: &OrderEmail) -> Result<()> {
+        if let Some(email) = Self::order_email(ctx, order, email).await? {
+            Self::mail(ctx, &email).await?;
+        }
+        Ok(())
+    }
+
+    /// Sending the store staff a product running out of stock
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn low_stock(ctx: &AppContext, product: &products::Model, stock: f32) -> Result<()> {
+        let settings = Settings::from_context(ctx)?;
+        let Some(to) = settings.emails.admin_email else {
+            return Ok(());
+        };
+
+        let email = render(
+            &low_stock,
+            settings.emails.locale.as_deref().unwrap_or(DEFAULT_LOCALE),
+            &to,
+            &json!({
+              "product": product,
+              "stock": stock,
+              "domain": ctx.config.server.full_url(),
+            }),
+        )?;
+        Self::mail(ctx, &email).await?;
+
+        Ok(())
+    }
+
+    /// Sending the store staff a product out of stock
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn no_stock(ctx: &AppContext, product: &products::Model) -> Result<()> {
+        let settings = Settings::from_context(ctx)?;
+        let Some(to) = settings.emails.admin_email else {
+            return Ok(());
+        };
+
+        let email = render(
+            &no_stock,
+            settings.emails.locale.as_deref().unwrap_or(DEFAULT_LOCALE),
+            &to,
+            &json!({
+              "product": product,
+              "domain": ctx.config.server.full_url(),
+            }),
+        )?;
+        Self::mail(ctx, &email).await?;
+
+        Ok(())
+    }
+
+    /// Sending a customer who asked for it a product back in stock
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn back_in_stock(
+        ctx: &AppContext,
+        product: &products::Model,
+        to: &str,
+    ) -> Result<()> {
+        let settings = Settings::from_context(ctx)?;
+        let email = render(
+            &back_in_stock,
+            settings.emails.locale.as_deref().unwrap_or(DEFAULT_LOCALE),
+            to,
+            &json!({
+              "product": product,
+              "domain": ctx.config.server.full_url(),
+            }),
+        )?;
+        Self::mail(ctx, &email).await?;
+
+        Ok(())
+    }
+}
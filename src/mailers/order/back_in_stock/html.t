@@ -0,0 +1,9 @@
+<html>
+
+<body>
+  <p>{{ t(key="back-in-stock-intro", product=product.title) }}</p>
+  <a href="{{domain}}/products/p/{{product.slug}}">{{ t(key="back-in-stock-view") }}</a>
+  <p>{{ t(key="email-signature") }}</p>
+</body>
+
+</html>
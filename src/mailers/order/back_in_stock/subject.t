@@ -0,0 +1 @@
+{{ t(key="back-in-stock-subject", product=product.title) }}
@@ -0,0 +1,5 @@
+{{ t(key="back-in-stock-intro", product=product.title) }}
+
+{{ t(key="back-in-stock-view") }}: {{domain}}/products/p/{{product.slug}}
+
+{{ t(key="email-signature") }}
@@ -0,0 +1 @@
+{{ t(key="order-completed-subject", number=order.id) }}
@@ -0,0 +1 @@
+{{ t(key="order-customer-note-subject", number=order.id) }}
@@ -0,0 +1 @@
+{{ t(key="low-stock-subject", product=product.title) }}
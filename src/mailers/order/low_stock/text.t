@@ -0,0 +1,3 @@
+{{ t(key="low-stock-intro", product=product.title, stock=stock) }}
+
+{{ t(key="low-stock-edit") }}: {{domain}}/products/{{product.id}}/edit
@@ -0,0 +1 @@
+{{ t(key="order-new-order-subject", number=order.id) }}
@@ -0,0 +1,22 @@
+{{ t(key="order-new-order-intro", number=order.id, email=order.email) }}
+
+{{ t(key="order-details", number=order.id) }}
+
+{% for item in items -%}
+{{ item.name }} x {{ item.quantity }}: {{ item.total }}
+{% if item.backordered > 0 -%}
+  {{ t(key="order-backordered", quantity=item.backordered) }}
+{% endif -%}
+{% endfor -%}
+{% if order.shipping_method -%}
+{{ t(key="order-shipping") }}: {{ order.shipping_method }}: {{ order.shipping_total }}
+{% endif -%}
+{% for tax in taxes -%}
+{{ tax.name }} ({{ tax.rate }}%): {{ tax.amount }}
+{% endfor -%}
+{{ t(key="order-total") }}: {{ order.total }}
+{% if order.payment_method_title -%}
+{{ t(key="order-payment-method") }}: {{ order.payment_method_title }}
+{% endif %}{% if order.customer_note %}
+{{ t(key="order-customer-note") }}: {{ order.customer_note }}
+{% endif %}
\ No newline at end of file
@@ -0,0 +1,8 @@
+<html>
+
+<body>
+  <p>{{ t(key="no-stock-intro", product=product.title) }}</p>
+  <a href="{{domain}}/products/{{product.id}}/edit">{{ t(key="low-stock-edit") }}</a>
+</body>
+
+</html>
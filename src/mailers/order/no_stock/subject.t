@@ -0,0 +1 @@
+{{ t(key="no-stock-subject", product=product.title) }}
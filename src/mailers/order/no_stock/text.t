@@ -0,0 +1,3 @@
+{{ t(key="no-stock-intro", product=product.title) }}
+
+{{ t(key="low-stock-edit") }}: {{domain}}/products/{{product.id}}/edit
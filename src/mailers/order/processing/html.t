@@ -0,0 +1,44 @@
+<html>
+
+<body>
+  <p>{{ t(key="email-greeting", name=name) }}</p>
+  <p>{{ t(key="order-processing-intro", number=order.id, status=order.status) }}</p>
+  <h2>{{ t(key="order-details", number=order.id) }}</h2>
+  <table>
+    <tr>
+      <th>{{ t(key="order-product") }}</th>
+      <th>{{ t(key="order-quantity") }}</th>
+      <th>{{ t(key="order-price") }}</th>
+    </tr>
+    {% for item in items %}
+    <tr>
+      <td>{{ item.name }}</td>
+      <td>{{ item.quantity }}</td>
+      <td>{{ item.total }}</td>
+    </tr>
+    {% endfor %}
+    {% if order.shipping_method %}
+    <tr>
+      <th colspan="2">{{ t(key="order-shipping") }}: {{ order.shipping_method }}</th>
+      <td>{{ order.shipping_total }}</td>
+    </tr>
+    {% endif %}
+    {% for tax in taxes %}
+    <tr>
+      <th colspan="2">{{ tax.name }} ({{ tax.rate }}%)</th>
+      <td>{{ tax.amount }}</td>
+    </tr>
+    {% endfor %}
+    <tr>
+      <th colspan="2">{{ t(key="order-total") }}</th>
+      <td>{{ order.total }}</td>
+    </tr>
+  </table>
+  {% if order.payment_method_title %}
+  <p>{{ t(key="order-payment-method") }}: {{ order.payment_method_title }}</p>
+  {% endif %}
+  <a href="{{domain}}{{url}}">{{ t(key="order-view") }}</a>
+  <p>{{ t(key="email-signature") }}</p>
+</body>
+
+</html>
@@ -0,0 +1 @@
+{{ t(key="order-processing-subject", number=order.id) }}
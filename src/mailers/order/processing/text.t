@@ -0,0 +1,22 @@
+{{ t(key="email-greeting", name=name) }}
+
+{{ t(key="order-processing-intro", number=order.id, status=order.status) }}
+
+{{ t(key="order-details", number=order.id) }}
+
+{% for item in items -%}
+{{ item.name }} x {{ item.quantity }}: {{ item.total }}
+{% endfor -%}
+{% if order.shipping_method -%}
+{{ t(key="order-shipping") }}: {{ order.shipping_method }}: {{ order.shipping_total }}
+{% endif -%}
+{% for tax in taxes -%}
+{{ tax.name }} ({{ tax.rate }}%): {{ tax.amount }}
+{% endfor -%}
+{{ t(key="order-total") }}: {{ order.total }}
+{% if order.payment_method_title -%}
+{{ t(key="order-payment-method") }}: {{ order.payment_method_title }}
+{% endif %}
+{{ t(key="order-view") }}: {{domain}}{{url}}
+
+{{ t(key="email-signature") }}
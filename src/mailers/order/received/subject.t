@@ -0,0 +1 @@
+{{ t(key="order-received-subject", number=order.id) }}
@@ -0,0 +1 @@
+{{ t(key="order-refunded-subject", number=order.id) }}
@@ -2,6 +2,29 @@
 
 pub mod prelude;
 
+pub mod addresses;
+pub mod api_keys;
+pub mod audit_logs;
+pub mod bundle_items;
+pub mod download_permissions;
+pub mod failed_emails;
+pub mod login_attempts;
+pub mod media;
+pub mod order_items;
+pub mod orders;
 pub mod postmetas;
+pub mod product_downloads;
+pub mod product_revisions;
+pub mod product_slugs;
 pub mod products;
+pub mod recovery_codes;
+pub mod refresh_tokens;
+pub mod reviews;
+pub mod shipping_methods;
+pub mod shipping_zone_locations;
+pub mod shipping_zones;
+pub mod stock_movements;
+pub mod stock_subscriptions;
+pub mod tax_rates;
+pub mod user_identities;
 pub mod users;
@@ -1,5 +1,28 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.1
 
+pub use super::addresses::Entity as Addresses;
+pub use super::api_keys::Entity as ApiKeys;
+pub use super::audit_logs::Entity as AuditLogs;
+pub use super::bundle_items::Entity as BundleItems;
+pub use super::download_permissions::Entity as DownloadPermissions;
+pub use super::failed_emails::Entity as FailedEmails;
+pub use super::login_attempts::Entity as LoginAttempts;
+pub use super::media::Entity as Media;
+pub use super::order_items::Entity as OrderItems;
+pub use super::orders::Entity as Orders;
 pub use super::postmetas::Entity as Postmetas;
+pub use super::product_downloads::Entity as ProductDownloads;
+pub use super::product_revisions::Entity as ProductRevisions;
+pub use super::product_slugs::Entity as ProductSlugs;
 pub use super::products::Entity as Products;
+pub use super::recovery_codes::Entity as RecoveryCodes;
+pub use super::refresh_tokens::Entity as RefreshTokens;
+pub use super::reviews::Entity as Reviews;
+pub use super::shipping_methods::Entity as ShippingMethods;
+pub use super::shipping_zone_locations::Entity as ShippingZoneLocations;
+pub use super::shipping_zones::Entity as ShippingZones;
+pub use super::stock_movements::Entity as StockMovements;
+pub use super::stock_subscriptions::Entity as StockSubscriptions;
+pub use super::tax_rates::Entity as TaxRates;
+pub use super::user_identities::Entity as UserIdentities;
 pub use super::users::Entity as Users;
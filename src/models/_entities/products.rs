@@ -17,12 +17,27 @@ pub struct Model {
     #[sea_orm(unique)]
     pub slug: Option<String>,
     pub author_id: i32,
+    pub published_at: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::order_items::Entity")]
+    OrderItems,
     #[sea_orm(has_many = "super::postmetas::Entity")]
     Postmetas,
+    #[sea_orm(has_many = "super::product_downloads::Entity")]
+    ProductDownloads,
+    #[sea_orm(has_many = "super::product_revisions::Entity")]
+    ProductRevisions,
+    #[sea_orm(has_many = "super::product_slugs::Entity")]
+    ProductSlugs,
+    #[sea_orm(has_many = "super::reviews::Entity")]
+    Reviews,
+    #[sea_orm(has_many = "super::stock_movements::Entity")]
+    StockMovements,
+    #[sea_orm(has_many = "super::stock_subscriptions::Entity")]
+    StockSubscriptions,
     #[sea_orm(
         belongs_to = "super::users::Entity",
         from = "Column::AuthorId",
@@ -39,8 +54,50 @@ impl Related<super::users::Entity> for Entity {
     }
 }
 
+impl Related<super::order_items::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::OrderItems.def()
+    }
+}
+
 impl Related<super::postmetas::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Postmetas.def()
     }
 }
+
+impl Related<super::product_downloads::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProductDownloads.def()
+    }
+}
+
+impl Related<super::product_revisions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProductRevisions.def()
+    }
+}
+
+impl Related<super::product_slugs::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProductSlugs.def()
+    }
+}
+
+impl Related<super::reviews::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Reviews.def()
+    }
+}
+
+impl Related<super::stock_movements::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::StockMovements.def()
+    }
+}
+
+impl Related<super::stock_subscriptions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::StockSubscriptions.def()
+    }
+}
@@ -0,0 +1,224 @@
+use loco_rs::{prelude::*, validator::ValidationError};
+use sea_orm::TryIntoModel;
+use serde::{Deserialize, Serialize};
+
+pub use super::_entities::addresses::{self, ActiveModel, Column, Entity, Model};
+use crate::common::countries;
+pub type Addresses = Entity;
+
+pub const BILLING: &str = "billing";
+pub const SHIPPING: &str = "shipping";
+
+/// Address fields as submitted by forms, also stored as JSON on orders
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct AddressParams {
+    pub first_name: String,
+    pub last_name: String,
+    pub company: Option<String>,
+    pub address_1: String,
+    pub address_2: Option<String>,
+    pub city: String,
+    pub state: Option<String>,
+    pub postcode: String,
+    pub country: String,
+    pub phone: Option<String>,
+}
+
+/// Empty optional inputs of a form are submitted as empty strings
+fn none_if_empty(value: Option<&String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+impl AddressParams {
+    /// Checks the address against the rules of its country, e.g. US
+    /// addresses need a state and Irish ones may have no postcode. Returns
+    /// the invalid fields along with what is wrong with them.
+    #[must_use]
+    pub fn check(&self) -> Vec<(&'static str, String)> {
+        let mut errors = vec![];
+        for (field, value, label) in [
+            ("first_name", &self.first_name, "First name"),
+            ("last_name", &self.last_name, "Last name"),
+            ("address_1", &self.address_1, "Address"),
+            ("city", &self.city, "City"),
+        ] {
+            if value.trim().is_empty() {
+                errors.push((field, format!("{label} is required.")));
+            }
+        }
+        let country = self.country.trim();
+        if country.len() != 2 {
+            errors.push(("country", "Country must be a 2 letters code.".to_string()));
+            return errors;
+        }
+
+        let rules = countries::rules(country);
+        match none_if_empty(self.state.as_ref()) {
+            None if rules.state_required => {
+                errors.push(("state", format!("{} is required.", rules.state_label)));
+            }
+            Some(state) if !rules.is_valid_state(&state) => {
+                errors.push(("state", format!("{} is not valid.", rules.state_label)));
+            }
+            _ => {}
+        }
+        let postcode = self.postcode.trim();
+        if postcode.is_empty() {
+            if rules.postcode_required {
+                errors.push(("postcode", "Postcode is required.".to_string()));
+            }
+        } else if !rules.is_valid_postcode(postcode) {
+            errors.push(("postcode", "Postcode is not valid.".to_string()));
+        }
+        errors
+    }
+
+    fn update(&self, item: &mut ActiveModel) {
+        item.first_name = Set(self.first_name.trim().to_string());
+        item.last_name = Set(self.last_name.trim().to_string());
+        item.company = Set(none_if_empty(self.company.as_ref()));
+        item.address_1 = Set(self.address_1.trim().to_string());
+        item.address_2 = Set(none_if_empty(self.address_2.as_ref()));
+        item.city = Set(self.city.trim().to_string());
+        item.state = Set(none_if_empty(self.state.as_ref()));
+        item.postcode = Set(self.postcode.trim().to_string());
+        item.country = Set(self.country.trim().to_uppercase());
+        item.phone = Set(none_if_empty(self.phone.as_ref()));
+    }
+}
+
+impl From<Model> for AddressParams {
+    fn from(address: Model) -> Self {
+        Self {
+            first_name: address.first_name,
+            last_name: address.last_name,
+            company: address.company,
+            address_1: address.address_1,
+            address_2: address.address_2,
+            city: address.city,
+            state: address.state,
+            postcode: address.postcode,
+            country: address.country,
+            phone: address.phone,
+        }
+    }
+}
+
+fn is_valid_kind(kind: &str) -> Result<(), ValidationError> {
+    if kind == BILLING || kind == SHIPPING {
+        Ok(())
+    } else {
+        Err(ValidationError::new("invalid address kind"))
+    }
+}
+
+#[derive(Debug, Validate, Deserialize)]
+pub struct Validator {
+    #[validate(custom(function = "is_valid_kind"))]
+    pub kind: String,
+    #[validate(length(min = 1, message = "First name is required."))]
+    pub first_name: String,
+    #[validate(length(min = 1, message = "Last name is required."))]
+    pub last_name: String,
+    #[validate(length(min = 1, message = "Address is required."))]
+    pub address_1: String,
+    #[validate(length(min = 1, message = "City is required."))]
+    pub city: String,
+    #[validate(length(min = 1, message = "Postcode is required."))]
+    pub postcode: String,
+    #[validate(length(equal = 2, message = "Country must be a 2 letters code."))]
+    pub country: String,
+}
+
+impl Validatable for ActiveModel {
+    fn validator(&self) -> Box<dyn Validate> {
+        Box::new(Validator {
+            kind: self.kind.as_ref().to_owned(),
+            first_name: self.first_name.as_ref().to_owned(),
+            last_name: self.last_name.as_ref().to_owned(),
+            address_1: self.address_1.as_ref().to_owned(),
+            city: self.city.as_ref().to_owned(),
+            postcode: self.postcode.as_ref().to_owned(),
+            country: self.country.as_ref().to_owned(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        self.validate()?;
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    /// finds the saved addresses of a user
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn find_by_user(db: &DatabaseConnection, user_id: i32) -> ModelResult<Vec<Self>> {
+        let addresses = Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .all(db)
+            .await?;
+        Ok(addresses)
+    }
+
+    /// finds the billing or shipping address of a user
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn find_by_user_and_kind(
+        db: &DatabaseConnection,
+        user_id: i32,
+        kind: &str,
+    ) -> ModelResult<Option<Self>> {
+        let address = Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .filter(Column::Kind.eq(kind))
+            .one(db)
+            .await?;
+        Ok(address)
+    }
+
+    /// Saves the billing or shipping address of a user, replacing the
+    /// existing one if any
+    ///
+    /// # Errors
+    ///
+    /// When the address is invalid or has DB query error
+    pub async fn save_for_user(
+        db: &DatabaseConnection,
+        user_id: i32,
+        kind: &str,
+        params: &AddressParams,
+    ) -> ModelResult<Self> {
+        let mut item = match Self::find_by_user_and_kind(db, user_id, kind).await? {
+            Some(address) => address.into_active_model(),
+            None => ActiveModel {
+                user_id: Set(user_id),
+                kind: Set(kind.to_string()),
+                ..Default::default()
+            },
+        };
+        params.update(&mut item);
+
+        Ok(item.save(db).await?.try_into_model()?)
+    }
+}
@@ -0,0 +1,193 @@
+use async_trait::async_trait;
+use loco_rs::{prelude::*, validator::ValidationError};
+use sea_orm::QueryOrder;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+pub use super::_entities::api_keys::{self, ActiveModel, Column, Entity, Model};
+use super::users;
+pub type ApiKeys = Entity;
+
+pub const SCOPE_READ: &str = "read";
+pub const SCOPE_WRITE: &str = "write";
+pub const SCOPE_READ_WRITE: &str = "read_write";
+
+/// How many characters of a key are kept in clear to tell keys apart
+const KEY_PREFIX_LENGTH: usize = 11;
+/// `last_used_at` is only written again once older than this, so busy keys
+/// don't cost a write on every request
+const LAST_USED_PRECISION_SECONDS: i64 = 60;
+
+/// Hash under which a key is stored, the key itself is only shown once
+#[must_use]
+pub fn hash_key(key: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(key.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+fn is_valid_scope(scope: &str) -> Result<(), ValidationError> {
+    if [SCOPE_READ, SCOPE_WRITE, SCOPE_READ_WRITE].contains(&scope) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("invalid scope"))
+    }
+}
+
+#[derive(Debug, Validate, Deserialize)]
+pub struct Validator {
+    #[validate(length(min = 1, message = "Name is required."))]
+    pub name: String,
+    #[validate(custom(function = "is_valid_scope"))]
+    pub scope: String,
+}
+
+impl Validatable for ActiveModel {
+    fn validator(&self) -> Box<dyn Validate> {
+        Box::new(Validator {
+            name: self.name.as_ref().to_owned(),
+            scope: self.scope.as_ref().to_owned(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        self.validate()?;
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    /// Creates a key for the user. The returned key is not stored and cannot
+    /// be shown again.
+    ///
+    /// # Errors
+    ///
+    /// When the name or scope is invalid or has DB query error
+    pub async fn generate(
+        db: &DatabaseConnection,
+        user_id: i32,
+        name: &str,
+        scope: &str,
+    ) -> ModelResult<(Self, String)> {
+        let key = format!("lo-{}", Uuid::new_v4().simple());
+        let api_key = ActiveModel {
+            user_id: ActiveValue::set(user_id),
+            name: ActiveValue::set(name.trim().to_string()),
+            key_prefix: ActiveValue::set(key.chars().take(KEY_PREFIX_LENGTH).collect()),
+            key_hash: ActiveValue::set(hash_key(&key)),
+            scope: ActiveValue::set(scope.to_string()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+
+        Ok((api_key, key))
+    }
+
+    /// finds the keys of a user, revoked ones included, most recent first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn find_by_user(db: &DatabaseConnection, user_id: i32) -> ModelResult<Vec<Self>> {
+        let api_keys = Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .order_by_desc(Column::Id)
+            .all(db)
+            .await?;
+        Ok(api_keys)
+    }
+
+    /// finds a key which is not revoked, recording it got used
+    ///
+    /// # Errors
+    ///
+    /// When could not find a valid key or DB query error
+    pub async fn find_active_by_key(db: &DatabaseConnection, key: &str) -> ModelResult<Self> {
+        let api_key = Entity::find()
+            .filter(Column::KeyHash.eq(hash_key(key)))
+            .filter(Column::RevokedAt.is_null())
+            .one(db)
+            .await?
+            .ok_or_else(|| ModelError::EntityNotFound)?;
+
+        let now = chrono::Utc::now();
+        let recently_used = api_key.last_used_at.is_some_and(|last_used_at| {
+            now.signed_duration_since(last_used_at).num_seconds() < LAST_USED_PRECISION_SECONDS
+        });
+        if recently_used {
+            return Ok(api_key);
+        }
+        let mut api_key = api_key.into_active_model();
+        api_key.last_used_at = ActiveValue::set(Some(now.into()));
+        Ok(api_key.update(db).await?)
+    }
+
+    /// Revokes a key of the user, it can no longer be used afterwards
+    ///
+    /// # Errors
+    ///
+    /// When the key does not belong to the user or has DB query error
+    pub async fn revoke(db: &DatabaseConnection, user_id: i32, id: i32) -> ModelResult<Self> {
+        let api_key = Entity::find_by_id(id)
+            .filter(Column::UserId.eq(user_id))
+            .one(db)
+            .await?
+            .ok_or_else(|| ModelError::EntityNotFound)?;
+        if api_key.revoked_at.is_some() {
+            return Ok(api_key);
+        }
+
+        let mut api_key = api_key.into_active_model();
+        api_key.revoked_at = ActiveValue::set(Some(chrono::Utc::now().into()));
+        Ok(api_key.update(db).await?)
+    }
+
+    #[must_use]
+    pub fn can_read(&self) -> bool {
+        self.scope == SCOPE_READ || self.scope == SCOPE_READ_WRITE
+    }
+
+    #[must_use]
+    pub fn can_write(&self) -> bool {
+        self.scope == SCOPE_WRITE || self.scope == SCOPE_READ_WRITE
+    }
+}
+
+/// The user behind an API key along with the key, so handlers using
+/// `auth::ApiToken<ApiKeyUser>` can check its scope
+#[derive(Clone, Debug)]
+pub struct ApiKeyUser {
+    pub user: users::Model,
+    pub api_key: Model,
+}
+
+#[async_trait]
+impl Authenticable for ApiKeyUser {
+    async fn find_by_api_key(db: &DatabaseConnection, api_key: &str) -> ModelResult<Self> {
+        let api_key = Model::find_active_by_key(db, api_key).await?;
+        let user = users::Entity::find_by_id(api_key.user_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| ModelError::EntityNotFound)?;
+        Ok(Self { user, api_key })
+    }
+
+    async fn find_by_claims_key(_db: &DatabaseConnection, _claims_key: &str) -> ModelResult<Self> {
+        Err(ModelError::EntityNotFound)
+    }
+}
@@ -0,0 +1,72 @@
+use chrono::Utc;
+use loco_rs::prelude::*;
+
+pub use super::_entities::audit_logs::{self, ActiveModel, Column, Entity, Model};
+pub type AuditLogs = Entity;
+
+pub const LOGIN_SUCCEEDED: &str = "login.succeeded";
+pub const LOGIN_FAILED: &str = "login.failed";
+pub const LOGIN_THROTTLED: &str = "login.throttled";
+pub const ACCOUNT_LOCKED: &str = "account.locked";
+pub const ACCOUNT_UNLOCKED: &str = "account.unlocked";
+pub const ACCOUNT_UPDATED: &str = "account.updated";
+pub const EMAIL_CHANGE_REQUESTED: &str = "account.email_change_requested";
+pub const PASSWORD_CHANGED: &str = "account.password_changed";
+pub const ACCOUNT_DELETED: &str = "account.deleted";
+pub const API_KEY_CREATED: &str = "api_key.created";
+pub const API_KEY_REVOKED: &str = "api_key.revoked";
+pub const TWO_FACTOR_ENABLED: &str = "two_factor.enabled";
+pub const TWO_FACTOR_DISABLED: &str = "two_factor.disabled";
+pub const TWO_FACTOR_FAILED: &str = "two_factor.failed";
+pub const RECOVERY_CODE_USED: &str = "two_factor.recovery_code_used";
+pub const RECOVERY_CODES_GENERATED: &str = "two_factor.recovery_codes_generated";
+pub const IDENTITY_LINKED: &str = "account.identity_linked";
+pub const IDENTITY_UNLINKED: &str = "account.identity_unlinked";
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    /// Appends an entry to the audit log, `details` is stored as JSON
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn record(
+        db: &DatabaseConnection,
+        action: &str,
+        user_id: Option<i32>,
+        ip_address: Option<&str>,
+        details: &serde_json::Value,
+    ) -> ModelResult<Self> {
+        let now = Utc::now();
+        let entry = ActiveModel {
+            user_id: Set(user_id),
+            action: Set(action.to_string()),
+            ip_address: Set(ip_address.map(ToString::to_string)),
+            details: Set(Some(details.to_string())),
+            created_at: Set(now.into()),
+            updated_at: Set(now.into()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+
+        Ok(entry)
+    }
+}
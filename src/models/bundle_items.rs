@@ -0,0 +1,150 @@
+use loco_rs::{model::ModelValidation, prelude::*};
+use sea_orm::QueryOrder;
+
+pub use super::_entities::bundle_items::{self, ActiveModel, Column, Entity, Model};
+use super::products::{self, TYPE_SIMPLE, TYPE_VARIATION};
+pub type BundleItems = Entity;
+
+/// How a bundle is priced, either at its own price or at the price of its
+/// products less a discount
+pub const PRICING_META: &str = "_bundle_pricing";
+/// Percentage taken off the price of the products of a bundle
+pub const DISCOUNT_META: &str = "_bundle_discount";
+pub const PRICING_FIXED: &str = "fixed";
+pub const PRICING_DISCOUNT: &str = "discount";
+pub const PRICINGS: [&str; 2] = [PRICING_FIXED, PRICING_DISCOUNT];
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+fn invalid(code: &str, message: &str) -> ModelError {
+    ModelError::ModelValidation {
+        errors: ModelValidation {
+            code: code.to_string(),
+            message: Some(message.to_string()),
+        },
+    }
+}
+
+impl Model {
+    /// Adds a product to a bundle, which is always part of it or which the
+    /// customer may choose when optional
+    ///
+    /// # Errors
+    ///
+    /// When the product is not a simple product or a variation, the quantity
+    /// is not positive or has DB query error
+    pub async fn create(
+        db: &DatabaseConnection,
+        bundle_id: i32,
+        product_id: i32,
+        quantity: i32,
+        optional: bool,
+    ) -> ModelResult<Self> {
+        if quantity < 1 {
+            return Err(invalid("quantity", "Quantity must be at least 1."));
+        }
+        let product = products::Entity::find_by_id(product_id).one(db).await?;
+        let bundled = product.is_some_and(|product| {
+            product.id != bundle_id
+                && matches!(
+                    product.product_type.as_deref(),
+                    Some(TYPE_SIMPLE | TYPE_VARIATION)
+                )
+        });
+        if !bundled {
+            return Err(invalid(
+                "product_id",
+                "Bundles are made of simple products and variations.",
+            ));
+        }
+
+        let item = ActiveModel {
+            bundle_id: ActiveValue::set(bundle_id),
+            product_id: ActiveValue::set(product_id),
+            quantity: ActiveValue::set(quantity),
+            optional: ActiveValue::set(optional),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+
+        Ok(item)
+    }
+
+    /// Lists the products of a bundle, in the order they were added
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn find_by_bundle(
+        db: &DatabaseConnection,
+        bundle_id: i32,
+    ) -> ModelResult<Vec<(Self, products::Model)>> {
+        let items = Entity::find()
+            .find_also_related(products::Entity)
+            .filter(Column::BundleId.eq(bundle_id))
+            .order_by_asc(Column::Id)
+            .all(db)
+            .await?;
+        Ok(items
+            .into_iter()
+            .filter_map(|(item, product)| Some((item, product?)))
+            .collect())
+    }
+
+    /// Products a bundle is sold with: those always part of it, and the
+    /// optional ones among `options`, every one of them when `None`
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn included<C>(
+        db: &C,
+        bundle_id: i32,
+        options: Option<&[i32]>,
+    ) -> ModelResult<Vec<Self>>
+    where
+        C: ConnectionTrait,
+    {
+        let items = Entity::find()
+            .filter(Column::BundleId.eq(bundle_id))
+            .order_by_asc(Column::Id)
+            .all(db)
+            .await?;
+        Ok(items
+            .into_iter()
+            .filter(|item| !item.optional || options.is_none_or(|ids| ids.contains(&item.id)))
+            .collect())
+    }
+
+    /// Removes a product from a bundle
+    ///
+    /// # Errors
+    ///
+    /// When could not find the product in the bundle or has DB query error
+    pub async fn remove(db: &DatabaseConnection, bundle_id: i32, id: i32) -> ModelResult<()> {
+        let item = Entity::find_by_id(id)
+            .filter(Column::BundleId.eq(bundle_id))
+            .one(db)
+            .await?
+            .ok_or_else(|| ModelError::EntityNotFound)?;
+        item.delete(db).await?;
+        Ok(())
+    }
+}
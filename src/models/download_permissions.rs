@@ -0,0 +1,249 @@
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use loco_rs::{model::ModelValidation, prelude::*};
+use sea_orm::{sea_query::Expr, Condition, QueryOrder};
+use serde::Serialize;
+use sha2::Sha256;
+
+pub use super::_entities::download_permissions::{self, ActiveModel, Column, Entity, Model};
+use super::_entities::{orders, postmetas, product_downloads};
+pub type DownloadPermissions = Entity;
+
+/// A file the customer of an order may download, with its signed link
+#[derive(Clone, Debug, Serialize)]
+pub struct DownloadLink {
+    pub name: String,
+    pub url: String,
+    pub downloads_remaining: Option<i32>,
+    pub access_expires: Option<DateTimeWithTimeZone>,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+fn denied(code: &str, message: &str) -> ModelError {
+    ModelError::ModelValidation {
+        errors: ModelValidation {
+            code: code.to_string(),
+            message: Some(message.to_string()),
+        },
+    }
+}
+
+/// Signature of a download link, so its expiry cannot be changed
+fn signature(secret: &str, id: i32, key: &str, expires: i64) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac accepts any key size");
+    mac.update(format!("{id}:{key}:{expires}").as_bytes());
+    format!("{:x}", mac.finalize().into_bytes())
+}
+
+/// Download limit and expiry in days of a downloadable product, none when
+/// the product is not downloadable
+async fn download_terms(
+    db: &DatabaseConnection,
+    product_id: i32,
+) -> ModelResult<Option<(Option<i32>, Option<i64>)>> {
+    let metas = postmetas::Entity::find()
+        .filter(postmetas::Column::ProductId.eq(product_id))
+        .filter(postmetas::Column::MetaKey.is_in([
+            "_downloadable",
+            "_download_limit",
+            "_download_expiry",
+        ]))
+        .all(db)
+        .await?;
+    let meta = |key: &str| {
+        metas
+            .iter()
+            .find(|meta| meta.meta_key.as_deref() == Some(key))
+            .and_then(|meta| meta.meta_value.as_deref())
+    };
+    if meta("_downloadable") != Some("yes") {
+        return Ok(None);
+    }
+    Ok(Some((
+        meta("_download_limit").and_then(|limit| limit.parse().ok()),
+        meta("_download_expiry").and_then(|days| days.parse().ok()),
+    )))
+}
+
+impl Model {
+    /// Gives the customer of an order access to the files of its
+    /// downloadable products. Files already granted are skipped, so granting
+    /// again only adds the files attached since.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn grant_for_order(
+        db: &DatabaseConnection,
+        order: &orders::Model,
+    ) -> ModelResult<Vec<Self>> {
+        let items = order.find_related(super::_entities::order_items::Entity).all(db).await?;
+        let granted = Entity::find()
+            .filter(Column::OrderId.eq(order.id))
+            .all(db)
+            .await?;
+
+        let mut permissions = vec![];
+        for product_id in items.iter().filter_map(|item| item.product_id) {
+            let Some((limit, expiry_days)) = download_terms(db, product_id).await? else {
+                continue;
+            };
+            let downloads = product_downloads::Entity::find()
+                .filter(product_downloads::Column::ProductId.eq(product_id))
+                .all(db)
+                .await?;
+            for download in downloads {
+                if granted.iter().any(|permission| permission.download_id == download.id) {
+                    continue;
+                }
+                let permission = ActiveModel {
+                    order_id: ActiveValue::set(order.id),
+                    product_id: ActiveValue::set(product_id),
+                    download_id: ActiveValue::set(download.id),
+                    user_id: ActiveValue::set(order.user_id),
+                    email: ActiveValue::set(order.email.clone().unwrap_or_default()),
+                    download_key: ActiveValue::set(Uuid::new_v4().simple().to_string()),
+                    downloads_remaining: ActiveValue::set(limit),
+                    access_expires: ActiveValue::set(
+                        expiry_days.map(|days| (Utc::now() + Duration::days(days)).into()),
+                    ),
+                    ..Default::default()
+                }
+                .insert(db)
+                .await?;
+                permissions.push(permission);
+            }
+        }
+
+        Ok(permissions)
+    }
+
+    /// Lists the files granted to the customer of an order, with their
+    /// links signed for `ttl`
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn links_for_order(
+        db: &DatabaseConnection,
+        secret: &str,
+        order_id: i32,
+        ttl: Duration,
+    ) -> ModelResult<Vec<DownloadLink>> {
+        let permissions = Entity::find()
+            .filter(Column::OrderId.eq(order_id))
+            .find_also_related(product_downloads::Entity)
+            .order_by_asc(Column::Id)
+            .all(db)
+            .await?;
+        let expires = (Utc::now() + ttl).timestamp();
+
+        Ok(permissions
+            .into_iter()
+            .filter_map(|(permission, download)| {
+                download.map(|download| DownloadLink {
+                    name: download.name,
+                    url: permission.signed_url(secret, expires),
+                    downloads_remaining: permission.downloads_remaining,
+                    access_expires: permission.access_expires,
+                })
+            })
+            .collect())
+    }
+
+    /// Link to download the file, working until `expires` (a UNIX timestamp)
+    #[must_use]
+    pub fn signed_url(&self, secret: &str, expires: i64) -> String {
+        format!(
+            "/downloads/{}?key={}&expires={}&signature={}",
+            self.id,
+            self.download_key,
+            expires,
+            signature(secret, self.id, &self.download_key, expires)
+        )
+    }
+
+    /// Counts a download through a signed link, and gives the file to send
+    ///
+    /// # Errors
+    ///
+    /// When the link is unknown, altered or expired, the access to the file
+    /// expired, the download limit is reached or has DB query error
+    pub async fn download(
+        db: &DatabaseConnection,
+        secret: &str,
+        id: i32,
+        key: &str,
+        expires: i64,
+        given_signature: &str,
+    ) -> ModelResult<(Self, product_downloads::Model)> {
+        let (permission, download) = Entity::find_by_id(id)
+            .filter(Column::DownloadKey.eq(key))
+            .find_also_related(product_downloads::Entity)
+            .one(db)
+            .await?
+            .ok_or_else(|| ModelError::EntityNotFound)?;
+        let download = download.ok_or_else(|| ModelError::EntityNotFound)?;
+
+        if signature(secret, id, key, expires) != given_signature {
+            return Err(denied("link", "this download link is not valid"));
+        }
+        let now = Utc::now();
+        if expires < now.timestamp() {
+            return Err(denied("link", "this download link has expired"));
+        }
+        if permission
+            .access_expires
+            .is_some_and(|access_expires| access_expires < now)
+        {
+            return Err(denied("access", "the access to this file has expired"));
+        }
+
+        // counted at once, so parallel downloads cannot go over the limit
+        let counted = Entity::update_many()
+            .col_expr(
+                Column::DownloadCount,
+                Expr::col(Column::DownloadCount).add(1),
+            )
+            .col_expr(
+                Column::DownloadsRemaining,
+                Expr::col(Column::DownloadsRemaining).sub(1),
+            )
+            .col_expr(Column::UpdatedAt, Expr::value(now))
+            .filter(Column::Id.eq(id))
+            .filter(
+                Condition::any()
+                    .add(Column::DownloadsRemaining.is_null())
+                    .add(Column::DownloadsRemaining.gt(0)),
+            )
+            .exec(db)
+            .await?;
+        if counted.rows_affected == 0 {
+            return Err(denied("limit", "the download limit of this file is reached"));
+        }
+
+        let permission = Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or_else(|| ModelError::EntityNotFound)?;
+        Ok((permission, download))
+    }
+}
@@ -0,0 +1,96 @@
+use loco_rs::{mailer::Email, prelude::*};
+use sea_orm::QueryOrder;
+
+pub use super::_entities::failed_emails::{self, ActiveModel, Column, Entity, Model};
+pub type FailedEmails = Entity;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    /// Keeps an email which could not be delivered, with the last error, so
+    /// it can be sent again later on
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn record(
+        db: &DatabaseConnection,
+        email: &Email,
+        error: &str,
+        attempts: i32,
+    ) -> ModelResult<Self> {
+        let failed = ActiveModel {
+            recipient: ActiveValue::set(email.to.clone()),
+            subject: ActiveValue::set(email.subject.clone()),
+            email: ActiveValue::set(
+                serde_json::to_string(email).map_err(|e| ModelError::Any(e.into()))?,
+            ),
+            error: ActiveValue::set(error.to_string()),
+            attempts: ActiveValue::set(attempts),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+
+        Ok(failed)
+    }
+
+    /// Lists the emails which could not be delivered, latest first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list(db: &DatabaseConnection) -> ModelResult<Vec<Self>> {
+        let failed = Entity::find()
+            .order_by_desc(Column::CreatedAt)
+            .order_by_desc(Column::Id)
+            .all(db)
+            .await?;
+        Ok(failed)
+    }
+
+    /// Takes an email out of the failed ones, to send it again
+    ///
+    /// # Errors
+    ///
+    /// When could not find the email, it cannot be read or has DB query error
+    pub async fn take(db: &DatabaseConnection, id: i32) -> ModelResult<Email> {
+        let failed = Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or_else(|| ModelError::EntityNotFound)?;
+        let email = serde_json::from_str(&failed.email).map_err(|e| ModelError::Any(e.into()))?;
+        failed.delete(db).await?;
+        Ok(email)
+    }
+
+    /// Deletes a failed email for good
+    ///
+    /// # Errors
+    ///
+    /// When could not find the email or has DB query error
+    pub async fn remove(db: &DatabaseConnection, id: i32) -> ModelResult<()> {
+        let failed = Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or_else(|| ModelError::EntityNotFound)?;
+        failed.delete(db).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+use chrono::{Duration, Utc};
+use loco_rs::prelude::*;
+use sea_orm::{sea_query::Expr, PaginatorTrait, QueryOrder};
+
+pub use super::_entities::login_attempts::{self, ActiveModel, Column, Entity, Model};
+pub type LoginAttempts = Entity;
+
+/// Failed attempts allowed on an email before every new attempt gets delayed
+pub const THROTTLE_AFTER_FAILURES: u64 = 3;
+/// Failed attempts on an existing account before it gets locked
+pub const LOCK_AFTER_FAILURES: u64 = 5;
+/// Failed attempts allowed from a single IP address, whatever the email
+pub const MAX_FAILURES_PER_IP: u64 = 20;
+/// Window in which failed attempts are taken into account
+pub const FAILURE_WINDOW_MINUTES: i64 = 15;
+/// How long an account stays locked unless it is unlocked by email
+pub const LOCKOUT_MINUTES: i64 = 30;
+/// Upper bound of the progressive delay between two attempts
+pub const MAX_DELAY_SECONDS: i64 = 300;
+
+/// Whether a login attempt is allowed to go ahead
+#[derive(Debug, PartialEq, Eq)]
+pub enum AttemptCheck {
+    Allowed,
+    /// too many recent failures for this email, retry after the given seconds
+    Throttled(i64),
+    /// too many recent failures coming from this IP address
+    IpBlocked,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+/// Emails are compared case insensitively so `User@x.com` and `user@x.com`
+/// share the same counter
+fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// Seconds a client has to wait after its last failure, doubling with every
+/// failure past [`THROTTLE_AFTER_FAILURES`]
+#[must_use]
+pub fn delay_for(failures: u64) -> i64 {
+    if failures < THROTTLE_AFTER_FAILURES {
+        return 0;
+    }
+    let exponent = u32::try_from(failures - THROTTLE_AFTER_FAILURES + 1).unwrap_or(u32::MAX);
+    2_i64
+        .checked_pow(exponent)
+        .map_or(MAX_DELAY_SECONDS, |delay| delay.min(MAX_DELAY_SECONDS))
+}
+
+impl Model {
+    /// Records a login attempt for the given email and IP address
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn record(
+        db: &DatabaseConnection,
+        email: &str,
+        ip_address: Option<&str>,
+        successful: bool,
+    ) -> ModelResult<Self> {
+        let now = Utc::now();
+        let attempt = ActiveModel {
+            email: Set(normalize_email(email)),
+            ip_address: Set(ip_address.map(ToString::to_string)),
+            successful: Set(successful),
+            created_at: Set(now.into()),
+            updated_at: Set(now.into()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+
+        Ok(attempt)
+    }
+
+    /// Counts the failed attempts made on an email inside the failure window,
+    /// leaving out the cleared ones
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn recent_failures_for_email(
+        db: &DatabaseConnection,
+        email: &str,
+    ) -> ModelResult<u64> {
+        let since = Utc::now() - Duration::minutes(FAILURE_WINDOW_MINUTES);
+        let count = Entity::find()
+            .filter(Column::Email.eq(normalize_email(email)))
+            .filter(Column::Successful.eq(false))
+            .filter(Column::ClearedAt.is_null())
+            .filter(Column::CreatedAt.gt(since))
+            .count(db)
+            .await?;
+
+        Ok(count)
+    }
+
+    /// Counts the failed attempts made from an IP address inside the failure
+    /// window, cleared ones included
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn recent_failures_for_ip(
+        db: &DatabaseConnection,
+        ip_address: &str,
+    ) -> ModelResult<u64> {
+        let since = Utc::now() - Duration::minutes(FAILURE_WINDOW_MINUTES);
+        let count = Entity::find()
+            .filter(Column::IpAddress.eq(ip_address))
+            .filter(Column::Successful.eq(false))
+            .filter(Column::CreatedAt.gt(since))
+            .count(db)
+            .await?;
+
+        Ok(count)
+    }
+
+    /// Checks whether a new login attempt on the given email and from the
+    /// given IP address can be processed. The check does not depend on the
+    /// email belonging to an account so it can't be used to enumerate users.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn check(
+        db: &DatabaseConnection,
+        email: &str,
+        ip_address: Option<&str>,
+    ) -> ModelResult<AttemptCheck> {
+        if let Some(ip_address) = ip_address {
+            if Self::recent_failures_for_ip(db, ip_address).await? >= MAX_FAILURES_PER_IP {
+                return Ok(AttemptCheck::IpBlocked);
+            }
+        }
+
+        let delay = delay_for(Self::recent_failures_for_email(db, email).await?);
+        if delay == 0 {
+            return Ok(AttemptCheck::Allowed);
+        }
+
+        let last_failure = Entity::find()
+            .filter(Column::Email.eq(normalize_email(email)))
+            .filter(Column::Successful.eq(false))
+            .filter(Column::ClearedAt.is_null())
+            .order_by_desc(Column::CreatedAt)
+            .one(db)
+            .await?;
+
+        let Some(last_failure) = last_failure else {
+            return Ok(AttemptCheck::Allowed);
+        };
+        let retry_at = last_failure.created_at + Duration::seconds(delay);
+        let remaining = (retry_at - Utc::now().fixed_offset()).num_seconds();
+
+        if remaining > 0 {
+            Ok(AttemptCheck::Throttled(remaining))
+        } else {
+            Ok(AttemptCheck::Allowed)
+        }
+    }
+
+    /// Forgets the failed attempts of an email, once its owner has logged in
+    /// or unlocked the account. The attempts are only marked as cleared so
+    /// they still count against their IP address.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn clear_failures(db: &DatabaseConnection, email: &str) -> ModelResult<()> {
+        Entity::update_many()
+            .col_expr(Column::ClearedAt, Expr::value(Utc::now().fixed_offset()))
+            .filter(Column::Email.eq(normalize_email(email)))
+            .filter(Column::Successful.eq(false))
+            .filter(Column::ClearedAt.is_null())
+            .exec(db)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,334 @@
+use std::{collections::BTreeMap, io::Cursor, path::Path};
+
+use axum::body::Bytes;
+use chrono::Utc;
+use image::{ImageFormat, ImageReader};
+use loco_rs::{
+    model::ModelValidation,
+    prelude::*,
+    storage::{drivers, Storage},
+};
+use sea_orm::QueryOrder;
+use slug::slugify;
+
+pub use super::_entities::media::{self, ActiveModel, Column, Entity, Model};
+use super::_entities::postmetas;
+use crate::common::settings::{MediaSettings, MediaStorage};
+pub type Media = Entity;
+
+pub const DEFAULT_DIR: &str = "storage/media";
+
+/// Thumbnails made of every image, fitting in squares of these sizes like
+/// the WordPress image sizes. Images smaller than a size have no thumbnail
+/// of it.
+pub const THUMBNAIL_SIZES: [(&str, u32); 3] =
+    [("thumbnail", 150), ("medium", 300), ("large", 1024)];
+
+/// Formats which can be uploaded
+const FORMATS: [ImageFormat; 4] = [
+    ImageFormat::Png,
+    ImageFormat::Jpeg,
+    ImageFormat::Gif,
+    ImageFormat::WebP,
+];
+
+/// Product metas referring to images: the featured image and the comma
+/// separated ids of the gallery, as in WooCommerce
+pub const FEATURED_IMAGE_META: &str = "_thumbnail_id";
+pub const GALLERY_META: &str = "_product_image_gallery";
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+/// The storage of the media library, in the place the settings tell
+///
+/// # Errors
+///
+/// When the local directory cannot be created
+pub fn storage(settings: &MediaSettings) -> Result<Storage> {
+    let driver = match settings.storage {
+        MediaStorage::Local => {
+            let dir = settings.dir.as_deref().unwrap_or(DEFAULT_DIR);
+            std::fs::create_dir_all(dir)?;
+            drivers::local::new_with_prefix(dir)?
+        }
+        MediaStorage::Memory => drivers::mem::new(),
+    };
+    Ok(Storage::single(driver))
+}
+
+fn invalid(message: &str) -> ModelError {
+    ModelError::ModelValidation {
+        errors: ModelValidation {
+            code: "file".to_string(),
+            message: Some(message.to_string()),
+        },
+    }
+}
+
+fn storage_error(err: impl std::error::Error + Send + Sync + 'static) -> ModelError {
+    ModelError::Any(Box::new(err))
+}
+
+/// Path of a thumbnail, next to its image: `a/b.png` has `a/b-medium.png`
+fn thumbnail_path(path: &str, size: &str) -> String {
+    match path.rsplit_once('.') {
+        Some((stem, extension)) => format!("{stem}-{size}.{extension}"),
+        None => format!("{path}-{size}"),
+    }
+}
+
+/// Resizes an image to every thumbnail size it is larger than
+fn make_thumbnails(
+    content: &[u8],
+    format: ImageFormat,
+) -> ModelResult<Vec<(&'static str, Vec<u8>)>> {
+    let image = image::load_from_memory_with_format(content, format)
+        .map_err(|err| ModelError::Any(err.into()))?;
+    let mut thumbnails = vec![];
+    for (size, max) in THUMBNAIL_SIZES {
+        if image.width() <= max && image.height() <= max {
+            continue;
+        }
+        let mut thumbnail = vec![];
+        image
+            .thumbnail(max, max)
+            .write_to(&mut Cursor::new(&mut thumbnail), format)
+            .map_err(|err| ModelError::Any(err.into()))?;
+        thumbnails.push((size, thumbnail));
+    }
+    Ok(thumbnails)
+}
+
+/// Ids of a comma separated list, skipping what is not an id
+#[must_use]
+pub fn parse_ids(ids: &str) -> Vec<i32> {
+    ids.split(',')
+        .filter_map(|id| id.trim().parse().ok())
+        .collect()
+}
+
+impl Model {
+    /// Keeps an uploaded image in the storage and adds it to the library.
+    /// The content tells the format, whatever the file name says.
+    ///
+    /// # Errors
+    ///
+    /// When the file is not an image of a known format, cannot be stored or
+    /// has DB query error
+    pub async fn upload(
+        db: &DatabaseConnection,
+        storage: &Storage,
+        filename: &str,
+        content: Bytes,
+        alt: Option<String>,
+        user_id: Option<i32>,
+    ) -> ModelResult<Self> {
+        let format = image::guess_format(&content)
+            .ok()
+            .filter(|format| FORMATS.contains(format))
+            .ok_or_else(|| invalid("only PNG, JPEG, GIF and WebP images can be uploaded"))?;
+        let (width, height) = ImageReader::with_format(Cursor::new(&content), format)
+            .into_dimensions()
+            .map_err(|_| invalid("the image cannot be read"))?;
+
+        let stem = Path::new(filename)
+            .file_stem()
+            .map(|stem| slugify(stem.to_string_lossy()))
+            .filter(|stem| !stem.is_empty())
+            .unwrap_or_else(|| "image".to_string());
+        let path = format!(
+            "{}/{}-{stem}.{}",
+            Utc::now().format("%Y/%m"),
+            Uuid::new_v4().simple(),
+            format.extensions_str()[0]
+        );
+        storage
+            .upload(Path::new(&path), &content)
+            .await
+            .map_err(storage_error)?;
+
+        let media = ActiveModel {
+            filename: ActiveValue::set(filename.to_string()),
+            path: ActiveValue::set(path),
+            mime_type: ActiveValue::set(format.to_mime_type().to_string()),
+            size: ActiveValue::set(i64::try_from(content.len()).unwrap_or(i64::MAX)),
+            width: ActiveValue::set(i32::try_from(width).ok()),
+            height: ActiveValue::set(i32::try_from(height).ok()),
+            alt: ActiveValue::set(
+                alt.map(|alt| alt.trim().to_string())
+                    .filter(|alt| !alt.is_empty()),
+            ),
+            user_id: ActiveValue::set(user_id),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+
+        Ok(media)
+    }
+
+    /// Lists the library, latest uploads first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list(db: &DatabaseConnection) -> ModelResult<Vec<Self>> {
+        let media = Entity::find()
+            .order_by_desc(Column::CreatedAt)
+            .order_by_desc(Column::Id)
+            .all(db)
+            .await?;
+        Ok(media)
+    }
+
+    /// Featured image and gallery of a product, in the order of the gallery
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn for_product(
+        db: &DatabaseConnection,
+        product_id: i32,
+    ) -> ModelResult<(Option<Self>, Vec<Self>)> {
+        let metas = postmetas::Entity::find()
+            .filter(postmetas::Column::ProductId.eq(product_id))
+            .filter(postmetas::Column::MetaKey.is_in([FEATURED_IMAGE_META, GALLERY_META]))
+            .all(db)
+            .await?;
+        let meta = |key: &str| {
+            metas
+                .iter()
+                .find(|meta| meta.meta_key.as_deref() == Some(key))
+                .and_then(|meta| meta.meta_value.as_deref())
+                .map(parse_ids)
+                .unwrap_or_default()
+        };
+        let featured = meta(FEATURED_IMAGE_META);
+        let gallery = meta(GALLERY_META);
+
+        let media = Entity::find()
+            .filter(Column::Id.is_in(featured.iter().chain(&gallery).copied()))
+            .all(db)
+            .await?;
+        let find = |id: &i32| media.iter().find(|media| media.id == *id).cloned();
+        Ok((
+            featured.first().and_then(find),
+            gallery.iter().filter_map(find).collect(),
+        ))
+    }
+
+    /// Paths of the thumbnails made so far, by size
+    #[must_use]
+    pub fn thumbnails(&self) -> BTreeMap<String, String> {
+        self.thumbnails
+            .as_deref()
+            .and_then(|thumbnails| serde_json::from_str(thumbnails).ok())
+            .unwrap_or_default()
+    }
+
+    /// Path of the image in a size, the original one when it has no
+    /// thumbnail of this size
+    #[must_use]
+    pub fn path_of(&self, size: Option<&str>) -> String {
+        size.and_then(|size| self.thumbnails().remove(size))
+            .unwrap_or_else(|| self.path.clone())
+    }
+
+    /// Makes the thumbnails of the image, replacing the ones made before
+    ///
+    /// # Errors
+    ///
+    /// When the image cannot be read or stored or has DB query error
+    pub async fn generate_thumbnails(
+        self,
+        db: &DatabaseConnection,
+        storage: &Storage,
+    ) -> ModelResult<Self> {
+        let content: Vec<u8> = storage
+            .download(Path::new(&self.path))
+            .await
+            .map_err(storage_error)?;
+        let format = ImageFormat::from_mime_type(&self.mime_type)
+            .ok_or_else(|| invalid("the image format is not known"))?;
+        let thumbnails = tokio::task::spawn_blocking(move || make_thumbnails(&content, format))
+            .await
+            .map_err(|err| ModelError::Any(err.into()))??;
+
+        let mut paths = BTreeMap::new();
+        for (size, thumbnail) in thumbnails {
+            let path = thumbnail_path(&self.path, size);
+            storage
+                .upload(Path::new(&path), &Bytes::from(thumbnail))
+                .await
+                .map_err(storage_error)?;
+            paths.insert(size.to_string(), path);
+        }
+
+        let mut media = self.into_active_model();
+        media.thumbnails = ActiveValue::set(Some(
+            serde_json::to_string(&paths).map_err(|err| ModelError::Any(err.into()))?,
+        ));
+        Ok(media.update(db).await?)
+    }
+
+    /// Deletes an image along with its thumbnails, and takes it off the
+    /// products showing it
+    ///
+    /// # Errors
+    ///
+    /// When the image is not found or has DB query error
+    pub async fn remove(db: &DatabaseConnection, storage: &Storage, id: i32) -> ModelResult<()> {
+        let media = Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or_else(|| ModelError::EntityNotFound)?;
+
+        for path in std::iter::once(media.path.clone()).chain(media.thumbnails().into_values()) {
+            // a missing file is not worth keeping the image in the library
+            if let Err(err) = storage.delete(Path::new(&path)).await {
+                tracing::warn!(err = err.to_string(), path, "cannot delete media file");
+            }
+        }
+
+        postmetas::Entity::delete_many()
+            .filter(postmetas::Column::MetaKey.eq(FEATURED_IMAGE_META))
+            .filter(postmetas::Column::MetaValue.eq(id.to_string()))
+            .exec(db)
+            .await?;
+        let galleries = postmetas::Entity::find()
+            .filter(postmetas::Column::MetaKey.eq(GALLERY_META))
+            .all(db)
+            .await?;
+        for gallery in galleries {
+            let ids = gallery.meta_value.clone().unwrap_or_default();
+            let kept: Vec<&str> = ids
+                .split(',')
+                .filter(|gallery_id| gallery_id.trim() != id.to_string())
+                .collect();
+            if kept.len() != ids.split(',').count() {
+                let mut gallery = gallery.into_active_model();
+                gallery.meta_value = ActiveValue::set(Some(kept.join(",")));
+                gallery.update(db).await?;
+            }
+        }
+
+        media.delete(db).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,380 @@
+use std::collections::BTreeMap;
+
+use loco_rs::{model::ModelValidation, prelude::*};
+use sea_orm::{sea_query::Expr, QueryOrder};
+use serde::Deserialize;
+
+pub use super::_entities::orders::{self, ActiveModel, Column, Entity, Model};
+use super::{
+    _entities::order_items,
+    addresses::AddressParams,
+    bundle_items,
+    shipping_zones::ShippingRate,
+    stock_movements,
+    tax_rates::{self, TaxLine},
+};
+use crate::common::payments::PaymentMethod;
+pub type Orders = Entity;
+
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_PROCESSING: &str = "processing";
+pub const STATUS_ON_HOLD: &str = "on-hold";
+pub const STATUS_COMPLETED: &str = "completed";
+pub const STATUS_CANCELLED: &str = "cancelled";
+pub const STATUS_REFUNDED: &str = "refunded";
+pub const STATUS_FAILED: &str = "failed";
+pub const STATUSES: [&str; 7] = [
+    STATUS_PENDING,
+    STATUS_PROCESSING,
+    STATUS_ON_HOLD,
+    STATUS_COMPLETED,
+    STATUS_CANCELLED,
+    STATUS_REFUNDED,
+    STATUS_FAILED,
+];
+
+/// A product line of an order, priced when the order is placed
+#[derive(Clone, Debug, Default)]
+pub struct OrderLine {
+    pub product_id: i32,
+    pub name: String,
+    pub quantity: i32,
+    /// unit price, without taxes
+    pub price: f32,
+    /// taxes of the whole line
+    pub taxes: Vec<TaxLine>,
+    /// items beyond the stock of the product, sent once it is restocked
+    pub backordered: i32,
+    /// optional products of a bundle chosen by the customer
+    pub options: Vec<i32>,
+}
+
+/// Everything needed to place an order, with or without a customer account
+#[derive(Clone, Debug, Default)]
+pub struct PlaceOrderParams {
+    pub user_id: Option<i32>,
+    pub email: String,
+    pub billing: AddressParams,
+    pub shipping: Option<AddressParams>,
+    pub customer_note: Option<String>,
+    pub lines: Vec<OrderLine>,
+    /// chosen shipping method, none when nothing needs shipping
+    pub shipping_rate: Option<ShippingRate>,
+    /// taxes of the shipping cost
+    pub shipping_taxes: Vec<TaxLine>,
+    /// whether the customer saw prices with their taxes included
+    pub prices_include_tax: bool,
+    /// how the order gets paid, which decides its status once placed
+    pub payment_method: Option<PaymentMethod>,
+    /// key of the checkout submission, placing it again fails
+    pub checkout_token: Option<String>,
+}
+
+#[derive(Debug, Validate, Deserialize)]
+pub struct Validator {
+    #[validate(custom(function = "validation::is_valid_email"))]
+    pub email: Option<String>,
+}
+
+impl Validatable for ActiveModel {
+    fn validator(&self) -> Box<dyn Validate> {
+        Box::new(Validator {
+            email: self.email.try_as_ref().cloned().flatten(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        self.validate()?;
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    /// Places an order for the given lines. Guest orders only carry the
+    /// customer email, which is how they get claimed later on.
+    ///
+    /// # Errors
+    ///
+    /// When the email is invalid, there are no lines or has DB query error
+    pub async fn place(db: &DatabaseConnection, params: &PlaceOrderParams) -> ModelResult<Self> {
+        if params.lines.is_empty() {
+            return Err(ModelError::ModelValidation {
+                errors: ModelValidation {
+                    code: "items".to_string(),
+                    message: Some("cannot place an order without items".to_string()),
+                },
+            });
+        }
+        let to_json = |address: &AddressParams| {
+            serde_json::to_string(address).map_err(|e| ModelError::Any(e.into()))
+        };
+        let shipping_total = params.shipping_rate.as_ref().map_or(0.0, |rate| rate.cost);
+        let shipping_tax = tax_rates::total(&params.shipping_taxes);
+        let taxes = tax_rates::merge(
+            params
+                .lines
+                .iter()
+                .flat_map(|line| &line.taxes)
+                .chain(&params.shipping_taxes),
+        );
+        let tax_total = tax_rates::total(&taxes);
+        let total = params
+            .lines
+            .iter()
+            .map(|line| line.price * line.quantity as f32)
+            .sum::<f32>()
+            + shipping_total
+            + tax_total;
+        let taxes_to_json = |taxes: &[TaxLine]| {
+            if taxes.is_empty() {
+                return Ok(None);
+            }
+            serde_json::to_string(taxes)
+                .map(Some)
+                .map_err(|e| ModelError::Any(e.into()))
+        };
+
+        let txn = db.begin().await?;
+
+        let order = ActiveModel {
+            user_id: ActiveValue::set(params.user_id),
+            email: ActiveValue::set(Some(params.email.trim().to_lowercase())),
+            order_key: ActiveValue::set(Some(format!("order_{}", Uuid::new_v4().simple()))),
+            status: ActiveValue::set(
+                params
+                    .payment_method
+                    .map_or(STATUS_PENDING, |method| method.order_status)
+                    .to_string(),
+            ),
+            total: ActiveValue::set(total),
+            billing_address: ActiveValue::set(Some(to_json(&params.billing)?)),
+            shipping_address: ActiveValue::set(params.shipping.as_ref().map(to_json).transpose()?),
+            customer_note: ActiveValue::set(params.customer_note.clone()),
+            shipping_total: ActiveValue::set(shipping_total),
+            shipping_method: ActiveValue::set(
+                params.shipping_rate.as_ref().map(|rate| rate.title.clone()),
+            ),
+            tax_total: ActiveValue::set(tax_total),
+            shipping_tax: ActiveValue::set(shipping_tax),
+            taxes: ActiveValue::set(taxes_to_json(&taxes)?),
+            prices_include_tax: ActiveValue::set(params.prices_include_tax),
+            payment_method: ActiveValue::set(
+                params.payment_method.map(|method| method.id.to_string()),
+            ),
+            payment_method_title: ActiveValue::set(
+                params.payment_method.map(|method| method.title.to_string()),
+            ),
+            checkout_token: ActiveValue::set(params.checkout_token.clone()),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        for line in &params.lines {
+            order_items::ActiveModel {
+                order_id: ActiveValue::set(order.id),
+                product_id: ActiveValue::set(Some(line.product_id)),
+                name: ActiveValue::set(line.name.clone()),
+                quantity: ActiveValue::set(line.quantity),
+                price: ActiveValue::set(line.price),
+                total: ActiveValue::set(line.price * line.quantity as f32),
+                tax_total: ActiveValue::set(tax_rates::total(&line.taxes)),
+                taxes: ActiveValue::set(taxes_to_json(&line.taxes)?),
+                backordered: ActiveValue::set(line.backordered),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+        }
+
+        // the stock of the products, those of the bundles included
+        let mut quantities = BTreeMap::new();
+        for line in &params.lines {
+            *quantities.entry(line.product_id).or_insert(0) += line.quantity;
+            for bundled in
+                bundle_items::Model::included(&txn, line.product_id, Some(&line.options)).await?
+            {
+                *quantities.entry(bundled.product_id).or_insert(0) +=
+                    bundled.quantity * line.quantity;
+            }
+        }
+        stock_movements::Model::record_order(&txn, &order, &quantities).await?;
+
+        txn.commit().await?;
+
+        Ok(order)
+    }
+
+    /// finds an order by id only if the given order key matches, which is how
+    /// guests get back to their order
+    ///
+    /// # Errors
+    ///
+    /// When could not find the order with this key or DB query error
+    pub async fn find_by_key(db: &DatabaseConnection, id: i32, key: &str) -> ModelResult<Self> {
+        let order = Entity::find_by_id(id)
+            .filter(Column::OrderKey.eq(key))
+            .one(db)
+            .await?;
+        order.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// finds the order placed by a checkout submission, if it got placed
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn find_by_checkout_token(
+        db: &DatabaseConnection,
+        token: &str,
+    ) -> ModelResult<Option<Self>> {
+        let order = Entity::find()
+            .filter(Column::CheckoutToken.eq(token))
+            .one(db)
+            .await?;
+        Ok(order)
+    }
+
+    /// Links the guest orders placed with the given email to the user. Only
+    /// call this once the user proved owning the email.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn claim_guest_orders(
+        db: &DatabaseConnection,
+        user_id: i32,
+        email: &str,
+    ) -> ModelResult<u64> {
+        let res = Entity::update_many()
+            .col_expr(Column::UserId, Expr::value(user_id))
+            .filter(Column::UserId.is_null())
+            .filter(Column::Email.eq(email.trim().to_lowercase()))
+            .exec(db)
+            .await?;
+        Ok(res.rows_affected)
+    }
+
+    /// finds the orders of a user, most recent first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn find_by_user(db: &DatabaseConnection, user_id: i32) -> ModelResult<Vec<Self>> {
+        let orders = Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .order_by_desc(Column::Id)
+            .all(db)
+            .await?;
+        Ok(orders)
+    }
+
+    /// finds an order by id only if it belongs to the given user
+    ///
+    /// # Errors
+    ///
+    /// When could not find the order for this user or DB query error
+    pub async fn find_for_user(
+        db: &DatabaseConnection,
+        user_id: i32,
+        id: i32,
+    ) -> ModelResult<Self> {
+        let order = Entity::find_by_id(id)
+            .filter(Column::UserId.eq(user_id))
+            .one(db)
+            .await?;
+        order.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Lists the items of the order
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn items(&self, db: &DatabaseConnection) -> ModelResult<Vec<order_items::Model>> {
+        let items = self
+            .find_related(order_items::Entity)
+            .order_by_asc(order_items::Column::Id)
+            .all(db)
+            .await?;
+        Ok(items)
+    }
+
+    /// Billing address as it was when the order was placed
+    #[must_use]
+    pub fn billing(&self) -> Option<AddressParams> {
+        self.billing_address
+            .as_deref()
+            .and_then(|address| serde_json::from_str(address).ok())
+    }
+
+    /// Taxes of the order by rate, shipping included
+    #[must_use]
+    pub fn tax_lines(&self) -> Vec<TaxLine> {
+        self.taxes
+            .as_deref()
+            .and_then(|taxes| serde_json::from_str(taxes).ok())
+            .unwrap_or_default()
+    }
+
+    /// Shipping address as it was when the order was placed
+    #[must_use]
+    pub fn shipping(&self) -> Option<AddressParams> {
+        self.shipping_address
+            .as_deref()
+            .and_then(|address| serde_json::from_str(address).ok())
+    }
+
+    /// Whether the order got paid, which gives access to its downloads
+    #[must_use]
+    pub fn is_paid(&self) -> bool {
+        self.status == STATUS_PROCESSING || self.status == STATUS_COMPLETED
+    }
+
+    /// Lists every order, latest first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list(db: &DatabaseConnection) -> ModelResult<Vec<Self>> {
+        let orders = Entity::find()
+            .order_by_desc(Column::CreatedAt)
+            .order_by_desc(Column::Id)
+            .all(db)
+            .await?;
+        Ok(orders)
+    }
+
+    /// Moves the order to another status
+    ///
+    /// # Errors
+    ///
+    /// When the status is unknown or has DB query error
+    pub async fn update_status(self, db: &DatabaseConnection, status: &str) -> ModelResult<Self> {
+        if !STATUSES.contains(&status) {
+            return Err(ModelError::ModelValidation {
+                errors: ModelValidation {
+                    code: "status".to_string(),
+                    message: Some("unknown order status".to_string()),
+                },
+            });
+        }
+        let mut order = self.into_active_model();
+        order.status = ActiveValue::set(status.to_string());
+        Ok(order.update(db).await?)
+    }
+}
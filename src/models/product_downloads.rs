@@ -0,0 +1,129 @@
+use std::path::{Component, Path, PathBuf};
+
+use loco_rs::{prelude::*, validator::ValidationError};
+use sea_orm::QueryOrder;
+use serde::Deserialize;
+
+pub use super::_entities::product_downloads::{self, ActiveModel, Column, Entity, Model};
+pub type ProductDownloads = Entity;
+
+/// Files are either links to another server or paths inside the downloads
+/// directory, which cannot point out of it
+fn is_valid_file(file: &str) -> Result<(), ValidationError> {
+    if file.starts_with("https://") || file.starts_with("http://") {
+        return Ok(());
+    }
+    let path = Path::new(file);
+    if !file.is_empty()
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        Ok(())
+    } else {
+        Err(ValidationError::new("invalid file"))
+    }
+}
+
+#[derive(Debug, Validate, Deserialize)]
+pub struct Validator {
+    #[validate(length(min = 1, message = "Name is required."))]
+    pub name: String,
+    #[validate(custom(function = "is_valid_file"))]
+    pub file: String,
+}
+
+impl Validatable for ActiveModel {
+    fn validator(&self) -> Box<dyn Validate> {
+        Box::new(Validator {
+            name: self.name.as_ref().to_owned(),
+            file: self.file.as_ref().to_owned(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        self.validate()?;
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    /// Attaches a file to a product
+    ///
+    /// # Errors
+    ///
+    /// When the name or file is invalid or has DB query error
+    pub async fn create(
+        db: &DatabaseConnection,
+        product_id: i32,
+        name: &str,
+        file: &str,
+    ) -> ModelResult<Self> {
+        let download = ActiveModel {
+            product_id: ActiveValue::set(product_id),
+            name: ActiveValue::set(name.trim().to_string()),
+            file: ActiveValue::set(file.trim().to_string()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+
+        Ok(download)
+    }
+
+    /// Lists the files of a product, in the order they were attached
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn find_by_product(db: &DatabaseConnection, product_id: i32) -> ModelResult<Vec<Self>> {
+        let downloads = Entity::find()
+            .filter(Column::ProductId.eq(product_id))
+            .order_by_asc(Column::Id)
+            .all(db)
+            .await?;
+        Ok(downloads)
+    }
+
+    /// Detaches a file of a product, the customers who bought it lose access
+    /// to it
+    ///
+    /// # Errors
+    ///
+    /// When could not find the file of the product or has DB query error
+    pub async fn remove(db: &DatabaseConnection, product_id: i32, id: i32) -> ModelResult<()> {
+        let download = Entity::find_by_id(id)
+            .filter(Column::ProductId.eq(product_id))
+            .one(db)
+            .await?
+            .ok_or_else(|| ModelError::EntityNotFound)?;
+        download.delete(db).await?;
+        Ok(())
+    }
+
+    /// Whether the file is a link to another server
+    #[must_use]
+    pub fn is_url(&self) -> bool {
+        self.file.starts_with("https://") || self.file.starts_with("http://")
+    }
+
+    /// Path of the file inside the downloads directory
+    #[must_use]
+    pub fn path(&self, dir: &str) -> PathBuf {
+        Path::new(dir).join(&self.file)
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::BTreeSet;
+
+use loco_rs::prelude::*;
+use sea_orm::{QueryOrder, TransactionTrait};
+use serde::{Deserialize, Serialize};
+
+pub use super::_entities::product_revisions::{self, ActiveModel, Column, Entity, Model};
+use super::{
+    _entities::{postmetas, products, users},
+    products::{ProductStatus, TRASH_STATUS_META},
+    reviews::{AVERAGE_RATING_META, REVIEW_COUNT_META},
+    stock_movements,
+};
+pub type ProductRevisions = Entity;
+
+/// Metas the store keeps up to date by itself, which a restore leaves as
+/// they are: the stock moves with the orders and the rating with the reviews
+pub const KEPT_METAS: [&str; 5] = [
+    "_stock",
+    "_stock_status",
+    AVERAGE_RATING_META,
+    REVIEW_COUNT_META,
+    TRASH_STATUS_META,
+];
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+/// Number of product fields in a revision, before its metas
+const FIELDS: usize = 6;
+
+/// A meta of the product when the revision was made
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Meta {
+    pub key: String,
+    pub value: Option<String>,
+}
+
+/// A field or meta which differs between two revisions
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Change {
+    pub field: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+fn json_error(err: serde_json::Error) -> ModelError {
+    ModelError::Any(err.into())
+}
+
+impl Model {
+    /// Metas of the product in the revision, by key
+    #[must_use]
+    pub fn metas(&self) -> Vec<Meta> {
+        serde_json::from_str(&self.metas).unwrap_or_default()
+    }
+
+    /// Fields and metas of the revision, the values of a meta set more than
+    /// once being on a line each
+    fn values(&self) -> Vec<(String, Option<String>)> {
+        let mut values = vec![
+            ("title".to_string(), Some(self.title.clone())),
+            ("excerpt".to_string(), self.excerpt.clone()),
+            ("status".to_string(), self.status.clone()),
+            ("product_type".to_string(), self.product_type.clone()),
+            ("slug".to_string(), self.slug.clone()),
+            (
+                "published_at".to_string(),
+                self.published_at.map(|date| date.to_rfc3339()),
+            ),
+        ];
+        for meta in self.metas() {
+            match values.last_mut() {
+                Some((key, Some(value))) if *key == meta.key => {
+                    value.push('\n');
+                    value.push_str(meta.value.as_deref().unwrap_or_default());
+                }
+                _ => values.push((meta.key, meta.value)),
+            }
+        }
+        values
+    }
+
+    /// What changed from this revision to another one, fields first and
+    /// then metas by key
+    #[must_use]
+    pub fn diff(&self, other: &Self) -> Vec<Change> {
+        let from = self.values();
+        let to = other.values();
+        let value = |values: &[(String, Option<String>)], field: &str| {
+            values
+                .iter()
+                .find(|(key, _)| key == field)
+                .and_then(|(_, value)| value.clone())
+        };
+
+        // the product fields come first in both, followed by the metas
+        let metas: BTreeSet<&String> = from[FIELDS..]
+            .iter()
+            .chain(&to[FIELDS..])
+            .map(|(key, _)| key)
+            .collect();
+        from[..FIELDS]
+            .iter()
+            .map(|(key, _)| key)
+            .chain(metas)
+            .filter_map(|field| {
+                let change = Change {
+                    field: field.clone(),
+                    from: value(&from, field),
+                    to: value(&to, field),
+                };
+                (change.from != change.to).then_some(change)
+            })
+            .collect()
+    }
+
+    /// Records the product as it is now, along with all its metas. Nothing
+    /// is recorded when it did not change since the latest revision.
+    ///
+    /// # Errors
+    ///
+    /// When the product is not found or has DB query error
+    pub async fn record<C>(db: &C, product_id: i32, user_id: Option<i32>) -> ModelResult<Self>
+    where
+        C: ConnectionTrait,
+    {
+        let product = products::Entity::find_by_id(product_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| ModelError::EntityNotFound)?;
+        let metas: Vec<Meta> = postmetas::Entity::find()
+            .filter(postmetas::Column::ProductId.eq(product_id))
+            .order_by_asc(postmetas::Column::MetaKey)
+            .order_by_asc(postmetas::Column::Id)
+            .all(db)
+            .await?
+            .into_iter()
+            .filter_map(|meta| {
+                Some(Meta {
+                    key: meta.meta_key?,
+                    value: meta.meta_value,
+                })
+            })
+            .collect();
+        let metas = serde_json::to_string(&metas).map_err(json_error)?;
+
+        let latest = Entity::find()
+            .filter(Column::ProductId.eq(product_id))
+            .order_by_desc(Column::Id)
+            .one(db)
+            .await?;
+        if let Some(latest) = latest {
+            if latest.title == product.title
+                && latest.excerpt == product.excerpt
+                && latest.status == product.status
+                && latest.product_type == product.product_type
+                && latest.slug == product.slug
+                && latest.published_at == product.published_at
+                && latest.metas == metas
+            {
+                return Ok(latest);
+            }
+        }
+
+        let revision = ActiveModel {
+            product_id: ActiveValue::set(product_id),
+            user_id: ActiveValue::set(user_id),
+            title: ActiveValue::set(product.title),
+            excerpt: ActiveValue::set(product.excerpt),
+            status: ActiveValue::set(product.status),
+            product_type: ActiveValue::set(product.product_type),
+            slug: ActiveValue::set(product.slug),
+            published_at: ActiveValue::set(product.published_at),
+            metas: ActiveValue::set(metas),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        Ok(revision)
+    }
+
+    /// Lists the revisions of a product with their author, latest first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_for_product(
+        db: &DatabaseConnection,
+        product_id: i32,
+    ) -> ModelResult<Vec<(Self, Option<users::Model>)>> {
+        let revisions = Entity::find()
+            .find_also_related(users::Entity)
+            .filter(Column::ProductId.eq(product_id))
+            .order_by_desc(Column::Id)
+            .all(db)
+            .await?;
+        Ok(revisions)
+    }
+
+    /// Finds a revision of a product
+    ///
+    /// # Errors
+    ///
+    /// When the product has no such revision or has DB query error
+    pub async fn find_for_product(
+        db: &DatabaseConnection,
+        product_id: i32,
+        id: i32,
+    ) -> ModelResult<Self> {
+        let revision = Entity::find_by_id(id)
+            .filter(Column::ProductId.eq(product_id))
+            .one(db)
+            .await?;
+        revision.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// The revision made before this one, if any
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn previous(&self, db: &DatabaseConnection) -> ModelResult<Option<Self>> {
+        let revision = Entity::find()
+            .filter(Column::ProductId.eq(self.product_id))
+            .filter(Column::Id.lt(self.id))
+            .order_by_desc(Column::Id)
+            .one(db)
+            .await?;
+        Ok(revision)
+    }
+
+    /// Puts the product back as it was in this revision, but for the
+    /// `KEPT_METAS`, and records it as a new revision. The product goes to
+    /// or out of the trash as when trashed or restored, and its stock status
+    /// follows the stock settings put back.
+    ///
+    /// # Errors
+    ///
+    /// When the product is not found or has DB query error
+    pub async fn restore(
+        &self,
+        db: &DatabaseConnection,
+        user_id: Option<i32>,
+    ) -> ModelResult<Self> {
+        let txn = db.begin().await?;
+
+        let product = products::Entity::find_by_id(self.product_id)
+            .one(&txn)
+            .await?
+            .ok_or_else(|| ModelError::EntityNotFound)?;
+        let trashed = self.status.as_deref() == Some(ProductStatus::Trash.as_str());
+        let product = if trashed {
+            product
+        } else {
+            product.restore(&txn).await?
+        };
+        let mut product = product.into_active_model();
+        product.title = ActiveValue::set(self.title.clone());
+        product.excerpt = ActiveValue::set(self.excerpt.clone());
+        if !trashed {
+            product.status = ActiveValue::set(self.status.clone());
+        }
+        product.product_type = ActiveValue::set(self.product_type.clone());
+        product.published_at = ActiveValue::set(self.published_at);
+        let mut product = product.update(&txn).await?;
+        if trashed {
+            product = product.trash(&txn).await?;
+        }
+        // the slug changes as when edited, its current links redirecting
+        if let Some(slug) = &self.slug {
+            product.set_slug(&txn, slug).await?;
+        }
+
+        postmetas::Entity::delete_many()
+            .filter(postmetas::Column::ProductId.eq(self.product_id))
+            .filter(postmetas::Column::MetaKey.is_not_in(KEPT_METAS))
+            .exec(&txn)
+            .await?;
+        for meta in self.metas() {
+            if KEPT_METAS.contains(&meta.key.as_str()) {
+                continue;
+            }
+            postmetas::ActiveModel {
+                product_id: ActiveValue::set(self.product_id),
+                meta_key: ActiveValue::set(Some(meta.key)),
+                meta_value: ActiveValue::set(meta.value),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+        }
+        stock_movements::Model::sync_metas(&txn, self.product_id).await?;
+
+        let revision = Self::record(&txn, self.product_id, user_id).await?;
+        txn.commit().await?;
+
+        Ok(revision)
+    }
+}
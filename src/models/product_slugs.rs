@@ -0,0 +1,42 @@
+use loco_rs::prelude::*;
+
+pub use super::_entities::product_slugs::{self, ActiveModel, Column, Entity, Model};
+use super::_entities::products;
+pub type ProductSlugs = Entity;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    /// The product which had this slug before, to redirect its old links to
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn find_product(
+        db: &DatabaseConnection,
+        slug: &str,
+    ) -> ModelResult<Option<products::Model>> {
+        let product = Entity::find()
+            .find_also_related(products::Entity)
+            .filter(Column::Slug.eq(slug))
+            .one(db)
+            .await?;
+        Ok(product.and_then(|(_, product)| product))
+    }
+}
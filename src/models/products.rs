@@ -1,7 +1,129 @@
-use sea_orm::entity::prelude::*;
-use super::_entities::products::{ActiveModel, Entity};
+use std::{fmt, str::FromStr};
+
+use chrono::Utc;
+use loco_rs::model::{ModelError, ModelResult};
+use sea_orm::{entity::prelude::*, ActiveValue, IntoActiveModel, PaginatorTrait, QueryOrder};
+use serde::{Deserialize, Serialize};
+use slug::slugify;
+
+pub use super::_entities::products::{ActiveModel, Column, Entity, Model};
+use super::{
+    _entities::{postmetas, product_slugs},
+    media::parse_ids,
+};
 pub type Products = Entity;
 
+/// Status of a product, with the values WordPress gives to posts
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProductStatus {
+    #[default]
+    Draft,
+    /// waiting for a review before being published
+    Pending,
+    /// published once its `published_at` date comes
+    Future,
+    Publish,
+    /// only seen by the store staff
+    Private,
+    Trash,
+}
+
+impl ProductStatus {
+    pub const ALL: [Self; 6] = [
+        Self::Draft,
+        Self::Pending,
+        Self::Future,
+        Self::Publish,
+        Self::Private,
+        Self::Trash,
+    ];
+
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Draft => "draft",
+            Self::Pending => "pending",
+            Self::Future => "future",
+            Self::Publish => "publish",
+            Self::Private => "private",
+            Self::Trash => "trash",
+        }
+    }
+}
+
+impl fmt::Display for ProductStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ProductStatus {
+    type Err = String;
+
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|known| known.as_str() == status)
+            .ok_or_else(|| format!("unknown product status {status}"))
+    }
+}
+
+pub const TYPE_SIMPLE: &str = "simple";
+pub const TYPE_VARIABLE: &str = "variable";
+/// a variant of a variable product, linked to it by the `_parent_id` meta
+pub const TYPE_VARIATION: &str = "variation";
+/// a set of simple products listed together, bought each on its own
+pub const TYPE_GROUPED: &str = "grouped";
+/// a product sold elsewhere, its buy button leading to another site
+pub const TYPE_EXTERNAL: &str = "external";
+/// a kit of simple products and variations sold together at one price, its
+/// stock being the one of its products
+pub const TYPE_BUNDLE: &str = "bundle";
+pub const TYPES: [&str; 6] = [
+    TYPE_SIMPLE,
+    TYPE_VARIABLE,
+    TYPE_VARIATION,
+    TYPE_GROUPED,
+    TYPE_EXTERNAL,
+    TYPE_BUNDLE,
+];
+
+/// Product metas, named as in WooCommerce: the comma separated ids of the
+/// products of a grouped product, and the link and button label of an
+/// external product
+pub const CHILDREN_META: &str = "_children";
+pub const PRODUCT_URL_META: &str = "_product_url";
+pub const BUTTON_TEXT_META: &str = "_button_text";
+
+/// Whether a product whose stock is managed can be bought beyond its stock,
+/// as in WooCommerce: not at all, telling the customer it is on backorder, or
+/// without telling
+pub const BACKORDERS_META: &str = "_backorders";
+pub const BACKORDERS_NO: &str = "no";
+pub const BACKORDERS_NOTIFY: &str = "notify";
+pub const BACKORDERS_YES: &str = "yes";
+pub const BACKORDERS: [&str; 3] = [BACKORDERS_NO, BACKORDERS_NOTIFY, BACKORDERS_YES];
+
+/// Stock at or under which a product whose stock is managed is low in
+/// stock, as in WooCommerce. The store setting applies to the products
+/// without one.
+pub const LOW_STOCK_AMOUNT_META: &str = "_low_stock_amount";
+
+/// Status a product had before going to the trash, as in WordPress
+pub const TRASH_STATUS_META: &str = "_wp_trash_meta_status";
+
+/// A product low in stock, with its stock and the threshold it is under
+#[derive(Clone, Debug, Serialize)]
+pub struct LowStockProduct {
+    pub product: Model,
+    pub stock: f32,
+    pub low_stock_amount: f32,
+}
+
+/// Slug of the products whose title makes no slug
+const DEFAULT_SLUG: &str = "product";
+
 #[async_trait::async_trait]
 impl ActiveModelBehavior for ActiveModel {
     // extend activemodel below (keep comment for generators)
@@ -10,12 +132,335 @@ impl ActiveModelBehavior for ActiveModel {
     where
         C: ConnectionTrait,
     {
-        if !insert && self.updated_at.is_unchanged() {
-            let mut this = self;
-            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
-            Ok(this)
+        let mut this = self;
+        // a product scheduled in the past is published right away, and a
+        // published one keeps the date it was first published
+        let status = this.status.try_as_ref().cloned().flatten();
+        let published_at = this.published_at.try_as_ref().cloned().flatten();
+        let now = Utc::now();
+        match status.as_deref().map(ProductStatus::from_str) {
+            Some(Ok(ProductStatus::Future)) => match published_at {
+                Some(published_at) if published_at <= now => {
+                    this.status = ActiveValue::Set(Some(ProductStatus::Publish.to_string()));
+                }
+                Some(_) => {}
+                None => this.status = ActiveValue::Set(Some(ProductStatus::Draft.to_string())),
+            },
+            Some(Ok(ProductStatus::Publish)) if published_at.is_none() => {
+                this.published_at = ActiveValue::Set(Some(now.into()));
+            }
+            _ => {}
+        }
+
+        if !insert && this.updated_at.is_unchanged() {
+            this.updated_at = sea_orm::ActiveValue::Set(now.into());
+        }
+        Ok(this)
+    }
+}
+
+/// A slug no other product has or had, made of the wanted one followed by
+/// a number when it is taken, as WordPress does
+///
+/// # Errors
+///
+/// When has DB query error
+pub async fn unique_slug<C>(db: &C, wanted: &str, product_id: Option<i32>) -> ModelResult<String>
+where
+    C: ConnectionTrait,
+{
+    let base = Some(slugify(wanted))
+        .filter(|slug| !slug.is_empty())
+        .unwrap_or_else(|| DEFAULT_SLUG.to_string());
+    let mut slug = base.clone();
+    for n in 2.. {
+        let mut current = Entity::find().filter(Column::Slug.eq(&slug));
+        let mut former =
+            product_slugs::Entity::find().filter(product_slugs::Column::Slug.eq(&slug));
+        if let Some(id) = product_id {
+            current = current.filter(Column::Id.ne(id));
+            former = former.filter(product_slugs::Column::ProductId.ne(id));
+        }
+        if current.count(db).await? == 0 && former.count(db).await? == 0 {
+            break;
+        }
+        slug = format!("{base}-{n}");
+    }
+    Ok(slug)
+}
+
+impl Model {
+    /// Status of the product, a draft when it has none
+    #[must_use]
+    pub fn status(&self) -> ProductStatus {
+        self.status
+            .as_deref()
+            .and_then(|status| status.parse().ok())
+            .unwrap_or_default()
+    }
+
+    /// Whether the product can be put in the cart: published, grouped and
+    /// external products being bought elsewhere
+    #[must_use]
+    pub fn purchasable(&self) -> bool {
+        self.status() == ProductStatus::Publish
+            && !matches!(
+                self.product_type.as_deref(),
+                Some(TYPE_GROUPED | TYPE_EXTERNAL)
+            )
+    }
+
+    /// Products of a grouped product which anyone can see, in the order of
+    /// the group
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn children(&self, db: &DatabaseConnection) -> ModelResult<Vec<Self>> {
+        let ids = postmetas::Entity::find()
+            .filter(postmetas::Column::ProductId.eq(self.id))
+            .filter(postmetas::Column::MetaKey.eq(CHILDREN_META))
+            .one(db)
+            .await?
+            .and_then(|meta| meta.meta_value)
+            .map(|ids| parse_ids(&ids))
+            .unwrap_or_default();
+        let children = Entity::find()
+            .filter(Column::Id.is_in(ids.clone()))
+            .filter(Column::Status.eq(ProductStatus::Publish.as_str()))
+            .all(db)
+            .await?;
+        Ok(ids
+            .iter()
+            .filter_map(|id| children.iter().find(|child| child.id == *id).cloned())
+            .collect())
+    }
+
+    /// Lists the products anyone can see, latest first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_published(db: &DatabaseConnection) -> ModelResult<Vec<Self>> {
+        Self::list_by_status(db, ProductStatus::Publish).await
+    }
+
+    /// Lists the products in a status, latest first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_by_status(
+        db: &DatabaseConnection,
+        status: ProductStatus,
+    ) -> ModelResult<Vec<Self>> {
+        let products = Entity::find()
+            .filter(Column::Status.eq(status.as_str()))
+            .order_by_desc(Column::Id)
+            .all(db)
+            .await?;
+        Ok(products)
+    }
+
+    /// Finds a published product by its slug
+    ///
+    /// # Errors
+    ///
+    /// When there is no published product with this slug or has DB query
+    /// error
+    pub async fn find_published_by_slug(db: &DatabaseConnection, slug: &str) -> ModelResult<Self> {
+        let product = Entity::find()
+            .filter(Column::Slug.eq(slug))
+            .filter(Column::Status.eq(ProductStatus::Publish.as_str()))
+            .one(db)
+            .await?;
+        product.ok_or_else(|| ModelError::EntityNotFound)
+    }
+
+    /// Gives the product a unique slug made of the wanted one, or of the
+    /// title when empty. The previous slug is kept so that its links
+    /// redirect to the new one.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn set_slug<C>(self, db: &C, wanted: &str) -> ModelResult<Self>
+    where
+        C: ConnectionTrait,
+    {
+        let wanted = if wanted.trim().is_empty() {
+            self.title.as_str()
         } else {
-            Ok(self)
+            wanted
+        };
+        let slug = unique_slug(db, wanted, Some(self.id)).await?;
+        if self.slug.as_deref() == Some(slug.as_str()) {
+            return Ok(self);
+        }
+
+        // a slug the product had before is its own again
+        product_slugs::Entity::delete_many()
+            .filter(product_slugs::Column::ProductId.eq(self.id))
+            .filter(product_slugs::Column::Slug.eq(&slug))
+            .exec(db)
+            .await?;
+        if let Some(previous) = self.slug.clone().filter(|previous| !previous.is_empty()) {
+            product_slugs::ActiveModel {
+                product_id: ActiveValue::set(self.id),
+                slug: ActiveValue::set(previous),
+                ..Default::default()
+            }
+            .insert(db)
+            .await?;
+        }
+
+        let mut product = self.into_active_model();
+        product.slug = ActiveValue::set(Some(slug));
+        Ok(product.update(db).await?)
+    }
+
+    /// Publishes the scheduled products whose date came
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn publish_scheduled(db: &DatabaseConnection) -> ModelResult<Vec<Self>> {
+        let due = Entity::find()
+            .filter(Column::Status.eq(ProductStatus::Future.as_str()))
+            .filter(Column::PublishedAt.lte(Utc::now()))
+            .all(db)
+            .await?;
+        let mut published = Vec::with_capacity(due.len());
+        for product in due {
+            let mut product = product.into_active_model();
+            product.status = ActiveValue::set(Some(ProductStatus::Publish.to_string()));
+            published.push(product.update(db).await?);
+        }
+        Ok(published)
+    }
+
+    /// Moves the product to the trash, remembering its status for a restore
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn trash<C>(self, db: &C) -> ModelResult<Self>
+    where
+        C: ConnectionTrait,
+    {
+        if self.status() == ProductStatus::Trash {
+            return Ok(self);
+        }
+        postmetas::Entity::delete_many()
+            .filter(postmetas::Column::ProductId.eq(self.id))
+            .filter(postmetas::Column::MetaKey.eq(TRASH_STATUS_META))
+            .exec(db)
+            .await?;
+        postmetas::ActiveModel {
+            product_id: ActiveValue::set(self.id),
+            meta_key: ActiveValue::set(Some(TRASH_STATUS_META.to_string())),
+            meta_value: ActiveValue::set(Some(self.status().to_string())),
+            ..Default::default()
         }
+        .insert(db)
+        .await?;
+
+        let mut product = self.into_active_model();
+        product.status = ActiveValue::set(Some(ProductStatus::Trash.to_string()));
+        Ok(product.update(db).await?)
+    }
+
+    /// Takes the product out of the trash, back in the status it had
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn restore<C>(self, db: &C) -> ModelResult<Self>
+    where
+        C: ConnectionTrait,
+    {
+        if self.status() != ProductStatus::Trash {
+            return Ok(self);
+        }
+        let meta = postmetas::Entity::find()
+            .filter(postmetas::Column::ProductId.eq(self.id))
+            .filter(postmetas::Column::MetaKey.eq(TRASH_STATUS_META))
+            .one(db)
+            .await?;
+        let status: ProductStatus = meta
+            .as_ref()
+            .and_then(|meta| meta.meta_value.as_deref())
+            .and_then(|status| status.parse().ok())
+            .filter(|status| *status != ProductStatus::Trash)
+            .unwrap_or_default();
+        if let Some(meta) = meta {
+            meta.delete(db).await?;
+        }
+
+        let mut product = self.into_active_model();
+        product.status = ActiveValue::set(Some(status.to_string()));
+        Ok(product.update(db).await?)
+    }
+
+    /// Lists the products out of the trash whose managed stock is at or
+    /// under their threshold, `default_amount` when they have none, the
+    /// lowest stock first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_low_stock(
+        db: &DatabaseConnection,
+        default_amount: f32,
+    ) -> ModelResult<Vec<LowStockProduct>> {
+        let metas = postmetas::Entity::find()
+            .filter(postmetas::Column::MetaKey.is_in(["_stock", LOW_STOCK_AMOUNT_META]))
+            .all(db)
+            .await?;
+        let amount = |product_id: i32| {
+            metas
+                .iter()
+                .filter(|meta| meta.meta_key.as_deref() == Some(LOW_STOCK_AMOUNT_META))
+                .find(|meta| meta.product_id == product_id)
+                .and_then(|meta| meta.meta_value.as_deref()?.parse::<f32>().ok())
+        };
+
+        let mut low: Vec<(i32, f32, f32)> = vec![];
+        for meta in &metas {
+            if meta.meta_key.as_deref() != Some("_stock") {
+                continue;
+            }
+            let Some(stock) = meta
+                .meta_value
+                .as_deref()
+                .and_then(|stock| stock.parse::<f32>().ok())
+            else {
+                continue;
+            };
+            let amount = amount(meta.product_id).unwrap_or(default_amount);
+            if stock <= amount {
+                low.push((meta.product_id, stock, amount));
+            }
+        }
+        let products = Entity::find()
+            .filter(Column::Id.is_in(low.iter().map(|(id, _, _)| *id)))
+            .filter(Column::Status.ne(ProductStatus::Trash.as_str()))
+            .all(db)
+            .await?;
+
+        let mut items: Vec<LowStockProduct> = products
+            .into_iter()
+            .filter_map(|product| {
+                let (_, stock, low_stock_amount) =
+                    low.iter().find(|(id, _, _)| *id == product.id).copied()?;
+                Some(LowStockProduct {
+                    product,
+                    stock,
+                    low_stock_amount,
+                })
+            })
+            .collect();
+        items.sort_by(|a, b| a.stock.total_cmp(&b.stock));
+        Ok(items)
     }
 }
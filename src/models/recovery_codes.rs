@@ -0,0 +1,121 @@
+use loco_rs::prelude::*;
+use sea_orm::PaginatorTrait;
+
+pub use super::_entities::recovery_codes::{self, ActiveModel, Column, Entity, Model};
+use super::api_keys::hash_key;
+pub type RecoveryCodes = Entity;
+
+/// How many recovery codes a user gets, each of them can be used once
+pub const RECOVERY_CODES: usize = 10;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+/// Codes are shown as `xxxxx-xxxxx` but may be typed without the dash, in
+/// upper case or with spaces around
+fn normalize_code(code: &str) -> String {
+    code.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+impl Model {
+    /// Replaces the recovery codes of the user with new ones. The returned
+    /// codes are not stored, only their hash.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn generate(db: &DatabaseConnection, user_id: i32) -> ModelResult<Vec<String>> {
+        let txn = db.begin().await?;
+
+        Entity::delete_many()
+            .filter(Column::UserId.eq(user_id))
+            .exec(&txn)
+            .await?;
+
+        let mut codes = Vec::with_capacity(RECOVERY_CODES);
+        for _ in 0..RECOVERY_CODES {
+            let random = Uuid::new_v4().simple().to_string();
+            let code = format!("{}-{}", &random[..5], &random[5..10]);
+            ActiveModel {
+                user_id: ActiveValue::set(user_id),
+                code_hash: ActiveValue::set(hash_key(&normalize_code(&code))),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+            codes.push(code);
+        }
+
+        txn.commit().await?;
+
+        Ok(codes)
+    }
+
+    /// Uses up a recovery code of the user. Returns whether the code was
+    /// valid, a code can only be redeemed once.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn redeem(db: &DatabaseConnection, user_id: i32, code: &str) -> ModelResult<bool> {
+        let Some(recovery_code) = Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .filter(Column::CodeHash.eq(hash_key(&normalize_code(code))))
+            .filter(Column::UsedAt.is_null())
+            .one(db)
+            .await?
+        else {
+            return Ok(false);
+        };
+
+        let mut recovery_code = recovery_code.into_active_model();
+        recovery_code.used_at = ActiveValue::set(Some(chrono::Utc::now().into()));
+        recovery_code.update(db).await?;
+
+        Ok(true)
+    }
+
+    /// Counts the recovery codes of the user which are not used yet
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn remaining(db: &DatabaseConnection, user_id: i32) -> ModelResult<u64> {
+        Ok(Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .filter(Column::UsedAt.is_null())
+            .count(db)
+            .await?)
+    }
+
+    /// Deletes every recovery code of the user
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn delete_all(db: &DatabaseConnection, user_id: i32) -> ModelResult<()> {
+        Entity::delete_many()
+            .filter(Column::UserId.eq(user_id))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+use loco_rs::prelude::*;
+use sea_orm::sea_query::Expr;
+
+pub use super::_entities::refresh_tokens::{self, ActiveModel, Column, Entity, Model};
+use super::api_keys::hash_key;
+pub type RefreshTokens = Entity;
+
+/// How long a refresh token can be exchanged for a new access token
+pub const REFRESH_TOKEN_DAYS: i64 = 30;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    /// Issues a refresh token for the user. Tokens rotated from one another
+    /// share a family, so a reused token can revoke the whole chain. The
+    /// returned token is not stored, only its hash.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn issue(
+        db: &DatabaseConnection,
+        user_id: i32,
+        family: Option<&str>,
+    ) -> ModelResult<(Self, String)> {
+        let token = format!("rt-{}", Uuid::new_v4().simple());
+        let family = family.map_or_else(|| Uuid::new_v4().to_string(), str::to_string);
+        let refresh_token = ActiveModel {
+            user_id: ActiveValue::set(user_id),
+            token_hash: ActiveValue::set(hash_key(&token)),
+            family: ActiveValue::set(family),
+            expires_at: ActiveValue::set(
+                (chrono::Utc::now() + chrono::Duration::days(REFRESH_TOKEN_DAYS)).into(),
+            ),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+
+        Ok((refresh_token, token))
+    }
+
+    /// Exchanges a refresh token for a new one of the same family. Presenting
+    /// a token which was already rotated means it leaked: the whole family
+    /// gets revoked.
+    ///
+    /// # Errors
+    ///
+    /// When the token is unknown, expired, revoked or has DB query error
+    pub async fn rotate(db: &DatabaseConnection, token: &str) -> ModelResult<(Self, String)> {
+        let refresh_token = Entity::find()
+            .filter(Column::TokenHash.eq(hash_key(token)))
+            .one(db)
+            .await?
+            .ok_or_else(|| ModelError::EntityNotFound)?;
+
+        if refresh_token.revoked_at.is_some() {
+            tracing::warn!(
+                user_id = refresh_token.user_id,
+                "revoked refresh token reused, revoking its family"
+            );
+            Self::revoke_family(db, &refresh_token.family).await?;
+            return Err(ModelError::EntityNotFound);
+        }
+        if refresh_token.expires_at < chrono::Utc::now() {
+            return Err(ModelError::EntityNotFound);
+        }
+
+        let user_id = refresh_token.user_id;
+        let family = refresh_token.family.clone();
+        let mut refresh_token = refresh_token.into_active_model();
+        refresh_token.revoked_at = ActiveValue::set(Some(chrono::Utc::now().into()));
+        refresh_token.update(db).await?;
+
+        Self::issue(db, user_id, Some(&family)).await
+    }
+
+    /// Revokes a refresh token of the user, unknown tokens are ignored
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn revoke(db: &DatabaseConnection, user_id: i32, token: &str) -> ModelResult<()> {
+        Entity::update_many()
+            .col_expr(Column::RevokedAt, Expr::value(chrono::Utc::now()))
+            .filter(Column::UserId.eq(user_id))
+            .filter(Column::TokenHash.eq(hash_key(token)))
+            .filter(Column::RevokedAt.is_null())
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Revokes every refresh token of the user
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn revoke_all(db: &DatabaseConnection, user_id: i32) -> ModelResult<()> {
+        Entity::update_many()
+            .col_expr(Column::RevokedAt, Expr::value(chrono::Utc::now()))
+            .filter(Column::UserId.eq(user_id))
+            .filter(Column::RevokedAt.is_null())
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    async fn revoke_family(db: &DatabaseConnection, family: &str) -> ModelResult<()> {
+        Entity::update_many()
+            .col_expr(Column::RevokedAt, Expr::value(chrono::Utc::now()))
+            .filter(Column::Family.eq(family))
+            .filter(Column::RevokedAt.is_null())
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+}
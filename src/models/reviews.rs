@@ -0,0 +1,263 @@
+use loco_rs::{model::ModelValidation, prelude::*};
+use sea_orm::{PaginatorTrait, QueryOrder, QuerySelect};
+use serde::{Deserialize, Serialize};
+
+pub use super::_entities::reviews::{self, ActiveModel, Column, Entity, Model};
+use super::{
+    _entities::{order_items, orders, postmetas, products, users},
+    orders::{STATUS_COMPLETED, STATUS_PROCESSING},
+};
+pub type Reviews = Entity;
+
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_APPROVED: &str = "approved";
+pub const STATUS_SPAM: &str = "spam";
+pub const STATUSES: [&str; 3] = [STATUS_PENDING, STATUS_APPROVED, STATUS_SPAM];
+
+/// Product metas of the approved reviews, named as in WooCommerce, which
+/// the products can be sorted by
+pub const AVERAGE_RATING_META: &str = "_wc_average_rating";
+pub const REVIEW_COUNT_META: &str = "_wc_review_count";
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReviewParams {
+    /// stars, from 1 to 5
+    pub rating: i32,
+    pub content: String,
+}
+
+#[derive(Debug, Validate, Deserialize)]
+pub struct Validator {
+    #[validate(range(min = 1, max = 5, message = "Rating must be from 1 to 5 stars."))]
+    pub rating: i32,
+    #[validate(length(min = 1, message = "Review is required."))]
+    pub content: String,
+}
+
+impl Validatable for ActiveModel {
+    fn validator(&self) -> Box<dyn Validate> {
+        Box::new(Validator {
+            rating: *self.rating.as_ref(),
+            content: self.content.as_ref().to_owned(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        self.validate()?;
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+/// Average rating and number of the approved reviews of a product
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Rating {
+    pub average: f32,
+    pub count: usize,
+}
+
+impl Rating {
+    #[must_use]
+    pub fn of(reviews: &[Model]) -> Self {
+        if reviews.is_empty() {
+            return Self::default();
+        }
+        let total: i32 = reviews.iter().map(|review| review.rating).sum();
+        #[allow(clippy::cast_precision_loss)]
+        let average = total as f32 / reviews.len() as f32;
+        Self {
+            average,
+            count: reviews.len(),
+        }
+    }
+}
+
+async fn set_meta(
+    db: &DatabaseConnection,
+    product_id: i32,
+    key: &str,
+    value: String,
+) -> ModelResult<()> {
+    let meta = postmetas::Entity::find()
+        .filter(postmetas::Column::ProductId.eq(product_id))
+        .filter(postmetas::Column::MetaKey.eq(key))
+        .one(db)
+        .await?;
+    if let Some(meta) = meta {
+        let mut meta = meta.into_active_model();
+        meta.meta_value = ActiveValue::set(Some(value));
+        meta.update(db).await?;
+    } else {
+        postmetas::ActiveModel {
+            product_id: ActiveValue::set(product_id),
+            meta_key: ActiveValue::set(Some(key.to_string())),
+            meta_value: ActiveValue::set(Some(value)),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+    }
+    Ok(())
+}
+
+impl Model {
+    /// Whether the user paid an order of the product or of one of its
+    /// variations
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn bought(
+        db: &DatabaseConnection,
+        user: &users::Model,
+        product_id: i32,
+    ) -> ModelResult<bool> {
+        let mut product_ids: Vec<i32> = postmetas::Entity::find()
+            .select_only()
+            .column(postmetas::Column::ProductId)
+            .filter(postmetas::Column::MetaKey.eq("_parent_id"))
+            .filter(postmetas::Column::MetaValue.eq(product_id.to_string()))
+            .into_tuple()
+            .all(db)
+            .await?;
+        product_ids.push(product_id);
+
+        let paid = order_items::Entity::find()
+            .inner_join(orders::Entity)
+            .filter(order_items::Column::ProductId.is_in(product_ids))
+            .filter(
+                orders::Column::UserId
+                    .eq(user.id)
+                    .or(orders::Column::Email.eq(user.email.to_lowercase())),
+            )
+            .filter(orders::Column::Status.is_in([STATUS_PROCESSING, STATUS_COMPLETED]))
+            .count(db)
+            .await?;
+        Ok(paid > 0)
+    }
+
+    /// Adds a review of a user, waiting for moderation. It shows a verified
+    /// buyer badge when the user paid for the product.
+    ///
+    /// # Errors
+    ///
+    /// When the rating or content is invalid or has DB query error
+    pub async fn create(
+        db: &DatabaseConnection,
+        product_id: i32,
+        user: &users::Model,
+        params: &ReviewParams,
+    ) -> ModelResult<Self> {
+        let verified = Self::bought(db, user, product_id).await?;
+        let review = ActiveModel {
+            product_id: ActiveValue::set(product_id),
+            user_id: ActiveValue::set(Some(user.id)),
+            author: ActiveValue::set(user.name.clone()),
+            email: ActiveValue::set(user.email.clone()),
+            rating: ActiveValue::set(params.rating),
+            content: ActiveValue::set(params.content.trim().to_string()),
+            status: ActiveValue::set(STATUS_PENDING.to_string()),
+            verified: ActiveValue::set(verified),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        Ok(review)
+    }
+
+    /// Lists the approved reviews of a product, latest first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn approved_for_product(
+        db: &DatabaseConnection,
+        product_id: i32,
+    ) -> ModelResult<Vec<Self>> {
+        let reviews = Entity::find()
+            .filter(Column::ProductId.eq(product_id))
+            .filter(Column::Status.eq(STATUS_APPROVED))
+            .order_by_desc(Column::CreatedAt)
+            .order_by_desc(Column::Id)
+            .all(db)
+            .await?;
+        Ok(reviews)
+    }
+
+    /// Lists the reviews in a status with their product, oldest first as a
+    /// moderation queue
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_by_status(
+        db: &DatabaseConnection,
+        status: &str,
+    ) -> ModelResult<Vec<(Self, Option<products::Model>)>> {
+        let reviews = Entity::find()
+            .find_also_related(products::Entity)
+            .filter(Column::Status.eq(status))
+            .order_by_asc(Column::CreatedAt)
+            .order_by_asc(Column::Id)
+            .all(db)
+            .await?;
+        Ok(reviews)
+    }
+
+    /// Approves a review or marks it as spam, updating the rating of its
+    /// product
+    ///
+    /// # Errors
+    ///
+    /// When the status is unknown or has DB query error
+    pub async fn moderate(self, db: &DatabaseConnection, status: &str) -> ModelResult<Self> {
+        if !STATUSES.contains(&status) {
+            return Err(ModelError::ModelValidation {
+                errors: ModelValidation {
+                    code: "status".to_string(),
+                    message: Some(format!("unknown review status {status}")),
+                },
+            });
+        }
+        let mut review = self.into_active_model();
+        review.status = ActiveValue::set(status.to_string());
+        let review = review.update(db).await?;
+        Self::update_product_rating(db, review.product_id).await?;
+        Ok(review)
+    }
+
+    /// Stores the average rating and the number of approved reviews of a
+    /// product in its metas
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn update_product_rating(
+        db: &DatabaseConnection,
+        product_id: i32,
+    ) -> ModelResult<Rating> {
+        let rating = Rating::of(&Self::approved_for_product(db, product_id).await?);
+        set_meta(
+            db,
+            product_id,
+            AVERAGE_RATING_META,
+            format!("{:.2}", rating.average),
+        )
+        .await?;
+        set_meta(db, product_id, REVIEW_COUNT_META, rating.count.to_string()).await?;
+        Ok(rating)
+    }
+}
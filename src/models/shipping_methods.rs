@@ -0,0 +1,202 @@
+use loco_rs::{model::ModelValidation, prelude::*, validator::ValidationError};
+use sea_orm::PaginatorTrait;
+use serde::{Deserialize, Serialize};
+
+pub use super::_entities::shipping_methods::{self, ActiveModel, Column, Entity, Model};
+use super::shipping_zones::ShippingPackage;
+pub type ShippingMethods = Entity;
+
+/// Same cost for any package
+pub const KIND_FLAT_RATE: &str = "flat_rate";
+/// Free, only once the items cost at least `min_amount` if set
+pub const KIND_FREE_SHIPPING: &str = "free_shipping";
+/// Cost depending on the weight of the package, see [`WeightRate`]
+pub const KIND_TABLE_RATE: &str = "table_rate";
+/// Picked up at the shop, for `cost` if set
+pub const KIND_LOCAL_PICKUP: &str = "local_pickup";
+
+/// A row of a table rate: packages up to `max_weight` kg cost `cost`
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct WeightRate {
+    pub max_weight: f32,
+    pub cost: f32,
+}
+
+/// A shipping method of a zone
+#[derive(Clone, Debug, Default)]
+pub struct MethodParams {
+    pub kind: String,
+    pub title: String,
+    pub cost: Option<f32>,
+    pub min_amount: Option<f32>,
+    pub rates: Vec<WeightRate>,
+}
+
+fn is_valid_kind(kind: &str) -> Result<(), ValidationError> {
+    if [
+        KIND_FLAT_RATE,
+        KIND_FREE_SHIPPING,
+        KIND_TABLE_RATE,
+        KIND_LOCAL_PICKUP,
+    ]
+    .contains(&kind)
+    {
+        Ok(())
+    } else {
+        Err(ValidationError::new("invalid shipping method"))
+    }
+}
+
+#[derive(Debug, Validate, Deserialize)]
+pub struct Validator {
+    #[validate(custom(function = "is_valid_kind"))]
+    pub kind: String,
+    #[validate(length(min = 1, message = "Title is required."))]
+    pub title: String,
+}
+
+impl Validatable for ActiveModel {
+    fn validator(&self) -> Box<dyn Validate> {
+        Box::new(Validator {
+            kind: self.kind.as_ref().to_owned(),
+            title: self.title.as_ref().to_owned(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        self.validate()?;
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+fn invalid_rates(message: &str) -> ModelError {
+    ModelError::ModelValidation {
+        errors: ModelValidation {
+            code: "rates".to_string(),
+            message: Some(message.to_string()),
+        },
+    }
+}
+
+/// Reads a table rate written one `max_weight:cost` row per line, e.g.
+/// `1:4.5` then `5:9`
+///
+/// # Errors
+///
+/// When a row is not made of two numbers
+pub fn parse_weight_rates(input: &str) -> ModelResult<Vec<WeightRate>> {
+    input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (max_weight, cost) = line
+                .split_once(':')
+                .ok_or_else(|| invalid_rates("each rate must be written max_weight:cost"))?;
+            match (max_weight.trim().parse(), cost.trim().parse()) {
+                (Ok(max_weight), Ok(cost)) => Ok(WeightRate { max_weight, cost }),
+                _ => Err(invalid_rates("weights and costs must be numbers")),
+            }
+        })
+        .collect()
+}
+
+impl Model {
+    /// Adds a method to a zone, offered after the existing ones
+    ///
+    /// # Errors
+    ///
+    /// When the method is invalid or has DB query error
+    pub async fn create(
+        db: &DatabaseConnection,
+        zone_id: i32,
+        params: &MethodParams,
+    ) -> ModelResult<Self> {
+        if params.kind == KIND_TABLE_RATE && params.rates.is_empty() {
+            return Err(invalid_rates("a table rate needs at least one rate"));
+        }
+        let mut rates = params.rates.clone();
+        rates.sort_by(|a, b| a.max_weight.total_cmp(&b.max_weight));
+        let rates = if rates.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(&rates).map_err(|e| ModelError::Any(e.into()))?)
+        };
+        let position = Entity::find()
+            .filter(Column::ZoneId.eq(zone_id))
+            .count(db)
+            .await?;
+
+        let method = ActiveModel {
+            zone_id: ActiveValue::set(zone_id),
+            kind: ActiveValue::set(params.kind.clone()),
+            title: ActiveValue::set(params.title.trim().to_string()),
+            enabled: ActiveValue::set(true),
+            position: ActiveValue::set(i32::try_from(position).unwrap_or(i32::MAX)),
+            cost: ActiveValue::set(params.cost),
+            min_amount: ActiveValue::set(params.min_amount),
+            rates: ActiveValue::set(rates),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+
+        Ok(method)
+    }
+
+    /// Rows of the table rate, by increasing weight
+    #[must_use]
+    pub fn weight_rates(&self) -> Vec<WeightRate> {
+        self.rates
+            .as_deref()
+            .and_then(|rates| serde_json::from_str(rates).ok())
+            .unwrap_or_default()
+    }
+
+    /// Cost of shipping the package with this method, if it is available
+    /// for it
+    #[must_use]
+    pub fn cost_for(&self, package: &ShippingPackage) -> Option<f32> {
+        match self.kind.as_str() {
+            KIND_FLAT_RATE | KIND_LOCAL_PICKUP => Some(self.cost.unwrap_or(0.0)),
+            KIND_FREE_SHIPPING => self
+                .min_amount
+                .is_none_or(|min_amount| package.contents_cost >= min_amount)
+                .then_some(0.0),
+            KIND_TABLE_RATE => self
+                .weight_rates()
+                .into_iter()
+                .find(|rate| package.weight <= rate.max_weight)
+                .map(|rate| rate.cost),
+            _ => None,
+        }
+    }
+
+    /// Deletes a method
+    ///
+    /// # Errors
+    ///
+    /// When could not find the method or has DB query error
+    pub async fn remove(db: &DatabaseConnection, id: i32) -> ModelResult<()> {
+        let method = Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or_else(|| ModelError::EntityNotFound)?;
+        method.delete(db).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+use loco_rs::{prelude::*, validator::ValidationError};
+use serde::Deserialize;
+
+pub use super::_entities::shipping_zone_locations::{self, ActiveModel, Column, Entity, Model};
+pub type ShippingZoneLocations = Entity;
+
+/// Two letters country code, e.g. `FR`
+pub const KIND_COUNTRY: &str = "country";
+/// Country and state codes, e.g. `US:CA`
+pub const KIND_STATE: &str = "state";
+/// Exact postcode, prefix ending with `*` (`75*`) or range (`10000...19999`)
+pub const KIND_POSTCODE: &str = "postcode";
+
+fn is_valid_kind(kind: &str) -> Result<(), ValidationError> {
+    if [KIND_COUNTRY, KIND_STATE, KIND_POSTCODE].contains(&kind) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("invalid location kind"))
+    }
+}
+
+#[derive(Debug, Validate, Deserialize)]
+pub struct Validator {
+    #[validate(custom(function = "is_valid_kind"))]
+    pub kind: String,
+    #[validate(length(min = 1, message = "Code is required."))]
+    pub code: String,
+}
+
+impl Validatable for ActiveModel {
+    fn validator(&self) -> Box<dyn Validate> {
+        Box::new(Validator {
+            kind: self.kind.as_ref().to_owned(),
+            code: self.code.as_ref().to_owned(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        self.validate()?;
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+/// Postcodes are compared without spaces and case, `sw1a 1aa` is `SW1A1AA`
+#[must_use]
+pub fn normalize_postcode(postcode: &str) -> String {
+    postcode
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_uppercase()
+}
+
+/// Whether a normalized postcode matches an exact, prefix or range pattern
+#[must_use]
+pub fn postcode_matches(pattern: &str, postcode: &str) -> bool {
+    let pattern = normalize_postcode(pattern);
+    if let Some((from, to)) = pattern.split_once("...") {
+        return match (
+            from.parse::<u64>(),
+            to.parse::<u64>(),
+            postcode.parse::<u64>(),
+        ) {
+            (Ok(from), Ok(to), Ok(postcode)) => (from..=to).contains(&postcode),
+            _ => from <= postcode && postcode <= to,
+        };
+    }
+    match pattern.strip_suffix('*') {
+        Some(prefix) => postcode.starts_with(prefix),
+        None => pattern == postcode,
+    }
+}
+
+impl Model {
+    /// Whether the location is a country or a state
+    #[must_use]
+    pub fn is_region(&self) -> bool {
+        self.kind != KIND_POSTCODE
+    }
+
+    /// Whether an address in this country, state and postcode is covered by
+    /// the location
+    #[must_use]
+    pub fn matches(&self, country: &str, state: Option<&str>, postcode: &str) -> bool {
+        match self.kind.as_str() {
+            KIND_COUNTRY => self.code.eq_ignore_ascii_case(country.trim()),
+            KIND_STATE => state.is_some_and(|state| {
+                self.code
+                    .eq_ignore_ascii_case(&format!("{}:{}", country.trim(), state.trim()))
+            }),
+            KIND_POSTCODE => postcode_matches(&self.code, &normalize_postcode(postcode)),
+            _ => false,
+        }
+    }
+}
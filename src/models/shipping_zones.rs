@@ -0,0 +1,230 @@
+use loco_rs::prelude::*;
+use sea_orm::{LoaderTrait, PaginatorTrait, QueryOrder};
+use serde::{Deserialize, Serialize};
+
+pub use super::_entities::shipping_zones::{self, ActiveModel, Column, Entity, Model};
+use super::{
+    _entities::{shipping_methods, shipping_zone_locations},
+    shipping_zone_locations::{KIND_COUNTRY, KIND_POSTCODE, KIND_STATE},
+};
+pub type ShippingZones = Entity;
+
+/// What shipping is calculated for: where the items go and what they weigh
+#[derive(Clone, Debug, Default)]
+pub struct ShippingPackage {
+    pub country: String,
+    pub state: Option<String>,
+    pub postcode: String,
+    /// total price of the items, compared to free shipping thresholds
+    pub contents_cost: f32,
+    /// total weight of the items, in kg
+    pub weight: f32,
+}
+
+/// A shipping method available for a package, along with its cost
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ShippingRate {
+    pub method_id: i32,
+    pub title: String,
+    pub cost: f32,
+}
+
+/// A zone and where it applies. A zone without any location applies
+/// everywhere, which makes it the fallback when sorted last.
+#[derive(Clone, Debug, Default)]
+pub struct ZoneParams {
+    pub name: String,
+    pub position: i32,
+    /// two letters country codes
+    pub countries: Vec<String>,
+    /// `country:state` codes
+    pub states: Vec<String>,
+    /// postcode patterns, restricting the countries and states if any
+    pub postcodes: Vec<String>,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+/// A package is covered when its country or state is one of the zone, and
+/// its postcode matches one of the zone patterns. Missing kinds of location
+/// don't restrict the zone.
+fn covers(locations: &[shipping_zone_locations::Model], package: &ShippingPackage) -> bool {
+    let state = package.state.as_deref();
+    let (regions, postcodes): (Vec<_>, Vec<_>) =
+        locations.iter().partition(|location| location.is_region());
+
+    (regions.is_empty()
+        || regions
+            .iter()
+            .any(|location| location.matches(&package.country, state, &package.postcode)))
+        && (postcodes.is_empty()
+            || postcodes
+                .iter()
+                .any(|location| location.matches(&package.country, state, &package.postcode)))
+}
+
+impl Model {
+    /// Creates a zone along with its locations
+    ///
+    /// # Errors
+    ///
+    /// When a location is invalid or has DB query error
+    pub async fn create(db: &DatabaseConnection, params: &ZoneParams) -> ModelResult<Self> {
+        let txn = db.begin().await?;
+
+        let zone = ActiveModel {
+            name: ActiveValue::set(params.name.trim().to_string()),
+            position: ActiveValue::set(params.position),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        let locations = params
+            .countries
+            .iter()
+            .map(|code| (KIND_COUNTRY, code))
+            .chain(params.states.iter().map(|code| (KIND_STATE, code)))
+            .chain(params.postcodes.iter().map(|code| (KIND_POSTCODE, code)));
+        for (kind, code) in locations {
+            let code = if kind == KIND_POSTCODE {
+                code.trim().to_string()
+            } else {
+                code.trim().to_uppercase()
+            };
+            shipping_zone_locations::ActiveModel {
+                zone_id: ActiveValue::set(zone.id),
+                kind: ActiveValue::set(kind.to_string()),
+                code: ActiveValue::set(code),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+        }
+
+        txn.commit().await?;
+
+        Ok(zone)
+    }
+
+    /// Lists every zone in the order they are matched, along with their
+    /// locations
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list(
+        db: &DatabaseConnection,
+    ) -> ModelResult<Vec<(Self, Vec<shipping_zone_locations::Model>)>> {
+        // `find_with_related` would sort the zones by id first
+        let zones = Entity::find()
+            .order_by_asc(Column::Position)
+            .order_by_asc(Column::Id)
+            .all(db)
+            .await?;
+        let locations = zones.load_many(shipping_zone_locations::Entity, db).await?;
+        Ok(zones.into_iter().zip(locations).collect())
+    }
+
+    /// Whether any zone is set up, shipping is not required otherwise
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn any(db: &DatabaseConnection) -> ModelResult<bool> {
+        Ok(Entity::find().count(db).await? > 0)
+    }
+
+    /// finds the first zone covering the package
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn find_for_package(
+        db: &DatabaseConnection,
+        package: &ShippingPackage,
+    ) -> ModelResult<Option<Self>> {
+        Ok(Self::list(db)
+            .await?
+            .into_iter()
+            .find(|(_, locations)| covers(locations, package))
+            .map(|(zone, _)| zone))
+    }
+
+    /// Lists the methods of the zone, in the order they are offered
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn methods(
+        &self,
+        db: &DatabaseConnection,
+    ) -> ModelResult<Vec<shipping_methods::Model>> {
+        let methods = self
+            .find_related(shipping_methods::Entity)
+            .order_by_asc(shipping_methods::Column::Position)
+            .order_by_asc(shipping_methods::Column::Id)
+            .all(db)
+            .await?;
+        Ok(methods)
+    }
+
+    /// Calculates the rates of the methods available for the package, from
+    /// the zone covering it. No rate means the package cannot be shipped.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn rates(
+        db: &DatabaseConnection,
+        package: &ShippingPackage,
+    ) -> ModelResult<Vec<ShippingRate>> {
+        let Some(zone) = Self::find_for_package(db, package).await? else {
+            return Ok(vec![]);
+        };
+
+        Ok(zone
+            .methods(db)
+            .await?
+            .into_iter()
+            .filter(|method| method.enabled)
+            .filter_map(|method| {
+                method.cost_for(package).map(|cost| ShippingRate {
+                    method_id: method.id,
+                    title: method.title,
+                    cost,
+                })
+            })
+            .collect())
+    }
+
+    /// Deletes a zone along with its locations and methods
+    ///
+    /// # Errors
+    ///
+    /// When could not find the zone or has DB query error
+    pub async fn remove(db: &DatabaseConnection, id: i32) -> ModelResult<()> {
+        let zone = Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or_else(|| ModelError::EntityNotFound)?;
+        zone.delete(db).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,468 @@
+use std::collections::BTreeMap;
+
+use loco_rs::{model::ModelValidation, prelude::*};
+use sea_orm::{QueryOrder, QuerySelect};
+
+pub use super::_entities::stock_movements::{self, ActiveModel, Column, Entity, Model};
+use super::{
+    _entities::{orders, postmetas, users},
+    orders::{STATUS_CANCELLED, STATUS_FAILED, STATUS_REFUNDED},
+    products::{self, BACKORDERS_META, BACKORDERS_NO},
+};
+use crate::common::stock_alerts::StockLevel;
+pub type StockMovements = Entity;
+
+/// stock set by hand on the product form
+pub const REASON_MANUAL: &str = "manual";
+/// stock taken by an order
+pub const REASON_ORDER: &str = "order";
+/// stock given back by a refunded order
+pub const REASON_REFUND: &str = "refund";
+/// stock given back by a cancelled or failed order
+pub const REASON_CANCELLATION: &str = "cancellation";
+/// stock set by a CSV import
+pub const REASON_IMPORT: &str = "import";
+/// stock the product had before its first movement
+pub const REASON_INITIAL: &str = "initial";
+pub const REASONS: [&str; 6] = [
+    REASON_MANUAL,
+    REASON_ORDER,
+    REASON_REFUND,
+    REASON_CANCELLATION,
+    REASON_IMPORT,
+    REASON_INITIAL,
+];
+
+/// Who and what moved the stock
+#[derive(Clone, Debug, Default)]
+pub struct Source {
+    pub user_id: Option<i32>,
+    pub order_id: Option<i32>,
+    pub note: Option<String>,
+}
+
+/// A recorded movement, with the stock of its product around it
+#[derive(Clone, Debug)]
+pub struct StockChange {
+    pub movement: Model,
+    pub before: StockLevel,
+    pub after: StockLevel,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        // the ledger is append-only, a mistake gets fixed by another movement
+        if !insert {
+            return Err(DbErr::Custom(
+                "stock movements cannot be changed".to_string(),
+            ));
+        }
+        Ok(self)
+    }
+
+    async fn before_delete<C>(self, _db: &C) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Err(DbErr::Custom(
+            "stock movements cannot be deleted".to_string(),
+        ))
+    }
+}
+
+async fn set_meta<C>(db: &C, product_id: i32, key: &str, value: String) -> ModelResult<()>
+where
+    C: ConnectionTrait,
+{
+    let meta = postmetas::Entity::find()
+        .filter(postmetas::Column::ProductId.eq(product_id))
+        .filter(postmetas::Column::MetaKey.eq(key))
+        .one(db)
+        .await?;
+    if let Some(meta) = meta {
+        let mut meta = meta.into_active_model();
+        meta.meta_value = ActiveValue::set(Some(value));
+        meta.update(db).await?;
+    } else {
+        postmetas::ActiveModel {
+            product_id: ActiveValue::set(product_id),
+            meta_key: ActiveValue::set(Some(key.to_string())),
+            meta_value: ActiveValue::set(Some(value)),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+    }
+    Ok(())
+}
+
+async fn allows_backorders<C>(db: &C, product_id: i32) -> ModelResult<bool>
+where
+    C: ConnectionTrait,
+{
+    let backorders = postmetas::Entity::find()
+        .filter(postmetas::Column::ProductId.eq(product_id))
+        .filter(postmetas::Column::MetaKey.eq(BACKORDERS_META))
+        .one(db)
+        .await?
+        .and_then(|meta| meta.meta_value);
+    Ok(backorders.is_some_and(|backorders| backorders != BACKORDERS_NO))
+}
+
+impl From<Model> for StockChange {
+    /// The change of a recorded movement, whose stock statuses are unknown
+    fn from(movement: Model) -> Self {
+        Self {
+            before: StockLevel {
+                stock: Some(movement.stock - movement.quantity),
+                status: None,
+            },
+            after: StockLevel {
+                stock: Some(movement.stock),
+                status: None,
+            },
+            movement,
+        }
+    }
+}
+
+impl Model {
+    /// The stock of a product according to its movements, none when it has
+    /// no movement yet
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn balance<C>(db: &C, product_id: i32) -> ModelResult<Option<f32>>
+    where
+        C: ConnectionTrait,
+    {
+        let (count, sum): (i64, Option<f32>) = Entity::find()
+            .select_only()
+            .column_as(Column::Id.count(), "count")
+            .column_as(Column::Quantity.sum(), "sum")
+            .filter(Column::ProductId.eq(product_id))
+            .into_tuple()
+            .one(db)
+            .await?
+            .unwrap_or_default();
+        if count == 0 {
+            return Ok(None);
+        }
+        Ok(Some(sum.unwrap_or_default()))
+    }
+
+    /// Moves the stock of a product by `quantity`, negative when it goes
+    /// down, and derives its `_stock` and `_stock_status` from the ledger.
+    /// The stock a product had before its first movement is recorded as an
+    /// initial movement.
+    ///
+    /// # Errors
+    ///
+    /// When the reason is unknown or has DB query error
+    pub async fn record<C>(
+        db: &C,
+        product_id: i32,
+        quantity: f32,
+        reason: &str,
+        source: Source,
+    ) -> ModelResult<StockChange>
+    where
+        C: ConnectionTrait,
+    {
+        if !REASONS.contains(&reason) {
+            return Err(ModelError::ModelValidation {
+                errors: ModelValidation {
+                    code: "reason".to_string(),
+                    message: Some("unknown stock movement reason".to_string()),
+                },
+            });
+        }
+        let before = StockLevel::load(db, product_id).await?;
+        let balance = match Self::balance(db, product_id).await? {
+            Some(balance) => balance,
+            None => match before.stock.filter(|stock| *stock != 0.0) {
+                Some(stock) => {
+                    Self::insert(
+                        db,
+                        product_id,
+                        stock,
+                        stock,
+                        REASON_INITIAL,
+                        Source::default(),
+                    )
+                    .await?;
+                    stock
+                }
+                None => 0.0,
+            },
+        };
+
+        let stock = balance + quantity;
+        let movement = Self::insert(db, product_id, quantity, stock, reason, source).await?;
+
+        let status = if stock > 0.0 {
+            "instock"
+        } else if allows_backorders(db, product_id).await? {
+            "onbackorder"
+        } else {
+            "outofstock"
+        };
+        set_meta(db, product_id, "_stock", stock.to_string()).await?;
+        set_meta(db, product_id, "_stock_status", status.to_string()).await?;
+
+        let after = StockLevel::load(db, product_id).await?;
+        Ok(StockChange {
+            movement,
+            before,
+            after,
+        })
+    }
+
+    async fn insert<C>(
+        db: &C,
+        product_id: i32,
+        quantity: f32,
+        stock: f32,
+        reason: &str,
+        source: Source,
+    ) -> ModelResult<Self>
+    where
+        C: ConnectionTrait,
+    {
+        let movement = ActiveModel {
+            product_id: ActiveValue::set(product_id),
+            quantity: ActiveValue::set(quantity),
+            stock: ActiveValue::set(stock),
+            reason: ActiveValue::set(reason.to_string()),
+            user_id: ActiveValue::set(source.user_id),
+            order_id: ActiveValue::set(source.order_id),
+            note: ActiveValue::set(source.note),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        Ok(movement)
+    }
+
+    /// Sets the stock of a product, recording the difference with its
+    /// current stock. Nothing is recorded when the stock stays the same.
+    ///
+    /// # Errors
+    ///
+    /// When the reason is unknown or has DB query error
+    pub async fn set_stock<C>(
+        db: &C,
+        product_id: i32,
+        stock: f32,
+        reason: &str,
+        source: Source,
+    ) -> ModelResult<Option<StockChange>>
+    where
+        C: ConnectionTrait,
+    {
+        let level = StockLevel::load(db, product_id).await?;
+        let current = match Self::balance(db, product_id).await? {
+            Some(balance) => balance,
+            None => level.stock.unwrap_or(0.0),
+        };
+        if (stock - current).abs() < f32::EPSILON {
+            // a product managing its stock again gets it back from the ledger
+            if level.stock.is_none() {
+                set_meta(db, product_id, "_stock", current.to_string()).await?;
+            }
+            return Ok(None);
+        }
+        let change = Self::record(db, product_id, stock - current, reason, source).await?;
+        Ok(Some(change))
+    }
+
+    /// Brings `_stock` and `_stock_status` back in line with `_manage_stock`
+    /// and `_backorders` once these were set by hand: a managed stock comes
+    /// back from the ledger and an unmanaged one goes away.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn sync_metas<C>(db: &C, product_id: i32) -> ModelResult<()>
+    where
+        C: ConnectionTrait,
+    {
+        let managed = postmetas::Entity::find()
+            .filter(postmetas::Column::ProductId.eq(product_id))
+            .filter(postmetas::Column::MetaKey.eq("_manage_stock"))
+            .one(db)
+            .await?
+            .and_then(|meta| meta.meta_value);
+        let mut stock = StockLevel::load(db, product_id).await?.stock;
+        match managed.as_deref() {
+            Some("false") => {
+                postmetas::Entity::delete_many()
+                    .filter(postmetas::Column::ProductId.eq(product_id))
+                    .filter(postmetas::Column::MetaKey.eq("_stock"))
+                    .exec(db)
+                    .await?;
+                stock = None;
+            }
+            Some(_) if stock.is_none() => {
+                let balance = Self::balance(db, product_id).await?.unwrap_or(0.0);
+                set_meta(db, product_id, "_stock", balance.to_string()).await?;
+                stock = Some(balance);
+            }
+            _ => {}
+        }
+
+        let backorders = allows_backorders(db, product_id).await?;
+        let status = match stock {
+            Some(stock) if stock > 0.0 => "instock",
+            // without a stock to count down, the product can always be bought
+            None if !backorders => "instock",
+            Some(_) if !backorders => "outofstock",
+            _ => "onbackorder",
+        };
+        set_meta(db, product_id, "_stock_status", status.to_string()).await?;
+        Ok(())
+    }
+
+    /// Takes from the stock what an order holds, `quantities` being by
+    /// product. Products whose stock is not managed are left alone, and
+    /// those not allowing backorders cannot go below zero.
+    ///
+    /// # Errors
+    ///
+    /// When a product has not enough stock or has DB query error
+    pub async fn record_order<C>(
+        db: &C,
+        order: &orders::Model,
+        quantities: &BTreeMap<i32, i32>,
+    ) -> ModelResult<Vec<StockChange>>
+    where
+        C: ConnectionTrait,
+    {
+        let mut changes = vec![];
+        for (&product_id, &quantity) in quantities {
+            let Some(stock) = StockLevel::load(db, product_id).await?.stock else {
+                continue;
+            };
+            if quantity == 0 {
+                continue;
+            }
+            let stock = Self::balance(db, product_id).await?.unwrap_or(stock);
+            if stock < quantity as f32 && !allows_backorders(db, product_id).await? {
+                let title = products::Entity::find_by_id(product_id)
+                    .one(db)
+                    .await?
+                    .map(|product| product.title)
+                    .unwrap_or_default();
+                return Err(ModelError::ModelValidation {
+                    errors: ModelValidation {
+                        code: "stock".to_string(),
+                        message: Some(format!("only {} of {title} left in stock", stock.max(0.0))),
+                    },
+                });
+            }
+            let source = Source {
+                user_id: order.user_id,
+                order_id: Some(order.id),
+                note: None,
+            };
+            changes.push(
+                Self::record(db, product_id, -(quantity as f32), REASON_ORDER, source).await?,
+            );
+        }
+        Ok(changes)
+    }
+
+    /// Brings the stock in line with the status of an order: a cancelled,
+    /// failed or refunded order gives its stock back, and takes it again
+    /// once back to another status.
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn sync_order<C>(
+        db: &C,
+        order: &orders::Model,
+        user_id: Option<i32>,
+    ) -> ModelResult<Vec<StockChange>>
+    where
+        C: ConnectionTrait,
+    {
+        let movements = Entity::find()
+            .filter(Column::OrderId.eq(order.id))
+            .order_by_asc(Column::Id)
+            .all(db)
+            .await?;
+        // what the order took when placed, and what it holds now
+        let mut held: BTreeMap<i32, (Option<f32>, f32)> = BTreeMap::new();
+        for movement in &movements {
+            let (placed, net) = held.entry(movement.product_id).or_default();
+            if placed.is_none() && movement.reason == REASON_ORDER {
+                *placed = Some(movement.quantity);
+            }
+            *net += movement.quantity;
+        }
+
+        let (reason, restocked) = match order.status.as_str() {
+            STATUS_REFUNDED => (REASON_REFUND, true),
+            STATUS_CANCELLED | STATUS_FAILED => (REASON_CANCELLATION, true),
+            _ => (REASON_ORDER, false),
+        };
+        let mut changes = vec![];
+        for (product_id, (placed, net)) in held {
+            let target = if restocked {
+                0.0
+            } else {
+                placed.unwrap_or(0.0)
+            };
+            if (target - net).abs() < f32::EPSILON {
+                continue;
+            }
+            let source = Source {
+                user_id,
+                order_id: Some(order.id),
+                note: Some(format!("order {}", order.status)),
+            };
+            changes.push(Self::record(db, product_id, target - net, reason, source).await?);
+        }
+        Ok(changes)
+    }
+
+    /// Lists the movements of an order, oldest first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn find_by_order(db: &DatabaseConnection, order_id: i32) -> ModelResult<Vec<Self>> {
+        let movements = Entity::find()
+            .filter(Column::OrderId.eq(order_id))
+            .order_by_asc(Column::Id)
+            .all(db)
+            .await?;
+        Ok(movements)
+    }
+
+    /// Lists the movements of a product with who made them, latest first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list_for_product(
+        db: &DatabaseConnection,
+        product_id: i32,
+    ) -> ModelResult<Vec<(Self, Option<users::Model>)>> {
+        let movements = Entity::find()
+            .find_also_related(users::Entity)
+            .filter(Column::ProductId.eq(product_id))
+            .order_by_desc(Column::Id)
+            .all(db)
+            .await?;
+        Ok(movements)
+    }
+}
@@ -0,0 +1,89 @@
+use loco_rs::{prelude::*, validation};
+use sea_orm::QueryOrder;
+use serde::Deserialize;
+
+pub use super::_entities::stock_subscriptions::{self, ActiveModel, Column, Entity, Model};
+pub type StockSubscriptions = Entity;
+
+#[derive(Debug, Validate, Deserialize)]
+pub struct Validator {
+    #[validate(custom(function = "validation::is_valid_email"))]
+    pub email: String,
+}
+
+impl Validatable for ActiveModel {
+    fn validator(&self) -> Box<dyn Validate> {
+        Box::new(Validator {
+            email: self.email.as_ref().to_owned(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        self.validate()?;
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl Model {
+    /// Asks for an email once the product is back in stock. Subscribing
+    /// twice with the same address keeps the first subscription.
+    ///
+    /// # Errors
+    ///
+    /// When the email is not valid or has DB query error
+    pub async fn subscribe(
+        db: &DatabaseConnection,
+        product_id: i32,
+        email: &str,
+    ) -> ModelResult<Self> {
+        let email = email.trim().to_lowercase();
+        let subscription = Entity::find()
+            .filter(Column::ProductId.eq(product_id))
+            .filter(Column::Email.eq(&email))
+            .one(db)
+            .await?;
+        if let Some(subscription) = subscription {
+            return Ok(subscription);
+        }
+
+        let subscription = ActiveModel {
+            product_id: ActiveValue::set(product_id),
+            email: ActiveValue::set(email),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        Ok(subscription)
+    }
+
+    /// Lists the subscriptions to a product, oldest first
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn find_by_product(
+        db: &DatabaseConnection,
+        product_id: i32,
+    ) -> ModelResult<Vec<Self>> {
+        let subscriptions = Entity::find()
+            .filter(Column::ProductId.eq(product_id))
+            .order_by_asc(Column::Id)
+            .all(db)
+            .await?;
+        Ok(subscriptions)
+    }
+}
@@ -0,0 +1,316 @@
+use loco_rs::{model::ModelValidation, prelude::*, validator::ValidationError};
+use sea_orm::{PaginatorTrait, QueryOrder};
+use serde::{Deserialize, Serialize};
+
+pub use super::_entities::tax_rates::{self, ActiveModel, Column, Entity, Model};
+use super::shipping_zone_locations::{normalize_postcode, postcode_matches};
+pub type TaxRates = Entity;
+
+/// Class of the products without a tax class, and of shipping
+pub const CLASS_STANDARD: &str = "standard";
+pub const CLASS_REDUCED: &str = "reduced";
+pub const CLASS_ZERO: &str = "zero";
+pub const CLASSES: [&str; 3] = [CLASS_STANDARD, CLASS_REDUCED, CLASS_ZERO];
+
+/// Where the taxes are calculated for, the address the order ships to
+#[derive(Clone, Debug, Default)]
+pub struct TaxLocation {
+    pub country: String,
+    pub state: Option<String>,
+    pub postcode: String,
+}
+
+/// Tax charged by a rate on an amount, a line of the tax breakdown
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct TaxLine {
+    pub rate_id: i32,
+    pub name: String,
+    /// percentage of the rate
+    pub rate: f32,
+    pub compound: bool,
+    pub amount: f32,
+}
+
+/// A rate of a tax class. Missing country, state or postcode match any
+/// address.
+#[derive(Clone, Debug, Default)]
+pub struct RateParams {
+    pub tax_class: String,
+    pub country: Option<String>,
+    pub state: Option<String>,
+    /// exact postcode, prefix ending with `*` or range
+    pub postcode: Option<String>,
+    pub name: String,
+    pub rate: f32,
+    /// only the first matching rate of each priority applies
+    pub priority: i32,
+    /// compound rates apply on top of the other taxes
+    pub compound: bool,
+    /// whether the rate applies to shipping too
+    pub shipping: bool,
+}
+
+fn is_valid_class(tax_class: &str) -> Result<(), ValidationError> {
+    if CLASSES.contains(&tax_class) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("invalid tax class"))
+    }
+}
+
+#[derive(Debug, Validate, Deserialize)]
+pub struct Validator {
+    #[validate(custom(function = "is_valid_class"))]
+    pub tax_class: String,
+    #[validate(length(min = 1, message = "Name is required."))]
+    pub name: String,
+    #[validate(range(min = 0.0, message = "Rate cannot be negative."))]
+    pub rate: f32,
+}
+
+impl Validatable for ActiveModel {
+    fn validator(&self) -> Box<dyn Validate> {
+        Box::new(Validator {
+            tax_class: self.tax_class.as_ref().to_owned(),
+            name: self.name.as_ref().to_owned(),
+            rate: *self.rate.as_ref(),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        self.validate()?;
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+/// Rounds an amount to cents
+fn round(amount: f32) -> f32 {
+    (amount * 100.0).round() / 100.0
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+/// Calculates the taxes of an amount with the given rates, sorted by
+/// priority as returned by [`Model::find_for`]. When the amount includes its
+/// taxes, they are taken out of it instead of added on top.
+#[must_use]
+pub fn calculate(amount: f32, rates: &[Model], amount_includes_tax: bool) -> Vec<TaxLine> {
+    let simple = rates
+        .iter()
+        .filter(|rate| !rate.compound)
+        .map(|rate| rate.rate / 100.0)
+        .sum::<f32>();
+    let base = if amount_includes_tax {
+        let compound = rates
+            .iter()
+            .filter(|rate| rate.compound)
+            .map(|rate| 1.0 + rate.rate / 100.0)
+            .product::<f32>();
+        amount / ((1.0 + simple) * compound)
+    } else {
+        amount
+    };
+
+    let line = |rate: &Model, amount: f32| TaxLine {
+        rate_id: rate.id,
+        name: rate.name.clone(),
+        rate: rate.rate,
+        compound: rate.compound,
+        amount,
+    };
+    let mut lines = rates
+        .iter()
+        .filter(|rate| !rate.compound)
+        .map(|rate| line(rate, base * rate.rate / 100.0))
+        .collect::<Vec<_>>();
+    // compound rates apply on the amount and the taxes before them
+    let mut compound_base = base * (1.0 + simple);
+    for rate in rates.iter().filter(|rate| rate.compound) {
+        let amount = compound_base * rate.rate / 100.0;
+        compound_base += amount;
+        lines.push(line(rate, amount));
+    }
+
+    for line in &mut lines {
+        line.amount = round(line.amount);
+    }
+    lines
+}
+
+/// Sums the tax lines by rate, e.g. the lines of every item of an order
+#[must_use]
+pub fn merge<'a>(lines: impl IntoIterator<Item = &'a TaxLine>) -> Vec<TaxLine> {
+    let mut merged: Vec<TaxLine> = vec![];
+    for line in lines {
+        match merged
+            .iter_mut()
+            .find(|merged| merged.rate_id == line.rate_id)
+        {
+            Some(merged) => merged.amount = round(merged.amount + line.amount),
+            None => merged.push(line.clone()),
+        }
+    }
+    merged
+}
+
+/// Total amount of the tax lines
+#[must_use]
+pub fn total(lines: &[TaxLine]) -> f32 {
+    round(lines.iter().map(|line| line.amount).sum())
+}
+
+impl Model {
+    /// Adds a rate, matched after the existing ones of the same priority
+    ///
+    /// # Errors
+    ///
+    /// When the rate is invalid or has DB query error
+    pub async fn create(db: &DatabaseConnection, params: &RateParams) -> ModelResult<Self> {
+        let country = non_empty(params.country.as_deref()).map(|code| code.to_uppercase());
+        let state = non_empty(params.state.as_deref()).map(|code| code.to_uppercase());
+        if country.is_none() && state.is_some() {
+            return Err(ModelError::ModelValidation {
+                errors: ModelValidation {
+                    code: "state".to_string(),
+                    message: Some("a state needs a country".to_string()),
+                },
+            });
+        }
+        let position = Entity::find().count(db).await?;
+
+        let rate = ActiveModel {
+            tax_class: ActiveValue::set(params.tax_class.clone()),
+            country: ActiveValue::set(country),
+            state: ActiveValue::set(state),
+            postcode: ActiveValue::set(non_empty(params.postcode.as_deref())),
+            name: ActiveValue::set(params.name.trim().to_string()),
+            rate: ActiveValue::set(params.rate),
+            priority: ActiveValue::set(params.priority),
+            compound: ActiveValue::set(params.compound),
+            shipping: ActiveValue::set(params.shipping),
+            position: ActiveValue::set(i32::try_from(position).unwrap_or(i32::MAX)),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+
+        Ok(rate)
+    }
+
+    /// Lists every rate by class, in the order they are matched
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn list(db: &DatabaseConnection) -> ModelResult<Vec<Self>> {
+        let rates = Entity::find()
+            .order_by_asc(Column::TaxClass)
+            .order_by_asc(Column::Priority)
+            .order_by_asc(Column::Position)
+            .order_by_asc(Column::Id)
+            .all(db)
+            .await?;
+        Ok(rates)
+    }
+
+    /// Whether an address at this location is taxed by the rate
+    #[must_use]
+    pub fn matches(&self, location: &TaxLocation) -> bool {
+        self.country
+            .as_deref()
+            .is_none_or(|country| country.eq_ignore_ascii_case(location.country.trim()))
+            && self.state.as_deref().is_none_or(|state| {
+                location
+                    .state
+                    .as_deref()
+                    .is_some_and(|other| state.eq_ignore_ascii_case(other.trim()))
+            })
+            && self.postcode.as_deref().is_none_or(|postcode| {
+                postcode_matches(postcode, &normalize_postcode(&location.postcode))
+            })
+    }
+
+    async fn find_matching(
+        db: &DatabaseConnection,
+        tax_class: &str,
+        location: &TaxLocation,
+        shipping: bool,
+    ) -> ModelResult<Vec<Self>> {
+        let candidates = Entity::find()
+            .filter(Column::TaxClass.eq(tax_class))
+            .order_by_asc(Column::Priority)
+            .order_by_asc(Column::Position)
+            .order_by_asc(Column::Id)
+            .all(db)
+            .await?;
+        let mut rates: Vec<Self> = vec![];
+        for rate in candidates {
+            let taken = rates
+                .last()
+                .is_some_and(|last| last.priority == rate.priority);
+            if !taken && (!shipping || rate.shipping) && rate.matches(location) {
+                rates.push(rate);
+            }
+        }
+        Ok(rates)
+    }
+
+    /// finds the rates of a tax class applying at the location, the first
+    /// matching one of each priority, by priority
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn find_for(
+        db: &DatabaseConnection,
+        tax_class: &str,
+        location: &TaxLocation,
+    ) -> ModelResult<Vec<Self>> {
+        Self::find_matching(db, tax_class, location, false).await
+    }
+
+    /// finds the standard rates applying to shipping at the location
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn find_for_shipping(
+        db: &DatabaseConnection,
+        location: &TaxLocation,
+    ) -> ModelResult<Vec<Self>> {
+        Self::find_matching(db, CLASS_STANDARD, location, true).await
+    }
+
+    /// Deletes a rate
+    ///
+    /// # Errors
+    ///
+    /// When could not find the rate or has DB query error
+    pub async fn remove(db: &DatabaseConnection, id: i32) -> ModelResult<()> {
+        let rate = Entity::find_by_id(id)
+            .one(db)
+            .await?
+            .ok_or_else(|| ModelError::EntityNotFound)?;
+        rate.delete(db).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use loco_rs::{model::ModelValidation, prelude::*};
+use sea_orm::QueryOrder;
+
+pub use super::_entities::user_identities::{self, ActiveModel, Column, Entity, Model};
+use super::users::{self, RegisterParams};
+pub type UserIdentities = Entity;
+
+/// Profile of a user at an identity provider
+#[derive(Clone, Debug)]
+pub struct ExternalProfile {
+    /// identifier of the user at the provider, which never changes
+    pub subject: String,
+    pub email: Option<String>,
+    /// whether the provider checked the user owns the email
+    pub email_verified: bool,
+    pub name: Option<String>,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    // extend activemodel below (keep comment for generators)
+
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+fn rejected(code: &str, message: &str) -> ModelError {
+    ModelError::ModelValidation {
+        errors: ModelValidation {
+            code: code.to_string(),
+            message: Some(message.to_string()),
+        },
+    }
+}
+
+impl Model {
+    /// finds the identities linked to a user
+    ///
+    /// # Errors
+    ///
+    /// When has DB query error
+    pub async fn find_by_user(db: &DatabaseConnection, user_id: i32) -> ModelResult<Vec<Self>> {
+        let identities = Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .order_by_asc(Column::Provider)
+            .all(db)
+            .await?;
+        Ok(identities)
+    }
+
+    async fn link(
+        db: &DatabaseConnection,
+        user: &users::Model,
+        provider: &str,
+        profile: &ExternalProfile,
+    ) -> ModelResult<Self> {
+        let identity = ActiveModel {
+            user_id: ActiveValue::set(user.id),
+            provider: ActiveValue::set(provider.to_string()),
+            subject: ActiveValue::set(profile.subject.clone()),
+            email: ActiveValue::set(profile.email.clone()),
+            last_login_at: ActiveValue::set(Some(chrono::Utc::now().into())),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        tracing::info!(
+            pid = user.pid.to_string(),
+            provider,
+            "external identity linked"
+        );
+        Ok(identity)
+    }
+
+    /// Finds the user of an external identity, linking it first when needed:
+    /// to the logged in user if any, otherwise to the account with the same
+    /// email. A new account is created when there is none. Emails are only
+    /// trusted when both the provider and the account verified them, so an
+    /// account registered with someone else's email cannot be taken over.
+    ///
+    /// Returns the user and whether the identity just got linked.
+    ///
+    /// # Errors
+    ///
+    /// When the identity is linked to another user, the email cannot be
+    /// trusted or has DB query error
+    pub async fn login(
+        db: &DatabaseConnection,
+        provider: &str,
+        profile: &ExternalProfile,
+        current_user: Option<&users::Model>,
+    ) -> ModelResult<(users::Model, bool)> {
+        let identity = Entity::find()
+            .filter(Column::Provider.eq(provider))
+            .filter(Column::Subject.eq(&profile.subject))
+            .one(db)
+            .await?;
+
+        if let Some(identity) = identity {
+            if current_user.is_some_and(|user| user.id != identity.user_id) {
+                return Err(ModelError::EntityAlreadyExists);
+            }
+            let user = users::Entity::find_by_id(identity.user_id)
+                .one(db)
+                .await?
+                .ok_or_else(|| ModelError::EntityNotFound)?;
+            let mut identity = identity.into_active_model();
+            identity.email = ActiveValue::set(profile.email.clone());
+            identity.last_login_at = ActiveValue::set(Some(chrono::Utc::now().into()));
+            identity.update(db).await?;
+            return Ok((user, false));
+        }
+
+        if let Some(user) = current_user {
+            Self::link(db, user, provider, profile).await?;
+            return Ok((user.clone(), true));
+        }
+
+        let email = match &profile.email {
+            Some(email) if profile.email_verified => email.trim().to_string(),
+            _ => {
+                return Err(rejected(
+                    "unverified_email",
+                    "the provider did not share a verified email",
+                ))
+            }
+        };
+
+        let user = match users::Model::find_by_email(db, &email).await {
+            Ok(user) if user.email_verified_at.is_some() => user,
+            Ok(_) => {
+                return Err(rejected(
+                    "unverified_account",
+                    "an account exists with this email, log in with your password to link it",
+                ))
+            }
+            Err(ModelError::EntityNotFound) => {
+                let name = profile
+                    .name
+                    .clone()
+                    .filter(|name| name.trim().len() >= 2)
+                    .unwrap_or_else(|| email.split('@').next().unwrap_or_default().to_string());
+                // the password is random: the user signs in with the provider,
+                // or sets one with the forgotten password link
+                let user = users::Model::create_with_password(
+                    db,
+                    &RegisterParams {
+                        email: email.clone(),
+                        password: Uuid::new_v4().to_string(),
+                        name,
+                    },
+                )
+                .await?;
+                user.into_active_model().verified(db).await?
+            }
+            Err(err) => return Err(err),
+        };
+
+        Self::link(db, &user, provider, profile).await?;
+        Ok((user, true))
+    }
+
+    /// Unlinks an identity of the user
+    ///
+    /// # Errors
+    ///
+    /// When the identity does not belong to the user or has DB query error
+    pub async fn unlink(db: &DatabaseConnection, user_id: i32, id: i32) -> ModelResult<Self> {
+        let identity = Entity::find_by_id(id)
+            .filter(Column::UserId.eq(user_id))
+            .one(db)
+            .await?
+            .ok_or_else(|| ModelError::EntityNotFound)?;
+        identity.clone().delete(db).await?;
+        Ok(identity)
+    }
+}
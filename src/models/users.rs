@@ -1,10 +1,33 @@
 use async_trait::async_trait;
 use chrono::offset::Local;
-use loco_rs::{auth::jwt, hash, prelude::*};
+use loco_rs::{auth::jwt, hash, prelude::*, validator::ValidationError};
 use serde::{Deserialize, Serialize};
+use totp_rs::{Algorithm, Secret, TOTP};
 use uuid::Uuid;
 
 pub use super::_entities::users::{self, ActiveModel, Entity, Model};
+use super::{
+    api_keys::ApiKeyUser, login_attempts::LOCKOUT_MINUTES, orders, recovery_codes, refresh_tokens,
+};
+
+pub const ROLE_CUSTOMER: &str = "customer";
+pub const ROLE_SHOP_MANAGER: &str = "shop_manager";
+pub const ROLE_ADMINISTRATOR: &str = "administrator";
+
+/// Name the account is listed under in authenticator apps
+const TOTP_ISSUER: &str = "commust";
+/// Seconds each TOTP code is valid for
+const TOTP_STEP: u64 = 30;
+/// Seconds left to complete the second login step once the password is
+/// checked
+pub const TWO_FACTOR_CHALLENGE_SECONDS: u64 = 300;
+/// `purpose` claim of the challenge tokens, which access tokens don't have
+const TWO_FACTOR_PURPOSE: &str = "two_factor";
+
+/// Hash checked against when no user matches a login email, so rejecting an
+/// unknown email takes as long as rejecting a wrong password
+const DUMMY_PASSWORD_HASH: &str =
+    "$argon2id$v=19$m=19456,t=2,p=1$ETQBx4rTgNAZhSaeYZKOZg$eYTdH26CRT6nUJtacLDEboP0li6xUwUF/q5nSlQ8uuc";
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct LoginParams {
@@ -19,12 +42,34 @@ pub struct RegisterParams {
     pub name: String,
 }
 
+/// Outcome of a code given at the second login step
+pub enum SecondFactor {
+    /// a code of the authenticator app
+    Totp(Box<Model>),
+    /// the first code of a pending enrollment, which is now enabled along with
+    /// the returned recovery codes
+    Enrolled(Box<Model>, Vec<String>),
+    /// a recovery code, which is now used up
+    RecoveryCode(Box<Model>),
+    Invalid,
+}
+
+fn is_valid_role(role: &str) -> Result<(), ValidationError> {
+    if [ROLE_CUSTOMER, ROLE_SHOP_MANAGER, ROLE_ADMINISTRATOR].contains(&role) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("invalid role"))
+    }
+}
+
 #[derive(Debug, Validate, Deserialize)]
 pub struct Validator {
     #[validate(length(min = 2, message = "Name must be at least 2 characters long."))]
     pub name: String,
     #[validate(custom(function = "validation::is_valid_email"))]
     pub email: String,
+    #[validate(custom(function = "is_valid_role"))]
+    pub role: String,
 }
 
 impl Validatable for super::_entities::users::ActiveModel {
@@ -32,10 +77,32 @@ impl Validatable for super::_entities::users::ActiveModel {
         Box::new(Validator {
             name: self.name.as_ref().to_owned(),
             email: self.email.as_ref().to_owned(),
+            role: self
+                .role
+                .try_as_ref()
+                .map_or_else(|| ROLE_CUSTOMER.to_string(), ToOwned::to_owned),
         })
     }
 }
 
+/// Token version carried by the claims of a JWT
+fn claimed_token_version(claims: &jwt::UserClaims) -> Option<i64> {
+    claims
+        .claims
+        .as_ref()
+        .and_then(|claims| claims.get("token_version"))
+        .and_then(serde_json::Value::as_i64)
+}
+
+/// Purpose of a JWT, access tokens have none
+fn claimed_purpose(claims: &jwt::UserClaims) -> Option<&str> {
+    claims
+        .claims
+        .as_ref()
+        .and_then(|claims| claims.get("purpose"))
+        .and_then(serde_json::Value::as_str)
+}
+
 #[async_trait::async_trait]
 impl ActiveModelBehavior for super::_entities::users::ActiveModel {
     async fn before_save<C>(self, _db: &C, insert: bool) -> Result<Self, DbErr>
@@ -46,6 +113,8 @@ impl ActiveModelBehavior for super::_entities::users::ActiveModel {
         if insert {
             let mut this = self;
             this.pid = ActiveValue::Set(Uuid::new_v4());
+            // api keys live in `api_keys`, this random value only fills the
+            // required unique column
             this.api_key = ActiveValue::Set(format!("lo-{}", Uuid::new_v4()));
             Ok(this)
         } else {
@@ -57,15 +126,7 @@ impl ActiveModelBehavior for super::_entities::users::ActiveModel {
 #[async_trait]
 impl Authenticable for super::_entities::users::Model {
     async fn find_by_api_key(db: &DatabaseConnection, api_key: &str) -> ModelResult<Self> {
-        let user = users::Entity::find()
-            .filter(
-                model::query::condition()
-                    .eq(users::Column::ApiKey, api_key)
-                    .build(),
-            )
-            .one(db)
-            .await?;
-        user.ok_or_else(|| ModelError::EntityNotFound)
+        Self::find_by_api_key(db, api_key).await
     }
 
     async fn find_by_claims_key(db: &DatabaseConnection, claims_key: &str) -> ModelResult<Self> {
@@ -146,16 +207,26 @@ impl super::_entities::users::Model {
         user.ok_or_else(|| ModelError::EntityNotFound)
     }
 
-    /// finds a user by the provided api key
+    /// finds a user by one of their api keys which is not revoked, whatever
+    /// its scope
     ///
     /// # Errors
     ///
     /// When could not find user by the given token or DB query error
     pub async fn find_by_api_key(db: &DatabaseConnection, api_key: &str) -> ModelResult<Self> {
+        Ok(ApiKeyUser::find_by_api_key(db, api_key).await?.user)
+    }
+
+    /// finds a user by the provided unlock token
+    ///
+    /// # Errors
+    ///
+    /// When could not find user by the given token or DB query error
+    pub async fn find_by_unlock_token(db: &DatabaseConnection, token: &str) -> ModelResult<Self> {
         let user = users::Entity::find()
             .filter(
                 model::query::condition()
-                    .eq(users::Column::ApiKey, api_key)
+                    .eq(users::Column::UnlockToken, token)
                     .build(),
             )
             .one(db)
@@ -163,6 +234,20 @@ impl super::_entities::users::Model {
         user.ok_or_else(|| ModelError::EntityNotFound)
     }
 
+    /// Runs a password verification against a dummy hash. Used when the login
+    /// email is unknown to keep the response time the same as for a wrong
+    /// password.
+    pub fn verify_dummy_password(password: &str) {
+        let _ = hash::verify_password(password, DUMMY_PASSWORD_HASH);
+    }
+
+    /// Whether the account is currently locked after too many failed logins
+    #[must_use]
+    pub fn is_locked(&self) -> bool {
+        self.locked_until
+            .is_some_and(|locked_until| locked_until > Local::now().fixed_offset())
+    }
+
     /// Verifies whether the provided plain password matches the hashed password
     ///
     /// # Errors
@@ -204,6 +289,7 @@ impl super::_entities::users::Model {
             email: ActiveValue::set(params.email.to_string()),
             password: ActiveValue::set(password_hash),
             name: ActiveValue::set(params.name.to_string()),
+            role: ActiveValue::set(ROLE_CUSTOMER.to_string()),
             ..Default::default()
         }
         .insert(&txn)
@@ -214,13 +300,193 @@ impl super::_entities::users::Model {
         Ok(user)
     }
 
-    /// Creates a JWT
+    /// Creates a JWT. It carries the token version of the user so bumping
+    /// the version revokes it.
     ///
     /// # Errors
     ///
     /// when could not convert user claims to jwt token
     pub fn generate_jwt(&self, secret: &str, expiration: &u64) -> ModelResult<String> {
-        Ok(jwt::JWT::new(secret).generate_token(expiration, self.pid.to_string(), None)?)
+        Ok(jwt::JWT::new(secret).generate_token(
+            expiration,
+            self.pid.to_string(),
+            Some(serde_json::json!({ "token_version": self.token_version })),
+        )?)
+    }
+
+    /// finds the user of a decoded JWT, as long as the token was not revoked
+    /// by bumping the token version since it was issued. Challenge tokens of
+    /// the second login step are rejected.
+    ///
+    /// # Errors
+    ///
+    /// When could not find user, the token is revoked or DB query error
+    pub async fn find_by_claims(
+        db: &DatabaseConnection,
+        claims: &jwt::UserClaims,
+    ) -> ModelResult<Self> {
+        if claimed_purpose(claims).is_some() {
+            return Err(ModelError::EntityNotFound);
+        }
+        let user = Self::find_by_pid(db, &claims.pid).await?;
+        if claimed_token_version(claims) != Some(i64::from(user.token_version)) {
+            return Err(ModelError::EntityNotFound);
+        }
+        Ok(user)
+    }
+
+    /// Creates the short lived token handed out once the password is checked,
+    /// to be exchanged for an access token at the second login step.
+    ///
+    /// # Errors
+    ///
+    /// when could not convert user claims to jwt token
+    pub fn generate_challenge_jwt(&self, secret: &str) -> ModelResult<String> {
+        Ok(jwt::JWT::new(secret).generate_token(
+            &TWO_FACTOR_CHALLENGE_SECONDS,
+            self.pid.to_string(),
+            Some(serde_json::json!({
+                "purpose": TWO_FACTOR_PURPOSE,
+                "token_version": self.token_version,
+            })),
+        )?)
+    }
+
+    /// finds the user of a challenge token of the second login step
+    ///
+    /// # Errors
+    ///
+    /// When the token is invalid, expired, revoked or has DB query error
+    pub async fn find_by_challenge_jwt(
+        db: &DatabaseConnection,
+        secret: &str,
+        token: &str,
+    ) -> ModelResult<Self> {
+        let claims = jwt::JWT::new(secret).validate(token)?.claims;
+        if claimed_purpose(&claims) != Some(TWO_FACTOR_PURPOSE) {
+            return Err(ModelError::EntityNotFound);
+        }
+        let user = Self::find_by_pid(db, &claims.pid).await?;
+        if claimed_token_version(&claims) != Some(i64::from(user.token_version)) {
+            return Err(ModelError::EntityNotFound);
+        }
+        Ok(user)
+    }
+
+    /// Whether the user enabled two-factor authentication
+    #[must_use]
+    pub fn has_two_factor(&self) -> bool {
+        self.totp_enabled_at.is_some()
+    }
+
+    /// Whether the user may manage the shop settings, e.g. shipping
+    #[must_use]
+    pub fn can_manage_shop(&self) -> bool {
+        self.role == ROLE_SHOP_MANAGER || self.role == ROLE_ADMINISTRATOR
+    }
+
+    /// Whether the role of the user forbids logging in without two-factor
+    /// authentication
+    #[must_use]
+    pub fn must_use_two_factor(&self, required_roles: &[String]) -> bool {
+        required_roles.iter().any(|role| role == &self.role)
+    }
+
+    fn totp(&self) -> ModelResult<Option<TOTP>> {
+        let Some(secret) = &self.totp_secret else {
+            return Ok(None);
+        };
+        let secret = Secret::Encoded(secret.clone())
+            .to_bytes()
+            .map_err(|e| ModelError::Any(e.into()))?;
+        let totp = TOTP::new(
+            Algorithm::SHA1,
+            6,
+            0,
+            TOTP_STEP,
+            secret,
+            Some(TOTP_ISSUER.to_string()),
+            self.email.clone(),
+        )
+        .map_err(|e| ModelError::Any(e.into()))?;
+        Ok(Some(totp))
+    }
+
+    /// The `otpauth://` URI adding the secret of the user to an authenticator
+    /// app, along with its QR code as a base64 encoded PNG
+    ///
+    /// # Errors
+    ///
+    /// When the stored secret or the QR code is invalid
+    pub fn totp_provisioning(&self) -> ModelResult<Option<(String, String)>> {
+        let Some(totp) = self.totp()? else {
+            return Ok(None);
+        };
+        let qr_code = totp
+            .get_qr_base64()
+            .map_err(|e| ModelError::Any(e.into()))?;
+        Ok(Some((totp.get_url(), qr_code)))
+    }
+
+    /// Checks a code of the authenticator app. The codes of the previous and
+    /// next steps are accepted too for clock drift, but each code only once.
+    /// Returns the time step of the code.
+    ///
+    /// # Errors
+    ///
+    /// When the stored secret is invalid
+    pub fn verify_totp(&self, code: &str) -> ModelResult<Option<i64>> {
+        let Some(totp) = self.totp()? else {
+            return Ok(None);
+        };
+        let code = code.replace(' ', "");
+        let now = u64::try_from(chrono::Utc::now().timestamp()).unwrap_or_default();
+        let current_step = now / TOTP_STEP;
+
+        for step in [current_step - 1, current_step, current_step + 1] {
+            let Ok(step_number) = i64::try_from(step) else {
+                continue;
+            };
+            if self
+                .totp_last_step
+                .is_some_and(|last_step| step_number <= last_step)
+            {
+                continue;
+            }
+            if totp.check(&code, step * TOTP_STEP) {
+                return Ok(Some(step_number));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Checks the code given at the second login step: a code of the
+    /// authenticator app, confirming the enrollment when still pending, or
+    /// one of the recovery codes.
+    ///
+    /// # Errors
+    ///
+    /// When the stored secret is invalid or has DB query error
+    pub async fn verify_second_factor(
+        self,
+        db: &DatabaseConnection,
+        code: &str,
+    ) -> ModelResult<SecondFactor> {
+        if let Some(step) = self.verify_totp(code)? {
+            if self.has_two_factor() {
+                let user = self.into_active_model().record_totp_step(db, step).await?;
+                return Ok(SecondFactor::Totp(Box::new(user)));
+            }
+            let user = self.into_active_model().enable_two_factor(db, step).await?;
+            let codes = recovery_codes::Model::generate(db, user.id).await?;
+            return Ok(SecondFactor::Enrolled(Box::new(user), codes));
+        }
+
+        if self.has_two_factor() && recovery_codes::Model::redeem(db, self.id, code).await? {
+            return Ok(SecondFactor::RecoveryCode(Box::new(self)));
+        }
+
+        Ok(SecondFactor::Invalid)
     }
 }
 
@@ -265,13 +531,81 @@ impl super::_entities::users::ActiveModel {
     /// email and updates it in the database.
     ///
     /// This method sets the timestamp when the user successfully verifies their
-    /// email.
+    /// email. When the user asked for an email change, the pending email
+    /// replaces the current one. Guest orders placed with the verified email
+    /// are linked to the user.
     ///
     /// # Errors
     ///
     /// when has DB query error
     pub async fn verified(mut self, db: &DatabaseConnection) -> ModelResult<Model> {
+        if let Some(pending_email) = self.pending_email.clone().take().flatten() {
+            self.email = ActiveValue::set(pending_email);
+            self.pending_email = ActiveValue::set(None);
+        }
         self.email_verified_at = ActiveValue::set(Some(Local::now().into()));
+        let user = self.update(db).await?;
+        orders::Model::claim_guest_orders(db, user.id, &user.email).await?;
+        Ok(user)
+    }
+
+    /// Stores the requested email as pending until it gets verified with the
+    /// generated verification token. The current email keeps being used
+    /// meanwhile.
+    ///
+    /// # Errors
+    ///
+    /// when the email is invalid, already used by another user or has DB query
+    /// error
+    pub async fn request_email_change(
+        mut self,
+        db: &DatabaseConnection,
+        email: &str,
+    ) -> ModelResult<Model> {
+        let email = email.trim();
+        validation::is_valid_email(email).map_err(|e| ModelError::Any(e.into()))?;
+
+        if users::Entity::find()
+            .filter(
+                model::query::condition()
+                    .eq(users::Column::Email, email)
+                    .build(),
+            )
+            .one(db)
+            .await?
+            .is_some()
+        {
+            return Err(ModelError::EntityAlreadyExists {});
+        }
+
+        self.pending_email = ActiveValue::set(Some(email.to_string()));
+        self.email_verification_sent_at = ActiveValue::set(Some(Local::now().into()));
+        self.email_verification_token = ActiveValue::Set(Some(Uuid::new_v4().to_string()));
+        Ok(self.update(db).await?)
+    }
+
+    /// Locks the account for [`LOCKOUT_MINUTES`] and generates the token
+    /// sent by email to unlock it earlier.
+    ///
+    /// # Errors
+    ///
+    /// when has DB query error
+    pub async fn lock(mut self, db: &DatabaseConnection) -> ModelResult<Model> {
+        self.locked_until = ActiveValue::set(Some(
+            (Local::now() + chrono::Duration::minutes(LOCKOUT_MINUTES)).into(),
+        ));
+        self.unlock_token = ActiveValue::Set(Some(Uuid::new_v4().to_string()));
+        Ok(self.update(db).await?)
+    }
+
+    /// Lifts an account lock and invalidates the unlock token.
+    ///
+    /// # Errors
+    ///
+    /// when has DB query error
+    pub async fn unlock(mut self, db: &DatabaseConnection) -> ModelResult<Model> {
+        self.locked_until = ActiveValue::set(None);
+        self.unlock_token = ActiveValue::Set(None);
         Ok(self.update(db).await?)
     }
 
@@ -279,7 +613,7 @@ impl super::_entities::users::ActiveModel {
     /// updates it in the database.
     ///
     /// This method hashes the provided password and sets it as the new password
-    /// for the user.
+    /// for the user. Existing sessions of the user are revoked.
     ///
     /// # Errors
     ///
@@ -293,6 +627,78 @@ impl super::_entities::users::ActiveModel {
             ActiveValue::set(hash::hash_password(password).map_err(|e| ModelError::Any(e.into()))?);
         self.reset_token = ActiveValue::Set(None);
         self.reset_sent_at = ActiveValue::Set(None);
+        self.revoke_sessions(db).await
+    }
+
+    /// Generates a new TOTP secret, pending until a first code confirms the
+    /// user added it to their authenticator app
+    ///
+    /// # Errors
+    ///
+    /// when has DB query error
+    pub async fn start_two_factor(mut self, db: &DatabaseConnection) -> ModelResult<Model> {
+        self.totp_secret =
+            ActiveValue::set(Some(Secret::generate_secret().to_encoded().to_string()));
+        self.totp_enabled_at = ActiveValue::set(None);
+        self.totp_last_step = ActiveValue::set(None);
+        Ok(self.update(db).await?)
+    }
+
+    /// Enables the pending TOTP secret, `step` being the time step of the code
+    /// which confirmed it
+    ///
+    /// # Errors
+    ///
+    /// when has DB query error
+    pub async fn enable_two_factor(
+        mut self,
+        db: &DatabaseConnection,
+        step: i64,
+    ) -> ModelResult<Model> {
+        self.totp_enabled_at = ActiveValue::set(Some(Local::now().into()));
+        self.totp_last_step = ActiveValue::set(Some(step));
+        Ok(self.update(db).await?)
+    }
+
+    /// Records the time step of the last accepted code, so it cannot be
+    /// replayed
+    ///
+    /// # Errors
+    ///
+    /// when has DB query error
+    pub async fn record_totp_step(
+        mut self,
+        db: &DatabaseConnection,
+        step: i64,
+    ) -> ModelResult<Model> {
+        self.totp_last_step = ActiveValue::set(Some(step));
         Ok(self.update(db).await?)
     }
+
+    /// Removes the TOTP secret and the recovery codes of the user
+    ///
+    /// # Errors
+    ///
+    /// when has DB query error
+    pub async fn disable_two_factor(mut self, db: &DatabaseConnection) -> ModelResult<Model> {
+        self.totp_secret = ActiveValue::set(None);
+        self.totp_enabled_at = ActiveValue::set(None);
+        self.totp_last_step = ActiveValue::set(None);
+        let user = self.update(db).await?;
+        recovery_codes::Model::delete_all(db, user.id).await?;
+        Ok(user)
+    }
+
+    /// Revokes every access and refresh token issued to the user so far
+    ///
+    /// # Errors
+    ///
+    /// when has DB query error
+    pub async fn revoke_sessions(mut self, db: &DatabaseConnection) -> ModelResult<Model> {
+        let token_version = *self.token_version.as_ref();
+        self.token_version = ActiveValue::set(token_version + 1);
+        let user = self.update(db).await?;
+        refresh_tokens::Model::revoke_all(db, user.id).await?;
+        Ok(user)
+    }
 }
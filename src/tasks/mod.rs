@@ -1 +1,4 @@
+pub mod product_export;
+pub mod product_import;
+pub mod publish_scheduled;
 pub mod seed;
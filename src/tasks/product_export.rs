@@ -0,0 +1,41 @@
+//! This task exports every product but the trashed ones to a CSV file in the
+//! format of the WooCommerce product importer, which the `product_import`
+//! task reads back.
+//!
+//! # Example
+//!
+//! Run the task with the following command:
+//! ```sh
+//! cargo loco task product_export file:products.csv
+//! ```
+//!
+//! Without the `file` argument, the CSV is written to the standard output.
+
+use loco_rs::prelude::*;
+
+use crate::common::product_csv;
+
+#[allow(clippy::module_name_repetitions)]
+pub struct ProductExport;
+#[async_trait]
+impl Task for ProductExport {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "product_export".to_string(),
+            detail: "Export products to a WooCommerce CSV file".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, vars: &task::Vars) -> Result<()> {
+        match vars.cli_arg("file") {
+            Ok(file) => {
+                let count = product_csv::export(app_context, std::fs::File::create(file)?).await?;
+                println!("{count} products exported to {file}");
+            }
+            Err(_) => {
+                product_csv::export(app_context, std::io::stdout()).await?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+//! This task imports products from a CSV file in the format of the
+//! WooCommerce product exporter. Products of a SKU already in the store are
+//! updated, the other ones are created and credited to the shop manager
+//! given by email with the `author` argument.
+//!
+//! # Example
+//!
+//! Run the task with the following command:
+//! ```sh
+//! cargo loco task product_import file:products.csv author:admin@example.com
+//! ```
+//!
+//! To check the file without saving anything, use the `dry_run:true`
+//! argument:
+//! ```sh
+//! cargo loco task product_import file:products.csv author:admin@example.com dry_run:true
+//! ```
+
+use loco_rs::prelude::*;
+
+use crate::{common::product_csv, models::users};
+
+#[allow(clippy::module_name_repetitions)]
+pub struct ProductImport;
+#[async_trait]
+impl Task for ProductImport {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "product_import".to_string(),
+            detail: "Import products from a WooCommerce CSV file".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, vars: &task::Vars) -> Result<()> {
+        let file = vars.cli_arg("file")?;
+        let dry_run = vars
+            .cli_arg("dry_run")
+            .is_ok_and(|dry_run| dry_run == "true");
+        let email = vars.cli_arg("author")?;
+        let author = match users::Model::find_by_email(&app_context.db, email).await {
+            Ok(author) => author,
+            Err(ModelError::EntityNotFound) => {
+                return Err(Error::Message(format!("no user with the email {email}")));
+            }
+            Err(err) => return Err(err.into()),
+        };
+        if !author.can_manage_shop() {
+            return Err(Error::Message(format!(
+                "{email} is not a shop manager, who alone may author products"
+            )));
+        }
+
+        let report =
+            product_csv::import(app_context, std::fs::File::open(file)?, dry_run, &author).await?;
+        for error in &report.errors {
+            println!("line {}: {}", error.line, error.message);
+        }
+        let verb = if dry_run { "would be" } else { "were" };
+        println!(
+            "{} products {verb} created, {} {verb} updated, {} lines skipped",
+            report.created,
+            report.updated,
+            report.errors.len()
+        );
+
+        if report.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Message(format!(
+                "{} lines could not be imported",
+                report.errors.len()
+            )))
+        }
+    }
+}
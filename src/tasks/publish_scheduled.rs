@@ -0,0 +1,37 @@
+//! This task publishes the scheduled products whose date came. The scheduler
+//! of the development configuration runs it every minute.
+//!
+//! # Example
+//!
+//! Run the task with the following command:
+//! ```sh
+//! cargo loco task publish_scheduled
+//! ```
+//!
+//! or start the scheduler with:
+//! ```sh
+//! cargo loco scheduler --all
+//! ```
+
+use loco_rs::prelude::*;
+
+use crate::workers::scheduled_products::{ScheduledProductsWorker, ScheduledProductsWorkerArgs};
+
+#[allow(clippy::module_name_repetitions)]
+pub struct PublishScheduled;
+#[async_trait]
+impl Task for PublishScheduled {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "publish_scheduled".to_string(),
+            detail: "Publish the scheduled products whose date came".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, _vars: &task::Vars) -> Result<()> {
+        // performed right away, the task process would not wait for a queue
+        ScheduledProductsWorker::build(app_context)
+            .perform(ScheduledProductsWorkerArgs::default())
+            .await
+    }
+}
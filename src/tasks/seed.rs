@@ -1,5 +1,6 @@
 //! This task implements data seeding functionality for initializing new
-//! development/demo environments.
+//! development/demo environments: users, a small catalog with a variable
+//! product and its variations, and a few historic orders.
 //!
 //! # Example
 //!
@@ -13,11 +14,32 @@
 //! ```sh
 //! cargo run task seed_data refresh:true
 //! ```
+//!
+//! To load test the store, the `size` argument adds as many synthetic
+//! products and orders on top of the fixtures:
+//! ```sh
+//! cargo run task seed_data refresh:true size:5000
+//! ```
 
+use chrono::{Duration, Utc};
 use loco_rs::{db, prelude::*};
 use migration::Migrator;
+use sea_orm::{QueryOrder, QuerySelect};
+use slug::slugify;
+
+use crate::{
+    app::App,
+    models::{
+        _entities::{order_items, orders, postmetas, products},
+        orders::{STATUS_COMPLETED, STATUS_ON_HOLD, STATUS_PROCESSING},
+        products::{ProductStatus, TYPE_SIMPLE},
+    },
+};
+
+/// Rows inserted at once, under the bind parameters limit of SQLite
+const BATCH_SIZE: usize = 500;
 
-use crate::app::App;
+const CATEGORIES: [&str; 4] = ["Clothing", "Clothing > Hoodies", "Books", "Kitchen"];
 
 #[allow(clippy::module_name_repetitions)]
 pub struct SeedData;
@@ -34,12 +56,167 @@ impl Task for SeedData {
         let refresh = vars
             .cli_arg("refresh")
             .is_ok_and(|refresh| refresh == "true");
+        let size = match vars.cli_arg("size") {
+            Ok(size) => size
+                .parse::<usize>()
+                .map_err(|_| Error::Message(format!("size must be a number, not {size}")))?,
+            Err(_) => 0,
+        };
 
         if refresh {
             db::reset::<Migrator>(&app_context.db).await?;
         }
         let path = std::path::Path::new("src/fixtures");
         db::run_app_seed::<App>(&app_context.db, path).await?;
+        if size > 0 {
+            generate(&app_context.db, size).await?;
+        }
         Ok(())
     }
 }
+
+async fn last_id<E>(db: &DatabaseConnection, column: E::Column) -> Result<i32>
+where
+    E: EntityTrait,
+{
+    let id = E::find()
+        .select_only()
+        .column(column)
+        .order_by_desc(column)
+        .into_tuple::<i32>()
+        .one(db)
+        .await?;
+    Ok(id.unwrap_or_default())
+}
+
+/// Adds `size` published products in stock, and `size` orders of one to
+/// three of them spread over the last year
+async fn generate(db: &DatabaseConnection, size: usize) -> Result<()> {
+    let now = Utc::now();
+    let first = last_id::<products::Entity>(db, products::Column::Id).await? + 1;
+    let mut catalog = Vec::with_capacity(size);
+
+    for batch in (0..size).collect::<Vec<_>>().chunks(BATCH_SIZE) {
+        let titles: Vec<String> = batch
+            .iter()
+            .map(|n| format!("Load test product {}", first as usize + n))
+            .collect();
+        let slugs: Vec<String> = titles.iter().map(slugify).collect();
+        products::Entity::insert_many(titles.iter().zip(&slugs).map(|(title, slug)| {
+            products::ActiveModel {
+                title: ActiveValue::set(title.clone()),
+                status: ActiveValue::set(Some(ProductStatus::Publish.to_string())),
+                product_type: ActiveValue::set(Some(TYPE_SIMPLE.to_string())),
+                slug: ActiveValue::set(Some(slug.clone())),
+                author_id: ActiveValue::set(1),
+                published_at: ActiveValue::set(Some(now.into())),
+                ..Default::default()
+            }
+        }))
+        .exec(db)
+        .await?;
+        let ids: Vec<i32> = products::Entity::find()
+            .select_only()
+            .column(products::Column::Id)
+            .filter(products::Column::Slug.is_in(slugs))
+            .order_by_asc(products::Column::Id)
+            .into_tuple()
+            .all(db)
+            .await?;
+
+        let metas = ids.iter().zip(batch).flat_map(|(id, n)| {
+            [
+                ("_sku", format!("LOAD-{}", first as usize + n)),
+                ("_regular_price", (5 + n * 37 % 95).to_string()),
+                ("_manage_stock", true.to_string()),
+                ("_stock", (n * 13 % 50).to_string()),
+                (
+                    "_stock_status",
+                    if n * 13 % 50 > 0 {
+                        "instock"
+                    } else {
+                        "outofstock"
+                    }
+                    .to_string(),
+                ),
+                ("_categories", CATEGORIES[n % CATEGORIES.len()].to_string()),
+            ]
+            .map(|(key, value)| postmetas::ActiveModel {
+                product_id: ActiveValue::set(*id),
+                meta_key: ActiveValue::set(Some(key.to_string())),
+                meta_value: ActiveValue::set(Some(value)),
+                ..Default::default()
+            })
+        });
+        postmetas::Entity::insert_many(metas).exec(db).await?;
+        catalog.extend(ids.into_iter().zip(titles));
+    }
+
+    let first = last_id::<orders::Entity>(db, orders::Column::Id).await? + 1;
+    let statuses = [STATUS_COMPLETED, STATUS_PROCESSING, STATUS_ON_HOLD];
+    for batch in (0..size).collect::<Vec<_>>().chunks(BATCH_SIZE) {
+        let keys: Vec<String> = batch
+            .iter()
+            .map(|n| format!("wc_order_load{}", first as usize + n))
+            .collect();
+        // quantity and price of the items of each order
+        let lines =
+            |n: usize| (0..=n % 3).map(move |line| (1 + (n + line) % 2, 5 + (n + line) * 37 % 95));
+
+        orders::Entity::insert_many(keys.iter().zip(batch).map(|(key, n)| {
+            #[allow(clippy::cast_precision_loss)]
+            let total: f32 = lines(*n)
+                .map(|(quantity, price)| (quantity * price) as f32)
+                .sum();
+            let created_at = now - Duration::hours((n * 7919 % (365 * 24)) as i64);
+            orders::ActiveModel {
+                user_id: ActiveValue::set((n % 4 == 0).then_some(2)),
+                status: ActiveValue::set(statuses[n % statuses.len()].to_string()),
+                total: ActiveValue::set(total),
+                email: ActiveValue::set(Some(format!("customer{n}@example.com"))),
+                order_key: ActiveValue::set(Some(key.clone())),
+                shipping_total: ActiveValue::set(0.0),
+                tax_total: ActiveValue::set(0.0),
+                shipping_tax: ActiveValue::set(0.0),
+                prices_include_tax: ActiveValue::set(false),
+                payment_method: ActiveValue::set(Some("cod".to_string())),
+                payment_method_title: ActiveValue::set(Some("Cash on delivery".to_string())),
+                created_at: ActiveValue::set(created_at.into()),
+                updated_at: ActiveValue::set(created_at.into()),
+                ..Default::default()
+            }
+        }))
+        .exec(db)
+        .await?;
+        let ids: Vec<i32> = orders::Entity::find()
+            .select_only()
+            .column(orders::Column::Id)
+            .filter(orders::Column::OrderKey.is_in(keys))
+            .order_by_asc(orders::Column::Id)
+            .into_tuple()
+            .all(db)
+            .await?;
+
+        let catalog = &catalog;
+        let items = ids.iter().zip(batch).flat_map(|(id, n)| {
+            lines(*n).enumerate().map(move |(line, (quantity, price))| {
+                let (product_id, name) = &catalog[(n + line) * 7 % catalog.len()];
+                #[allow(clippy::cast_precision_loss)]
+                order_items::ActiveModel {
+                    order_id: ActiveValue::set(*id),
+                    product_id: ActiveValue::set(Some(*product_id)),
+                    name: ActiveValue::set(name.clone()),
+                    quantity: ActiveValue::set(quantity as i32),
+                    price: ActiveValue::set(price as f32),
+                    total: ActiveValue::set((quantity * price) as f32),
+                    tax_total: ActiveValue::set(0.0),
+                    ..Default::default()
+                }
+            })
+        });
+        order_items::Entity::insert_many(items).exec(db).await?;
+    }
+
+    tracing::info!(size, "load test products and orders generated");
+    Ok(())
+}
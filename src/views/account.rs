@@ -0,0 +1,154 @@
+use loco_rs::prelude::*;
+
+use crate::{
+    models::{
+        _entities::{addresses, api_keys, order_items, orders, user_identities},
+        addresses::AddressParams,
+        download_permissions::DownloadLink,
+    },
+    views::auth::{CurrentResponse, TwoFactorSetupResponse},
+};
+
+/// Render the account overview with the profile forms and recent orders.
+///
+/// # Errors
+///
+/// When there is an issue with rendering the view.
+pub fn show(
+    v: &impl ViewRenderer,
+    user: &CurrentResponse,
+    pending_email: Option<&str>,
+    orders: &Vec<orders::Model>,
+    errors: &serde_json::Value,
+) -> Result<Response> {
+    format::render().view(
+        v,
+        "account/show.html",
+        data!({
+            "user": user,
+            "pending_email": pending_email,
+            "orders": orders,
+            "errors": errors,
+        }),
+    )
+}
+
+/// Render the billing and shipping address forms.
+///
+/// # Errors
+///
+/// When there is an issue with rendering the view.
+pub fn addresses(
+    v: &impl ViewRenderer,
+    billing: Option<&addresses::Model>,
+    shipping: Option<&addresses::Model>,
+    errors: &serde_json::Value,
+) -> Result<Response> {
+    format::render().view(
+        v,
+        "account/addresses.html",
+        data!({"billing": billing, "shipping": shipping, "errors": errors}),
+    )
+}
+
+/// Render the order history of the user.
+///
+/// # Errors
+///
+/// When there is an issue with rendering the view.
+pub fn orders(v: &impl ViewRenderer, orders: &Vec<orders::Model>) -> Result<Response> {
+    format::render().view(v, "account/orders.html", data!({"orders": orders}))
+}
+
+/// Render a single order of the user.
+///
+/// # Errors
+///
+/// When there is an issue with rendering the view.
+pub fn order(
+    v: &impl ViewRenderer,
+    order: &orders::Model,
+    items: &Vec<order_items::Model>,
+    downloads: &[DownloadLink],
+) -> Result<Response> {
+    let billing: Option<AddressParams> = order.billing();
+    let shipping: Option<AddressParams> = order.shipping();
+
+    format::render().view(
+        v,
+        "account/order.html",
+        data!({
+            "order": order,
+            "items": items,
+            "billing": billing,
+            "shipping": shipping,
+            "taxes": order.tax_lines(),
+            "downloads": downloads,
+        }),
+    )
+}
+
+/// Render the API keys of the user, with the key just created if any.
+///
+/// # Errors
+///
+/// When there is an issue with rendering the view.
+pub fn api_keys(
+    v: &impl ViewRenderer,
+    api_keys: &Vec<api_keys::Model>,
+    new_key: Option<&str>,
+    errors: &serde_json::Value,
+) -> Result<Response> {
+    format::render().view(
+        v,
+        "account/api_keys.html",
+        data!({"api_keys": api_keys, "new_key": new_key, "errors": errors}),
+    )
+}
+
+/// Render the two-factor authentication settings, with the pending secret
+/// until enrolled and the recovery codes just generated if any.
+///
+/// # Errors
+///
+/// When there is an issue with rendering the view.
+pub fn two_factor(
+    v: &impl ViewRenderer,
+    enabled: bool,
+    required: bool,
+    setup: Option<&TwoFactorSetupResponse>,
+    remaining_codes: u64,
+    new_codes: Option<&Vec<String>>,
+    errors: &serde_json::Value,
+) -> Result<Response> {
+    format::render().view(
+        v,
+        "account/two_factor.html",
+        data!({
+            "enabled": enabled,
+            "required": required,
+            "setup": setup,
+            "remaining_codes": remaining_codes,
+            "new_codes": new_codes,
+            "errors": errors,
+        }),
+    )
+}
+
+/// Render the external identities linked to the account.
+///
+/// # Errors
+///
+/// When there is an issue with rendering the view.
+pub fn identities(
+    v: &impl ViewRenderer,
+    identities: &Vec<user_identities::Model>,
+    providers: &[String],
+    errors: &serde_json::Value,
+) -> Result<Response> {
+    format::render().view(
+        v,
+        "account/identities.html",
+        data!({"identities": identities, "providers": providers, "errors": errors}),
+    )
+}
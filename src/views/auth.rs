@@ -6,21 +6,65 @@ use crate::models::_entities::users;
 #[derive(Debug, Deserialize, Serialize)]
 pub struct LoginResponse {
     pub token: String,
+    pub refresh_token: String,
     pub pid: String,
     pub name: String,
     pub is_verified: bool,
+    /// recovery codes generated when this login completed the two-factor
+    /// enrollment, they are not shown again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recovery_codes: Option<Vec<String>>,
 }
 
 impl LoginResponse {
     #[must_use]
-    pub fn new(user: &users::Model, token: &String) -> Self {
+    pub fn new(user: &users::Model, token: &String, refresh_token: &str) -> Self {
         Self {
             token: token.to_string(),
+            refresh_token: refresh_token.to_string(),
             pid: user.pid.to_string(),
             name: user.name.clone(),
             is_verified: user.email_verified_at.is_some(),
+            recovery_codes: None,
         }
     }
+
+    #[must_use]
+    pub fn with_recovery_codes(mut self, recovery_codes: Option<Vec<String>>) -> Self {
+        self.recovery_codes = recovery_codes;
+        self
+    }
+}
+
+/// Answer to a login which needs a second step. `two_factor` is `verify` when
+/// the user has to give a code, `setup` when they have to enroll first.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TwoFactorChallengeResponse {
+    pub two_factor: String,
+    pub challenge_token: String,
+}
+
+impl TwoFactorChallengeResponse {
+    #[must_use]
+    pub fn new(user: &users::Model, challenge_token: &str) -> Self {
+        Self {
+            two_factor: if user.totp_enabled_at.is_some() {
+                "verify".to_string()
+            } else {
+                "setup".to_string()
+            },
+            challenge_token: challenge_token.to_string(),
+        }
+    }
+}
+
+/// Secret to add to an authenticator app, as text, URI and QR code
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TwoFactorSetupResponse {
+    pub secret: String,
+    pub provisioning_uri: String,
+    /// base64 encoded PNG
+    pub qr_code: String,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -45,6 +89,32 @@ pub fn register_view(v: &impl ViewRenderer) -> Result<Response> {
     format::render().view(v, "auth/register.html", data!({}))
 }
 
-pub fn login_view(v: &impl ViewRenderer, data: &serde_json::Value) -> Result<Response> {
-    format::render().view(v, "auth/login.html", data!({ "errors": data }))
+pub fn login_view(
+    v: &impl ViewRenderer,
+    data: &serde_json::Value,
+    providers: &[String],
+) -> Result<Response> {
+    format::render().view(
+        v,
+        "auth/login.html",
+        data!({ "errors": data, "providers": providers }),
+    )
+}
+
+/// Render the second login step. `setup` holds the pending secret as text,
+/// URI and QR code when the user has to enroll first.
+///
+/// # Errors
+///
+/// When there is an issue with rendering the view.
+pub fn two_factor_view(
+    v: &impl ViewRenderer,
+    setup: Option<&TwoFactorSetupResponse>,
+    errors: &serde_json::Value,
+) -> Result<Response> {
+    format::render().view(
+        v,
+        "auth/two_factor.html",
+        data!({ "setup": setup, "errors": errors }),
+    )
 }
@@ -1,4 +1,4 @@
-use crate::controllers::cart::PartialCartProduct;
+use crate::controllers::cart::{CartTotals, PartialCartProduct, ShippingChoice};
 use loco_rs::prelude::*;
 
 /// Render a cart view.
@@ -6,6 +6,15 @@ use loco_rs::prelude::*;
 /// # Errors
 ///
 /// When there is an issue with rendering the view.
-pub fn show(v: &impl ViewRenderer, items: &Vec<PartialCartProduct>) -> Result<Response> {
-    format::render().view(v, "cart/show.html", data!({"items": items}))
+pub fn show(
+    v: &impl ViewRenderer,
+    items: &Vec<PartialCartProduct>,
+    totals: &CartTotals,
+    shipping: Option<&ShippingChoice>,
+) -> Result<Response> {
+    format::render().view(
+        v,
+        "cart/show.html",
+        data!({"items": items, "totals": totals, "shipping": shipping}),
+    )
 }
@@ -0,0 +1,78 @@
+use loco_rs::prelude::*;
+
+use crate::{
+    common::{
+        countries::COUNTRIES,
+        payments::{PaymentMethod, METHODS},
+    },
+    controllers::{
+        cart::{CartTotals, PartialCartProduct},
+        checkout::CheckoutForm,
+    },
+    models::{
+        _entities::{order_items, orders},
+        addresses::AddressParams,
+        download_permissions::DownloadLink,
+    },
+};
+
+/// Render the checkout form with the cart summary.
+///
+/// # Errors
+///
+/// When there is an issue with rendering the view.
+pub fn show(
+    v: &impl ViewRenderer,
+    items: &Vec<PartialCartProduct>,
+    totals: &CartTotals,
+    form: &CheckoutForm,
+    payment_methods: &[PaymentMethod],
+    terms_url: Option<&str>,
+    errors: &serde_json::Value,
+) -> Result<Response> {
+    format::render().view(
+        v,
+        "checkout/show.html",
+        data!({
+            "items": items,
+            "totals": totals,
+            "form": form,
+            "countries": COUNTRIES,
+            "payment_methods": payment_methods,
+            "terms_url": terms_url,
+            "errors": errors,
+        }),
+    )
+}
+
+/// Render the confirmation of a placed order.
+///
+/// # Errors
+///
+/// When there is an issue with rendering the view.
+pub fn received(
+    v: &impl ViewRenderer,
+    order: &orders::Model,
+    items: &Vec<order_items::Model>,
+    downloads: &[DownloadLink],
+) -> Result<Response> {
+    let billing: Option<AddressParams> = order.billing();
+    let shipping: Option<AddressParams> = order.shipping();
+    let payment_method = METHODS
+        .iter()
+        .find(|method| order.payment_method.as_deref() == Some(method.id));
+
+    format::render().view(
+        v,
+        "checkout/received.html",
+        data!({
+            "order": order,
+            "items": items,
+            "billing": billing,
+            "shipping": shipping,
+            "payment_method": payment_method,
+            "taxes": order.tax_lines(),
+            "downloads": downloads,
+        }),
+    )
+}
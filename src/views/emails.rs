@@ -0,0 +1,12 @@
+use loco_rs::prelude::*;
+
+use crate::models::_entities::failed_emails;
+
+/// Render the emails which could not be delivered.
+///
+/// # Errors
+///
+/// When there is an issue with rendering the view.
+pub fn failed(v: &impl ViewRenderer, emails: &[failed_emails::Model]) -> Result<Response> {
+    format::render().view(v, "emails/failed.html", data!({"emails": emails}))
+}
@@ -0,0 +1,20 @@
+use loco_rs::prelude::*;
+
+use crate::models::media;
+
+/// Render the media library with the upload form.
+///
+/// # Errors
+///
+/// When there is an issue with rendering the view.
+pub fn library(
+    v: &impl ViewRenderer,
+    media: &[media::Model],
+    errors: &serde_json::Value,
+) -> Result<Response> {
+    format::render().view(
+        v,
+        "media/library.html",
+        data!({"media": media, "errors": errors}),
+    )
+}
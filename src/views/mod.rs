@@ -1,4 +1,12 @@
+pub mod account;
 pub mod auth;
 
 pub mod cart;
+pub mod checkout;
+pub mod emails;
+pub mod media;
+pub mod orders;
 pub mod products;
+pub mod reviews;
+pub mod shipping;
+pub mod taxes;
@@ -0,0 +1,20 @@
+use loco_rs::prelude::*;
+
+use crate::models::_entities::orders;
+
+/// Render the orders of the store, latest first.
+///
+/// # Errors
+///
+/// When there is an issue with rendering the view.
+pub fn list(
+    v: &impl ViewRenderer,
+    orders: &[orders::Model],
+    statuses: &[&str],
+) -> Result<Response> {
+    format::render().view(
+        v,
+        "orders/list.html",
+        data!({"orders": orders, "statuses": statuses}),
+    )
+}
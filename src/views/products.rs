@@ -1,6 +1,16 @@
 use loco_rs::prelude::*;
 
-use crate::{controllers::products::ProductView, models::_entities::products};
+use crate::{
+    controllers::products::ProductView,
+    models::{
+        _entities::{bundle_items, product_downloads, products, users},
+        media,
+        product_revisions::{self, Change},
+        products::{LowStockProduct, ProductStatus},
+        reviews::{self, Rating},
+        stock_movements,
+    },
+};
 
 /// Render a list view of products.
 ///
@@ -11,13 +21,94 @@ pub fn list(v: &impl ViewRenderer, items: &Vec<products::Model>) -> Result<Respo
     format::render().view(v, "products/list.html", data!({"items": items}))
 }
 
-/// Render a single products view.
+/// Render the products of a status for the store staff, every status but the
+/// trash when there is none.
 ///
 /// # Errors
 ///
 /// When there is an issue with rendering the view.
-pub fn show(v: &impl ViewRenderer, item: &products::Model, errors: &serde_json::Value) -> Result<Response> {
-    format::render().view(v, "products/show.html", data!({"item": item, "errors": errors}))
+pub fn admin_list(
+    v: &impl ViewRenderer,
+    items: &[products::Model],
+    status: Option<ProductStatus>,
+    statuses: &[ProductStatus],
+) -> Result<Response> {
+    format::render().view(
+        v,
+        "products/admin.html",
+        data!({"items": items, "status": status, "statuses": statuses}),
+    )
+}
+
+/// Render a single products view, with its images and approved reviews.
+/// Grouped products and bundles list their products, external ones link to
+/// where they are sold.
+///
+/// # Errors
+///
+/// When there is an issue with rendering the view.
+#[allow(clippy::too_many_arguments)]
+pub fn show(
+    v: &impl ViewRenderer,
+    item: &products::Model,
+    details: &ProductView,
+    children: &[ProductView],
+    bundle: &[(bundle_items::Model, ProductView)],
+    featured_image: Option<&media::Model>,
+    gallery: &[media::Model],
+    reviews: &[reviews::Model],
+    subscribed: bool,
+    errors: &serde_json::Value,
+) -> Result<Response> {
+    format::render().view(
+        v,
+        "products/show.html",
+        data!({
+            "item": item,
+            "details": details,
+            "children": children,
+            "bundle": bundle_view(bundle),
+            "featured_image": featured_image,
+            "gallery": gallery,
+            "rating": Rating::of(reviews),
+            "reviews": reviews,
+            "subscribed": subscribed,
+            "errors": errors,
+        }),
+    )
+}
+
+/// Render the products low in stock, with the store threshold for the
+/// products without one.
+///
+/// # Errors
+///
+/// When there is an issue with rendering the view.
+pub fn low_stock(
+    v: &impl ViewRenderer,
+    items: &[LowStockProduct],
+    default_amount: f32,
+) -> Result<Response> {
+    format::render().view(
+        v,
+        "products/low_stock.html",
+        data!({"items": items, "default_amount": default_amount}),
+    )
+}
+
+/// The products of a bundle, each along with how many of it the bundle has
+fn bundle_view<T: serde::Serialize>(bundle: &[(bundle_items::Model, T)]) -> Vec<serde_json::Value> {
+    bundle
+        .iter()
+        .map(|(item, product)| {
+            data!({
+                "id": item.id,
+                "quantity": item.quantity,
+                "optional": item.optional,
+                "product": product,
+            })
+        })
+        .collect()
 }
 
 /// Render a products create form.
@@ -25,15 +116,149 @@ pub fn show(v: &impl ViewRenderer, item: &products::Model, errors: &serde_json::
 /// # Errors
 ///
 /// When there is an issue with rendering the view.
-pub fn create(v: &impl ViewRenderer) -> Result<Response> {
-    format::render().view(v, "products/create.html", data!({}))
+pub fn create(
+    v: &impl ViewRenderer,
+    statuses: &[ProductStatus],
+    types: &[&str],
+    errors: &serde_json::Value,
+) -> Result<Response> {
+    format::render().view(
+        v,
+        "products/create.html",
+        data!({"statuses": statuses, "types": types, "errors": errors}),
+    )
+}
+
+/// Render a products edit form, with the files of downloadable products, the
+/// products of a grouped product or a bundle and the media library to pick
+/// the images from.
+///
+/// # Errors
+///
+/// When there is an issue with rendering the view.
+#[allow(clippy::too_many_arguments)]
+pub fn edit(
+    v: &impl ViewRenderer,
+    item: &ProductView,
+    downloads: &[product_downloads::Model],
+    library: &[media::Model],
+    children: &[products::Model],
+    bundle: &[(bundle_items::Model, products::Model)],
+    statuses: &[ProductStatus],
+    types: &[&str],
+    errors: &serde_json::Value,
+) -> Result<Response> {
+    format::render().view(
+        v,
+        "products/edit.html",
+        data!({
+            "item": item,
+            "downloads": downloads,
+            "library": library,
+            "children": children,
+            "bundle": bundle_view(bundle),
+            "statuses": statuses,
+            "types": types,
+            "errors": errors,
+        }),
+    )
+}
+
+/// Render the revisions of a product with their author.
+///
+/// # Errors
+///
+/// When there is an issue with rendering the view.
+pub fn revisions(
+    v: &impl ViewRenderer,
+    item: &products::Model,
+    revisions: &[(product_revisions::Model, Option<users::Model>)],
+) -> Result<Response> {
+    let revisions: Vec<_> = revisions
+        .iter()
+        .map(|(revision, user)| {
+            data!({
+                "id": revision.id,
+                "title": revision.title,
+                "status": revision.status,
+                "created_at": revision.created_at,
+                "author": user.as_ref().map(|user| &user.name),
+            })
+        })
+        .collect();
+    format::render().view(
+        v,
+        "products/revisions.html",
+        data!({"item": item, "revisions": revisions}),
+    )
+}
+
+/// Render the stock movements of a product with who made them, warning when
+/// the stock of the product is not the one of its movements.
+///
+/// # Errors
+///
+/// When there is an issue with rendering the view.
+pub fn stock_history(
+    v: &impl ViewRenderer,
+    item: &products::Model,
+    movements: &[(stock_movements::Model, Option<users::Model>)],
+    stock: Option<f32>,
+    balance: Option<f32>,
+) -> Result<Response> {
+    let movements: Vec<_> = movements
+        .iter()
+        .map(|(movement, user)| {
+            data!({
+                "created_at": movement.created_at,
+                "quantity": movement.quantity,
+                "stock": movement.stock,
+                "reason": movement.reason,
+                "order_id": movement.order_id,
+                "note": movement.note,
+                "author": user.as_ref().map(|user| &user.name),
+            })
+        })
+        .collect();
+    let mismatch = match (stock, balance) {
+        (Some(stock), Some(balance)) => (stock - balance).abs() >= f32::EPSILON,
+        _ => false,
+    };
+    format::render().view(
+        v,
+        "products/stock.html",
+        data!({
+            "item": item,
+            "movements": movements,
+            "stock": stock,
+            "balance": balance,
+            "mismatch": mismatch,
+        }),
+    )
 }
 
-/// Render a products edit form.
+/// Render the changes of a revision from the compared one, no changes being
+/// shown for the first revision.
 ///
 /// # Errors
 ///
 /// When there is an issue with rendering the view.
-pub fn edit(v: &impl ViewRenderer, item: &ProductView) -> Result<Response> {
-    format::render().view(v, "products/edit.html", data!({"item": item}))
+pub fn revision(
+    v: &impl ViewRenderer,
+    item: &products::Model,
+    revision: &product_revisions::Model,
+    compared: Option<&product_revisions::Model>,
+    changes: Option<&[Change]>,
+) -> Result<Response> {
+    format::render().view(
+        v,
+        "products/revision.html",
+        data!({
+            "item": item,
+            "revision": revision,
+            "compared": compared,
+            "changes": changes,
+            "metas": revision.metas(),
+        }),
+    )
 }
@@ -0,0 +1,76 @@
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{
+    _entities::products,
+    reviews::{self, Rating},
+};
+
+/// A review as shown to anyone, without the email of its author
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReviewResponse {
+    pub id: i32,
+    pub product_id: i32,
+    pub author: String,
+    pub rating: i32,
+    pub content: String,
+    pub verified: bool,
+    pub status: String,
+    pub created_at: DateTimeWithTimeZone,
+}
+
+impl ReviewResponse {
+    #[must_use]
+    pub fn new(review: &reviews::Model) -> Self {
+        Self {
+            id: review.id,
+            product_id: review.product_id,
+            author: review.author.clone(),
+            rating: review.rating,
+            content: review.content.clone(),
+            verified: review.verified,
+            status: review.status.clone(),
+            created_at: review.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReviewsResponse {
+    pub average_rating: f32,
+    pub review_count: usize,
+    pub reviews: Vec<ReviewResponse>,
+}
+
+impl ReviewsResponse {
+    #[must_use]
+    pub fn new(rating: &Rating, reviews: &[reviews::Model]) -> Self {
+        Self {
+            average_rating: rating.average,
+            review_count: rating.count,
+            reviews: reviews.iter().map(ReviewResponse::new).collect(),
+        }
+    }
+}
+
+/// Render the moderation queue of the reviews in a status.
+///
+/// # Errors
+///
+/// When there is an issue with rendering the view.
+pub fn queue(
+    v: &impl ViewRenderer,
+    reviews: &[(reviews::Model, Option<products::Model>)],
+    status: &str,
+    statuses: &[&str],
+) -> Result<Response> {
+    let reviews: Vec<_> = reviews
+        .iter()
+        .map(|(review, product)| data!({"review": review, "product": product}))
+        .collect();
+    format::render().view(
+        v,
+        "reviews/queue.html",
+        data!({"reviews": reviews, "status": status, "statuses": statuses}),
+    )
+}
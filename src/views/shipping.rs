@@ -0,0 +1,38 @@
+use loco_rs::prelude::*;
+
+use crate::models::_entities::{shipping_methods, shipping_zone_locations, shipping_zones};
+
+/// Render the shipping zones along with their locations and methods.
+///
+/// # Errors
+///
+/// When there is an issue with rendering the view.
+pub fn index(
+    v: &impl ViewRenderer,
+    zones: &[(
+        shipping_zones::Model,
+        Vec<shipping_zone_locations::Model>,
+        Vec<shipping_methods::Model>,
+    )],
+    errors: &serde_json::Value,
+) -> Result<Response> {
+    let zones = zones
+        .iter()
+        .map(|(zone, locations, methods)| {
+            data!({
+                "zone": zone,
+                "locations": locations,
+                "methods": methods
+                    .iter()
+                    .map(|method| data!({ "method": method, "rates": method.weight_rates() }))
+                    .collect::<Vec<_>>(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    format::render().view(
+        v,
+        "shipping/index.html",
+        data!({"zones": zones, "errors": errors}),
+    )
+}
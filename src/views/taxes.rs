@@ -0,0 +1,33 @@
+use loco_rs::prelude::*;
+
+use crate::models::{_entities::tax_rates, tax_rates::CLASSES};
+
+/// Render the tax rates grouped by class.
+///
+/// # Errors
+///
+/// When there is an issue with rendering the view.
+pub fn index(
+    v: &impl ViewRenderer,
+    rates: &[tax_rates::Model],
+    errors: &serde_json::Value,
+) -> Result<Response> {
+    let classes = CLASSES
+        .iter()
+        .map(|tax_class| {
+            data!({
+                "name": tax_class,
+                "rates": rates
+                    .iter()
+                    .filter(|rate| rate.tax_class == *tax_class)
+                    .collect::<Vec<_>>(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    format::render().view(
+        v,
+        "taxes/index.html",
+        data!({"classes": classes, "errors": errors}),
+    )
+}
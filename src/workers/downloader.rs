@@ -1,13 +1,17 @@
 use loco_rs::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use crate::models::{download_permissions, orders};
+
+/// Gives the customer of a paid order access to the files of its
+/// downloadable products
 pub struct DownloadWorker {
     pub ctx: AppContext,
 }
 
 #[derive(Deserialize, Debug, Serialize)]
 pub struct DownloadWorkerArgs {
-    pub user_guid: String,
+    pub order_id: i32,
 }
 
 #[async_trait]
@@ -15,9 +19,25 @@ impl BackgroundWorker<DownloadWorkerArgs> for DownloadWorker {
     fn build(ctx: &AppContext) -> Self {
         Self { ctx: ctx.clone() }
     }
-    async fn perform(&self, _args: DownloadWorkerArgs) -> Result<()> {
-        // TODO: Some actual work goes here...
+    async fn perform(&self, args: DownloadWorkerArgs) -> Result<()> {
+        let Some(order) = orders::Entity::find_by_id(args.order_id)
+            .one(&self.ctx.db)
+            .await?
+        else {
+            tracing::warn!(order_id = args.order_id, "order of the downloads not found");
+            return Ok(());
+        };
+        // the status may have changed since the job got queued
+        if !order.is_paid() {
+            return Ok(());
+        }
 
+        let granted = download_permissions::Model::grant_for_order(&self.ctx.db, &order).await?;
+        tracing::info!(
+            order_id = order.id,
+            granted = granted.len(),
+            "downloads granted"
+        );
         Ok(())
     }
 }
@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use loco_rs::{mailer::Email, prelude::*};
+
+use crate::{
+    common::settings::Settings,
+    mailers::delivery::{self, DEFAULT_BACKOFF_MS, DEFAULT_MAX_ATTEMPTS},
+    models::failed_emails,
+};
+
+/// Sends the emails of the mailers, retrying with a growing wait and keeping
+/// the ones which still fail in the failed emails
+pub struct MailDeliveryWorker {
+    pub ctx: AppContext,
+}
+
+#[async_trait]
+impl BackgroundWorker<Email> for MailDeliveryWorker {
+    fn queue() -> Option<String> {
+        Some("mailer".to_string())
+    }
+
+    fn build(ctx: &AppContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    async fn perform(&self, email: Email) -> Result<()> {
+        let settings = Settings::from_context(&self.ctx)?.mail_delivery;
+        let max_attempts = settings.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS).max(1);
+        let mut backoff = Duration::from_millis(settings.backoff_ms.unwrap_or(DEFAULT_BACKOFF_MS));
+
+        let mut attempt = 1;
+        loop {
+            let err = match delivery::send(&self.ctx, &settings, &email).await {
+                Ok(()) => return Ok(()),
+                Err(err) => err,
+            };
+            if attempt >= max_attempts {
+                tracing::error!(
+                    err = err.to_string(),
+                    to = email.to,
+                    attempt,
+                    "could not deliver email"
+                );
+                failed_emails::Model::record(
+                    &self.ctx.db,
+                    &email,
+                    &err.to_string(),
+                    i32::try_from(attempt).unwrap_or(i32::MAX),
+                )
+                .await?;
+                return Ok(());
+            }
+            tracing::warn!(
+                err = err.to_string(),
+                to = email.to,
+                attempt,
+                "could not deliver email, retrying"
+            );
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+            attempt += 1;
+        }
+    }
+}
@@ -1 +1,6 @@
 pub mod downloader;
+pub mod mail_delivery;
+pub mod order_emails;
+pub mod scheduled_products;
+pub mod stock_webhooks;
+pub mod thumbnails;
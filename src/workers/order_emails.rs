@@ -0,0 +1,117 @@
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    mailers::order::{OrderEmail, OrderMailer},
+    models::{_entities::products, orders, stock_subscriptions},
+};
+
+/// Sends the order emails out of the requests, loading what they show
+pub struct OrderEmailWorker {
+    pub ctx: AppContext,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OrderEmailWorkerArgs {
+    Order {
+        order_id: i32,
+        email: OrderEmail,
+    },
+    /// a product stock went down to `stock`
+    LowStock {
+        product_id: i32,
+        stock: f32,
+    },
+    /// a product stock ran out
+    NoStock {
+        product_id: i32,
+    },
+    /// a product is back in stock, for the customers who asked
+    BackInStock {
+        product_id: i32,
+    },
+}
+
+impl OrderEmailWorker {
+    /// Queues the emails of a newly placed order, to the customer and the
+    /// store staff
+    ///
+    /// # Errors
+    ///
+    /// When the emails cannot be queued
+    pub async fn order_placed(ctx: &AppContext, order: &orders::Model) -> Result<()> {
+        let emails = OrderEmail::for_status(&order.status)
+            .into_iter()
+            .chain([OrderEmail::NewOrder]);
+        for email in emails {
+            Self::perform_later(
+                ctx,
+                OrderEmailWorkerArgs::Order {
+                    order_id: order.id,
+                    email,
+                },
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// The product of a stock email, none when it got deleted since
+    async fn product(&self, product_id: i32) -> Result<Option<products::Model>> {
+        let product = products::Entity::find_by_id(product_id)
+            .one(&self.ctx.db)
+            .await?;
+        if product.is_none() {
+            tracing::warn!(product_id, "product of the email not found");
+        }
+        Ok(product)
+    }
+}
+
+#[async_trait]
+impl BackgroundWorker<OrderEmailWorkerArgs> for OrderEmailWorker {
+    fn build(ctx: &AppContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+    async fn perform(&self, args: OrderEmailWorkerArgs) -> Result<()> {
+        match args {
+            OrderEmailWorkerArgs::Order { order_id, email } => {
+                let Some(order) = orders::Entity::find_by_id(order_id)
+                    .one(&self.ctx.db)
+                    .await?
+                else {
+                    tracing::warn!(order_id, "order of the email not found");
+                    return Ok(());
+                };
+                OrderMailer::send(&self.ctx, &order, &email).await
+            }
+            OrderEmailWorkerArgs::LowStock { product_id, stock } => {
+                let Some(product) = self.product(product_id).await? else {
+                    return Ok(());
+                };
+                OrderMailer::low_stock(&self.ctx, &product, stock).await
+            }
+            OrderEmailWorkerArgs::NoStock { product_id } => {
+                let Some(product) = self.product(product_id).await? else {
+                    return Ok(());
+                };
+                OrderMailer::no_stock(&self.ctx, &product).await
+            }
+            OrderEmailWorkerArgs::BackInStock { product_id } => {
+                let Some(product) = self.product(product_id).await? else {
+                    return Ok(());
+                };
+                // each customer is told once, their subscription ending with
+                // the email
+                let subscriptions =
+                    stock_subscriptions::Model::find_by_product(&self.ctx.db, product_id).await?;
+                for subscription in subscriptions {
+                    OrderMailer::back_in_stock(&self.ctx, &product, &subscription.email).await?;
+                    subscription.delete(&self.ctx.db).await?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
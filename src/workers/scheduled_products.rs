@@ -0,0 +1,27 @@
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::models::products;
+
+/// Publishes the scheduled products whose date came. The scheduler runs it
+/// every minute through the `publish_scheduled` task.
+pub struct ScheduledProductsWorker {
+    pub ctx: AppContext,
+}
+
+#[derive(Deserialize, Debug, Default, Serialize)]
+pub struct ScheduledProductsWorkerArgs {}
+
+#[async_trait]
+impl BackgroundWorker<ScheduledProductsWorkerArgs> for ScheduledProductsWorker {
+    fn build(ctx: &AppContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+    async fn perform(&self, _args: ScheduledProductsWorkerArgs) -> Result<()> {
+        let published = products::Model::publish_scheduled(&self.ctx.db).await?;
+        for product in &published {
+            tracing::info!(product_id = product.id, "scheduled product published");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::{settings::Settings, stock_alerts::StockAlert},
+    models::_entities::products,
+};
+
+/// Posts the stock alerts to the webhook of the `inventory` settings
+pub struct StockWebhookWorker {
+    pub ctx: AppContext,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+pub struct StockWebhookWorkerArgs {
+    pub product_id: i32,
+    pub alert: StockAlert,
+}
+
+/// What the webhook receives, the event name along with the fields of the
+/// alert
+#[derive(Debug, Serialize)]
+pub struct StockWebhookPayload<'a> {
+    pub product_id: i32,
+    pub title: &'a str,
+    pub slug: Option<&'a str>,
+    #[serde(flatten)]
+    pub alert: &'a StockAlert,
+}
+
+#[async_trait]
+impl BackgroundWorker<StockWebhookWorkerArgs> for StockWebhookWorker {
+    fn build(ctx: &AppContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+    async fn perform(&self, args: StockWebhookWorkerArgs) -> Result<()> {
+        let settings = Settings::from_context(&self.ctx)?;
+        let Some(url) = settings.inventory.webhook_url else {
+            return Ok(());
+        };
+        let Some(product) = products::Entity::find_by_id(args.product_id)
+            .one(&self.ctx.db)
+            .await?
+        else {
+            tracing::warn!(
+                product_id = args.product_id,
+                "product of the webhook not found"
+            );
+            return Ok(());
+        };
+
+        let payload = StockWebhookPayload {
+            product_id: product.id,
+            title: &product.title,
+            slug: product.slug.as_deref(),
+            alert: &args.alert,
+        };
+        reqwest::Client::new()
+            .post(&url)
+            .json(&payload)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| Error::string(&format!("could not post the stock alert: {e}")))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::models::media;
+
+/// Makes the thumbnails of an uploaded image, which is too slow for the
+/// upload request
+pub struct ThumbnailWorker {
+    pub ctx: AppContext,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+pub struct ThumbnailWorkerArgs {
+    pub media_id: i32,
+}
+
+#[async_trait]
+impl BackgroundWorker<ThumbnailWorkerArgs> for ThumbnailWorker {
+    fn build(ctx: &AppContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+    async fn perform(&self, args: ThumbnailWorkerArgs) -> Result<()> {
+        let Some(media) = media::Entity::find_by_id(args.media_id)
+            .one(&self.ctx.db)
+            .await?
+        else {
+            tracing::warn!(
+                media_id = args.media_id,
+                "image of the thumbnails not found"
+            );
+            return Ok(());
+        };
+
+        let media = media
+            .generate_thumbnails(&self.ctx.db, &self.ctx.storage)
+            .await?;
+        tracing::info!(
+            media_id = media.id,
+            thumbnails = media.thumbnails().len(),
+            "thumbnails generated"
+        );
+        Ok(())
+    }
+}
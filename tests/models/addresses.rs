@@ -0,0 +1,62 @@
+use commust::{
+    app::App,
+    models::addresses::{self, AddressParams},
+};
+use loco_rs::testing;
+use serial_test::serial;
+
+fn params(city: &str) -> AddressParams {
+    AddressParams {
+        first_name: "user1".to_string(),
+        last_name: "example".to_string(),
+        address_1: "1 main street".to_string(),
+        city: city.to_string(),
+        postcode: "10001".to_string(),
+        country: "us".to_string(),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn can_save_one_address_per_kind() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+
+    addresses::Model::save_for_user(db, 1, addresses::BILLING, &params("New York"))
+        .await
+        .unwrap();
+    let billing = addresses::Model::save_for_user(db, 1, addresses::BILLING, &params("Boston"))
+        .await
+        .unwrap();
+    addresses::Model::save_for_user(db, 1, addresses::SHIPPING, &params("Chicago"))
+        .await
+        .unwrap();
+
+    assert_eq!(billing.city, "Boston");
+    assert_eq!(billing.country, "US");
+    assert_eq!(
+        addresses::Model::find_by_user(db, 1).await.unwrap().len(),
+        2
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn can_validate_address() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+
+    assert!(
+        addresses::Model::save_for_user(db, 1, "office", &params("New York"))
+            .await
+            .is_err()
+    );
+    assert!(
+        addresses::Model::save_for_user(db, 1, addresses::BILLING, &params(""))
+            .await
+            .is_err()
+    );
+}
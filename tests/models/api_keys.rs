@@ -0,0 +1,45 @@
+use commust::{
+    app::App,
+    models::{api_keys, users},
+};
+use loco_rs::testing;
+use serial_test::serial;
+
+#[tokio::test]
+#[serial]
+async fn can_generate_and_find_api_key() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+
+    let (api_key, key) = api_keys::Model::generate(db, 1, "deploy", api_keys::SCOPE_READ)
+        .await
+        .unwrap();
+    assert!(key.starts_with(&api_key.key_prefix));
+    assert_ne!(api_key.key_hash, key);
+    assert_eq!(api_key.key_hash, api_keys::hash_key(&key));
+    assert!(api_key.last_used_at.is_none());
+    assert!(api_key.can_read());
+    assert!(!api_key.can_write());
+
+    let found = api_keys::Model::find_active_by_key(db, &key).await.unwrap();
+    assert!(found.last_used_at.is_some());
+    assert_eq!(users::Model::find_by_api_key(db, &key).await.unwrap().id, 1);
+    assert!(users::Model::find_by_api_key(db, &api_key.key_hash)
+        .await
+        .is_err());
+
+    assert!(api_keys::Model::revoke(db, 2, api_key.id).await.is_err());
+    api_keys::Model::revoke(db, 1, api_key.id).await.unwrap();
+    assert!(users::Model::find_by_api_key(db, &key).await.is_err());
+}
+
+#[tokio::test]
+#[serial]
+async fn cannot_generate_api_key_with_unknown_scope() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+
+    let res = api_keys::Model::generate(&boot.app_context.db, 1, "deploy", "admin").await;
+    assert!(res.is_err());
+}
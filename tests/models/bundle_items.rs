@@ -0,0 +1,57 @@
+use commust::{app::App, models::bundle_items};
+use loco_rs::{model::ModelError, testing};
+use serial_test::serial;
+
+#[tokio::test]
+#[serial]
+async fn test_bundles_are_made_of_simple_products_and_variations() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+
+    // the hoodie stands for the bundle, the t-shirt is variable
+    for (product_id, quantity) in [(2, 1), (7, 0), (1, 1), (99, 1)] {
+        let created = bundle_items::Model::create(db, 1, product_id, quantity, false).await;
+        assert!(
+            matches!(created, Err(ModelError::ModelValidation { .. })),
+            "{product_id} × {quantity}"
+        );
+    }
+
+    let hoodie = bundle_items::Model::create(db, 7, 1, 2, false)
+        .await
+        .unwrap();
+    let variation = bundle_items::Model::create(db, 7, 3, 1, true)
+        .await
+        .unwrap();
+    let cookbook = bundle_items::Model::create(db, 7, 6, 1, true)
+        .await
+        .unwrap();
+
+    let ids =
+        |items: Vec<bundle_items::Model>| items.iter().map(|item| item.id).collect::<Vec<_>>();
+    let included = bundle_items::Model::included(db, 7, Some(&[]))
+        .await
+        .unwrap();
+    assert_eq!(ids(included), vec![hoodie.id]);
+    let included = bundle_items::Model::included(db, 7, Some(&[cookbook.id]))
+        .await
+        .unwrap();
+    assert_eq!(ids(included), vec![hoodie.id, cookbook.id]);
+    let included = bundle_items::Model::included(db, 7, None).await.unwrap();
+    assert_eq!(ids(included), vec![hoodie.id, variation.id, cookbook.id]);
+
+    bundle_items::Model::remove(db, 7, variation.id)
+        .await
+        .unwrap();
+    assert!(matches!(
+        bundle_items::Model::remove(db, 1, hoodie.id).await,
+        Err(ModelError::EntityNotFound)
+    ));
+    let bundle = bundle_items::Model::find_by_bundle(db, 7).await.unwrap();
+    let titles: Vec<&str> = bundle
+        .iter()
+        .map(|(_, product)| product.title.as_str())
+        .collect();
+    assert_eq!(titles, vec!["Rust hoodie", "Rust cookbook"]);
+}
@@ -0,0 +1,140 @@
+use commust::{
+    app::App,
+    models::{
+        _entities::{postmetas, products},
+        download_permissions, product_downloads,
+    },
+};
+use loco_rs::{model::ModelError, testing};
+use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection};
+use serial_test::serial;
+
+use crate::requests::prepare_data::place_order;
+
+const SECRET: &str = "download-secret";
+
+async fn downloadable_product(db: &DatabaseConnection, limit: &str) -> products::Model {
+    let product = products::ActiveModel {
+        title: ActiveValue::set("Loco ebook".to_string()),
+        author_id: ActiveValue::set(1),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    for (key, value) in [("_downloadable", "yes"), ("_download_limit", limit)] {
+        postmetas::ActiveModel {
+            product_id: ActiveValue::set(product.id),
+            meta_key: ActiveValue::set(Some(key.to_string())),
+            meta_value: ActiveValue::set(Some(value.to_string())),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap();
+    }
+    product
+}
+
+#[tokio::test]
+#[serial]
+async fn can_grant_and_count_downloads() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+
+    let product = downloadable_product(db, "1").await;
+    let file = product_downloads::Model::create(db, product.id, "Chapter one", "ebook/one.pdf")
+        .await
+        .unwrap();
+    let order = place_order(db, &product).await;
+
+    let granted = download_permissions::Model::grant_for_order(db, &order)
+        .await
+        .unwrap();
+    assert_eq!(granted.len(), 1);
+    assert_eq!(granted[0].download_id, file.id);
+    assert_eq!(granted[0].downloads_remaining, Some(1));
+    // granting again does not give the file twice
+    let granted_again = download_permissions::Model::grant_for_order(db, &order)
+        .await
+        .unwrap();
+    assert!(granted_again.is_empty());
+
+    let permission = &granted[0];
+    let expires = chrono::Utc::now().timestamp() + 60;
+    let signed = permission.signed_url(SECRET, expires);
+    let signature = signed.rsplit("signature=").next().unwrap();
+
+    let tampered = download_permissions::Model::download(
+        db,
+        SECRET,
+        permission.id,
+        &permission.download_key,
+        expires + 3600,
+        signature,
+    )
+    .await;
+    assert!(matches!(
+        tampered,
+        Err(ModelError::ModelValidation { errors }) if errors.code == "link"
+    ));
+
+    let (counted, download) = download_permissions::Model::download(
+        db,
+        SECRET,
+        permission.id,
+        &permission.download_key,
+        expires,
+        signature,
+    )
+    .await
+    .unwrap();
+    assert_eq!(download.id, file.id);
+    assert_eq!(counted.download_count, 1);
+    assert_eq!(counted.downloads_remaining, Some(0));
+
+    let over_limit = download_permissions::Model::download(
+        db,
+        SECRET,
+        permission.id,
+        &permission.download_key,
+        expires,
+        signature,
+    )
+    .await;
+    assert!(matches!(
+        over_limit,
+        Err(ModelError::ModelValidation { errors }) if errors.code == "limit"
+    ));
+}
+
+#[tokio::test]
+#[serial]
+async fn can_list_links_of_an_order() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+
+    let product = downloadable_product(db, "").await;
+    product_downloads::Model::create(db, product.id, "Chapter one", "ebook/one.pdf")
+        .await
+        .unwrap();
+    let order = place_order(db, &product).await;
+    download_permissions::Model::grant_for_order(db, &order)
+        .await
+        .unwrap();
+
+    let links = download_permissions::Model::links_for_order(
+        db,
+        SECRET,
+        order.id,
+        chrono::Duration::hours(1),
+    )
+    .await
+    .unwrap();
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].name, "Chapter one");
+    assert_eq!(links[0].downloads_remaining, None);
+    assert!(links[0].url.starts_with("/downloads/"));
+}
@@ -0,0 +1,91 @@
+use commust::{
+    app::App,
+    models::login_attempts::{
+        self, AttemptCheck, MAX_DELAY_SECONDS, MAX_FAILURES_PER_IP, THROTTLE_AFTER_FAILURES,
+    },
+};
+use loco_rs::testing;
+use serial_test::serial;
+
+#[test]
+fn can_compute_progressive_delay() {
+    assert_eq!(login_attempts::delay_for(0), 0);
+    assert_eq!(login_attempts::delay_for(THROTTLE_AFTER_FAILURES - 1), 0);
+    assert_eq!(login_attempts::delay_for(THROTTLE_AFTER_FAILURES), 2);
+    assert_eq!(login_attempts::delay_for(THROTTLE_AFTER_FAILURES + 1), 4);
+    assert_eq!(login_attempts::delay_for(THROTTLE_AFTER_FAILURES + 2), 8);
+    assert_eq!(login_attempts::delay_for(1000), MAX_DELAY_SECONDS);
+}
+
+#[tokio::test]
+#[serial]
+async fn can_throttle_email_after_failures() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+
+    for _ in 0..THROTTLE_AFTER_FAILURES {
+        assert_eq!(
+            login_attempts::Model::check(db, "user1@example.com", None)
+                .await
+                .unwrap(),
+            AttemptCheck::Allowed
+        );
+        login_attempts::Model::record(db, "User1@example.com", None, false)
+            .await
+            .unwrap();
+    }
+
+    assert!(matches!(
+        login_attempts::Model::check(db, "user1@example.com", None)
+            .await
+            .unwrap(),
+        AttemptCheck::Throttled(_)
+    ));
+    // other emails are not affected
+    assert_eq!(
+        login_attempts::Model::check(db, "user2@example.com", None)
+            .await
+            .unwrap(),
+        AttemptCheck::Allowed
+    );
+
+    login_attempts::Model::clear_failures(db, "user1@example.com")
+        .await
+        .unwrap();
+    assert_eq!(
+        login_attempts::Model::recent_failures_for_email(db, "user1@example.com")
+            .await
+            .unwrap(),
+        0
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn can_block_ip_after_failures() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+
+    for i in 0..MAX_FAILURES_PER_IP {
+        login_attempts::Model::record(db, &format!("user{i}@example.com"), Some("10.0.0.1"), false)
+            .await
+            .unwrap();
+    }
+    // logging into one of the accounts does not lift the block
+    login_attempts::Model::clear_failures(db, "user0@example.com")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        login_attempts::Model::check(db, "someone@example.com", Some("10.0.0.1"))
+            .await
+            .unwrap(),
+        AttemptCheck::IpBlocked
+    );
+    assert_eq!(
+        login_attempts::Model::check(db, "someone@example.com", Some("10.0.0.2"))
+            .await
+            .unwrap(),
+        AttemptCheck::Allowed
+    );
+}
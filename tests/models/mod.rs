@@ -1,3 +1,16 @@
+mod addresses;
+mod api_keys;
+mod bundle_items;
+mod download_permissions;
+mod login_attempts;
+mod orders;
+mod product_revisions;
+mod refresh_tokens;
+mod reviews;
+mod shipping_zones;
+mod stock_movements;
+mod stock_subscriptions;
+mod tax_rates;
 mod users;
 
 mod products;
@@ -0,0 +1,122 @@
+use commust::{
+    app::App,
+    models::{
+        _entities::products,
+        addresses::AddressParams,
+        orders::{self, OrderLine, PlaceOrderParams},
+    },
+};
+use loco_rs::testing;
+use sea_orm::{ActiveModelTrait, ActiveValue};
+use serial_test::serial;
+
+async fn create_product(db: &sea_orm::DatabaseConnection) -> products::Model {
+    products::ActiveModel {
+        title: ActiveValue::set("Loco t-shirt".to_string()),
+        author_id: ActiveValue::set(1),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+fn params(product: &products::Model, user_id: Option<i32>, email: &str) -> PlaceOrderParams {
+    PlaceOrderParams {
+        user_id,
+        email: email.to_string(),
+        billing: AddressParams {
+            first_name: "guest".to_string(),
+            last_name: "example".to_string(),
+            address_1: "1 main street".to_string(),
+            city: "New York".to_string(),
+            postcode: "10001".to_string(),
+            country: "US".to_string(),
+            ..Default::default()
+        },
+        lines: vec![OrderLine {
+            product_id: product.id,
+            name: product.title.clone(),
+            quantity: 2,
+            price: 12.5,
+            ..Default::default()
+        }],
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn can_place_order() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+    let product = create_product(db).await;
+
+    let order = orders::Model::place(db, &params(&product, None, " Guest@Example.com "))
+        .await
+        .unwrap();
+    let items = order.items(db).await.unwrap();
+
+    assert_eq!(order.status, orders::STATUS_PENDING);
+    assert_eq!(order.email.as_deref(), Some("guest@example.com"));
+    assert!((order.total - 25.0).abs() < f32::EPSILON);
+    assert_eq!(items.len(), 1);
+    assert_eq!(order.billing().unwrap().city, "New York");
+
+    let key = order.order_key.clone().unwrap();
+    assert!(orders::Model::find_by_key(db, order.id, &key).await.is_ok());
+    assert!(orders::Model::find_by_key(db, order.id, "order_wrong")
+        .await
+        .is_err());
+
+    let mut empty = params(&product, None, "guest@example.com");
+    empty.lines.clear();
+    assert!(orders::Model::place(db, &empty).await.is_err());
+    assert!(
+        orders::Model::place(db, &params(&product, None, "not an email"))
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn can_claim_guest_orders() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+    let product = create_product(db).await;
+
+    let guest = orders::Model::place(db, &params(&product, None, "user1@example.com"))
+        .await
+        .unwrap();
+    let other_user = orders::Model::place(db, &params(&product, Some(2), "user1@example.com"))
+        .await
+        .unwrap();
+    let other_email = orders::Model::place(db, &params(&product, None, "guest@example.com"))
+        .await
+        .unwrap();
+
+    let claimed = orders::Model::claim_guest_orders(db, 1, "USER1@example.com")
+        .await
+        .unwrap();
+    assert_eq!(claimed, 1);
+
+    let claimed_ids = orders::Model::find_by_user(db, 1)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|order| order.id)
+        .collect::<Vec<_>>();
+    assert_eq!(claimed_ids, vec![guest.id]);
+    assert_eq!(
+        orders::Model::find_by_user(db, 2).await.unwrap()[0].id,
+        other_user.id
+    );
+    assert!(orders::Model::find_by_user(db, 1)
+        .await
+        .unwrap()
+        .iter()
+        .all(|order| order.id != other_email.id));
+}
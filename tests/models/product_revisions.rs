@@ -0,0 +1,207 @@
+use commust::{
+    app::App,
+    models::{
+        _entities::{postmetas, products},
+        product_revisions::{self, Change},
+        products::{ProductStatus, TRASH_STATUS_META},
+        stock_movements,
+    },
+};
+use loco_rs::testing;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel,
+    QueryFilter,
+};
+use serial_test::serial;
+
+async fn set_meta(db: &DatabaseConnection, key: &str, value: Option<&str>) {
+    postmetas::Entity::delete_many()
+        .filter(postmetas::Column::ProductId.eq(1))
+        .filter(postmetas::Column::MetaKey.eq(key))
+        .exec(db)
+        .await
+        .unwrap();
+    if let Some(value) = value {
+        postmetas::ActiveModel {
+            product_id: ActiveValue::set(1),
+            meta_key: ActiveValue::set(Some(key.to_string())),
+            meta_value: ActiveValue::set(Some(value.to_string())),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap();
+    }
+}
+
+async fn meta(db: &DatabaseConnection, key: &str) -> Option<String> {
+    postmetas::Entity::find()
+        .filter(postmetas::Column::ProductId.eq(1))
+        .filter(postmetas::Column::MetaKey.eq(key))
+        .one(db)
+        .await
+        .unwrap()
+        .and_then(|meta| meta.meta_value)
+}
+
+#[tokio::test]
+#[serial]
+async fn test_revisions_record_the_changes() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+
+    let first = product_revisions::Model::record(&ctx.db, 1, Some(1))
+        .await
+        .unwrap();
+    let again = product_revisions::Model::record(&ctx.db, 1, Some(1))
+        .await
+        .unwrap();
+    assert_eq!(again.id, first.id);
+    assert!(first
+        .metas()
+        .iter()
+        .any(|meta| meta.key == "_sku" && meta.value.as_deref() == Some("HOODIE")));
+
+    let mut product = products::Entity::find_by_id(1)
+        .one(&ctx.db)
+        .await
+        .unwrap()
+        .unwrap()
+        .into_active_model();
+    product.title = ActiveValue::set("Rust zip hoodie".to_string());
+    product.update(&ctx.db).await.unwrap();
+    set_meta(&ctx.db, "_sale_price", None).await;
+    set_meta(&ctx.db, "_color", Some("red")).await;
+    let second = product_revisions::Model::record(&ctx.db, 1, Some(2))
+        .await
+        .unwrap();
+
+    assert_eq!(second.previous(&ctx.db).await.unwrap(), Some(first.clone()));
+    assert_eq!(
+        first.diff(&second),
+        vec![
+            Change {
+                field: "title".to_string(),
+                from: Some("Rust hoodie".to_string()),
+                to: Some("Rust zip hoodie".to_string()),
+            },
+            Change {
+                field: "_color".to_string(),
+                from: None,
+                to: Some("red".to_string()),
+            },
+            Change {
+                field: "_sale_price".to_string(),
+                from: Some("39".to_string()),
+                to: None,
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_restoring_a_revision_keeps_the_stock() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    let first = product_revisions::Model::record(&ctx.db, 1, None)
+        .await
+        .unwrap();
+
+    set_meta(&ctx.db, "_regular_price", Some("50")).await;
+    set_meta(&ctx.db, "_stock", Some("20")).await;
+    product_revisions::Model::record(&ctx.db, 1, None)
+        .await
+        .unwrap();
+
+    let restored = first.restore(&ctx.db, Some(1)).await.unwrap();
+    assert!(restored.id > first.id);
+    assert_eq!(restored.user_id, Some(1));
+    assert_eq!(meta(&ctx.db, "_regular_price").await.as_deref(), Some("45"));
+    assert_eq!(meta(&ctx.db, "_stock").await.as_deref(), Some("20"));
+    assert_eq!(
+        product_revisions::Model::list_for_product(&ctx.db, 1)
+            .await
+            .unwrap()
+            .len(),
+        3
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_restoring_a_revision_trashes_and_restores_the_product() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    let product = || async {
+        products::Entity::find_by_id(1)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap()
+    };
+    let published = product_revisions::Model::record(&ctx.db, 1, None)
+        .await
+        .unwrap();
+    product().await.trash(&ctx.db).await.unwrap();
+    let trashed = product_revisions::Model::record(&ctx.db, 1, None)
+        .await
+        .unwrap();
+
+    published.restore(&ctx.db, None).await.unwrap();
+    assert_eq!(product().await.status(), ProductStatus::Publish);
+    assert_eq!(meta(&ctx.db, TRASH_STATUS_META).await, None);
+
+    trashed.restore(&ctx.db, None).await.unwrap();
+    assert_eq!(product().await.status(), ProductStatus::Trash);
+    assert_eq!(
+        meta(&ctx.db, TRASH_STATUS_META).await.as_deref(),
+        Some("publish")
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_restoring_a_revision_derives_the_stock_status() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    let managed = product_revisions::Model::record(&ctx.db, 1, None)
+        .await
+        .unwrap();
+    stock_movements::Model::set_stock(
+        &ctx.db,
+        1,
+        0.0,
+        stock_movements::REASON_MANUAL,
+        stock_movements::Source::default(),
+    )
+    .await
+    .unwrap();
+
+    // the stock stops being managed
+    set_meta(&ctx.db, "_manage_stock", Some("false")).await;
+    set_meta(&ctx.db, "_stock", None).await;
+    set_meta(&ctx.db, "_stock_status", Some("instock")).await;
+    let unmanaged = product_revisions::Model::record(&ctx.db, 1, None)
+        .await
+        .unwrap();
+
+    // the ledger gives back the stock, which ran out
+    managed.restore(&ctx.db, None).await.unwrap();
+    assert_eq!(meta(&ctx.db, "_stock").await.as_deref(), Some("0"));
+    assert_eq!(
+        meta(&ctx.db, "_stock_status").await.as_deref(),
+        Some("outofstock")
+    );
+
+    unmanaged.restore(&ctx.db, None).await.unwrap();
+    assert_eq!(meta(&ctx.db, "_stock").await, None);
+    assert_eq!(
+        meta(&ctx.db, "_stock_status").await.as_deref(),
+        Some("instock")
+    );
+}
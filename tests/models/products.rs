@@ -1,5 +1,18 @@
-use commust::app::App;
+use chrono::{DateTime, Duration, Utc};
+use commust::{
+    app::App,
+    common::stock_alerts::{StockAlert, StockLevel},
+    models::{
+        _entities::postmetas,
+        product_slugs,
+        products::{self, ProductStatus},
+    },
+};
 use loco_rs::testing;
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ActiveValue, ColumnTrait, DatabaseConnection, EntityTrait,
+    PaginatorTrait, QueryFilter,
+};
 use serial_test::serial;
 
 macro_rules! configure_insta {
@@ -29,3 +42,279 @@ async fn test_model() {
     // snapshot the result:
     // assert_debug_snapshot!(item);
 }
+
+async fn create_product(
+    db: &DatabaseConnection,
+    status: ProductStatus,
+    published_at: Option<DateTime<Utc>>,
+) -> products::Model {
+    products::ActiveModel {
+        title: ActiveValue::set("Loco sticker".to_string()),
+        status: ActiveValue::set(Some(status.to_string())),
+        published_at: ActiveValue::set(published_at.map(Into::into)),
+        author_id: ActiveValue::set(1),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn test_scheduled_products_get_published() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+
+    let published = create_product(db, ProductStatus::Publish, None).await;
+    assert!(published.published_at.is_some());
+    let undated = create_product(db, ProductStatus::Future, None).await;
+    assert_eq!(undated.status(), ProductStatus::Draft);
+    let late = create_product(
+        db,
+        ProductStatus::Future,
+        Some(Utc::now() - Duration::hours(1)),
+    )
+    .await;
+    assert_eq!(late.status(), ProductStatus::Publish);
+
+    let scheduled = create_product(
+        db,
+        ProductStatus::Future,
+        Some(Utc::now() + Duration::hours(1)),
+    )
+    .await;
+    assert_eq!(scheduled.status(), ProductStatus::Future);
+    assert!(products::Model::publish_scheduled(db)
+        .await
+        .unwrap()
+        .is_empty());
+
+    products::Entity::update_many()
+        .col_expr(
+            products::Column::PublishedAt,
+            Expr::value(Utc::now() - Duration::minutes(1)),
+        )
+        .filter(products::Column::Id.eq(scheduled.id))
+        .exec(db)
+        .await
+        .unwrap();
+    let published = products::Model::publish_scheduled(db).await.unwrap();
+    assert_eq!(published.len(), 1);
+    assert_eq!(published[0].id, scheduled.id);
+    assert_eq!(published[0].status(), ProductStatus::Publish);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_trashed_products_restore_their_status() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+    let product = create_product(db, ProductStatus::Private, None).await;
+
+    let product = product.trash(db).await.unwrap();
+    assert_eq!(product.status(), ProductStatus::Trash);
+    let product = product.trash(db).await.unwrap();
+    assert_eq!(
+        products::Model::list_by_status(db, ProductStatus::Trash)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+
+    let product = product.restore(db).await.unwrap();
+    assert_eq!(product.status(), ProductStatus::Private);
+    let metas = postmetas::Entity::find()
+        .filter(postmetas::Column::ProductId.eq(product.id))
+        .filter(postmetas::Column::MetaKey.eq(products::TRASH_STATUS_META))
+        .count(db)
+        .await
+        .unwrap();
+    assert_eq!(metas, 0);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_slugs_are_unique_and_remembered() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+    let find = |id: i32| async move {
+        products::Entity::find_by_id(id)
+            .one(db)
+            .await
+            .unwrap()
+            .unwrap()
+    };
+
+    assert_eq!(
+        products::unique_slug(db, "Rust hoodie", None)
+            .await
+            .unwrap(),
+        "rust-hoodie-2"
+    );
+    assert_eq!(
+        products::unique_slug(db, "Rust hoodie", Some(1))
+            .await
+            .unwrap(),
+        "rust-hoodie"
+    );
+    assert_eq!(
+        products::unique_slug(db, "!!", None).await.unwrap(),
+        "product"
+    );
+
+    let hoodie = find(1).await.set_slug(db, "Zip hoodie").await.unwrap();
+    assert_eq!(hoodie.slug.as_deref(), Some("zip-hoodie"));
+    let former = product_slugs::Model::find_product(db, "rust-hoodie")
+        .await
+        .unwrap();
+    assert_eq!(former.map(|product| product.id), Some(1));
+    let cookbook = find(6).await.set_slug(db, "rust-hoodie").await.unwrap();
+    assert_eq!(cookbook.slug.as_deref(), Some("rust-hoodie-2"));
+
+    let hoodie = hoodie.set_slug(db, "rust-hoodie").await.unwrap();
+    assert_eq!(hoodie.slug.as_deref(), Some("rust-hoodie"));
+    assert!(product_slugs::Model::find_product(db, "rust-hoodie")
+        .await
+        .unwrap()
+        .is_none());
+    let hoodie = hoodie.set_slug(db, "").await.unwrap();
+    assert_eq!(hoodie.slug.as_deref(), Some("rust-hoodie"));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_grouped_products_list_their_published_children() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+
+    let mut group = create_product(db, ProductStatus::Publish, None).await;
+    assert!(group.purchasable());
+    let mut active = products::ActiveModel::from(group);
+    active.product_type = ActiveValue::set(Some(products::TYPE_GROUPED.to_string()));
+    group = active.update(db).await.unwrap();
+    assert!(!group.purchasable());
+    postmetas::ActiveModel {
+        product_id: ActiveValue::set(group.id),
+        meta_key: ActiveValue::set(Some(products::CHILDREN_META.to_string())),
+        meta_value: ActiveValue::set(Some("6,7,1".to_string())),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    // the crab mug is still a draft
+    let children: Vec<i32> = group
+        .children(db)
+        .await
+        .unwrap()
+        .iter()
+        .map(|child| child.id)
+        .collect();
+    assert_eq!(children, vec![6, 1]);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_products_low_in_stock_are_listed() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+    let listed = |items: Vec<products::LowStockProduct>| -> Vec<(i32, f32)> {
+        items
+            .iter()
+            .map(|item| (item.product.id, item.stock))
+            .collect()
+    };
+
+    // the t-shirt in M is sold out, the one in L has 5 left
+    let items = products::Model::list_low_stock(db, 2.0).await.unwrap();
+    assert_eq!(listed(items), vec![(4, 0.0)]);
+    let items = products::Model::list_low_stock(db, 5.0).await.unwrap();
+    assert_eq!(listed(items), vec![(4, 0.0), (5, 5.0)]);
+
+    // a product threshold wins over the store one
+    postmetas::ActiveModel {
+        product_id: ActiveValue::set(1),
+        meta_key: ActiveValue::set(Some(products::LOW_STOCK_AMOUNT_META.to_string())),
+        meta_value: ActiveValue::set(Some("30".to_string())),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    let items = products::Model::list_low_stock(db, 2.0).await.unwrap();
+    assert!((items[1].low_stock_amount - 30.0).abs() < f32::EPSILON);
+    assert_eq!(listed(items), vec![(4, 0.0), (1, 25.0)]);
+
+    // trashed products are left aside
+    let mug = products::Entity::find_by_id(7)
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap();
+    mug.trash(db).await.unwrap();
+    let items = products::Model::list_low_stock(db, 50.0).await.unwrap();
+    assert!(items.iter().all(|item| item.product.id != 7));
+}
+
+#[test]
+fn test_stock_changes_raise_alerts() {
+    let level = |stock: Option<f32>, status: &str| StockLevel {
+        stock,
+        status: Some(status.to_string()),
+    };
+
+    assert_eq!(
+        StockAlert::for_change(
+            &level(Some(5.0), "instock"),
+            &level(Some(2.0), "instock"),
+            2.0
+        ),
+        Some(StockAlert::LowStock { stock: 2.0 })
+    );
+    // already low, it is not told again
+    assert_eq!(
+        StockAlert::for_change(
+            &level(Some(2.0), "instock"),
+            &level(Some(1.0), "instock"),
+            2.0
+        ),
+        None
+    );
+    assert_eq!(
+        StockAlert::for_change(
+            &level(Some(1.0), "instock"),
+            &level(Some(0.0), "outofstock"),
+            2.0
+        ),
+        Some(StockAlert::NoStock { stock: 0.0 })
+    );
+    assert_eq!(
+        StockAlert::for_change(
+            &level(Some(0.0), "outofstock"),
+            &level(Some(8.0), "instock"),
+            2.0
+        ),
+        Some(StockAlert::BackInStock)
+    );
+    // the stock of a new product, or of one getting managed, is no change
+    assert_eq!(
+        StockAlert::for_change(&StockLevel::default(), &level(Some(1.0), "instock"), 2.0),
+        None
+    );
+    assert_eq!(
+        StockAlert::for_change(
+            &level(None, "instock"),
+            &level(Some(0.0), "outofstock"),
+            2.0
+        ),
+        None
+    );
+}
@@ -0,0 +1,36 @@
+use chrono::{Duration, Utc};
+use commust::{app::App, models::refresh_tokens};
+use loco_rs::testing;
+use sea_orm::{ActiveModelTrait, ActiveValue, IntoActiveModel};
+use serial_test::serial;
+
+#[tokio::test]
+#[serial]
+async fn can_rotate_refresh_token() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+
+    let (issued, token) = refresh_tokens::Model::issue(db, 1, None).await.unwrap();
+    assert_ne!(issued.token_hash, token);
+
+    let (rotated, _) = refresh_tokens::Model::rotate(db, &token).await.unwrap();
+    assert_eq!(rotated.family, issued.family);
+    assert_eq!(rotated.user_id, 1);
+    assert!(refresh_tokens::Model::rotate(db, &token).await.is_err());
+}
+
+#[tokio::test]
+#[serial]
+async fn cannot_rotate_expired_refresh_token() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+
+    let (issued, token) = refresh_tokens::Model::issue(db, 1, None).await.unwrap();
+    let mut issued = issued.into_active_model();
+    issued.expires_at = ActiveValue::set((Utc::now() - Duration::minutes(1)).into());
+    issued.update(db).await.unwrap();
+
+    assert!(refresh_tokens::Model::rotate(db, &token).await.is_err());
+}
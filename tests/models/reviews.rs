@@ -0,0 +1,115 @@
+use commust::{
+    app::App,
+    models::{
+        _entities::postmetas,
+        reviews::{self, ReviewParams},
+        users,
+    },
+};
+use loco_rs::testing;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serial_test::serial;
+
+fn params(rating: i32) -> ReviewParams {
+    ReviewParams {
+        rating,
+        content: "Soft and warm.".to_string(),
+    }
+}
+
+async fn rating_metas(db: &DatabaseConnection, product_id: i32) -> (String, String) {
+    let meta = |key: &'static str| async move {
+        postmetas::Entity::find()
+            .filter(postmetas::Column::ProductId.eq(product_id))
+            .filter(postmetas::Column::MetaKey.eq(key))
+            .one(db)
+            .await
+            .unwrap()
+            .and_then(|meta| meta.meta_value)
+            .unwrap_or_default()
+    };
+    (
+        meta(reviews::AVERAGE_RATING_META).await,
+        meta(reviews::REVIEW_COUNT_META).await,
+    )
+}
+
+#[tokio::test]
+#[serial]
+async fn test_reviews_of_buyers_are_verified() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+    let buyer = users::Model::find_by_email(db, "user2@example.com")
+        .await
+        .unwrap();
+    let other = users::Model::find_by_email(db, "user1@example.com")
+        .await
+        .unwrap();
+
+    // the completed order of the fixtures has a variation of the t-shirt
+    let review = reviews::Model::create(db, 2, &buyer, &params(5))
+        .await
+        .unwrap();
+    assert!(review.verified);
+    assert_eq!(review.status, reviews::STATUS_PENDING);
+    assert_eq!(review.author, buyer.name);
+    let review = reviews::Model::create(db, 6, &buyer, &params(4))
+        .await
+        .unwrap();
+    assert!(!review.verified);
+    let review = reviews::Model::create(db, 2, &other, &params(4))
+        .await
+        .unwrap();
+    assert!(!review.verified);
+
+    assert!(reviews::Model::create(db, 2, &other, &params(6))
+        .await
+        .is_err());
+    let empty = ReviewParams {
+        rating: 3,
+        content: " ".to_string(),
+    };
+    assert!(reviews::Model::create(db, 2, &other, &empty).await.is_err());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_approved_reviews_make_the_product_rating() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+    let user1 = users::Model::find_by_email(db, "user1@example.com")
+        .await
+        .unwrap();
+    let user2 = users::Model::find_by_email(db, "user2@example.com")
+        .await
+        .unwrap();
+    let five = reviews::Model::create(db, 1, &user1, &params(5))
+        .await
+        .unwrap();
+    let two = reviews::Model::create(db, 1, &user2, &params(2))
+        .await
+        .unwrap();
+
+    five.moderate(db, reviews::STATUS_APPROVED).await.unwrap();
+    let two = two.moderate(db, reviews::STATUS_APPROVED).await.unwrap();
+    assert_eq!(
+        rating_metas(db, 1).await,
+        ("3.50".to_string(), "2".to_string())
+    );
+    assert_eq!(
+        reviews::Model::approved_for_product(db, 1)
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+
+    let two = two.moderate(db, reviews::STATUS_SPAM).await.unwrap();
+    assert_eq!(
+        rating_metas(db, 1).await,
+        ("5.00".to_string(), "1".to_string())
+    );
+    assert!(two.moderate(db, "trash").await.is_err());
+}
@@ -0,0 +1,188 @@
+use commust::{
+    app::App,
+    models::{
+        shipping_methods::{self, MethodParams, WeightRate},
+        shipping_zones::{self, ShippingPackage, ZoneParams},
+    },
+};
+use loco_rs::testing;
+use serial_test::serial;
+
+fn package(country: &str, state: Option<&str>, postcode: &str) -> ShippingPackage {
+    ShippingPackage {
+        country: country.to_string(),
+        state: state.map(str::to_string),
+        postcode: postcode.to_string(),
+        contents_cost: 40.0,
+        weight: 2.0,
+    }
+}
+
+fn zone(name: &str, position: i32) -> ZoneParams {
+    ZoneParams {
+        name: name.to_string(),
+        position,
+        ..Default::default()
+    }
+}
+
+fn method(kind: &str, title: &str) -> MethodParams {
+    MethodParams {
+        kind: kind.to_string(),
+        title: title.to_string(),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn can_find_zone_for_package() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+
+    let rest = shipping_zones::Model::create(db, &zone("Rest of the world", 10))
+        .await
+        .unwrap();
+    let paris = shipping_zones::Model::create(
+        db,
+        &ZoneParams {
+            countries: vec!["fr".to_string()],
+            postcodes: vec!["75*".to_string(), "92100...92190".to_string()],
+            ..zone("Paris", 0)
+        },
+    )
+    .await
+    .unwrap();
+    let west_coast = shipping_zones::Model::create(
+        db,
+        &ZoneParams {
+            states: vec!["us:ca".to_string(), "US:OR".to_string()],
+            ..zone("West coast", 1)
+        },
+    )
+    .await
+    .unwrap();
+
+    let find = |package: ShippingPackage| async move {
+        shipping_zones::Model::find_for_package(db, &package)
+            .await
+            .unwrap()
+            .map(|zone| zone.id)
+    };
+    assert_eq!(find(package("FR", None, "75 011")).await, Some(paris.id));
+    assert_eq!(find(package("FR", None, "92130")).await, Some(paris.id));
+    assert_eq!(find(package("FR", None, "69001")).await, Some(rest.id));
+    assert_eq!(
+        find(package("US", Some("CA"), "90001")).await,
+        Some(west_coast.id)
+    );
+    assert_eq!(
+        find(package("US", Some("NY"), "10001")).await,
+        Some(rest.id)
+    );
+
+    shipping_zones::Model::remove(db, rest.id).await.unwrap();
+    assert_eq!(find(package("US", Some("NY"), "10001")).await, None);
+}
+
+#[tokio::test]
+#[serial]
+async fn can_calculate_rates() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+
+    let zone = shipping_zones::Model::create(db, &zone("Everywhere", 0))
+        .await
+        .unwrap();
+    for params in [
+        MethodParams {
+            cost: Some(5.0),
+            ..method(shipping_methods::KIND_FLAT_RATE, "Flat rate")
+        },
+        MethodParams {
+            min_amount: Some(50.0),
+            ..method(shipping_methods::KIND_FREE_SHIPPING, "Free shipping")
+        },
+        MethodParams {
+            rates: shipping_methods::parse_weight_rates("5:9\n1 : 4.5").unwrap(),
+            ..method(shipping_methods::KIND_TABLE_RATE, "By weight")
+        },
+        method(shipping_methods::KIND_LOCAL_PICKUP, "Local pickup"),
+    ] {
+        shipping_methods::Model::create(db, zone.id, &params)
+            .await
+            .unwrap();
+    }
+
+    let rates = |package: ShippingPackage| async move {
+        shipping_zones::Model::rates(db, &package)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|rate| (rate.title, rate.cost))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        rates(package("FR", None, "75011")).await,
+        vec![
+            ("Flat rate".to_string(), 5.0),
+            ("By weight".to_string(), 9.0),
+            ("Local pickup".to_string(), 0.0),
+        ]
+    );
+    assert_eq!(
+        rates(ShippingPackage {
+            contents_cost: 50.0,
+            weight: 0.5,
+            ..package("FR", None, "75011")
+        })
+        .await,
+        vec![
+            ("Flat rate".to_string(), 5.0),
+            ("Free shipping".to_string(), 0.0),
+            ("By weight".to_string(), 4.5),
+            ("Local pickup".to_string(), 0.0),
+        ]
+    );
+    // too heavy for the table rate
+    assert_eq!(
+        rates(ShippingPackage {
+            weight: 12.0,
+            ..package("FR", None, "75011")
+        })
+        .await
+        .len(),
+        2
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn rejects_invalid_methods() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+    let zone = shipping_zones::Model::create(db, &zone("Everywhere", 0))
+        .await
+        .unwrap();
+
+    assert!(shipping_methods::parse_weight_rates("1:4.5\nheavy").is_err());
+    assert_eq!(
+        shipping_methods::parse_weight_rates("1:4.5").unwrap(),
+        vec![WeightRate {
+            max_weight: 1.0,
+            cost: 4.5
+        }]
+    );
+    assert!(shipping_methods::Model::create(
+        db,
+        zone.id,
+        &method(shipping_methods::KIND_TABLE_RATE, "By weight")
+    )
+    .await
+    .is_err());
+    assert!(
+        shipping_methods::Model::create(db, zone.id, &method("teleport", "Teleport"))
+            .await
+            .is_err()
+    );
+}
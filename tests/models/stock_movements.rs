@@ -0,0 +1,217 @@
+use commust::{
+    app::App,
+    common::stock_alerts::StockLevel,
+    models::{
+        addresses::AddressParams,
+        orders::{self, OrderLine, PlaceOrderParams},
+        stock_movements::{self, Source},
+    },
+};
+use loco_rs::testing;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, EntityTrait, IntoActiveModel, ModelTrait, PaginatorTrait,
+};
+use serial_test::serial;
+
+async fn stock(db: &sea_orm::DatabaseConnection, product_id: i32) -> Option<f32> {
+    StockLevel::load(db, product_id).await.unwrap().stock
+}
+
+#[tokio::test]
+#[serial]
+async fn test_movements_derive_the_stock() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+
+    assert_eq!(stock_movements::Model::balance(db, 1).await.unwrap(), None);
+    assert!(
+        stock_movements::Model::record(db, 1, -3.0, "lost", Source::default())
+            .await
+            .is_err()
+    );
+
+    // the stock the product had is its first movement
+    let change = stock_movements::Model::record(
+        db,
+        1,
+        -3.0,
+        stock_movements::REASON_MANUAL,
+        Source::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(change.before.stock, Some(25.0));
+    assert_eq!(change.after.stock, Some(22.0));
+    assert_eq!(change.movement.stock, 22.0);
+    assert_eq!(stock(db, 1).await, Some(22.0));
+
+    let change = stock_movements::Model::set_stock(
+        db,
+        1,
+        30.0,
+        stock_movements::REASON_IMPORT,
+        Source::default(),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(change.movement.quantity, 8.0);
+    assert!(stock_movements::Model::set_stock(
+        db,
+        1,
+        30.0,
+        stock_movements::REASON_IMPORT,
+        Source::default(),
+    )
+    .await
+    .unwrap()
+    .is_none());
+
+    let movements: Vec<(String, f32)> = stock_movements::Model::list_for_product(db, 1)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(movement, _)| (movement.reason, movement.quantity))
+        .collect();
+    assert_eq!(
+        movements,
+        vec![
+            (stock_movements::REASON_IMPORT.to_string(), 8.0),
+            (stock_movements::REASON_MANUAL.to_string(), -3.0),
+            (stock_movements::REASON_INITIAL.to_string(), 25.0),
+        ]
+    );
+    assert_eq!(
+        stock_movements::Model::balance(db, 1).await.unwrap(),
+        Some(30.0)
+    );
+
+    // going out of stock
+    let change = stock_movements::Model::set_stock(
+        db,
+        1,
+        0.0,
+        stock_movements::REASON_MANUAL,
+        Source::default(),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(change.after.status.as_deref(), Some("outofstock"));
+
+    // the ledger only grows
+    let (movement, _) = stock_movements::Model::list_for_product(db, 1)
+        .await
+        .unwrap()
+        .remove(0);
+    let mut changed = movement.clone().into_active_model();
+    changed.quantity = ActiveValue::set(1.0);
+    assert!(changed.update(db).await.is_err());
+    assert!(movement.delete(db).await.is_err());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_orders_give_their_stock_back_when_cancelled() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+
+    let params = PlaceOrderParams {
+        email: "guest@example.com".to_string(),
+        billing: AddressParams {
+            first_name: "Ada".to_string(),
+            country: "US".to_string(),
+            ..Default::default()
+        },
+        lines: vec![
+            OrderLine {
+                product_id: 1,
+                name: "Rust hoodie".to_string(),
+                quantity: 2,
+                price: 40.0,
+                ..Default::default()
+            },
+            OrderLine {
+                product_id: 6,
+                name: "Rust cookbook".to_string(),
+                quantity: 1,
+                price: 30.0,
+                ..Default::default()
+            },
+        ],
+        ..Default::default()
+    };
+    let order = orders::Model::place(db, &params).await.unwrap();
+    // the cookbook does not manage its stock
+    let movements = stock_movements::Model::find_by_order(db, order.id)
+        .await
+        .unwrap();
+    assert_eq!(movements.len(), 1);
+    assert_eq!(movements[0].product_id, 1);
+    assert_eq!(stock(db, 1).await, Some(23.0));
+    assert_eq!(stock(db, 6).await, None);
+
+    let order = order
+        .update_status(db, orders::STATUS_CANCELLED)
+        .await
+        .unwrap();
+    let changes = stock_movements::Model::sync_order(db, &order, None)
+        .await
+        .unwrap();
+    assert_eq!(
+        changes[0].movement.reason,
+        stock_movements::REASON_CANCELLATION
+    );
+    assert_eq!(stock(db, 1).await, Some(25.0));
+
+    // back to processing, the order takes its stock again
+    let order = order
+        .update_status(db, orders::STATUS_PROCESSING)
+        .await
+        .unwrap();
+    stock_movements::Model::sync_order(db, &order, None)
+        .await
+        .unwrap();
+    assert_eq!(stock(db, 1).await, Some(23.0));
+
+    let order = order
+        .update_status(db, orders::STATUS_REFUNDED)
+        .await
+        .unwrap();
+    let changes = stock_movements::Model::sync_order(db, &order, None)
+        .await
+        .unwrap();
+    assert_eq!(changes[0].movement.reason, stock_movements::REASON_REFUND);
+    assert_eq!(stock(db, 1).await, Some(25.0));
+    assert!(stock_movements::Model::sync_order(db, &order, None)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_orders_cannot_take_missing_stock() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+
+    // only 5 t-shirts L are left, and they cannot be backordered
+    let params = PlaceOrderParams {
+        email: "guest@example.com".to_string(),
+        lines: vec![OrderLine {
+            product_id: 5,
+            name: "Ferris t-shirt - L".to_string(),
+            quantity: 6,
+            price: 20.0,
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    let placed = orders::Entity::find().count(db).await.unwrap();
+    assert!(orders::Model::place(db, &params).await.is_err());
+    assert_eq!(orders::Entity::find().count(db).await.unwrap(), placed);
+    assert_eq!(stock(db, 5).await, Some(5.0));
+}
@@ -0,0 +1,32 @@
+use commust::{app::App, models::stock_subscriptions};
+use loco_rs::testing;
+use serial_test::serial;
+
+#[tokio::test]
+#[serial]
+async fn test_customers_subscribe_once_per_product() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+
+    assert!(stock_subscriptions::Model::subscribe(db, 4, "not an email")
+        .await
+        .is_err());
+
+    let first = stock_subscriptions::Model::subscribe(db, 4, " Ada@Example.com")
+        .await
+        .unwrap();
+    assert_eq!(first.email, "ada@example.com");
+    let again = stock_subscriptions::Model::subscribe(db, 4, "ada@example.com")
+        .await
+        .unwrap();
+    assert_eq!(again.id, first.id);
+    stock_subscriptions::Model::subscribe(db, 1, "ada@example.com")
+        .await
+        .unwrap();
+
+    let subscriptions = stock_subscriptions::Model::find_by_product(db, 4)
+        .await
+        .unwrap();
+    assert_eq!(subscriptions, vec![first]);
+}
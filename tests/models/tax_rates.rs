@@ -0,0 +1,166 @@
+use commust::{
+    app::App,
+    models::tax_rates::{self, RateParams, TaxLocation},
+};
+use loco_rs::testing;
+use sea_orm::DatabaseConnection;
+use serial_test::serial;
+
+fn location(country: &str, state: Option<&str>, postcode: &str) -> TaxLocation {
+    TaxLocation {
+        country: country.to_string(),
+        state: state.map(str::to_string),
+        postcode: postcode.to_string(),
+    }
+}
+
+fn rate(country: Option<&str>, name: &str, rate: f32) -> RateParams {
+    RateParams {
+        tax_class: tax_rates::CLASS_STANDARD.to_string(),
+        country: country.map(str::to_string),
+        name: name.to_string(),
+        rate,
+        priority: 1,
+        shipping: true,
+        ..Default::default()
+    }
+}
+
+async fn create(db: &DatabaseConnection, params: RateParams) -> tax_rates::Model {
+    tax_rates::Model::create(db, &params).await.unwrap()
+}
+
+fn names(rates: &[tax_rates::Model]) -> Vec<&str> {
+    rates.iter().map(|rate| rate.name.as_str()).collect()
+}
+
+#[tokio::test]
+#[serial]
+async fn can_find_rates_for_location() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+
+    create(
+        db,
+        RateParams {
+            state: Some("ny".to_string()),
+            ..rate(Some("us"), "NY state", 4.0)
+        },
+    )
+    .await;
+    create(db, rate(Some("US"), "US", 2.0)).await;
+    create(
+        db,
+        RateParams {
+            state: Some("NY".to_string()),
+            postcode: Some("100*".to_string()),
+            priority: 2,
+            shipping: false,
+            ..rate(Some("US"), "NYC", 4.5)
+        },
+    )
+    .await;
+    create(
+        db,
+        RateParams {
+            tax_class: tax_rates::CLASS_REDUCED.to_string(),
+            ..rate(Some("FR"), "TVA", 5.5)
+        },
+    )
+    .await;
+    create(db, rate(None, "Everywhere", 1.0)).await;
+
+    let find = |class: &'static str, location: TaxLocation| async move {
+        tax_rates::Model::find_for(db, class, &location)
+            .await
+            .unwrap()
+    };
+    // a single rate applies by priority
+    assert_eq!(
+        names(&find("standard", location("US", Some("NY"), "10001")).await),
+        vec!["NY state", "NYC"]
+    );
+    assert_eq!(
+        names(&find("standard", location("US", Some("NY"), "12201")).await),
+        vec!["NY state"]
+    );
+    assert_eq!(
+        names(&find("standard", location("US", Some("CA"), "90001")).await),
+        vec!["US"]
+    );
+    assert_eq!(
+        names(&find("standard", location("DE", None, "10115")).await),
+        vec!["Everywhere"]
+    );
+    assert_eq!(
+        names(&find("reduced", location("FR", None, "75001")).await),
+        vec!["TVA"]
+    );
+    assert!(find("zero", location("FR", None, "75001")).await.is_empty());
+
+    let shipping = tax_rates::Model::find_for_shipping(db, &location("US", Some("NY"), "10001"))
+        .await
+        .unwrap();
+    assert_eq!(names(&shipping), vec!["NY state"]);
+}
+
+#[tokio::test]
+#[serial]
+async fn can_calculate_taxes() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+
+    let gst = create(db, rate(Some("CA"), "GST", 5.0)).await;
+    let qst = create(
+        db,
+        RateParams {
+            priority: 2,
+            compound: true,
+            ..rate(Some("CA"), "QST", 10.0)
+        },
+    )
+    .await;
+    let rates = vec![gst, qst];
+
+    // compound taxes apply on the price and the taxes before them
+    let lines = tax_rates::calculate(100.0, &rates, false);
+    assert_eq!(
+        lines.iter().map(|line| line.amount).collect::<Vec<_>>(),
+        vec![5.0, 10.5]
+    );
+    assert!((tax_rates::total(&lines) - 15.5).abs() < f32::EPSILON);
+
+    // the same taxes are taken out of a price including them
+    assert_eq!(tax_rates::calculate(115.5, &rates, true), lines);
+
+    let merged = tax_rates::merge(lines.iter().chain(&lines));
+    assert_eq!(merged.len(), 2);
+    assert!((merged[0].amount - 10.0).abs() < f32::EPSILON);
+    assert!((tax_rates::total(&merged) - 31.0).abs() < f32::EPSILON);
+
+    assert!(tax_rates::calculate(100.0, &[], false).is_empty());
+}
+
+#[tokio::test]
+#[serial]
+async fn rejects_invalid_rates() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let db = &boot.app_context.db;
+
+    let invalid = [
+        RateParams {
+            tax_class: "luxury".to_string(),
+            ..rate(None, "Luxury", 30.0)
+        },
+        RateParams {
+            state: Some("CA".to_string()),
+            ..rate(None, "California", 7.25)
+        },
+        rate(Some("US"), "", 1.0),
+        rate(Some("US"), "Refund", -1.0),
+    ];
+    for params in invalid {
+        assert!(tax_rates::Model::create(db, &params).await.is_err());
+    }
+    assert!(tax_rates::Model::list(db).await.unwrap().is_empty());
+}
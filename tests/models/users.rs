@@ -1,11 +1,15 @@
 use commust::{
     app::App,
-    models::users::{self, Model, RegisterParams},
+    models::{
+        recovery_codes::{self, RECOVERY_CODES},
+        users::{self, Model, RegisterParams, SecondFactor},
+    },
 };
 use insta::assert_debug_snapshot;
 use loco_rs::{model::ModelError, testing};
 use sea_orm::{ActiveModelTrait, ActiveValue, IntoActiveModel};
 use serial_test::serial;
+use totp_rs::{Algorithm, Secret, TOTP};
 
 macro_rules! configure_insta {
     ($($expr:expr),*) => {
@@ -221,3 +225,82 @@ async fn can_reset_password() {
             .verify_password("new-password")
     );
 }
+
+#[tokio::test]
+#[serial]
+async fn can_enroll_two_factor() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let db = &boot.app_context.db;
+
+    let user = Model::find_by_pid(db, "11111111-1111-1111-1111-111111111111")
+        .await
+        .unwrap()
+        .into_active_model()
+        .start_two_factor(db)
+        .await
+        .unwrap();
+    assert!(!user.has_two_factor());
+    let (uri, _) = user.totp_provisioning().unwrap().unwrap();
+    assert!(uri.starts_with("otpauth://totp/commust:user1%40example.com?secret="));
+
+    let secret = user.totp_secret.clone().unwrap();
+    let code = TOTP::new(
+        Algorithm::SHA1,
+        6,
+        0,
+        30,
+        Secret::Encoded(secret).to_bytes().unwrap(),
+        None,
+        String::new(),
+    )
+    .unwrap()
+    .generate_current()
+    .unwrap();
+
+    let SecondFactor::Enrolled(user, recovery_codes) =
+        user.verify_second_factor(db, &code).await.unwrap()
+    else {
+        panic!("the first code should enable two-factor authentication");
+    };
+    let user = *user;
+    assert!(user.has_two_factor());
+    assert_eq!(recovery_codes.len(), RECOVERY_CODES);
+
+    // a code is only accepted once
+    assert!(matches!(
+        user.clone().verify_second_factor(db, &code).await.unwrap(),
+        SecondFactor::Invalid
+    ));
+
+    let recovery_code = recovery_codes[0].to_uppercase();
+    assert!(matches!(
+        user.clone()
+            .verify_second_factor(db, &recovery_code)
+            .await
+            .unwrap(),
+        SecondFactor::RecoveryCode(_)
+    ));
+    assert!(matches!(
+        user.clone()
+            .verify_second_factor(db, &recovery_code)
+            .await
+            .unwrap(),
+        SecondFactor::Invalid
+    ));
+    assert_eq!(
+        recovery_codes::Model::remaining(db, user.id).await.unwrap(),
+        9
+    );
+
+    let user = user
+        .into_active_model()
+        .disable_two_factor(db)
+        .await
+        .unwrap();
+    assert!(!user.has_two_factor());
+    assert_eq!(
+        recovery_codes::Model::remaining(db, user.id).await.unwrap(),
+        0
+    );
+}
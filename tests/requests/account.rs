@@ -0,0 +1,437 @@
+use commust::{
+    app::App,
+    models::{
+        _entities::{order_items, products},
+        addresses, api_keys, audit_logs, orders, users,
+    },
+};
+use loco_rs::testing;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, EntityTrait, IntoActiveModel, PaginatorTrait,
+    QueryFilter,
+};
+use serial_test::serial;
+
+use super::prepare_data;
+
+async fn create_order(db: &sea_orm::DatabaseConnection, user_id: Option<i32>) -> orders::Model {
+    let order = orders::ActiveModel {
+        user_id: ActiveValue::set(user_id),
+        status: ActiveValue::set(orders::STATUS_PROCESSING.to_string()),
+        total: ActiveValue::set(24.0),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    order_items::ActiveModel {
+        order_id: ActiveValue::set(order.id),
+        name: ActiveValue::set("Loco t-shirt".to_string()),
+        quantity: ActiveValue::set(2),
+        price: ActiveValue::set(12.0),
+        total: ActiveValue::set(24.0),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    order
+}
+
+#[tokio::test]
+#[serial]
+async fn can_show_account() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        let response = request.get("/account").await;
+        assert_eq!(response.status_code(), 401);
+
+        let response = request
+            .get("/account")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert!(response.text().contains(&user.user.email));
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_update_profile_and_change_email() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        request
+            .post("/account/profile")
+            .add_header(auth_key, auth_value)
+            .form(&serde_json::json!({
+                "name": "new name",
+                "email": "new@loco.com",
+            }))
+            .await;
+
+        let saved_user = users::Model::find_by_pid(&ctx.db, &user.user.pid.to_string())
+            .await
+            .unwrap();
+        assert_eq!(saved_user.name, "new name");
+        // the current email is kept until the new one is confirmed
+        assert_eq!(saved_user.email, user.user.email);
+        assert_eq!(saved_user.pending_email.as_deref(), Some("new@loco.com"));
+
+        let token = saved_user.email_verification_token.unwrap();
+        request.get(&format!("/auth/verify/{token}")).await;
+
+        let saved_user = users::Model::find_by_pid(&ctx.db, &user.user.pid.to_string())
+            .await
+            .unwrap();
+        assert_eq!(saved_user.email, "new@loco.com");
+        assert!(saved_user.pending_email.is_none());
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn unchanged_name_is_not_recorded() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        request
+            .post("/account/profile")
+            .add_header(auth_key, auth_value)
+            .form(&serde_json::json!({
+                "name": format!("  {}  ", user.user.name),
+                "email": user.user.email,
+            }))
+            .await;
+
+        let saved_user = users::Model::find_by_pid(&ctx.db, &user.user.pid.to_string())
+            .await
+            .unwrap();
+        assert_eq!(saved_user.name, user.user.name);
+        let updates = audit_logs::Entity::find()
+            .filter(audit_logs::Column::UserId.eq(saved_user.id))
+            .filter(audit_logs::Column::Action.eq(audit_logs::ACCOUNT_UPDATED))
+            .count(&ctx.db)
+            .await
+            .unwrap();
+        assert_eq!(updates, 0);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_change_password() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        request
+            .post("/account/password")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&serde_json::json!({
+                "current_password": "invalid-password",
+                "password": "new-password",
+                "password_confirmation": "new-password",
+            }))
+            .await;
+        let saved_user = users::Model::find_by_email(&ctx.db, &user.user.email)
+            .await
+            .unwrap();
+        assert!(!saved_user.verify_password("new-password"));
+
+        request
+            .post("/account/password")
+            .add_header(auth_key, auth_value)
+            .form(&serde_json::json!({
+                "current_password": "1234",
+                "password": "new-password",
+                "password_confirmation": "new-password",
+            }))
+            .await;
+        let saved_user = users::Model::find_by_email(&ctx.db, &user.user.email)
+            .await
+            .unwrap();
+        assert!(saved_user.verify_password("new-password"));
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_save_addresses() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        request
+            .post("/account/addresses/billing")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&serde_json::json!({
+                "first_name": "Loco",
+                "last_name": "Rs",
+                "company": "",
+                "address_1": "1 rue de la Gare",
+                "address_2": "",
+                "city": "Paris",
+                "state": "",
+                "postcode": "75001",
+                "country": "fr",
+                "phone": "",
+            }))
+            .await;
+
+        let billing =
+            addresses::Model::find_by_user_and_kind(&ctx.db, user.user.id, addresses::BILLING)
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(billing.city, "Paris");
+        assert_eq!(billing.country, "FR");
+        assert!(billing.company.is_none());
+
+        let response = request
+            .get("/account/addresses")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert!(response.text().contains("1 rue de la Gare"));
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_only_view_own_orders() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        let own_order = create_order(&ctx.db, Some(user.user.id)).await;
+        let other_order = create_order(&ctx.db, None).await;
+
+        let response = request
+            .get("/account/orders")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert!(response
+            .text()
+            .contains(&format!("/account/orders/{}", own_order.id)));
+        assert!(!response
+            .text()
+            .contains(&format!("/account/orders/{}", other_order.id)));
+
+        let response = request
+            .get(&format!("/account/orders/{}", own_order.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert!(response.text().contains("Loco t-shirt"));
+
+        let response = request
+            .get(&format!("/account/orders/{}", other_order.id))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 404);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_delete_account() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+        let order = create_order(&ctx.db, Some(user.user.id)).await;
+
+        request
+            .post("/account/delete")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&serde_json::json!({ "password": "invalid-password" }))
+            .await;
+        assert!(users::Model::find_by_email(&ctx.db, &user.user.email)
+            .await
+            .is_ok());
+
+        request
+            .post("/account/delete")
+            .add_header(auth_key, auth_value)
+            .form(&serde_json::json!({ "password": "1234" }))
+            .await;
+        assert!(users::Model::find_by_email(&ctx.db, &user.user.email)
+            .await
+            .is_err());
+
+        // orders are kept but no longer linked to the user
+        let order = orders::Entity::find_by_id(order.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(order.user_id.is_none());
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn staff_and_product_authors_keep_their_account() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login_data.token);
+        let delete = || {
+            request
+                .post("/account/delete")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .form(&serde_json::json!({ "password": "1234" }))
+        };
+
+        let mut user = login_data.user.clone().into_active_model();
+        user.role = ActiveValue::set(users::ROLE_SHOP_MANAGER.to_string());
+        let user = user.update(&ctx.db).await.unwrap();
+        delete().await;
+        assert!(users::Model::find_by_email(&ctx.db, &user.email)
+            .await
+            .is_ok());
+
+        // a customer authoring products keeps them
+        let mut user = user.into_active_model();
+        user.role = ActiveValue::set(users::ROLE_CUSTOMER.to_string());
+        let user = user.update(&ctx.db).await.unwrap();
+        let mut product = products::Entity::find_by_id(1)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap()
+            .into_active_model();
+        product.author_id = ActiveValue::set(user.id);
+        product.update(&ctx.db).await.unwrap();
+        delete().await;
+        assert!(users::Model::find_by_email(&ctx.db, &user.email)
+            .await
+            .is_ok());
+        assert!(products::Entity::find_by_id(1)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .is_some());
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_manage_api_keys() {
+    testing::request::<App, _, _>(|mut request, ctx| async move {
+        request.save_cookies();
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&user.token);
+
+        request
+            .post("/account/api-keys")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&serde_json::json!({ "name": "deploy", "scope": "read" }))
+            .await;
+        let keys = api_keys::Model::find_by_user(&ctx.db, user.user.id)
+            .await
+            .unwrap();
+        assert_eq!(keys.len(), 1);
+
+        // the key is shown once, only its prefix afterwards
+        let response = request
+            .get("/account/api-keys")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert!(response.text().contains("it will not be shown again"));
+        let response = request
+            .get("/account/api-keys")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert!(!response.text().contains("it will not be shown again"));
+        assert!(response.text().contains(&keys[0].key_prefix));
+
+        let response = request
+            .post(&format!("/account/api-keys/{}/revoke", keys[0].id))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 303);
+        let keys = api_keys::Model::find_by_user(&ctx.db, user.user.id)
+            .await
+            .unwrap();
+        assert!(keys[0].revoked_at.is_some());
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn api_keys_are_limited_to_their_scope() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        create_order(&ctx.db, Some(user.user.id)).await;
+        let (read_key, read) =
+            api_keys::Model::generate(&ctx.db, user.user.id, "read", api_keys::SCOPE_READ)
+                .await
+                .unwrap();
+        let (_, write) =
+            api_keys::Model::generate(&ctx.db, user.user.id, "write", api_keys::SCOPE_WRITE)
+                .await
+                .unwrap();
+        let address = serde_json::json!({
+            "first_name": "loco",
+            "last_name": "user",
+            "address_1": "1 main street",
+            "city": "New York",
+            "postcode": "10001",
+            "country": "US",
+        });
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&read);
+        let response = request
+            .get("/api/account/orders")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert_eq!(response.json::<Vec<serde_json::Value>>().len(), 1);
+        let response = request
+            .post("/api/account/addresses/billing")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&address)
+            .await;
+        assert_eq!(response.status_code(), 403);
+
+        let (write_key, write_value) = prepare_data::auth_header(&write);
+        let response = request
+            .get("/api/account/orders")
+            .add_header(write_key.clone(), write_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 403);
+        let response = request
+            .post("/api/account/addresses/billing")
+            .add_header(write_key, write_value)
+            .json(&address)
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        api_keys::Model::revoke(&ctx.db, user.user.id, read_key.id)
+            .await
+            .unwrap();
+        let response = request
+            .get("/api/account/orders")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 401);
+    })
+    .await;
+}
@@ -1,7 +1,19 @@
-use commust::{app::App, models::users};
+use chrono::{Duration, Utc};
+use commust::{
+    app::App,
+    models::{
+        audit_logs,
+        login_attempts::{self, LOCK_AFTER_FAILURES},
+        recovery_codes, users,
+    },
+    views::auth::{LoginResponse, TwoFactorChallengeResponse, TwoFactorSetupResponse},
+};
 use insta::{assert_debug_snapshot, with_settings};
-use loco_rs::testing;
+use loco_rs::{app::AppContext, testing};
 use rstest::rstest;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter,
+};
 use serial_test::serial;
 
 use super::prepare_data;
@@ -17,6 +29,12 @@ macro_rules! configure_insta {
     };
 }
 
+fn cleanup_login_response() -> Vec<(&'static str, &'static str)> {
+    let mut filters = testing::cleanup_user_model();
+    filters.push((r"rt-[0-9a-f]{32}", "REFRESH_TOKEN"));
+    filters
+}
+
 #[tokio::test]
 #[serial]
 async fn can_register() {
@@ -93,7 +111,7 @@ async fn can_login_with_verify(#[case] test_name: &str, #[case] password: &str)
             .is_some());
 
         with_settings!({
-            filters => testing::cleanup_user_model()
+            filters => cleanup_login_response()
         }, {
             assert_debug_snapshot!(test_name, (response.status_code(), response.text()));
         });
@@ -131,7 +149,7 @@ async fn can_login_without_verify() {
             .await;
 
         with_settings!({
-            filters => testing::cleanup_user_model()
+            filters => cleanup_login_response()
         }, {
             assert_debug_snapshot!((response.status_code(), response.text()));
         });
@@ -216,3 +234,405 @@ async fn can_get_current_user() {
     })
     .await;
 }
+
+#[tokio::test]
+#[serial]
+async fn login_with_unknown_email_looks_like_invalid_password() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+
+        let invalid_password = request
+            .post("/api/auth/login")
+            .json(&serde_json::json!({
+                "email": login_data.user.email,
+                "password": "invalid-password"
+            }))
+            .await;
+        let unknown_email = request
+            .post("/api/auth/login")
+            .json(&serde_json::json!({
+                "email": "unknown@loco.com",
+                "password": "invalid-password"
+            }))
+            .await;
+
+        assert_eq!(unknown_email.status_code(), 401);
+        assert_eq!(
+            (unknown_email.status_code(), unknown_email.text()),
+            (invalid_password.status_code(), invalid_password.text())
+        );
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_throttle_login_attempts() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, _ctx| async move {
+        let payload = serde_json::json!({
+            "email": "unknown@loco.com",
+            "password": "invalid-password"
+        });
+
+        for _ in 0..login_attempts::THROTTLE_AFTER_FAILURES {
+            let response = request.post("/api/auth/login").json(&payload).await;
+            assert_eq!(response.status_code(), 401);
+        }
+
+        let response = request.post("/api/auth/login").json(&payload).await;
+        assert_eq!(response.status_code(), 429);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_lock_and_unlock_account() {
+    configure_insta!();
+
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        let email = login_data.user.email.clone();
+
+        // previous failures, old enough to not be throttled anymore
+        for _ in 1..LOCK_AFTER_FAILURES {
+            let created_at = Utc::now() - Duration::minutes(5);
+            login_attempts::ActiveModel {
+                email: ActiveValue::set(email.clone()),
+                successful: ActiveValue::set(false),
+                created_at: ActiveValue::set(created_at.into()),
+                updated_at: ActiveValue::set(created_at.into()),
+                ..Default::default()
+            }
+            .insert(&ctx.db)
+            .await
+            .unwrap();
+        }
+
+        let response = request
+            .post("/api/auth/login")
+            .json(&serde_json::json!({
+                "email": email,
+                "password": "invalid-password"
+            }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        let user = users::Model::find_by_email(&ctx.db, &email).await.unwrap();
+        assert!(user.is_locked());
+        assert!(user.unlock_token.is_some());
+        assert!(audit_logs::Entity::find()
+            .filter(audit_logs::Column::UserId.eq(user.id))
+            .filter(audit_logs::Column::Action.eq(audit_logs::ACCOUNT_LOCKED))
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .is_some());
+
+        // a locked account rejects even the right password
+        login_attempts::Model::clear_failures(&ctx.db, &email)
+            .await
+            .unwrap();
+        let response = request
+            .post("/api/auth/login")
+            .json(&serde_json::json!({
+                "email": email,
+                "password": "1234"
+            }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        request
+            .post("/api/auth/unlock")
+            .json(&serde_json::json!({ "token": user.unlock_token }))
+            .await;
+
+        let user = users::Model::find_by_email(&ctx.db, &email).await.unwrap();
+        assert!(!user.is_locked());
+        assert!(user.unlock_token.is_none());
+
+        let response = request
+            .post("/api/auth/login")
+            .json(&serde_json::json!({
+                "email": email,
+                "password": "1234"
+            }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_refresh_and_rotate_tokens() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+
+        let response = request
+            .post("/api/auth/refresh")
+            .json(&serde_json::json!({ "refresh_token": login_data.refresh_token }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let refreshed: LoginResponse = serde_json::from_str(&response.text()).unwrap();
+        assert_ne!(refreshed.refresh_token, login_data.refresh_token);
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&refreshed.token);
+        let response = request
+            .get("/api/auth/current")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        // a rotated token used again revokes its whole family
+        let response = request
+            .post("/api/auth/refresh")
+            .json(&serde_json::json!({ "refresh_token": login_data.refresh_token }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+        let response = request
+            .post("/api/auth/refresh")
+            .json(&serde_json::json!({ "refresh_token": refreshed.refresh_token }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_logout() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login_data.token);
+
+        let response = request
+            .post("/api/auth/logout")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&serde_json::json!({ "refresh_token": login_data.refresh_token }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let response = request
+            .post("/api/auth/refresh")
+            .json(&serde_json::json!({ "refresh_token": login_data.refresh_token }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        // the access token is only revoked when signing out everywhere
+        let response = request
+            .get("/api/auth/current")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        request
+            .post("/api/auth/logout")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&serde_json::json!({ "all": true }))
+            .await;
+        let response = request
+            .get("/api/auth/current")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 401);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn reset_password_revokes_sessions() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+
+        request
+            .post("/api/auth/forgot")
+            .json(&serde_json::json!({ "email": login_data.user.email }))
+            .await;
+        let user = users::Model::find_by_email(&ctx.db, &login_data.user.email)
+            .await
+            .unwrap();
+        request
+            .post("/api/auth/reset")
+            .json(&serde_json::json!({
+                "token": user.reset_token,
+                "password": "new-password",
+            }))
+            .await;
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&login_data.token);
+        let response = request
+            .get("/api/auth/current")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 401);
+        let response = request
+            .post("/api/auth/refresh")
+            .json(&serde_json::json!({ "refresh_token": login_data.refresh_token }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+    })
+    .await;
+}
+
+/// Enrolls the user as if they had confirmed a code of a step long gone, so
+/// the current code can be used in the test
+async fn enable_two_factor(ctx: &AppContext, user: users::Model) -> (users::Model, Vec<String>) {
+    let user = user
+        .into_active_model()
+        .start_two_factor(&ctx.db)
+        .await
+        .unwrap()
+        .into_active_model()
+        .enable_two_factor(&ctx.db, 0)
+        .await
+        .unwrap();
+    let codes = recovery_codes::Model::generate(&ctx.db, user.id)
+        .await
+        .unwrap();
+    (user, codes)
+}
+
+#[tokio::test]
+#[serial]
+async fn can_login_with_two_factor() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        let (user, codes) = enable_two_factor(&ctx, login_data.user).await;
+        let credentials = serde_json::json!({ "email": user.email, "password": "1234" });
+
+        let response = request.post("/api/auth/login").json(&credentials).await;
+        let challenge: TwoFactorChallengeResponse = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(challenge.two_factor, "verify");
+
+        // the challenge is not an access token
+        let (auth_key, auth_value) = prepare_data::auth_header(&challenge.challenge_token);
+        let response = request
+            .get("/api/auth/current")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        let response = request
+            .post("/api/auth/login/two-factor")
+            .json(&serde_json::json!({
+                "challenge_token": challenge.challenge_token,
+                "code": "000000",
+            }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        let code = prepare_data::totp_code(user.totp_secret.as_deref().unwrap());
+        let response = request
+            .post("/api/auth/login/two-factor")
+            .json(&serde_json::json!({
+                "challenge_token": challenge.challenge_token,
+                "code": code,
+            }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let login: LoginResponse = serde_json::from_str(&response.text()).unwrap();
+        assert!(login.recovery_codes.is_none());
+
+        // the code of the authenticator app is spent, recovery codes once each
+        for (code, status) in [(code.as_str(), 401), (&codes[0], 200), (&codes[0], 401)] {
+            let response = request.post("/api/auth/login").json(&credentials).await;
+            let challenge: TwoFactorChallengeResponse =
+                serde_json::from_str(&response.text()).unwrap();
+            let response = request
+                .post("/api/auth/login/two-factor")
+                .json(&serde_json::json!({
+                    "challenge_token": challenge.challenge_token,
+                    "code": code,
+                }))
+                .await;
+            assert_eq!(response.status_code(), status);
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn shop_manager_must_enroll_before_login() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        let mut user = login_data.user.into_active_model();
+        user.role = ActiveValue::set(users::ROLE_SHOP_MANAGER.to_string());
+        let user = user.update(&ctx.db).await.unwrap();
+
+        let response = request
+            .post("/api/auth/login")
+            .json(&serde_json::json!({ "email": user.email, "password": "1234" }))
+            .await;
+        let challenge: TwoFactorChallengeResponse = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(challenge.two_factor, "setup");
+
+        let response = request
+            .post("/api/auth/login/two-factor/setup")
+            .json(&serde_json::json!({ "challenge_token": challenge.challenge_token }))
+            .await;
+        let setup: TwoFactorSetupResponse = serde_json::from_str(&response.text()).unwrap();
+        assert!(setup.provisioning_uri.starts_with("otpauth://totp/"));
+
+        let response = request
+            .post("/api/auth/login/two-factor")
+            .json(&serde_json::json!({
+                "challenge_token": challenge.challenge_token,
+                "code": prepare_data::totp_code(&setup.secret),
+            }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let login: LoginResponse = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(login.recovery_codes.map(|codes| codes.len()), Some(10));
+
+        let user = users::Model::find_by_email(&ctx.db, &user.email)
+            .await
+            .unwrap();
+        assert!(user.has_two_factor());
+
+        // once enrolled the secret can no longer be replaced before logging in
+        let response = request
+            .post("/api/auth/login/two-factor/setup")
+            .json(&serde_json::json!({ "challenge_token": challenge.challenge_token }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_login_via_form_with_two_factor() {
+    testing::request::<App, _, _>(|mut request, ctx| async move {
+        request.save_cookies();
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        let (user, _) = enable_two_factor(&ctx, login_data.user).await;
+
+        let response = request
+            .post("/auth/login")
+            .form(&serde_json::json!({ "email": user.email, "password": "1234" }))
+            .await;
+        assert_eq!(response.header("location"), "/auth/two-factor");
+        let response = request.get("/auth/two-factor").await;
+        assert!(response.text().contains("recovery code"));
+
+        let response = request
+            .post("/auth/two-factor")
+            .form(&serde_json::json!({ "code": "000000" }))
+            .await;
+        assert_eq!(response.header("location"), "/auth/two-factor");
+
+        let response = request
+            .post("/auth/two-factor")
+            .form(&serde_json::json!({
+                "code": prepare_data::totp_code(user.totp_secret.as_deref().unwrap()),
+            }))
+            .await;
+        assert_eq!(response.header("location"), "/account");
+    })
+    .await;
+}
@@ -2,6 +2,8 @@ use commust::app::App;
 use loco_rs::testing;
 use serial_test::serial;
 
+use super::prepare_data;
+
 #[tokio::test]
 #[serial]
 async fn can_get_carts() {
@@ -15,3 +17,32 @@ async fn can_get_carts() {
     .await;
 }
 
+#[tokio::test]
+#[serial]
+async fn only_published_products_can_be_bought() {
+    testing::request::<App, _, _>(|mut request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        request.save_cookies();
+        let product = prepare_data::create_product(&ctx.db, "loco-t-shirt", 12.5).await;
+        let add = |id: i32, slug: &str| {
+            request
+                .post("/cart/add-item")
+                .form(&serde_json::json!({ "id": id, "qty": 1, "slug": slug }))
+        };
+
+        // the crab mug is a draft
+        add(7, "crab-mug").await;
+        assert!(!request.get("/cart").await.text().contains("Crab mug"));
+
+        add(product.id, "loco-t-shirt").await;
+        assert!(request.get("/cart").await.text().contains("loco t shirt"));
+
+        // trashed while in the cart, it cannot be ordered anymore
+        product.clone().trash(&ctx.db).await.unwrap();
+        assert!(!request.get("/cart").await.text().contains("loco t shirt"));
+        assert_eq!(request.get("/checkout").await.header("location"), "/cart");
+        add(product.id, "loco-t-shirt").await;
+        assert!(!request.get("/cart").await.text().contains("loco t shirt"));
+    })
+    .await;
+}
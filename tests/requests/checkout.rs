@@ -0,0 +1,602 @@
+use commust::{
+    app::App,
+    models::{
+        _entities::{order_items, postmetas, products},
+        orders,
+        shipping_methods::{self, MethodParams},
+        shipping_zones::{self, ZoneParams},
+        stock_movements,
+        tax_rates::{self, RateParams},
+        users,
+    },
+};
+use loco_rs::{testing, TestServer};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter,
+};
+use serial_test::serial;
+
+use super::prepare_data;
+
+const GUEST_EMAIL: &str = "guest@example.com";
+
+fn checkout_form(email: &str) -> Vec<(&'static str, String)> {
+    vec![
+        ("email", email.to_string()),
+        ("first_name", "guest".to_string()),
+        ("last_name", "example".to_string()),
+        ("company", String::new()),
+        ("address_1", "1 main street".to_string()),
+        ("address_2", String::new()),
+        ("city", "New York".to_string()),
+        ("state", "NY".to_string()),
+        ("postcode", "10001".to_string()),
+        ("country", "us".to_string()),
+        ("phone", String::new()),
+        ("customer_note", "leave at the door".to_string()),
+        ("payment_method", "bacs".to_string()),
+        ("terms", "on".to_string()),
+        ("checkout_token", uuid::Uuid::new_v4().to_string()),
+    ]
+}
+
+async fn add_to_cart(request: &TestServer, product: &products::Model) {
+    let res = request
+        .post("/cart/add-item")
+        .form(&serde_json::json!({
+            "id": product.id,
+            "qty": 2,
+            "slug": product.slug,
+        }))
+        .await;
+    assert_eq!(res.status_code(), 303);
+}
+
+/// Orders placed by the guest, the seeded ones left aside
+async fn guest_orders(ctx: &loco_rs::app::AppContext) -> Vec<orders::Model> {
+    orders::Entity::find()
+        .filter(orders::Column::Email.eq(GUEST_EMAIL))
+        .all(&ctx.db)
+        .await
+        .unwrap()
+}
+
+async fn last_order(ctx: &loco_rs::app::AppContext) -> orders::Model {
+    orders::Entity::find()
+        .all(&ctx.db)
+        .await
+        .unwrap()
+        .pop()
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn can_place_guest_order() {
+    testing::request::<App, _, _>(|mut request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        request.save_cookies();
+        let product = prepare_data::create_product(&ctx.db, "loco-t-shirt", 12.5).await;
+
+        assert_eq!(request.get("/checkout").await.status_code(), 303);
+
+        add_to_cart(&request, &product).await;
+        let res = request.get("/checkout").await;
+        assert_eq!(res.status_code(), 200);
+        assert!(res.text().contains("Create an account?"));
+
+        let res = request
+            .post("/checkout")
+            .form(&checkout_form("Guest@Example.com"))
+            .await;
+        assert_eq!(res.status_code(), 303);
+
+        let order = last_order(&ctx).await;
+        let key = order.order_key.clone().unwrap();
+        assert_eq!(order.user_id, None);
+        assert_eq!(order.email.as_deref(), Some(GUEST_EMAIL));
+        assert!((order.total - 25.0).abs() < f32::EPSILON);
+        assert_eq!(order.customer_note.as_deref(), Some("leave at the door"));
+        assert_eq!(
+            res.header("location"),
+            format!("/checkout/order-received/{}?key={key}", order.id)
+        );
+
+        let res = request
+            .get(&format!("/checkout/order-received/{}?key={key}", order.id))
+            .await;
+        assert_eq!(res.status_code(), 200);
+        assert!(res.text().contains("loco t shirt"));
+        let res = request
+            .get(&format!(
+                "/checkout/order-received/{}?key=order_wrong",
+                order.id
+            ))
+            .await;
+        assert_eq!(res.status_code(), 404);
+
+        // the cart got emptied by the order
+        assert_eq!(request.get("/checkout").await.status_code(), 303);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_create_account_at_checkout() {
+    testing::request::<App, _, _>(|mut request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        request.save_cookies();
+        let product = prepare_data::create_product(&ctx.db, "loco-t-shirt", 12.5).await;
+        add_to_cart(&request, &product).await;
+
+        let mut form = checkout_form(GUEST_EMAIL);
+        form.push(("create_account", "on".to_string()));
+        let res = request.post("/checkout").form(&form).await;
+        assert_eq!(res.header("location"), "/checkout");
+        assert!(guest_orders(&ctx).await.is_empty());
+
+        form.push(("password", "12341234".to_string()));
+        let res = request.post("/checkout").form(&form).await;
+        assert_eq!(res.status_code(), 303);
+
+        let user = users::Model::find_by_email(&ctx.db, GUEST_EMAIL)
+            .await
+            .unwrap();
+        assert_eq!(user.name, "guest example");
+        assert!(user.email_verification_token.is_some());
+        assert_eq!(last_order(&ctx).await.user_id, Some(user.id));
+
+        // an existing account has to log in instead
+        add_to_cart(&request, &product).await;
+        let mut form = checkout_form("user1@example.com");
+        form.push(("create_account", "on".to_string()));
+        form.push(("password", "12341234".to_string()));
+        let res = request.post("/checkout").form(&form).await;
+        assert_eq!(res.header("location"), "/checkout");
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_claim_guest_orders_after_verification() {
+    testing::request::<App, _, _>(|mut request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        request.save_cookies();
+        let product = prepare_data::create_product(&ctx.db, "loco-t-shirt", 12.5).await;
+        add_to_cart(&request, &product).await;
+        request
+            .post("/checkout")
+            .form(&checkout_form(GUEST_EMAIL))
+            .await;
+        let order = last_order(&ctx).await;
+
+        request
+            .post("/api/auth/register")
+            .json(&serde_json::json!({
+                "name": "guest",
+                "email": GUEST_EMAIL,
+                "password": "12341234"
+            }))
+            .await;
+        let user = users::Model::find_by_email(&ctx.db, GUEST_EMAIL)
+            .await
+            .unwrap();
+        // registering alone proves nothing about the email
+        assert_eq!(last_order(&ctx).await.user_id, None);
+
+        request
+            .post("/api/auth/verify")
+            .json(&serde_json::json!({ "token": user.email_verification_token }))
+            .await;
+
+        let orders = orders::Model::find_by_user(&ctx.db, user.id).await.unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].id, order.id);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_charge_shipping_at_checkout() {
+    testing::request::<App, _, _>(|mut request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        request.save_cookies();
+        let product = prepare_data::create_product(&ctx.db, "loco-t-shirt", 12.5).await;
+        let zone = shipping_zones::Model::create(
+            &ctx.db,
+            &ZoneParams {
+                name: "United States".to_string(),
+                countries: vec!["US".to_string()],
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let flat_rate = shipping_methods::Model::create(
+            &ctx.db,
+            zone.id,
+            &MethodParams {
+                kind: shipping_methods::KIND_FLAT_RATE.to_string(),
+                title: "Flat rate".to_string(),
+                cost: Some(5.0),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        shipping_methods::Model::create(
+            &ctx.db,
+            zone.id,
+            &MethodParams {
+                kind: shipping_methods::KIND_LOCAL_PICKUP.to_string(),
+                title: "Local pickup".to_string(),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        add_to_cart(&request, &product).await;
+
+        // the cart estimates shipping once the destination is known
+        let res = request.get("/cart").await;
+        assert!(!res.text().contains("Flat rate"));
+        request
+            .post("/cart/shipping")
+            .form(&serde_json::json!({ "country": "us", "state": "", "postcode": "10001" }))
+            .await;
+        let res = request.get("/cart").await;
+        assert!(res.text().contains("Flat rate"));
+        assert!(res.text().contains("Local pickup"));
+
+        // nothing ships outside of the zones
+        let mut form = checkout_form(GUEST_EMAIL);
+        form.retain(|(key, _)| *key != "country");
+        form.push(("country", "fr".to_string()));
+        let res = request.post("/checkout").form(&form).await;
+        assert_eq!(res.header("location"), "/checkout");
+        assert!(request
+            .get("/checkout")
+            .await
+            .text()
+            .contains("no shipping method is available for this address"));
+        assert!(guest_orders(&ctx).await.is_empty());
+
+        let mut form = checkout_form(GUEST_EMAIL);
+        form.push(("shipping_method", flat_rate.id.to_string()));
+        let res = request.post("/checkout").form(&form).await;
+        assert_eq!(res.status_code(), 303);
+
+        let order = last_order(&ctx).await;
+        assert!((order.total - 30.0).abs() < f32::EPSILON);
+        assert!((order.shipping_total - 5.0).abs() < f32::EPSILON);
+        assert_eq!(order.shipping_method.as_deref(), Some("Flat rate"));
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_charge_taxes_at_checkout() {
+    testing::request::<App, _, _>(|mut request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        request.save_cookies();
+        let shirt = prepare_data::create_product(&ctx.db, "loco-t-shirt", 12.5).await;
+        let book = prepare_data::create_product(&ctx.db, "loco-book", 10.0).await;
+        postmetas::ActiveModel {
+            meta_key: ActiveValue::set(Some("_tax_class".to_string())),
+            meta_value: ActiveValue::set(Some(tax_rates::CLASS_REDUCED.to_string())),
+            product_id: ActiveValue::set(book.id),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+        for (tax_class, name, rate) in [
+            (tax_rates::CLASS_STANDARD, "Sales tax", 8.0),
+            (tax_rates::CLASS_REDUCED, "Reduced tax", 5.0),
+        ] {
+            tax_rates::Model::create(
+                &ctx.db,
+                &RateParams {
+                    tax_class: tax_class.to_string(),
+                    country: Some("US".to_string()),
+                    name: name.to_string(),
+                    rate,
+                    priority: 1,
+                    shipping: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        }
+        add_to_cart(&request, &shirt).await;
+        add_to_cart(&request, &book).await;
+
+        // taxes depend on the destination
+        let res = request.get("/cart").await;
+        assert!(!res.text().contains("Sales tax"));
+        request
+            .post("/cart/shipping")
+            .form(&serde_json::json!({ "country": "us", "state": "", "postcode": "10001" }))
+            .await;
+        let res = request.get("/cart").await;
+        assert!(res.text().contains("Sales tax (8%): 2"));
+        assert!(res.text().contains("Reduced tax (5%): 1"));
+        assert!(res.text().contains("Total: 48"));
+
+        let res = request
+            .post("/checkout")
+            .form(&checkout_form(GUEST_EMAIL))
+            .await;
+        assert_eq!(res.status_code(), 303);
+
+        let order = last_order(&ctx).await;
+        assert!((order.total - 48.0).abs() < f32::EPSILON);
+        assert!((order.tax_total - 3.0).abs() < f32::EPSILON);
+        assert_eq!(order.tax_lines().len(), 2);
+        let items = order_items::Entity::find()
+            .filter(order_items::Column::OrderId.eq(order.id))
+            .all(&ctx.db)
+            .await
+            .unwrap();
+        let taxes = items
+            .iter()
+            .map(|item| (item.product_id, item.tax_total))
+            .collect::<Vec<_>>();
+        assert!(taxes.contains(&(Some(shirt.id), 2.0)));
+        assert!(taxes.contains(&(Some(book.id), 1.0)));
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn validates_addresses_by_country() {
+    testing::request::<App, _, _>(|mut request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        request.save_cookies();
+        let product = prepare_data::create_product(&ctx.db, "loco-t-shirt", 12.5).await;
+        add_to_cart(&request, &product).await;
+
+        let mut form = checkout_form(GUEST_EMAIL);
+        form.retain(|(key, _)| !["country", "state", "terms"].contains(key));
+        form.push(("country", "ca".to_string()));
+        form.push(("state", String::new()));
+        let res = request.post("/checkout").form(&form).await;
+        assert_eq!(res.header("location"), "/checkout");
+        assert!(guest_orders(&ctx).await.is_empty());
+
+        // the form is filled with what was submitted
+        let page = request.get("/checkout").await.text();
+        assert!(page.contains("Province is required."));
+        assert!(page.contains("Postcode is not valid."));
+        assert!(page.contains("please accept the terms and conditions"));
+        assert!(page.contains("leave at the door"));
+
+        let mut form = checkout_form(GUEST_EMAIL);
+        form.retain(|(key, _)| !["country", "state", "postcode"].contains(key));
+        form.push(("country", "ca".to_string()));
+        form.push(("state", "qc".to_string()));
+        form.push(("postcode", "h2x 1y4".to_string()));
+        let res = request.post("/checkout").form(&form).await;
+        assert_eq!(res.status_code(), 303);
+        assert_ne!(res.header("location"), "/checkout");
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_ship_to_different_address() {
+    testing::request::<App, _, _>(|mut request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        request.save_cookies();
+        let product = prepare_data::create_product(&ctx.db, "loco-t-shirt", 12.5).await;
+        add_to_cart(&request, &product).await;
+
+        let mut form = checkout_form(GUEST_EMAIL);
+        form.retain(|(key, _)| *key != "payment_method");
+        form.extend([
+            ("payment_method", "cod".to_string()),
+            ("ship_to_different_address", "on".to_string()),
+            ("shipping_first_name", "loco".to_string()),
+            ("shipping_last_name", "friend".to_string()),
+            ("shipping_address_1", "10 downing street".to_string()),
+            ("shipping_city", "London".to_string()),
+            ("shipping_state", String::new()),
+            ("shipping_country", "gb".to_string()),
+            ("shipping_postcode", "nope".to_string()),
+        ]);
+        let res = request.post("/checkout").form(&form).await;
+        assert_eq!(res.header("location"), "/checkout");
+        let page = request.get("/checkout").await.text();
+        assert!(page.contains("Postcode is not valid."));
+
+        form.pop();
+        form.push(("shipping_postcode", "sw1a 2aa".to_string()));
+        let res = request.post("/checkout").form(&form).await;
+        assert_eq!(res.status_code(), 303);
+
+        let order = last_order(&ctx).await;
+        assert_eq!(order.status, orders::STATUS_PROCESSING);
+        assert_eq!(order.payment_method.as_deref(), Some("cod"));
+        assert_eq!(order.billing().unwrap().city, "New York");
+        assert_eq!(order.shipping().unwrap().city, "London");
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn places_a_single_order_when_submitted_twice() {
+    testing::request::<App, _, _>(|mut request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        request.save_cookies();
+        let product = prepare_data::create_product(&ctx.db, "loco-t-shirt", 12.5).await;
+        add_to_cart(&request, &product).await;
+
+        let form = checkout_form(GUEST_EMAIL);
+        let first = request.post("/checkout").form(&form).await;
+        let second = request.post("/checkout").form(&form).await;
+        assert_eq!(first.status_code(), 303);
+        assert_eq!(first.header("location"), second.header("location"));
+        assert_eq!(guest_orders(&ctx).await.len(), 1);
+
+        let order = last_order(&ctx).await;
+        assert_eq!(order.status, orders::STATUS_ON_HOLD);
+        let res = request
+            .get(first.header("location").to_str().unwrap())
+            .await;
+        assert!(res.text().contains("Direct bank transfer"));
+
+        // a form without token is sent back for review
+        add_to_cart(&request, &product).await;
+        let mut form = checkout_form(GUEST_EMAIL);
+        form.retain(|(key, _)| *key != "checkout_token");
+        let res = request.post("/checkout").form(&form).await;
+        assert_eq!(res.header("location"), "/checkout");
+        assert_eq!(guest_orders(&ctx).await.len(), 1);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_order_on_backorder_when_allowed() {
+    testing::request::<App, _, _>(|mut request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        request.save_cookies();
+        let product = prepare_data::create_product(&ctx.db, "loco-t-shirt", 12.5).await;
+        let stock = postmetas::Entity::find()
+            .filter(postmetas::Column::ProductId.eq(product.id))
+            .filter(postmetas::Column::MetaKey.eq("_stock"))
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        let mut stock: postmetas::ActiveModel = stock.into();
+        stock.meta_value = ActiveValue::set(Some("1".to_string()));
+        stock.update(&ctx.db).await.unwrap();
+        let backorders = postmetas::ActiveModel {
+            meta_key: ActiveValue::set(Some("_backorders".to_string())),
+            meta_value: ActiveValue::set(Some("no".to_string())),
+            product_id: ActiveValue::set(product.id),
+            ..Default::default()
+        }
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+
+        // only one is in stock
+        add_to_cart(&request, &product).await;
+        assert!(request
+            .get("/products/p/loco-t-shirt")
+            .await
+            .text()
+            .contains("The requested quantity is not available"));
+
+        let mut backorders: postmetas::ActiveModel = backorders.into();
+        backorders.meta_value = ActiveValue::set(Some("notify".to_string()));
+        backorders.update(&ctx.db).await.unwrap();
+        add_to_cart(&request, &product).await;
+        let cart = request.get("/cart").await.text();
+        assert!(cart.contains("loco t shirt &times; 2"));
+        assert!(cart.contains("1 on backorder"));
+
+        request
+            .post("/checkout")
+            .form(&checkout_form(GUEST_EMAIL))
+            .await;
+        let order = last_order(&ctx).await;
+        let items = order_items::Entity::find()
+            .filter(order_items::Column::OrderId.eq(order.id))
+            .all(&ctx.db)
+            .await
+            .unwrap();
+        assert_eq!(items[0].backordered, 1);
+        let key = order.order_key.unwrap();
+        assert!(request
+            .get(&format!("/checkout/order-received/{}?key={key}", order.id))
+            .await
+            .text()
+            .contains("Backordered: 1"));
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn orders_move_the_stock_of_their_products() {
+    testing::request::<App, _, _>(|mut request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        request.save_cookies();
+        let product = prepare_data::create_product(&ctx.db, "loco-t-shirt", 12.5).await;
+        let stock = || async {
+            postmetas::Entity::find()
+                .filter(postmetas::Column::ProductId.eq(product.id))
+                .filter(postmetas::Column::MetaKey.eq("_stock"))
+                .one(&ctx.db)
+                .await
+                .unwrap()
+                .and_then(|meta| meta.meta_value)
+        };
+
+        add_to_cart(&request, &product).await;
+        request
+            .post("/checkout")
+            .form(&checkout_form(GUEST_EMAIL))
+            .await;
+        let order = last_order(&ctx).await;
+        assert_eq!(stock().await.as_deref(), Some("8"));
+
+        // the stock ran out while the cart was being filled
+        add_to_cart(&request, &product).await;
+        stock_movements::Model::set_stock(
+            &ctx.db,
+            product.id,
+            1.0,
+            stock_movements::REASON_MANUAL,
+            stock_movements::Source::default(),
+        )
+        .await
+        .unwrap();
+        request
+            .post("/checkout")
+            .form(&checkout_form(GUEST_EMAIL))
+            .await;
+        assert_eq!(last_order(&ctx).await.id, order.id);
+        assert_eq!(stock().await.as_deref(), Some("1"));
+        assert!(request
+            .get("/checkout")
+            .await
+            .text()
+            .contains("only 1 of loco t shirt left in stock"));
+
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login_data.token);
+        let mut user = login_data.user.into_active_model();
+        user.role = ActiveValue::set(users::ROLE_SHOP_MANAGER.to_string());
+        user.update(&ctx.db).await.unwrap();
+        request
+            .post(&format!("/admin/orders/{}/status", order.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&serde_json::json!({"status": orders::STATUS_CANCELLED}))
+            .await;
+        assert_eq!(stock().await.as_deref(), Some("3"));
+
+        let page = request
+            .get(&format!("/admin/products/{}/stock", product.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .text();
+        assert!(page.contains("Current stock: 3"));
+        assert!(page.contains("cancellation"));
+        assert!(page.contains(&format!("#{}", order.id)));
+        assert!(!page.contains("is not the one of its movements"));
+    })
+    .await;
+}
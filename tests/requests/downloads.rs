@@ -0,0 +1,164 @@
+use commust::{
+    app::App,
+    models::{_entities::postmetas, download_permissions, orders, product_downloads, users},
+};
+use loco_rs::testing;
+use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection, EntityTrait, IntoActiveModel};
+use serial_test::serial;
+
+use super::prepare_data;
+
+async fn order_download(db: &DatabaseConnection, file: &str) -> orders::Model {
+    let product = prepare_data::create_product(db, "loco-ebook", 9.0).await;
+    for (key, value) in [("_downloadable", "yes"), ("_download_limit", "1")] {
+        postmetas::ActiveModel {
+            product_id: ActiveValue::set(product.id),
+            meta_key: ActiveValue::set(Some(key.to_string())),
+            meta_value: ActiveValue::set(Some(value.to_string())),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap();
+    }
+    product_downloads::Model::create(db, product.id, "Chapter one", file)
+        .await
+        .unwrap();
+    prepare_data::place_order(db, &product).await
+}
+
+#[tokio::test]
+#[serial]
+async fn can_download_through_signed_link_until_limit() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        std::fs::create_dir_all("tmp/downloads/ebook").unwrap();
+        std::fs::write("tmp/downloads/ebook/one.pdf", "chapter one").unwrap();
+        let order = order_download(&ctx.db, "ebook/one.pdf").await;
+        download_permissions::Model::grant_for_order(&ctx.db, &order)
+            .await
+            .unwrap();
+        let secret = ctx.config.get_jwt_config().unwrap().secret.clone();
+        let links = download_permissions::Model::links_for_order(
+            &ctx.db,
+            &secret,
+            order.id,
+            chrono::Duration::hours(1),
+        )
+        .await
+        .unwrap();
+        let url = &links[0].url;
+
+        let response = request.get(&url.replace("expires=", "expires=9")).await;
+        assert_eq!(response.status_code(), 403);
+
+        let response = request.get(url).await;
+        assert_eq!(response.status_code(), 200);
+        assert_eq!(
+            response.header("content-disposition"),
+            "attachment; filename=\"one.pdf\""
+        );
+        assert_eq!(response.text(), "chapter one");
+
+        let response = request.get(url).await;
+        assert_eq!(response.status_code(), 403);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn managers_can_change_order_status() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login_data.token);
+        let order = order_download(&ctx.db, "ebook/one.pdf").await;
+
+        let response = request
+            .post(&format!("/admin/orders/{}/status", order.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&serde_json::json!({"status": orders::STATUS_PROCESSING}))
+            .await;
+        assert_eq!(response.status_code(), 403);
+
+        let mut user = login_data.user.into_active_model();
+        user.role = ActiveValue::set(users::ROLE_SHOP_MANAGER.to_string());
+        user.update(&ctx.db).await.unwrap();
+
+        let response = request
+            .get("/admin/orders")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert!(response.text().contains("guest@example.com"));
+
+        let response = request
+            .post(&format!("/admin/orders/{}/status", order.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&serde_json::json!({"status": "shipped"}))
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        let response = request
+            .post(&format!("/admin/orders/{}/status", order.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&serde_json::json!({"status": orders::STATUS_PROCESSING}))
+            .await;
+        assert_eq!(response.header("location"), "/admin/orders");
+        let order = orders::Entity::find_by_id(order.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(order.status, orders::STATUS_PROCESSING);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn only_managers_change_the_files_of_a_product() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let product = prepare_data::create_product(&ctx.db, "loco-ebook", 9.0).await;
+        let url = format!("/products/{}/downloads", product.id);
+        let form = serde_json::json!({"name": "Chapter one", "file": "ebook/one.pdf"});
+
+        let response = request.post(&url).form(&form).await;
+        assert_eq!(response.status_code(), 401);
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login_data.token);
+        let response = request
+            .post(&url)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&form)
+            .await;
+        assert_eq!(response.status_code(), 403);
+
+        let mut user = login_data.user.into_active_model();
+        user.role = ActiveValue::set(users::ROLE_SHOP_MANAGER.to_string());
+        user.update(&ctx.db).await.unwrap();
+        request
+            .post(&url)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&form)
+            .await;
+        let downloads = product_downloads::Model::find_by_product(&ctx.db, product.id)
+            .await
+            .unwrap();
+        assert_eq!(downloads.len(), 1);
+
+        let remove = format!("{url}/{}/delete", downloads[0].id);
+        let response = request.post(&remove).await;
+        assert_eq!(response.status_code(), 401);
+        request.post(&remove).add_header(auth_key, auth_value).await;
+        assert!(
+            product_downloads::Model::find_by_product(&ctx.db, product.id)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    })
+    .await;
+}
@@ -0,0 +1,89 @@
+use commust::{
+    app::App,
+    mailers::delivery,
+    models::{failed_emails, users},
+};
+use loco_rs::{mailer::Email, testing};
+use sea_orm::{ActiveModelTrait, ActiveValue, IntoActiveModel};
+use serial_test::serial;
+
+use super::prepare_data;
+
+#[tokio::test]
+#[serial]
+async fn only_managers_can_manage_failed_emails() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login_data.token);
+        delivery::clear_mailbox();
+        let email = Email {
+            to: "customer@example.com".to_string(),
+            subject: "Your order has been received".to_string(),
+            text: "Thank you".to_string(),
+            ..Default::default()
+        };
+        let first = failed_emails::Model::record(&ctx.db, &email, "connection refused", 5)
+            .await
+            .unwrap();
+        let second = failed_emails::Model::record(&ctx.db, &email, "connection refused", 5)
+            .await
+            .unwrap();
+
+        let response = request
+            .get("/admin/emails/failed")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 403);
+        let response = request
+            .post(&format!("/admin/emails/failed/{}/retry", first.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 403);
+
+        let mut user = login_data.user.into_active_model();
+        user.role = ActiveValue::set(users::ROLE_SHOP_MANAGER.to_string());
+        user.update(&ctx.db).await.unwrap();
+
+        let response = request
+            .get("/admin/emails/failed")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let page = response.text();
+        assert!(page.contains("customer@example.com"));
+        assert!(page.contains("connection refused"));
+
+        let response = request
+            .post(&format!("/admin/emails/failed/{}/retry", first.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.header("location"), "/admin/emails/failed");
+        let response = request
+            .post(&format!("/admin/emails/failed/{}/delete", second.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.header("location"), "/admin/emails/failed");
+        assert!(failed_emails::Model::list(&ctx.db)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let response = request
+            .post(&format!("/admin/emails/failed/{}/retry", first.id))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 404);
+
+        // the email sent again gets delivered in the background
+        for _ in 0..50 {
+            if !delivery::mailbox().is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        let mailbox = delivery::mailbox();
+        assert_eq!(mailbox.len(), 1);
+        assert_eq!(mailbox[0].subject, "Your order has been received");
+    })
+    .await;
+}
@@ -0,0 +1,231 @@
+use std::io::Cursor;
+
+use axum_test::multipart::{MultipartForm, Part};
+use commust::{
+    app::App,
+    models::{_entities::postmetas, media, users},
+    workers::thumbnails::{ThumbnailWorker, ThumbnailWorkerArgs},
+};
+use loco_rs::{bgworker::BackgroundWorker, testing};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, EntityTrait, IntoActiveModel, PaginatorTrait,
+    QueryFilter,
+};
+use serial_test::serial;
+
+use super::prepare_data;
+
+fn png(width: u32, height: u32) -> Vec<u8> {
+    let mut content = vec![];
+    image::RgbImage::new(width, height)
+        .write_to(&mut Cursor::new(&mut content), image::ImageFormat::Png)
+        .unwrap();
+    content
+}
+
+#[tokio::test]
+#[serial]
+async fn managers_can_upload_images() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login_data.token);
+        let form = || {
+            MultipartForm::new()
+                .add_text("alt", "A red hoodie")
+                .add_part(
+                    "file",
+                    Part::bytes(png(400, 200))
+                        .file_name("Red Hoodie.png")
+                        .mime_type("image/png"),
+                )
+        };
+
+        let response = request
+            .post("/admin/media")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .multipart(form())
+            .await;
+        assert_eq!(response.status_code(), 403);
+
+        let mut user = login_data.user.into_active_model();
+        user.role = ActiveValue::set(users::ROLE_SHOP_MANAGER.to_string());
+        user.update(&ctx.db).await.unwrap();
+
+        let response = request
+            .post("/admin/media")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .multipart(form())
+            .await;
+        assert_eq!(response.header("location"), "/admin/media");
+        let image = media::Entity::find().one(&ctx.db).await.unwrap().unwrap();
+        assert_eq!(image.filename, "Red Hoodie.png");
+        assert_eq!(image.mime_type, "image/png");
+        assert_eq!((image.width, image.height), (Some(400), Some(200)));
+        assert_eq!(image.alt.as_deref(), Some("A red hoodie"));
+        assert!(image.path.ends_with("-red-hoodie.png"));
+
+        // the content tells the format, not the name
+        let response = request
+            .post("/admin/media")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .multipart(MultipartForm::new().add_part(
+                "file",
+                Part::bytes(b"not an image".to_vec()).file_name("fake.png"),
+            ))
+            .await;
+        assert_eq!(response.header("location"), "/admin/media");
+        assert_eq!(media::Entity::find().count(&ctx.db).await.unwrap(), 1);
+
+        let response = request
+            .get("/admin/media")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert!(response.text().contains("Red Hoodie.png"));
+
+        let response = request.get(&format!("/media/{}", image.id)).await;
+        assert_eq!(response.status_code(), 200);
+        assert_eq!(response.header("content-type"), "image/png");
+        assert_eq!(response.as_bytes().to_vec(), png(400, 200));
+
+        // the original is served until the thumbnails are made
+        let response = request.get(&format!("/media/{}/medium", image.id)).await;
+        assert_eq!(response.as_bytes().to_vec(), png(400, 200));
+        ThumbnailWorker::build(&ctx)
+            .perform(ThumbnailWorkerArgs { media_id: image.id })
+            .await
+            .unwrap();
+        let response = request.get(&format!("/media/{}/medium", image.id)).await;
+        let medium = image::load_from_memory(response.as_bytes()).unwrap();
+        assert_eq!((medium.width(), medium.height()), (300, 150));
+
+        let response = request
+            .post(&format!("/admin/media/{}/delete", image.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.header("location"), "/admin/media");
+        assert_eq!(media::Entity::find().count(&ctx.db).await.unwrap(), 0);
+        let response = request.get(&format!("/media/{}", image.id)).await;
+        assert_eq!(response.status_code(), 404);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn products_show_their_images() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login_data.token);
+        let product = prepare_data::create_product(&ctx.db, "loco-hoodie", 40.0).await;
+        let mut images = vec![];
+        for name in ["front.png", "back.png", "side.png"] {
+            let image =
+                media::Model::upload(&ctx.db, &ctx.storage, name, png(20, 20).into(), None, None)
+                    .await
+                    .unwrap();
+            images.push(image.id);
+        }
+        let metas = || async {
+            postmetas::Entity::find()
+                .filter(postmetas::Column::ProductId.eq(product.id))
+                .filter(
+                    postmetas::Column::MetaKey
+                        .is_in([media::FEATURED_IMAGE_META, media::GALLERY_META]),
+                )
+                .all(&ctx.db)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|meta| (meta.meta_key.unwrap(), meta.meta_value.unwrap()))
+                .collect::<Vec<_>>()
+        };
+
+        // only the staff change the images of a product
+        let images_url = format!("/products/{}/images", product.id);
+        let form = serde_json::json!({"featured": images[0], "gallery": ""});
+        let response = request.post(&images_url).form(&form).await;
+        assert_eq!(response.status_code(), 401);
+        let response = request
+            .post(&images_url)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&form)
+            .await;
+        assert_eq!(response.status_code(), 403);
+        let mut user = login_data.user.into_active_model();
+        user.role = ActiveValue::set(users::ROLE_SHOP_MANAGER.to_string());
+        user.update(&ctx.db).await.unwrap();
+
+        let response = request
+            .post(&images_url)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&serde_json::json!({"featured": images[0], "gallery": "999"}))
+            .await;
+        assert_eq!(
+            response.header("location"),
+            format!("/products/{}/edit", product.id)
+        );
+        assert!(metas().await.is_empty());
+
+        request
+            .post(&images_url)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&serde_json::json!({
+                "featured": images[0],
+                "gallery": format!("{}, {}", images[2], images[1]),
+            }))
+            .await;
+        let mut saved = metas().await;
+        saved.sort();
+        assert_eq!(
+            saved,
+            vec![
+                (
+                    media::GALLERY_META.to_string(),
+                    format!("{},{}", images[2], images[1])
+                ),
+                (
+                    media::FEATURED_IMAGE_META.to_string(),
+                    images[0].to_string()
+                ),
+            ]
+        );
+
+        let response = request.get(&format!("/products/{}", product.id)).await;
+        assert_eq!(response.status_code(), 200);
+        let page = response.text();
+        assert!(page.contains(&format!("/media/{}/medium", images[0])));
+        let side = page
+            .find(&format!("/media/{}/thumbnail", images[2]))
+            .unwrap();
+        let back = page
+            .find(&format!("/media/{}/thumbnail", images[1]))
+            .unwrap();
+        assert!(side < back);
+
+        let response = request
+            .get(&format!("/products/{}/edit", product.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        media::Model::remove(&ctx.db, &ctx.storage, images[2])
+            .await
+            .unwrap();
+        let mut saved = metas().await;
+        saved.sort();
+        assert_eq!(
+            saved,
+            vec![
+                (media::GALLERY_META.to_string(), images[1].to_string()),
+                (
+                    media::FEATURED_IMAGE_META.to_string(),
+                    images[0].to_string()
+                ),
+            ]
+        );
+    })
+    .await;
+}
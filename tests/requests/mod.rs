@@ -1,4 +1,14 @@
+mod account;
 mod auth;
-mod prepare_data;
+mod checkout;
+mod downloads;
+mod emails;
+mod media;
+mod oauth2;
+pub mod prepare_data;
+mod products;
+mod reviews;
+mod shipping;
+mod taxes;
 
 pub mod cart;
\ No newline at end of file
@@ -0,0 +1,252 @@
+use axum::{
+    routing::{get, post},
+    Json, Router,
+};
+use commust::{
+    app::App,
+    models::{user_identities, users},
+};
+use loco_rs::{testing, TestServer};
+use serial_test::serial;
+use tokio::{net::TcpListener, task::JoinHandle};
+
+use super::prepare_data;
+
+/// Stops the stub provider when dropped, even if the test fails
+struct StubProvider(JoinHandle<()>);
+
+impl Drop for StubProvider {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Serves the token and profile endpoints of the `stub` provider configured
+/// in `config/test.yaml`, answering the given profile for any code
+async fn stub_provider(profile: serde_json::Value) -> StubProvider {
+    let router = Router::new()
+        .route(
+            "/token",
+            post(|| async {
+                Json(serde_json::json!({
+                    "access_token": "stub-access-token",
+                    "token_type": "bearer",
+                    "expires_in": 3600,
+                }))
+            }),
+        )
+        .route("/userinfo", get(move || async move { Json(profile) }));
+    let listener = TcpListener::bind("127.0.0.1:5555").await.unwrap();
+    StubProvider(tokio::spawn(async move {
+        axum::serve(listener, router).await.unwrap();
+    }))
+}
+
+/// Starts a login with the stub provider and returns the state it was given
+async fn authorize(request: &TestServer) -> String {
+    let response = request.get("/auth/oauth2/stub").await;
+    let location = response.header("location");
+    let location = location.to_str().unwrap();
+    assert!(location.starts_with("http://127.0.0.1:5555/authorize?"));
+    assert!(location.contains("code_challenge="));
+
+    let url = reqwest::Url::parse(location).unwrap();
+    let (_, state) = url.query_pairs().find(|(key, _)| key == "state").unwrap();
+    state.to_string()
+}
+
+fn callback_url(state: &str) -> String {
+    format!("/auth/oauth2/stub/callback?code=stub-code&state={state}")
+}
+
+#[tokio::test]
+#[serial]
+async fn can_sign_up_with_provider() {
+    testing::request::<App, _, _>(|mut request, ctx| async move {
+        request.save_cookies();
+        let _provider = stub_provider(serde_json::json!({
+            "sub": "stub-42",
+            "email": "new@loco.com",
+            "email_verified": true,
+            "name": "New user",
+        }))
+        .await;
+
+        let state = authorize(&request).await;
+        let response = request.get(&callback_url(&state)).await;
+        assert_eq!(response.header("location"), "/account");
+
+        let user = users::Model::find_by_email(&ctx.db, "new@loco.com")
+            .await
+            .unwrap();
+        assert_eq!(user.name, "New user");
+        assert!(user.email_verified_at.is_some());
+        let identities = user_identities::Model::find_by_user(&ctx.db, user.id)
+            .await
+            .unwrap();
+        assert_eq!(identities.len(), 1);
+        assert_eq!(identities[0].provider, "stub");
+        assert_eq!(identities[0].subject, "stub-42");
+
+        // the identity logs the same user in afterwards
+        let state = authorize(&request).await;
+        let response = request.get(&callback_url(&state)).await;
+        assert_eq!(response.header("location"), "/account");
+        assert_eq!(
+            user_identities::Model::find_by_user(&ctx.db, user.id)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_link_provider_to_verified_account() {
+    testing::request::<App, _, _>(|mut request, ctx| async move {
+        request.save_cookies();
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        let _provider = stub_provider(serde_json::json!({
+            "sub": 4242,
+            "email": login_data.user.email,
+            "email_verified": true,
+        }))
+        .await;
+
+        let state = authorize(&request).await;
+        let response = request.get(&callback_url(&state)).await;
+        assert_eq!(response.header("location"), "/account");
+
+        let identities = user_identities::Model::find_by_user(&ctx.db, login_data.user.id)
+            .await
+            .unwrap();
+        assert_eq!(identities.len(), 1);
+        assert_eq!(identities[0].subject, "4242");
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_link_provider_from_account() {
+    testing::request::<App, _, _>(|mut request, ctx| async move {
+        request.save_cookies();
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        // a different email is fine, the user is logged in
+        let _provider = stub_provider(serde_json::json!({
+            "sub": "stub-7",
+            "email": "other@loco.com",
+            "email_verified": false,
+        }))
+        .await;
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&login_data.token);
+        let state = authorize(&request).await;
+        let response = request
+            .get(&callback_url(&state))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.header("location"), "/account/identities");
+
+        let identities = user_identities::Model::find_by_user(&ctx.db, login_data.user.id)
+            .await
+            .unwrap();
+        assert_eq!(identities.len(), 1);
+
+        let response = request
+            .get("/account/identities")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert!(response.text().contains("other@loco.com"));
+
+        let response = request
+            .post(&format!("/account/identities/{}/unlink", identities[0].id))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.header("location"), "/account/identities");
+        assert!(
+            user_identities::Model::find_by_user(&ctx.db, login_data.user.id)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn rejects_untrusted_emails() {
+    testing::request::<App, _, _>(|mut request, ctx| async move {
+        request.save_cookies();
+        let provider = stub_provider(serde_json::json!({
+            "sub": "stub-1",
+            "email": "test@loco.com",
+            "email_verified": false,
+        }))
+        .await;
+
+        let state = authorize(&request).await;
+        let response = request.get(&callback_url(&state)).await;
+        assert_eq!(response.header("location"), "/auth/login");
+        let response = request.get("/auth/login").await;
+        assert!(response.text().contains("verified email"));
+        drop(provider);
+
+        // an account which never verified its email is not linked either
+        request
+            .post("/api/auth/register")
+            .json(&serde_json::json!({
+                "name": "loco",
+                "email": "test@loco.com",
+                "password": "12341234"
+            }))
+            .await;
+        let _provider = stub_provider(serde_json::json!({
+            "sub": "stub-1",
+            "email": "test@loco.com",
+            "email_verified": true,
+        }))
+        .await;
+
+        let state = authorize(&request).await;
+        let response = request.get(&callback_url(&state)).await;
+        assert_eq!(response.header("location"), "/auth/login");
+        let user = users::Model::find_by_email(&ctx.db, "test@loco.com")
+            .await
+            .unwrap();
+        assert!(user_identities::Model::find_by_user(&ctx.db, user.id)
+            .await
+            .unwrap()
+            .is_empty());
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn rejects_unexpected_state() {
+    testing::request::<App, _, _>(|mut request, ctx| async move {
+        request.save_cookies();
+        let _provider = stub_provider(serde_json::json!({
+            "sub": "stub-1",
+            "email": "new@loco.com",
+            "email_verified": true,
+        }))
+        .await;
+
+        authorize(&request).await;
+        let response = request.get(&callback_url("forged")).await;
+        assert_eq!(response.header("location"), "/auth/login");
+        assert!(users::Model::find_by_email(&ctx.db, "new@loco.com")
+            .await
+            .is_err());
+
+        let response = request.get("/auth/oauth2/unknown").await;
+        assert_eq!(response.status_code(), 404);
+    })
+    .await;
+}
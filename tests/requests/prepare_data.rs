@@ -1,6 +1,16 @@
 use axum::http::{HeaderName, HeaderValue};
-use commust::{models::users, views::auth::LoginResponse};
+use commust::{
+    models::{
+        _entities::{postmetas, products},
+        addresses::AddressParams,
+        orders::{self, OrderLine, PlaceOrderParams},
+        users,
+    },
+    views::auth::LoginResponse,
+};
 use loco_rs::{app::AppContext, TestServer};
+use sea_orm::{ActiveModelTrait, ActiveValue};
+use totp_rs::{Algorithm, Secret, TOTP};
 
 const USER_EMAIL: &str = "test@loco.com";
 const USER_PASSWORD: &str = "1234";
@@ -8,6 +18,7 @@ const USER_PASSWORD: &str = "1234";
 pub struct LoggedInUser {
     pub user: users::Model,
     pub token: String,
+    pub refresh_token: String,
 }
 
 pub async fn init_user_login(request: &TestServer, ctx: &AppContext) -> LoggedInUser {
@@ -47,6 +58,7 @@ pub async fn init_user_login(request: &TestServer, ctx: &AppContext) -> LoggedIn
             .await
             .unwrap(),
         token: login_response.token,
+        refresh_token: login_response.refresh_token,
     }
 }
 
@@ -55,3 +67,77 @@ pub fn auth_header(token: &str) -> (HeaderName, HeaderValue) {
 
     (HeaderName::from_static("authorization"), auth_header_value)
 }
+
+/// Current code of an authenticator app set up with the given secret
+pub fn totp_code(secret: &str) -> String {
+    let secret = Secret::Encoded(secret.to_string()).to_bytes().unwrap();
+    TOTP::new(Algorithm::SHA1, 6, 0, 30, secret, None, String::new())
+        .unwrap()
+        .generate_current()
+        .unwrap()
+}
+
+/// Creates a simple product in stock, authored by the first seeded user
+pub async fn create_product(
+    db: &sea_orm::DatabaseConnection,
+    slug: &str,
+    price: f32,
+) -> products::Model {
+    let product = products::ActiveModel {
+        title: ActiveValue::set(slug.replace('-', " ")),
+        slug: ActiveValue::set(Some(slug.to_string())),
+        product_type: ActiveValue::set(Some("simple".to_string())),
+        status: ActiveValue::set(Some("publish".to_string())),
+        author_id: ActiveValue::set(1),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    for (key, value) in [
+        ("_regular_price", price.to_string()),
+        ("_stock_status", "instock".to_string()),
+        ("_stock", "10".to_string()),
+    ] {
+        postmetas::ActiveModel {
+            meta_key: ActiveValue::set(Some(key.to_string())),
+            meta_value: ActiveValue::set(Some(value)),
+            product_id: ActiveValue::set(product.id),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap();
+    }
+
+    product
+}
+
+/// Places a guest order of one unit of the product
+pub async fn place_order(
+    db: &sea_orm::DatabaseConnection,
+    product: &products::Model,
+) -> orders::Model {
+    let params = PlaceOrderParams {
+        email: "guest@example.com".to_string(),
+        billing: AddressParams {
+            first_name: "Ada".to_string(),
+            last_name: "Lovelace".to_string(),
+            address_1: "1 main street".to_string(),
+            city: "New York".to_string(),
+            postcode: "10001".to_string(),
+            country: "US".to_string(),
+            ..Default::default()
+        },
+        lines: vec![OrderLine {
+            product_id: product.id,
+            name: product.title.clone(),
+            quantity: 1,
+            price: 9.0,
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    orders::Model::place(db, &params).await.unwrap()
+}
@@ -0,0 +1,663 @@
+use commust::{
+    app::App,
+    mailers::delivery,
+    models::{
+        bundle_items, product_revisions,
+        products::{self, ProductStatus},
+        stock_movements, users,
+    },
+};
+use loco_rs::testing;
+use sea_orm::{ActiveModelTrait, ActiveValue, EntityTrait, IntoActiveModel, QueryOrder};
+use serial_test::serial;
+
+use super::prepare_data;
+
+#[tokio::test]
+#[serial]
+async fn only_published_products_are_shown() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+
+        let page = request.get("/products").await.text();
+        assert!(page.contains("Rust cookbook"));
+        assert!(!page.contains("Crab mug"));
+        let response = request.get("/products/p/rust-cookbook").await;
+        assert_eq!(response.status_code(), 200);
+        let response = request.get("/products/p/crab-mug").await;
+        assert_eq!(response.status_code(), 404);
+
+        // the draft mug is only shown by id to the staff
+        let response = request.get("/products/6").await;
+        assert_eq!(response.status_code(), 200);
+        let response = request.get("/products/7").await;
+        assert_eq!(response.status_code(), 404);
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login_data.token);
+        let response = request
+            .get("/products/7")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 404);
+        let mut user = login_data.user.into_active_model();
+        user.role = ActiveValue::set(users::ROLE_SHOP_MANAGER.to_string());
+        user.update(&ctx.db).await.unwrap();
+        let response = request
+            .get("/products/7")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn removed_products_go_to_the_trash_first() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login_data.token);
+
+        // only the staff trash and restore products
+        let response = request.delete("/products/7").await;
+        assert_eq!(response.status_code(), 401);
+        let response = request.post("/products/7/restore").await;
+        assert_eq!(response.status_code(), 401);
+        let response = request
+            .delete("/products/7")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 403);
+        let response = request
+            .get("/admin/products?status=trash")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 403);
+        let mut user = login_data.user.into_active_model();
+        user.role = ActiveValue::set(users::ROLE_SHOP_MANAGER.to_string());
+        user.update(&ctx.db).await.unwrap();
+
+        let response = request
+            .delete("/products/7")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let mug = products::Entity::find_by_id(7)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(mug.status(), ProductStatus::Trash);
+        let response = request
+            .get("/admin/products?status=trash")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert!(response.text().contains("Crab mug"));
+        let page = request
+            .get("/admin/products")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .text();
+        assert!(page.contains("Rust hoodie"));
+        assert!(!page.contains("Crab mug"));
+
+        let response = request
+            .post("/products/7/restore")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.header("location"), "/admin/products?status=trash");
+        let mug = products::Entity::find_by_id(7)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(mug.status(), ProductStatus::Draft);
+
+        request
+            .delete("/products/7")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        let response = request
+            .delete("/products/7")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert!(products::Entity::find_by_id(7)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .is_none());
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn only_managers_update_products() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login_data.token);
+        let form = serde_json::json!({
+            "title": "Crab mug",
+            "slug": "free-mug",
+            "status": "publish",
+            "_regular_price": "1",
+            "_sale_price": "",
+            "_stock": "100",
+        });
+
+        let response = request.post("/products/7").form(&form).await;
+        assert_eq!(response.status_code(), 401);
+        let response = request
+            .post("/products/7")
+            .add_header(auth_key, auth_value)
+            .form(&form)
+            .await;
+        assert_eq!(response.status_code(), 403);
+        let mug = products::Entity::find_by_id(7)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(mug.status(), ProductStatus::Draft);
+        assert_eq!(mug.slug.as_deref(), Some("crab-mug"));
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn saves_make_revisions_which_can_be_restored() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login_data.token);
+        let form = |title: &str| {
+            serde_json::json!({
+                "title": title,
+                "status": "publish",
+                "product_type": "simple",
+                "_sku": "HOODIE",
+                "_regular_price": "45",
+                "_sale_price": "39",
+                "_stock": "25",
+            })
+        };
+        let response = request
+            .get("/admin/products/1/revisions")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 403);
+        let mut user = login_data.user.clone().into_active_model();
+        user.role = ActiveValue::set(users::ROLE_SHOP_MANAGER.to_string());
+        user.update(&ctx.db).await.unwrap();
+
+        for title in ["Rust hoodie", "Rust zip hoodie"] {
+            request
+                .post("/products/1")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .form(&form(title))
+                .await;
+        }
+        let revisions = product_revisions::Model::list_for_product(&ctx.db, 1)
+            .await
+            .unwrap();
+        assert_eq!(revisions.len(), 2);
+        let (latest, author) = &revisions[0];
+        assert_eq!(latest.title, "Rust zip hoodie");
+        assert_eq!(author.as_ref().unwrap().id, login_data.user.id);
+        // the product keeps its author
+        let hoodie = products::Entity::find_by_id(1)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(hoodie.author_id, 1);
+        let first = revisions[1].0.id;
+
+        let url = format!("/admin/products/1/revisions/{}", latest.id);
+        let response = request
+            .get("/admin/products/1/revisions")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert!(response.text().contains("Rust zip hoodie"));
+        let page = request
+            .get(&url)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .text();
+        assert!(page.contains("Rust hoodie") && page.contains("Rust zip hoodie"));
+        let response = request
+            .get("/admin/products/2/revisions/1")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 404);
+
+        let response = request
+            .post(&format!("/admin/products/1/revisions/{first}/restore"))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.header("location"), "/admin/products/1/revisions");
+        let hoodie = products::Entity::find_by_id(1)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(hoodie.title, "Rust hoodie");
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn renamed_slugs_redirect_to_the_current_one() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login_data.token);
+        let mut user = login_data.user.clone().into_active_model();
+        user.role = ActiveValue::set(users::ROLE_SHOP_MANAGER.to_string());
+        user.update(&ctx.db).await.unwrap();
+        let form = |slug: &str| {
+            serde_json::json!({
+                "title": "Rust hoodie",
+                "slug": slug,
+                "status": "publish",
+                "_regular_price": "45",
+                "_sale_price": "39",
+                "_stock": "25",
+            })
+        };
+
+        request
+            .post("/products/1")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&form(""))
+            .await;
+        let response = request.get("/products/p/rust-hoodie").await;
+        assert_eq!(response.status_code(), 200);
+
+        request
+            .post("/products/1")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&form("Zip hoodie"))
+            .await;
+        let response = request.get("/products/p/rust-hoodie").await;
+        assert_eq!(response.status_code(), 301);
+        assert_eq!(response.header("location"), "/products/p/zip-hoodie");
+        let response = request.get("/products/p/zip-hoodie").await;
+        assert_eq!(response.status_code(), 200);
+
+        // a new product cannot take the former slug of the hoodie
+        let zip_hoodie = serde_json::json!({
+            "title": "Zip hoodie",
+            "slug": "rust-hoodie",
+            "status": "publish",
+            "_regular_price": "45",
+            "_sale_price": "",
+            "_stock": "",
+        });
+        let response = request.post("/products").form(&zip_hoodie).await;
+        assert_eq!(response.status_code(), 401);
+        let response = request
+            .post("/products")
+            .add_header(auth_key, auth_value)
+            .form(&zip_hoodie)
+            .await;
+        assert_eq!(response.status_code(), 303);
+        let zip_hoodie = products::Entity::find()
+            .order_by_desc(products::Column::Id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(zip_hoodie.author_id, login_data.user.id);
+        let response = request.get("/products/p/rust-hoodie-2").await;
+        assert_eq!(response.status_code(), 200);
+        let response = request.get("/products/p/zip-hoodie-2").await;
+        assert_eq!(response.status_code(), 404);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn grouped_and_external_products_are_sold_apart() {
+    testing::request::<App, _, _>(|mut request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        request.save_cookies();
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login_data.token);
+        let mut user = login_data.user.into_active_model();
+        user.role = ActiveValue::set(users::ROLE_SHOP_MANAGER.to_string());
+        user.update(&ctx.db).await.unwrap();
+        let form = |title: &str, product_type: &str, extra: serde_json::Value| {
+            let mut form = serde_json::json!({
+                "title": title,
+                "status": "publish",
+                "product_type": product_type,
+                "_regular_price": "",
+                "_sale_price": "",
+                "_stock": "",
+            });
+            form.as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            form
+        };
+
+        // the variable t-shirt cannot be part of a grouped product
+        let response = request
+            .post("/products/7")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&form(
+                "Crab mug",
+                products::TYPE_GROUPED,
+                serde_json::json!({ "_children": "1,2" }),
+            ))
+            .await;
+        assert_eq!(response.header("location"), "/products/7/edit");
+        let mug = products::Entity::find_by_id(7)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(mug.product_type.as_deref(), Some(products::TYPE_SIMPLE));
+
+        request
+            .post("/products/7")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&form(
+                "Crab set",
+                products::TYPE_GROUPED,
+                serde_json::json!({ "_children": "6,1" }),
+            ))
+            .await;
+        let page = request.get("/products/p/crab-mug").await.text();
+        assert!(page.contains("quantity[6]") && page.contains("quantity[1]"));
+        assert!(page.find("Rust cookbook") < page.find("Rust hoodie"));
+
+        // a grouped product is not bought itself but through its products
+        let response = request
+            .post("/cart/add-item")
+            .form(&serde_json::json!({ "id": 7, "qty": 1, "slug": "crab-mug" }))
+            .await;
+        assert_eq!(response.header("location"), "/products/p/crab-mug");
+        assert!(!request.get("/cart").await.text().contains("Crab set"));
+        let response = request
+            .post("/cart/add-items")
+            .form(&serde_json::json!({
+                "slug": "crab-mug",
+                "quantity[1]": "2",
+                "quantity[6]": "0",
+            }))
+            .await;
+        assert_eq!(response.header("location"), "/products/p/crab-mug");
+        let cart = request.get("/cart").await.text();
+        assert!(cart.contains("Rust hoodie &times; 2"));
+        assert!(!cart.contains("Rust cookbook"));
+
+        let response = request
+            .post("/products/6")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&form(
+                "Rust cookbook",
+                products::TYPE_EXTERNAL,
+                serde_json::json!({ "_product_url": "javascript:alert(1)" }),
+            ))
+            .await;
+        assert_eq!(response.header("location"), "/products/6/edit");
+        request
+            .post("/products/6")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&form(
+                "Rust cookbook",
+                products::TYPE_EXTERNAL,
+                serde_json::json!({
+                    "_product_url": "https://books.example.com/rust",
+                    "_button_text": "Buy at the bookshop",
+                }),
+            ))
+            .await;
+        let page = request.get("/products/p/rust-cookbook").await.text();
+        assert!(page.contains("https://books.example.com/rust"));
+        assert!(page.contains("Buy at the bookshop"));
+        assert!(!page.contains("/cart/add-item"));
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn bundles_are_priced_and_stocked_by_their_products() {
+    testing::request::<App, _, _>(|mut request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        request.save_cookies();
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login_data.token);
+        let kit = serde_json::json!({
+            "title": "Rust kit",
+            "status": "publish",
+            "product_type": products::TYPE_BUNDLE,
+            "_regular_price": "",
+            "_sale_price": "",
+            "_stock": "",
+            "_bundle_pricing": "discount",
+            "_bundle_discount": "10",
+        });
+
+        // only the staff add products and change what a bundle is made of
+        let response = request
+            .post("/products")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&kit)
+            .await;
+        assert_eq!(response.status_code(), 403);
+        let response = request
+            .post("/products/1/bundle-items")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&serde_json::json!({ "product_id": 2, "quantity": "1" }))
+            .await;
+        assert_eq!(response.status_code(), 403);
+        let mut user = login_data.user.into_active_model();
+        user.role = ActiveValue::set(users::ROLE_SHOP_MANAGER.to_string());
+        user.update(&ctx.db).await.unwrap();
+        request
+            .post("/products")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&kit)
+            .await;
+        let kit = products::Entity::find()
+            .all(&ctx.db)
+            .await
+            .unwrap()
+            .pop()
+            .unwrap();
+        let url = format!("/products/{}/bundle-items", kit.id);
+
+        // an empty bundle cannot be bought
+        let add = serde_json::json!({ "id": kit.id, "qty": 1, "slug": "rust-kit" });
+        request.post("/cart/add-item").form(&add).await;
+        assert!(request
+            .get("/products/p/rust-kit")
+            .await
+            .text()
+            .contains("This bundle has no products yet"));
+
+        let form = serde_json::json!({ "product_id": 1, "quantity": "2" });
+        let response = request.post(&url).form(&form).await;
+        assert_eq!(response.status_code(), 401);
+
+        for form in [
+            serde_json::json!({ "product_id": 2, "quantity": "1" }),
+            serde_json::json!({ "product_id": 1, "quantity": "2" }),
+            serde_json::json!({ "product_id": 6, "quantity": "1", "optional": "on" }),
+        ] {
+            request
+                .post(&url)
+                .add_header(auth_key.clone(), auth_value.clone())
+                .form(&form)
+                .await;
+        }
+        let bundle = bundle_items::Model::find_by_bundle(&ctx.db, kit.id)
+            .await
+            .unwrap();
+        assert_eq!(bundle.len(), 2, "the variable t-shirt is not bundled");
+        let cookbook = bundle[1].0.id;
+
+        // two hoodies on sale at 39, less 10%
+        let page = request.get("/products/p/rust-kit").await.text();
+        assert!(page.contains("70.2"));
+        assert!(page.contains(&format!("option[{cookbook}]")));
+
+        request
+            .post("/cart/add-bundle")
+            .form(&serde_json::json!({
+                "id": kit.id,
+                "qty": "1",
+                "slug": "rust-kit",
+                format!("option[{cookbook}]"): "on",
+            }))
+            .await;
+        request.post("/cart/add-item").form(&add).await;
+        let cart = request.get("/cart").await.text();
+        assert!(cart.contains("Rust kit (with Rust cookbook × 1) &times; 1"));
+        assert!(cart.contains(&f64::from(70.2_f32 + 13.5).to_string()));
+        assert!(cart.contains("Rust kit &times; 1"));
+
+        // with the 4 hoodies of the kits, the 25 in stock are all in the cart
+        let add_hoodie = serde_json::json!({ "id": 1, "qty": 21, "slug": "rust-hoodie" });
+        request.post("/cart/add-item").form(&add_hoodie).await;
+        assert!(request
+            .get("/cart")
+            .await
+            .text()
+            .contains("Rust hoodie &times; 21"));
+        request.post("/cart/add-item").form(&add).await;
+        assert!(request
+            .get("/products/p/rust-kit")
+            .await
+            .text()
+            .contains("A product of this bundle is not available in the requested quantity"));
+        assert!(request
+            .get("/cart")
+            .await
+            .text()
+            .contains("Rust kit &times; 1"));
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn stock_changes_alert_the_staff_and_subscribers() {
+    testing::request::<App, _, _>(|mut request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        request.save_cookies();
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login_data.token);
+        let response = request
+            .get("/admin/products/low-stock")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 403);
+        let mut user = login_data.user.clone().into_active_model();
+        user.role = ActiveValue::set(users::ROLE_SHOP_MANAGER.to_string());
+        user.update(&ctx.db).await.unwrap();
+        let product = prepare_data::create_product(&ctx.db, "loco-sticker", 2.5).await;
+        let url = format!("/products/{}", product.id);
+        let save = |stock: &str| {
+            request
+                .post(&url)
+                .add_header(auth_key.clone(), auth_value.clone())
+                .form(&serde_json::json!({
+                    "title": "loco sticker",
+                    "status": "publish",
+                    "product_type": "simple",
+                    "_regular_price": "2.5",
+                    "_sale_price": "",
+                    "_stock": stock,
+                    "_low_stock_amount": "3",
+                }))
+        };
+        let mailbox = || async {
+            for _ in 0..50 {
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                if !delivery::mailbox().is_empty() {
+                    break;
+                }
+            }
+            let subjects: Vec<(String, String)> = delivery::mailbox()
+                .into_iter()
+                .map(|email| (email.to, email.subject))
+                .collect();
+            delivery::clear_mailbox();
+            subjects
+        };
+        let admin = |subject: &str| ("admin@example.com".to_string(), subject.to_string());
+        delivery::clear_mailbox();
+
+        save("3").await;
+        assert_eq!(mailbox().await, vec![admin("Low stock: loco sticker")]);
+        save("0").await;
+        assert_eq!(mailbox().await, vec![admin("Out of stock: loco sticker")]);
+
+        let page = request.get("/products/p/loco-sticker").await.text();
+        assert!(page.contains("Email me when it is back in stock"));
+        request
+            .post(&format!("{url}/stock-subscriptions"))
+            .form(&serde_json::json!({ "email": "ada@example.com" }))
+            .await;
+        let page = request.get("/products/p/loco-sticker").await.text();
+        assert!(page.contains("We will email you once it is back in stock."));
+
+        // the staff find it in the low stock report
+        let page = request
+            .get("/admin/products/low-stock")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await
+            .text();
+        assert!(page.contains("loco sticker"));
+        assert!(page.contains("Ferris t-shirt - M"));
+        assert!(!page.contains("Rust hoodie"));
+
+        save("12").await;
+        assert_eq!(
+            mailbox().await,
+            vec![(
+                "ada@example.com".to_string(),
+                "loco sticker is back in stock".to_string()
+            )]
+        );
+
+        // every save moved the stock through the ledger
+        let movements: Vec<(String, f32, Option<i32>)> =
+            stock_movements::Model::list_for_product(&ctx.db, product.id)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|(movement, _)| (movement.reason, movement.quantity, movement.user_id))
+                .collect();
+        let manual = |quantity: f32| {
+            (
+                stock_movements::REASON_MANUAL.to_string(),
+                quantity,
+                Some(login_data.user.id),
+            )
+        };
+        assert_eq!(
+            movements,
+            vec![
+                manual(12.0),
+                manual(-3.0),
+                manual(-7.0),
+                (stock_movements::REASON_INITIAL.to_string(), 10.0, None),
+            ]
+        );
+    })
+    .await;
+}
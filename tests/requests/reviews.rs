@@ -0,0 +1,150 @@
+use commust::{
+    app::App,
+    models::{api_keys, reviews, users},
+};
+use loco_rs::testing;
+use sea_orm::{ActiveModelTrait, ActiveValue, EntityTrait, IntoActiveModel, PaginatorTrait};
+use serial_test::serial;
+
+use super::prepare_data;
+
+#[tokio::test]
+#[serial]
+async fn customers_review_and_managers_moderate() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login_data.token);
+
+        let response = request
+            .post("/products/1/reviews")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&serde_json::json!({"rating": 0, "content": "Meh."}))
+            .await;
+        assert_eq!(response.header("location"), "/products/1");
+        assert_eq!(reviews::Entity::find().count(&ctx.db).await.unwrap(), 0);
+
+        let response = request
+            .post("/products/1/reviews")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&serde_json::json!({"rating": 4, "content": "Warm and soft hoodie."}))
+            .await;
+        assert_eq!(response.header("location"), "/products/1");
+        let review = reviews::Entity::find().one(&ctx.db).await.unwrap().unwrap();
+        assert_eq!(review.status, reviews::STATUS_PENDING);
+        assert!(!review.verified);
+        let response = request.get("/products/1").await;
+        assert!(!response.text().contains("Warm and soft hoodie."));
+
+        let response = request
+            .get("/admin/reviews")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 403);
+
+        let mut user = login_data.user.into_active_model();
+        user.role = ActiveValue::set(users::ROLE_SHOP_MANAGER.to_string());
+        user.update(&ctx.db).await.unwrap();
+
+        let response = request
+            .get("/admin/reviews")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert!(response.text().contains("Warm and soft hoodie."));
+
+        let response = request
+            .post(&format!("/admin/reviews/{}/status", review.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&serde_json::json!({"status": "trash"}))
+            .await;
+        assert_eq!(response.status_code(), 400);
+        let response = request
+            .post(&format!("/admin/reviews/{}/status", review.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&serde_json::json!({"status": reviews::STATUS_APPROVED}))
+            .await;
+        assert_eq!(response.header("location"), "/admin/reviews");
+
+        let response = request.get("/products/1").await;
+        let page = response.text();
+        assert!(page.contains("Warm and soft hoodie."));
+        assert!(page.contains("Rated 4 out of 5 from 1 review."));
+
+        // the rated hoodie comes before the latest products
+        let page = request.get("/products").await.text();
+        assert!(page.find("Rust cookbook").unwrap() < page.find("Rust hoodie").unwrap());
+        let page = request.get("/products?orderby=rating").await.text();
+        assert!(page.find("Rust hoodie").unwrap() < page.find("Rust cookbook").unwrap());
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn reviews_have_json_endpoints() {
+    testing::request::<App, _, _>(|request, ctx| async move {
+        testing::seed::<App>(&ctx.db).await.unwrap();
+        let user = prepare_data::init_user_login(&request, &ctx).await;
+        let (_, read) =
+            api_keys::Model::generate(&ctx.db, user.user.id, "read", api_keys::SCOPE_READ)
+                .await
+                .unwrap();
+        let (_, write) =
+            api_keys::Model::generate(&ctx.db, user.user.id, "write", api_keys::SCOPE_WRITE)
+                .await
+                .unwrap();
+        let review = serde_json::json!({"rating": 5, "content": "Best mug."});
+
+        let response = request.get("/api/products/7/reviews").await;
+        assert_eq!(response.status_code(), 200);
+        assert_eq!(
+            response.json::<serde_json::Value>(),
+            serde_json::json!({"average_rating": 0.0, "review_count": 0, "reviews": []})
+        );
+        let response = request.get("/api/products/999/reviews").await;
+        assert_eq!(response.status_code(), 404);
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&read);
+        let response = request
+            .post("/api/products/7/reviews")
+            .add_header(auth_key, auth_value)
+            .json(&review)
+            .await;
+        assert_eq!(response.status_code(), 403);
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&write);
+        let response = request
+            .post("/api/products/7/reviews")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&serde_json::json!({"rating": 9, "content": "Best mug."}))
+            .await;
+        assert_eq!(response.status_code(), 400);
+        let response = request
+            .post("/api/products/7/reviews")
+            .add_header(auth_key, auth_value)
+            .json(&review)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let created = response.json::<serde_json::Value>();
+        assert_eq!(created["status"], reviews::STATUS_PENDING);
+        assert_eq!(created["author"], "loco");
+
+        let id = created["id"].as_i64().unwrap() as i32;
+        reviews::Entity::find_by_id(id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap()
+            .moderate(&ctx.db, reviews::STATUS_APPROVED)
+            .await
+            .unwrap();
+        let response = request.get("/api/products/7/reviews").await;
+        let reviews = response.json::<serde_json::Value>();
+        assert_eq!(reviews["average_rating"], 5.0);
+        assert_eq!(reviews["review_count"], 1);
+        assert_eq!(reviews["reviews"][0]["content"], "Best mug.");
+        assert!(reviews["reviews"][0].get("email").is_none());
+    })
+    .await;
+}
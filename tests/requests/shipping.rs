@@ -0,0 +1,105 @@
+use commust::{
+    app::App,
+    models::{shipping_zones, users},
+};
+use loco_rs::testing;
+use sea_orm::{ActiveModelTrait, ActiveValue, IntoActiveModel};
+use serial_test::serial;
+
+use super::prepare_data;
+
+#[tokio::test]
+#[serial]
+async fn only_managers_can_set_up_shipping() {
+    testing::request::<App, _, _>(|mut request, ctx| async move {
+        request.save_cookies();
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login_data.token);
+
+        let response = request
+            .get("/admin/shipping")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 403);
+        let response = request
+            .post("/admin/shipping/zones")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&serde_json::json!({ "name": "Everywhere" }))
+            .await;
+        assert_eq!(response.status_code(), 403);
+        assert!(!shipping_zones::Model::any(&ctx.db).await.unwrap());
+
+        let mut user = login_data.user.into_active_model();
+        user.role = ActiveValue::set(users::ROLE_SHOP_MANAGER.to_string());
+        user.update(&ctx.db).await.unwrap();
+
+        let response = request
+            .post("/admin/shipping/zones")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&serde_json::json!({
+                "name": "Europe",
+                "position": "",
+                "countries": "fr, be",
+                "states": "",
+                "postcodes": "",
+            }))
+            .await;
+        assert_eq!(response.header("location"), "/admin/shipping");
+        let (zone, locations) = shipping_zones::Model::list(&ctx.db)
+            .await
+            .unwrap()
+            .pop()
+            .unwrap();
+        assert_eq!(zone.name, "Europe");
+        assert_eq!(
+            locations
+                .iter()
+                .map(|location| location.code.as_str())
+                .collect::<Vec<_>>(),
+            vec!["FR", "BE"]
+        );
+
+        let response = request
+            .post(&format!("/admin/shipping/zones/{}/methods", zone.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&serde_json::json!({
+                "kind": "table_rate",
+                "title": "By weight",
+                "cost": "",
+                "min_amount": "",
+                "rates": "1:4.5\n5:9",
+            }))
+            .await;
+        assert_eq!(response.header("location"), "/admin/shipping");
+        let response = request
+            .post(&format!("/admin/shipping/zones/{}/methods", zone.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&serde_json::json!({
+                "kind": "table_rate",
+                "title": "Broken",
+                "rates": "heavy:1",
+            }))
+            .await;
+        assert_eq!(response.header("location"), "/admin/shipping");
+        let methods = zone.methods(&ctx.db).await.unwrap();
+        assert_eq!(methods.len(), 1);
+        assert_eq!(methods[0].weight_rates().len(), 2);
+
+        let response = request
+            .get("/admin/shipping")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let page = response.text();
+        assert!(page.contains("By weight"));
+        assert!(page.contains("weights and costs must be numbers"));
+
+        let response = request
+            .post(&format!("/admin/shipping/zones/{}/delete", zone.id))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.header("location"), "/admin/shipping");
+        assert!(!shipping_zones::Model::any(&ctx.db).await.unwrap());
+    })
+    .await;
+}
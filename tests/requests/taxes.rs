@@ -0,0 +1,90 @@
+use commust::{
+    app::App,
+    models::{tax_rates, users},
+};
+use loco_rs::testing;
+use sea_orm::{ActiveModelTrait, ActiveValue, IntoActiveModel};
+use serial_test::serial;
+
+use super::prepare_data;
+
+#[tokio::test]
+#[serial]
+async fn only_managers_can_set_up_taxes() {
+    testing::request::<App, _, _>(|mut request, ctx| async move {
+        request.save_cookies();
+        let login_data = prepare_data::init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = prepare_data::auth_header(&login_data.token);
+        let form = serde_json::json!({
+            "tax_class": "standard",
+            "country": "us",
+            "state": "ny",
+            "postcode": "",
+            "name": "Sales tax",
+            "rate": "8.875",
+            "priority": "",
+            "shipping": "on",
+        });
+
+        let response = request
+            .get("/admin/taxes")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 403);
+        let response = request
+            .post("/admin/taxes/rates")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&form)
+            .await;
+        assert_eq!(response.status_code(), 403);
+        assert!(tax_rates::Model::list(&ctx.db).await.unwrap().is_empty());
+
+        let mut user = login_data.user.into_active_model();
+        user.role = ActiveValue::set(users::ROLE_SHOP_MANAGER.to_string());
+        user.update(&ctx.db).await.unwrap();
+
+        let response = request
+            .post("/admin/taxes/rates")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&form)
+            .await;
+        assert_eq!(response.header("location"), "/admin/taxes");
+        let response = request
+            .post("/admin/taxes/rates")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .form(&serde_json::json!({
+                "tax_class": "reduced",
+                "state": "CA",
+                "name": "California",
+                "rate": "7.25",
+            }))
+            .await;
+        assert_eq!(response.header("location"), "/admin/taxes");
+
+        let rates = tax_rates::Model::list(&ctx.db).await.unwrap();
+        assert_eq!(rates.len(), 1);
+        assert_eq!(rates[0].country.as_deref(), Some("US"));
+        assert_eq!(rates[0].state.as_deref(), Some("NY"));
+        assert_eq!(rates[0].postcode, None);
+        assert_eq!(rates[0].priority, 1);
+        assert!(rates[0].shipping);
+        assert!(!rates[0].compound);
+
+        let response = request
+            .get("/admin/taxes")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let page = response.text();
+        assert!(page.contains("Sales tax"));
+        assert!(page.contains("a state needs a country"));
+
+        let response = request
+            .post(&format!("/admin/taxes/rates/{}/delete", rates[0].id))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.header("location"), "/admin/taxes");
+        assert!(tax_rates::Model::list(&ctx.db).await.unwrap().is_empty());
+    })
+    .await;
+}
@@ -1 +1,2 @@
+pub mod product_csv;
 pub mod seed;
@@ -0,0 +1,327 @@
+use commust::{
+    app::App,
+    common::product_csv,
+    models::{
+        _entities::{postmetas, products},
+        users,
+    },
+};
+use loco_rs::{boot::run_task, task, testing};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue, ColumnTrait, EntityTrait, IntoActiveModel, PaginatorTrait,
+    QueryFilter,
+};
+use serial_test::serial;
+
+const CSV: &str = "\
+Type,SKU,Name,Slug,Published,Regular price,Sale price,Stock,Categories,Images,Parent,Attribute 1 name,Attribute 1 value(s)
+\"simple, virtual\",EBOOK,Loco ebook,loco-ebook,1,9,,,\"Books, Books > Rust\",https://example.com/ebook.png,,,
+variable,TSHIRT,Loco t-shirt,,1,20,,,Clothing,,,Size,\"S, M\"
+variation,TSHIRT-S,Loco t-shirt S,,1,20,15,4,,,TSHIRT,Size,S
+variation,TSHIRT-XL,Loco t-shirt XL,,1,20,,,,,MUG,Size,XL
+gift,CARD,Gift card,,1,10,,,,,,,
+simple,MUG,,,1,10,,,,,,,
+simple,PEN,Pen,,1,cheap,,,,,,,
+";
+
+/// The first seeded user, made a shop manager to author the products
+async fn manager(db: &sea_orm::DatabaseConnection) -> users::Model {
+    let mut user = users::Entity::find_by_id(1)
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap()
+        .into_active_model();
+    user.role = ActiveValue::set(users::ROLE_SHOP_MANAGER.to_string());
+    user.update(db).await.unwrap()
+}
+
+async fn find_by_sku(db: &sea_orm::DatabaseConnection, sku: &str) -> Option<products::Model> {
+    let meta = postmetas::Entity::find()
+        .filter(postmetas::Column::MetaKey.eq("_sku"))
+        .filter(postmetas::Column::MetaValue.eq(sku))
+        .one(db)
+        .await
+        .unwrap()?;
+    products::Entity::find_by_id(meta.product_id)
+        .one(db)
+        .await
+        .unwrap()
+}
+
+async fn meta(db: &sea_orm::DatabaseConnection, product_id: i32, key: &str) -> Option<String> {
+    postmetas::Entity::find()
+        .filter(postmetas::Column::ProductId.eq(product_id))
+        .filter(postmetas::Column::MetaKey.eq(key))
+        .one(db)
+        .await
+        .unwrap()
+        .and_then(|meta| meta.meta_value)
+}
+
+#[tokio::test]
+#[serial]
+async fn can_dry_run_and_import_products() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    let author = manager(&ctx.db).await;
+    let seeded = products::Entity::find().count(&ctx.db).await.unwrap();
+
+    let report = product_csv::import(ctx, CSV.as_bytes(), true, &author)
+        .await
+        .unwrap();
+    assert_eq!(report.created, 3);
+    assert_eq!(report.updated, 0);
+    let errors: Vec<(u64, &str)> = report
+        .errors
+        .iter()
+        .map(|error| (error.line, error.message.as_str()))
+        .collect();
+    assert_eq!(errors[0], (5, "parent MUG is not a variable product"));
+    assert_eq!(errors[1], (6, "unknown type gift"));
+    assert_eq!(errors[2], (7, "Name is required"));
+    assert_eq!(errors[3].0, 8);
+    assert_eq!(
+        products::Entity::find().count(&ctx.db).await.unwrap(),
+        seeded
+    );
+
+    let report = product_csv::import(ctx, CSV.as_bytes(), false, &author)
+        .await
+        .unwrap();
+    assert_eq!(report.created, 3);
+    assert_eq!(report.errors.len(), 4);
+
+    let ebook = find_by_sku(&ctx.db, "EBOOK").await.unwrap();
+    assert_eq!(ebook.slug.as_deref(), Some("loco-ebook"));
+    assert_eq!(ebook.status.as_deref(), Some("publish"));
+    assert_eq!(ebook.author_id, author.id);
+    assert_eq!(
+        meta(&ctx.db, ebook.id, "_virtual").await.as_deref(),
+        Some("yes")
+    );
+    assert_eq!(
+        meta(&ctx.db, ebook.id, "_regular_price").await.as_deref(),
+        Some("9")
+    );
+
+    let tshirt = find_by_sku(&ctx.db, "TSHIRT").await.unwrap();
+    let small = find_by_sku(&ctx.db, "TSHIRT-S").await.unwrap();
+    assert_eq!(small.product_type.as_deref(), Some("variation"));
+    assert_eq!(
+        meta(&ctx.db, small.id, "_parent_id").await,
+        Some(tshirt.id.to_string())
+    );
+    assert_eq!(
+        meta(&ctx.db, small.id, "_sale_price").await.as_deref(),
+        Some("15")
+    );
+    assert_eq!(
+        meta(&ctx.db, small.id, "_stock").await.as_deref(),
+        Some("4")
+    );
+
+    // a SKU already in the store is updated, and a missing sale price removed
+    let update = "\
+Type,SKU,Name,Published,Regular price,Sale price,Parent
+variation,TSHIRT-S,Loco t-shirt small,0,22,,TSHIRT
+";
+    let report = product_csv::import(ctx, update.as_bytes(), false, &author)
+        .await
+        .unwrap();
+    assert_eq!((report.created, report.updated), (0, 1));
+    let small = find_by_sku(&ctx.db, "TSHIRT-S").await.unwrap();
+    assert_eq!(small.title, "Loco t-shirt small");
+    assert_eq!(small.status.as_deref(), Some("draft"));
+    assert_eq!(
+        meta(&ctx.db, small.id, "_regular_price").await.as_deref(),
+        Some("22")
+    );
+    assert_eq!(meta(&ctx.db, small.id, "_sale_price").await, None);
+    assert_eq!(
+        products::Entity::find().count(&ctx.db).await.unwrap(),
+        seeded + 3
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn can_export_and_import_back_products() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    let author = manager(&ctx.db).await;
+    let seeded = products::Entity::find().count(&ctx.db).await.unwrap();
+    product_csv::import(ctx, CSV.as_bytes(), false, &author)
+        .await
+        .unwrap();
+    // trashed products are left out
+    products::Entity::find_by_id(7)
+        .one(&ctx.db)
+        .await
+        .unwrap()
+        .unwrap()
+        .trash(&ctx.db)
+        .await
+        .unwrap();
+
+    std::fs::create_dir_all("tmp").unwrap();
+    let file = "tmp/products_export.csv".to_string();
+    let vars = task::Vars::from_cli_args(vec![("file".to_string(), file.clone())]);
+    run_task::<App>(ctx, Some(&"product_export".to_string()), &vars)
+        .await
+        .unwrap();
+    let exported = std::fs::read_to_string(&file).unwrap();
+    assert!(exported.starts_with("Type,SKU,Name,Slug,Published,"));
+    assert!(exported.contains("\"simple, virtual\",EBOOK,Loco ebook,loco-ebook,1,"));
+    assert!(exported.contains("\"Books, Books > Rust\""));
+    assert!(exported.contains(",TSHIRT,,,,,,,,Size,S"));
+    assert!(!exported.contains("Crab mug"));
+
+    // importing the export back updates every product, the seeded ones too
+    let report = product_csv::import(ctx, exported.as_bytes(), false, &author)
+        .await
+        .unwrap();
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    assert_eq!(report.created, 0);
+    assert_eq!(report.updated as u64, seeded + 2);
+
+    let import = |author: Option<&str>| {
+        let mut vars = vec![
+            ("file".to_string(), file.clone()),
+            ("dry_run".to_string(), "true".to_string()),
+        ];
+        if let Some(author) = author {
+            vars.push(("author".to_string(), author.to_string()));
+        }
+        let vars = task::Vars::from_cli_args(vars);
+        async move { run_task::<App>(ctx, Some(&"product_import".to_string()), &vars).await }
+    };
+    assert!(import(Some(&author.email)).await.is_ok());
+    // new products need a shop manager to author them
+    assert!(import(None).await.is_err());
+    let customer = users::Entity::find_by_id(2)
+        .one(&ctx.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(import(Some(&customer.email)).await.is_err());
+}
+
+#[tokio::test]
+#[serial]
+async fn can_import_grouped_and_external_products() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    let author = manager(&ctx.db).await;
+    let csv = "\
+Type,SKU,Name,Published,Regular price,Grouped products,External URL,Button text
+simple,PEN,Pen,1,2,,,
+grouped,SET,Desk set,1,,\"PEN, HOODIE, id:6\",,
+external,BOOK,Rust book,1,30,,https://books.example.com/rust,Buy at the bookshop
+grouped,BAD,Bad set,1,,\"PEN, id:2\",,
+external,LINK,Bad link,1,,,books.example.com,
+simple,KID,Kid,1,2,PEN,,
+";
+
+    let report = product_csv::import(ctx, csv.as_bytes(), false, &author)
+        .await
+        .unwrap();
+    assert_eq!(report.created, 3);
+    let lines: Vec<u64> = report.errors.iter().map(|error| error.line).collect();
+    assert_eq!(lines, vec![5, 6, 7]);
+
+    let pen = find_by_sku(&ctx.db, "PEN").await.unwrap();
+    let set = find_by_sku(&ctx.db, "SET").await.unwrap();
+    assert_eq!(set.product_type.as_deref(), Some("grouped"));
+    assert_eq!(
+        meta(&ctx.db, set.id, "_children").await,
+        Some(format!("{},1,6", pen.id))
+    );
+    let book = find_by_sku(&ctx.db, "BOOK").await.unwrap();
+    assert_eq!(
+        meta(&ctx.db, book.id, "_product_url").await.as_deref(),
+        Some("https://books.example.com/rust")
+    );
+    assert_eq!(
+        meta(&ctx.db, book.id, "_button_text").await.as_deref(),
+        Some("Buy at the bookshop")
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn can_import_bundle_pricing() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    let author = manager(&ctx.db).await;
+    let csv = "\
+Type,SKU,Name,Published,Regular price,Bundle pricing,Bundle discount
+bundle,KIT,Rust kit,1,,discount,15
+bundle,BOX,Gift box,1,50,fixed,
+simple,PEN,Pen,1,2,discount,
+bundle,BAD,Bad kit,1,,free,
+bundle,BIG,Big kit,1,,discount,120
+";
+
+    let report = product_csv::import(ctx, csv.as_bytes(), false, &author)
+        .await
+        .unwrap();
+    assert_eq!(report.created, 2);
+    let lines: Vec<u64> = report.errors.iter().map(|error| error.line).collect();
+    assert_eq!(lines, vec![4, 5, 6]);
+
+    let kit = find_by_sku(&ctx.db, "KIT").await.unwrap();
+    assert_eq!(
+        meta(&ctx.db, kit.id, "_bundle_pricing").await.as_deref(),
+        Some("discount")
+    );
+    assert_eq!(
+        meta(&ctx.db, kit.id, "_bundle_discount").await.as_deref(),
+        Some("15")
+    );
+    let gift_box = find_by_sku(&ctx.db, "BOX").await.unwrap();
+    assert_eq!(meta(&ctx.db, gift_box.id, "_bundle_discount").await, None);
+}
+
+#[tokio::test]
+#[serial]
+async fn can_import_backorders() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    let author = manager(&ctx.db).await;
+    let csv = "\
+Type,SKU,Name,Published,Regular price,Stock,Backorders allowed?
+simple,PEN,Pen,1,2,0,notify
+simple,INK,Ink,1,3,,1
+simple,PAD,Pad,1,4,0,
+simple,BAD,Bad,1,5,0,maybe
+";
+
+    let report = product_csv::import(ctx, csv.as_bytes(), false, &author)
+        .await
+        .unwrap();
+    assert_eq!(report.created, 3);
+    let lines: Vec<u64> = report.errors.iter().map(|error| error.line).collect();
+    assert_eq!(lines, vec![5]);
+
+    for (sku, backorders, stock_status) in [
+        ("PEN", "notify", "onbackorder"),
+        ("INK", "yes", "onbackorder"),
+        ("PAD", "no", "outofstock"),
+    ] {
+        let product = find_by_sku(&ctx.db, sku).await.unwrap();
+        assert_eq!(
+            meta(&ctx.db, product.id, "_backorders").await.as_deref(),
+            Some(backorders)
+        );
+        assert_eq!(
+            meta(&ctx.db, product.id, "_stock_status").await.as_deref(),
+            Some(stock_status)
+        );
+    }
+}
@@ -1,5 +1,9 @@
-use commust::app::App;
+use commust::{
+    app::App,
+    models::_entities::{order_items, orders, products},
+};
 use loco_rs::{boot::run_task, task, testing};
+use sea_orm::{ColumnTrait, EntityTrait, ModelTrait, PaginatorTrait, QueryFilter};
 use serial_test::serial;
 
 #[tokio::test]
@@ -15,3 +19,38 @@ async fn test_can_seed_data() {
     .await
     .is_ok());
 }
+
+#[tokio::test]
+#[serial]
+async fn test_can_seed_load_test_data() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    let vars = task::Vars::from_cli_args(vec![("size".to_string(), "3".to_string())]);
+
+    run_task::<App>(&boot.app_context, Some(&"seed_data".to_string()), &vars)
+        .await
+        .unwrap();
+
+    let db = &boot.app_context.db;
+    let generated = products::Entity::find()
+        .filter(products::Column::Slug.starts_with("load-test-product-"))
+        .count(db)
+        .await
+        .unwrap();
+    assert_eq!(generated, 3);
+    let loaded = orders::Entity::find()
+        .filter(orders::Column::OrderKey.starts_with("wc_order_load"))
+        .all(db)
+        .await
+        .unwrap();
+    assert_eq!(loaded.len(), 3);
+    for order in loaded {
+        let items = order
+            .find_related(order_items::Entity)
+            .all(db)
+            .await
+            .unwrap();
+        assert!(!items.is_empty());
+        let total: f32 = items.iter().map(|item| item.total).sum();
+        assert!((total - order.total).abs() < f32::EPSILON);
+    }
+}
@@ -0,0 +1,69 @@
+use commust::{
+    app::App, mailers::delivery, models::failed_emails, workers::mail_delivery::MailDeliveryWorker,
+};
+use loco_rs::{bgworker::BackgroundWorker, mailer::Email, testing};
+use serial_test::serial;
+
+fn email() -> Email {
+    Email {
+        from: Some("shop@example.com".to_string()),
+        to: "customer@example.com".to_string(),
+        subject: "Hello".to_string(),
+        text: "Hello there".to_string(),
+        html: "<p>Hello there</p>".to_string(),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn can_deliver_to_the_mailbox() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    delivery::clear_mailbox();
+
+    MailDeliveryWorker::build(&boot.app_context)
+        .perform(email())
+        .await
+        .unwrap();
+
+    let mailbox = delivery::mailbox();
+    assert_eq!(mailbox.len(), 1);
+    assert_eq!(mailbox[0].to, "customer@example.com");
+    assert_eq!(mailbox[0].subject, "Hello");
+}
+
+#[tokio::test]
+#[serial]
+async fn keeps_undeliverable_emails_as_failed() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    delivery::clear_mailbox();
+    // send to the mailer of the config, without any mailer
+    let mut ctx = boot.app_context.clone();
+    ctx.mailer = None;
+    let mut settings = ctx.config.settings.clone().unwrap();
+    settings["mail_delivery"]["sink"] = serde_json::json!("mailer");
+    ctx.config.settings = Some(settings);
+
+    MailDeliveryWorker::build(&ctx)
+        .perform(email())
+        .await
+        .unwrap();
+
+    let failed = failed_emails::Model::list(&ctx.db).await.unwrap();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].recipient, "customer@example.com");
+    assert_eq!(failed[0].subject, "Hello");
+    assert_eq!(failed[0].attempts, 3);
+    assert!(failed[0].error.contains("no email sender configured"));
+    assert!(delivery::mailbox().is_empty());
+
+    let email = failed_emails::Model::take(&ctx.db, failed[0].id)
+        .await
+        .unwrap();
+    assert_eq!(email.text, "Hello there");
+    assert!(failed_emails::Model::list(&ctx.db)
+        .await
+        .unwrap()
+        .is_empty());
+}
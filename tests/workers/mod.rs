@@ -1 +1,4 @@
-
+mod mail_delivery;
+mod order_emails;
+mod scheduled_products;
+mod thumbnails;
@@ -0,0 +1,168 @@
+use commust::{
+    app::App,
+    mailers::{
+        delivery,
+        order::{OrderEmail, OrderMailer},
+    },
+    models::{_entities::products, orders, stock_subscriptions},
+    workers::order_emails::{OrderEmailWorker, OrderEmailWorkerArgs},
+};
+use loco_rs::{bgworker::BackgroundWorker, testing};
+use sea_orm::{ActiveModelTrait, ActiveValue};
+use serial_test::serial;
+
+use crate::requests::prepare_data;
+
+async fn place_order(db: &sea_orm::DatabaseConnection) -> (products::Model, orders::Model) {
+    let product = products::ActiveModel {
+        title: ActiveValue::set("Loco t-shirt".to_string()),
+        author_id: ActiveValue::set(1),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    let order = prepare_data::place_order(db, &product).await;
+    (product, order)
+}
+
+#[tokio::test]
+#[serial]
+async fn can_render_order_emails() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    let (_, order) = place_order(&ctx.db).await;
+
+    let email = OrderMailer::order_email(ctx, &order, &OrderEmail::Received)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(email.to, "guest@example.com");
+    assert_eq!(
+        email.subject,
+        format!("Your order #{} has been received", order.id)
+    );
+    assert!(email.text.starts_with("Hi Ada,"));
+    assert!(email.text.contains("Loco t-shirt x 1: 9"));
+    assert!(email.html.contains(&format!(
+        "/checkout/order-received/{}?key={}",
+        order.id,
+        order.order_key.as_deref().unwrap()
+    )));
+
+    let note = OrderEmail::CustomerNote {
+        note: "Your parcel left today".to_string(),
+    };
+    let email = OrderMailer::order_email(ctx, &order, &note)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(email.text.contains("Your parcel left today"));
+
+    // staff emails go to the configured admin address
+    let email = OrderMailer::order_email(ctx, &order, &OrderEmail::NewOrder)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(email.to, "admin@example.com");
+    assert_eq!(email.subject, format!("New order #{}", order.id));
+    assert!(email.text.contains("from guest@example.com"));
+}
+
+#[tokio::test]
+#[serial]
+async fn can_send_order_emails_from_worker() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    let (product, order) = place_order(&ctx.db).await;
+
+    assert_eq!(
+        OrderEmail::for_status(orders::STATUS_PROCESSING),
+        Some(OrderEmail::Processing)
+    );
+    assert_eq!(OrderEmail::for_status(orders::STATUS_FAILED), None);
+
+    let worker = OrderEmailWorker::build(ctx);
+    worker
+        .perform(OrderEmailWorkerArgs::Order {
+            order_id: order.id,
+            email: OrderEmail::Completed,
+        })
+        .await
+        .unwrap();
+    worker
+        .perform(OrderEmailWorkerArgs::LowStock {
+            product_id: product.id,
+            stock: 2.0,
+        })
+        .await
+        .unwrap();
+    // emails of deleted orders are dropped
+    worker
+        .perform(OrderEmailWorkerArgs::Order {
+            order_id: order.id + 100,
+            email: OrderEmail::Completed,
+        })
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn can_send_stock_emails_from_worker() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = &boot.app_context;
+    delivery::clear_mailbox();
+    for email in ["ada@example.com", "grace@example.com"] {
+        stock_subscriptions::Model::subscribe(&ctx.db, 4, email)
+            .await
+            .unwrap();
+    }
+
+    let worker = OrderEmailWorker::build(ctx);
+    worker
+        .perform(OrderEmailWorkerArgs::NoStock { product_id: 1 })
+        .await
+        .unwrap();
+    worker
+        .perform(OrderEmailWorkerArgs::BackInStock { product_id: 4 })
+        .await
+        .unwrap();
+    // customers are told once
+    assert!(stock_subscriptions::Model::find_by_product(&ctx.db, 4)
+        .await
+        .unwrap()
+        .is_empty());
+
+    for _ in 0..50 {
+        if delivery::mailbox().len() >= 3 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    let mut mailbox: Vec<(String, String)> = delivery::mailbox()
+        .into_iter()
+        .map(|email| (email.to, email.subject))
+        .collect();
+    mailbox.sort();
+    assert_eq!(
+        mailbox,
+        vec![
+            (
+                "ada@example.com".to_string(),
+                "Ferris t-shirt - M is back in stock".to_string()
+            ),
+            (
+                "admin@example.com".to_string(),
+                "Out of stock: Rust hoodie".to_string()
+            ),
+            (
+                "grace@example.com".to_string(),
+                "Ferris t-shirt - M is back in stock".to_string()
+            ),
+        ]
+    );
+}
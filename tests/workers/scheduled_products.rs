@@ -0,0 +1,43 @@
+use chrono::{Duration, Utc};
+use commust::{
+    app::App,
+    models::products::{self, ProductStatus},
+    workers::scheduled_products::{ScheduledProductsWorker, ScheduledProductsWorkerArgs},
+};
+use loco_rs::{bgworker::BackgroundWorker, testing};
+use sea_orm::{sea_query::Expr, ColumnTrait, EntityTrait, QueryFilter};
+use serial_test::serial;
+
+#[tokio::test]
+#[serial]
+async fn publishes_the_products_whose_date_came() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = boot.app_context;
+    // the draft mug of the fixtures, scheduled a minute ago
+    products::Entity::update_many()
+        .col_expr(
+            products::Column::Status,
+            Expr::value(ProductStatus::Future.as_str()),
+        )
+        .col_expr(
+            products::Column::PublishedAt,
+            Expr::value(Utc::now() - Duration::minutes(1)),
+        )
+        .filter(products::Column::Id.eq(7))
+        .exec(&ctx.db)
+        .await
+        .unwrap();
+
+    ScheduledProductsWorker::build(&ctx)
+        .perform(ScheduledProductsWorkerArgs::default())
+        .await
+        .unwrap();
+
+    let mug = products::Entity::find_by_id(7)
+        .one(&ctx.db)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(mug.status(), ProductStatus::Publish);
+}
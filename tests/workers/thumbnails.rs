@@ -0,0 +1,63 @@
+use std::io::Cursor;
+
+use commust::{
+    app::App,
+    models::media,
+    workers::thumbnails::{ThumbnailWorker, ThumbnailWorkerArgs},
+};
+use loco_rs::{bgworker::BackgroundWorker, testing};
+use sea_orm::EntityTrait;
+use serial_test::serial;
+
+#[tokio::test]
+#[serial]
+async fn makes_the_thumbnails_an_image_is_larger_than() {
+    let boot = testing::boot_test::<App>().await.unwrap();
+    testing::seed::<App>(&boot.app_context.db).await.unwrap();
+    let ctx = boot.app_context;
+    let mut content = vec![];
+    image::RgbaImage::new(200, 600)
+        .write_to(&mut Cursor::new(&mut content), image::ImageFormat::Png)
+        .unwrap();
+    let image = media::Model::upload(
+        &ctx.db,
+        &ctx.storage,
+        "poster.png",
+        content.into(),
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    assert!(image.thumbnails().is_empty());
+
+    ThumbnailWorker::build(&ctx)
+        .perform(ThumbnailWorkerArgs { media_id: image.id })
+        .await
+        .unwrap();
+
+    let image = media::Entity::find_by_id(image.id)
+        .one(&ctx.db)
+        .await
+        .unwrap()
+        .unwrap();
+    let thumbnails = image.thumbnails();
+    assert_eq!(
+        thumbnails.keys().collect::<Vec<_>>(),
+        vec!["medium", "thumbnail"]
+    );
+    assert_eq!(
+        image.path_of(Some("thumbnail")),
+        image.path.replace(".png", "-thumbnail.png")
+    );
+    // no large thumbnail of a smaller image
+    assert_eq!(image.path_of(Some("large")), image.path);
+
+    let thumbnail: Vec<u8> = ctx
+        .storage
+        .download(std::path::Path::new(&thumbnails["thumbnail"]))
+        .await
+        .unwrap();
+    let thumbnail = image::load_from_memory(&thumbnail).unwrap();
+    assert_eq!((thumbnail.width(), thumbnail.height()), (50, 150));
+}